@@ -0,0 +1,344 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::chapters::ChapterDef;
+use crate::filter::Filter;
+use crate::rating::{Rating, RatingUnit};
+use id3::{Frame, Tag, Version};
+
+/// Represents a single action passed by the user on the command line.
+/// Actions are executed on the tag of each file in order (see `engine::Executor`). Frames given
+/// to actions are queries, matched against the tag's frames by their ID, description and language
+/// (see `query::parse_frame_query`), and carry the value to be set, where one is needed.
+#[derive(Debug)]
+pub enum Action {
+    /// Print the value of the frame matching the query.
+    Print(Frame),
+    /// Set a frame, replacing the frame matching it as a query, if any.
+    Set(Frame),
+    /// Delete the frame matching the query.
+    Delete(Frame),
+    /// Convert the tag to another version.
+    Convert(ConvertOpt),
+    /// Remove a tag from the file, which happens immediately rather than when the tag is written.
+    Purge(PurgeOpt),
+    /// Print a field of the ID3v1 tag.
+    PrintId3v1(Id3v1Field),
+    /// Write an ID3v1.1 tag derived from the ID3v2 tag.
+    SyncId3v1,
+    /// Print the raw layout of the tag, as stored in the file.
+    TagInfo,
+    /// Print the raw bytes of the frame matching the query, as a hexdump or as they are, if set.
+    PrintRaw(Frame, bool),
+    /// Write the raw bytes of the tag, as stored in the file, to a file named after the template.
+    DumpTag(String),
+    /// Replace the tag with the raw bytes of a tag read from a file named after the template,
+    /// which happens immediately rather than when the tag is written.
+    RestoreTag(String),
+    /// Export the lyrics to a text file, named after the given template, if any.
+    ExportLyrics(Option<String>),
+    /// Print all chapters.
+    PrintChapters,
+    /// Print all tables of contents.
+    PrintTocs,
+    /// Create a top-level table of contents listing all chapters.
+    AutoToc,
+    /// Replace all chapters with the given ones.
+    ImportChapters(Vec<ChapterDef>),
+    /// Print all chapters in the given format.
+    ExportChapters(ChaptersFormat),
+    /// Print the rating in the given unit.
+    PrintRating(RatingUnit),
+    /// Set the rating.
+    SetRating(Rating),
+    /// Print whether the file is part of a compilation, as "on" or "off".
+    PrintCompilation,
+    /// Mark the file as part of a compilation, or clear the mark.
+    SetCompilation(bool),
+    /// Increment the play counter, and the counter in POPM as well, if set.
+    IncrementPlayCount(bool),
+    /// Extract the GEOB frame with the given description to a file named after the given template.
+    ExtractGeob(String, String),
+    /// Append the values of a multi-valued text frame to the frame in the tag.
+    AppendValue(Frame),
+    /// Remove the value of a multi-valued text frame from the frame in the tag.
+    RemoveValue(Frame),
+    /// Print the frame the alias stands for, in the tag's version.
+    PrintAlias(FrameAlias),
+    /// Set the frame the alias stands for, in the tag's version.
+    SetAlias(FrameAlias, String),
+    /// Delete the frame the alias stands for, in the tag's version.
+    DeleteAlias(FrameAlias),
+    /// Print a component of TRCK or TPOS.
+    PrintNumberPart(NumberPart),
+    /// Set a component of TRCK or TPOS.
+    SetNumberPart(NumberPart, u32),
+    /// Delete a component of TRCK or TPOS.
+    DeleteNumberPart(NumberPart),
+    /// Number the track after the position of the file among all files, starting at the given number.
+    AutoTrack(u32, Option<AutoTrackTotal>),
+    /// Pad the numbers in TRCK and TPOS.
+    NormalizeNumbers,
+    /// Set TLEN to the duration of the audio, computed from the MPEG frame headers.
+    SetLength,
+    /// Store the MD5 digest of the audio data in TXXX[AUDIO_MD5].
+    SetChecksum,
+    /// Compare the MD5 digest of the audio data to the one stored in TXXX[AUDIO_MD5].
+    VerifyChecksum,
+    /// Print the problems found in the tag.
+    Check,
+    /// Apply the given kinds of fixes to the tag.
+    Fix(Vec<FixKind>),
+    /// Recover UTF-8 text mis-decoded as Latin-1.
+    FixEncoding,
+    /// Convert the text of all frames to a Unicode normalization form.
+    NormalizeFrames,
+    /// Trim whitespace in the text of all frames.
+    Trim,
+    /// Delete all text and link frames with an empty value.
+    StripEmpty,
+    /// Delete duplicate frames.
+    Dedupe,
+    /// Change the case of the values of all frames with the given ID.
+    ChangeCase(String, CaseChange),
+    /// Replace the tag with the given tag.
+    CopyFrom(Box<Tag>),
+    /// Add the frames of the given tag, overwriting conflicting frames, if set.
+    MergeFrom(Box<Tag>, bool),
+    /// Set the frame which was found by the query in another file, if any.
+    CopyFrame(Frame, Option<Frame>),
+    /// Set the value of the second frame to the value of the first frame.
+    CopyValue(Frame, Frame),
+    /// Like `CopyValue`, but also delete the first frame.
+    MoveValue(Frame, Frame),
+    /// Set (or clear, if false) a status flag in the header of all frames with the given ID.
+    /// The flag is changed in the stored frames once the tag is written.
+    SetFrameFlag(String, FrameFlag, bool),
+    /// Skip all remaining actions, unless the tag matches the filter.
+    Where(Filter),
+}
+
+impl Action {
+    /// Returns the name of the action, e.g. "print-rating" for `Action::PrintRating`.
+    pub fn name(&self) -> String {
+        let debug = format!("{self:?}");
+        let mut name = String::new();
+        for c in debug.chars().take_while(char::is_ascii_alphanumeric) {
+            if c.is_ascii_uppercase() && !name.is_empty() {
+                name.push('-');
+            }
+            name.push(c.to_ascii_lowercase());
+        }
+        name
+    }
+
+    /// Checks if the action prints a value to the output.
+    pub fn is_print(&self) -> bool {
+        matches!(self, Action::Print(_) | Action::PrintRaw(..) | Action::PrintAlias(_) | Action::PrintNumberPart(_) | Action::PrintId3v1(_) | Action::TagInfo | Action::PrintChapters
+            | Action::PrintTocs | Action::ExportChapters(_) | Action::PrintRating(_) | Action::PrintCompilation)
+    }
+
+    /// Checks if the action accesses the file itself, rather than only its tag, e.g. to read the
+    /// tag as stored or to write other files named after it.
+    pub fn accesses_file(&self) -> bool {
+        matches!(self, Action::Purge(_) | Action::PrintId3v1(_) | Action::SyncId3v1 | Action::TagInfo | Action::DumpTag(_) | Action::RestoreTag(_) | Action::ExportLyrics(_)
+            | Action::ExtractGeob(..) | Action::Fix(_) | Action::Check | Action::Dedupe | Action::SetFrameFlag(..) | Action::SetLength
+            | Action::SetChecksum | Action::VerifyChecksum)
+    }
+
+    /// Checks if the action may modify the file, as opposed to only reading it (or writing other
+    /// files, e.g. exported lyrics).
+    pub fn modifies_file(&self) -> bool {
+        !self.is_print() && !matches!(self, Action::Check | Action::VerifyChecksum | Action::Where(_) | Action::DumpTag(_) | Action::ExportLyrics(_) | Action::ExtractGeob(..))
+    }
+}
+
+/// Represents one of convert options passed to the program on the command line.
+#[derive(Debug, Copy, Clone)]
+pub enum ConvertOpt {
+    Id3v22,
+    Id3v23,
+    Id3v24,
+    Id3v22Force,
+    Id3v23Force,
+    Id3v24Force,
+}
+
+impl ConvertOpt {
+    /// Returns the version which a tag is converted to, and whether the conversion is forced,
+    /// i.e. drops frames which do not exist in that version.
+    pub fn target(self) -> (Version, bool) {
+        match self {
+            ConvertOpt::Id3v22 => (Version::Id3v22, false),
+            ConvertOpt::Id3v23 => (Version::Id3v23, false),
+            ConvertOpt::Id3v24 => (Version::Id3v24, false),
+            ConvertOpt::Id3v22Force => (Version::Id3v22, true),
+            ConvertOpt::Id3v23Force => (Version::Id3v23, true),
+            ConvertOpt::Id3v24Force => (Version::Id3v24, true),
+        }
+    }
+}
+
+/// Represents one of purge options passed to the program on the command line.
+#[derive(Debug, Copy, Clone)]
+pub enum PurgeOpt {
+    Id3v22,
+    Id3v23,
+    Id3v24,
+    All,
+    Id3v1,
+    Ape,
+}
+
+/// Represents a chapter file format, as passed on the command line.
+#[derive(Debug, Copy, Clone)]
+pub enum ChaptersFormat {
+    Cue,
+    Ffmetadata,
+}
+
+/// Represents how binary frame data is printed, as passed on the command line.
+#[derive(Debug, Copy, Clone)]
+pub enum BinaryFormat {
+    Hex,
+    Base64,
+    /// Written to stdout as it is, e.g. to be piped to another program. Only single frames are
+    /// printed this way, and hex is used wherever binary data is embedded in text.
+    Raw,
+}
+
+/// Represents how star ratings map onto the 0-255 POPM scale, as passed on the command line.
+#[derive(Debug, Copy, Clone)]
+pub enum RatingScale {
+    /// 1-5 stars are 1, 64, 128, 196 and 255, as written by Windows Media Player and most others.
+    Wmp,
+    /// 1-5 stars are 51, 102, 153, 204 and 255.
+    Linear,
+}
+
+/// Represents the total track count assigned by `--auto-track`, as passed on the command line.
+#[derive(Debug, Copy, Clone)]
+pub enum AutoTrackTotal {
+    /// Assign the given total.
+    Fixed(u32),
+    /// Assign the number of the last file as the total.
+    Auto,
+}
+
+/// Represents one of the friendly frame aliases passed to the program on the command line.
+#[derive(Debug, Copy, Clone)]
+pub enum FrameAlias {
+    Artist,
+    Title,
+    Album,
+    AlbumArtist,
+    Year,
+    Genre,
+    Comment,
+}
+
+/// Represents one of the TRCK or TPOS components passed to the program on the command line.
+#[derive(Debug, Copy, Clone)]
+pub enum NumberPart {
+    TrackNumber,
+    TrackTotal,
+    DiscNumber,
+    DiscTotal,
+}
+
+/// Represents one of the kinds of fixes applied by `--fix`, as passed on the command line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FixKind {
+    /// Remove frames which cannot be decoded.
+    Corrupt,
+    /// Recover UTF-8 text stored in frames declared as Latin-1.
+    Encoding,
+    /// Merge frames which conflict with each other.
+    Duplicates,
+    /// Remove frames which hold no value.
+    Empty,
+    /// Resolve numeric genre references in TCON to genre names.
+    Genres,
+    /// Remove whitespace from TRCK and TPOS.
+    Numbers,
+}
+
+/// Represents one of the status flags of a frame header, as passed on the command line.
+/// Format flags (e.g. compression) are not included, as they change how the frame is stored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrameFlag {
+    /// The frame should be discarded if the tag is altered by software unaware of the frame.
+    TagAlterPreservation,
+    /// The frame should be discarded if the audio data is altered.
+    FileAlterPreservation,
+    /// The frame should not be changed without knowing why it was marked read-only.
+    ReadOnly,
+}
+
+impl FrameFlag {
+    /// Returns the name of the flag, as printed by `--show-flags`.
+    pub fn name(self) -> &'static str {
+        match self {
+            FrameFlag::TagAlterPreservation => "tag alter preservation",
+            FrameFlag::FileAlterPreservation => "file alter preservation",
+            FrameFlag::ReadOnly => "read-only",
+        }
+    }
+
+    /// Returns the bit of the flag in the frame header of the given ID3v2 major version, or
+    /// `None` in ID3v2.2, which has no frame flags.
+    pub fn mask(self, major_version: u8) -> Option<u16> {
+        let mask = match self {
+            FrameFlag::TagAlterPreservation => 0x8000,
+            FrameFlag::FileAlterPreservation => 0x4000,
+            FrameFlag::ReadOnly => 0x2000,
+        };
+        match major_version {
+            3 => Some(mask),
+            4 => Some(mask >> 1),
+            _ => None,
+        }
+    }
+}
+
+/// Represents a change of letter case applied to frame values, as passed on the command line.
+#[derive(Debug, Copy, Clone)]
+pub enum CaseChange {
+    Title,
+    Upper,
+    Lower,
+}
+
+/// Represents a Unicode normalization form, as passed on the command line.
+#[derive(Debug, Copy, Clone)]
+pub enum NormalizationForm {
+    /// Canonical composition, e.g. "é" as a single character.
+    Nfc,
+    /// Canonical decomposition, e.g. "é" as "e" followed by a combining acute accent.
+    Nfd,
+}
+
+/// Represents one of ID3v1 fields passed to the program on the command line.
+#[derive(Debug, Copy, Clone)]
+pub enum Id3v1Field {
+    Title,
+    Artist,
+    Album,
+    Year,
+    Comment,
+    Track,
+    Genre,
+}
+
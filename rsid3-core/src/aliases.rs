@@ -0,0 +1,81 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::action::FrameAlias;
+use id3::{Content, Frame, Version};
+use id3::frame::Comment;
+
+/// Language of comments accessed via the `comment` alias.
+const COMMENT_ALIAS_LANG: &str = "eng";
+
+/// All frame aliases.
+pub const ALL_FRAME_ALIASES: &[FrameAlias] = &[
+    FrameAlias::Artist,
+    FrameAlias::Title,
+    FrameAlias::Album,
+    FrameAlias::AlbumArtist,
+    FrameAlias::Year,
+    FrameAlias::Genre,
+    FrameAlias::Comment,
+];
+
+/// Returns the alias's name, as used on the command line (without the leading dashes).
+pub fn frame_alias_name(alias: FrameAlias) -> &'static str {
+    match alias {
+        FrameAlias::Artist => "artist",
+        FrameAlias::Title => "title",
+        FrameAlias::Album => "album",
+        FrameAlias::AlbumArtist => "album-artist",
+        FrameAlias::Year => "year",
+        FrameAlias::Genre => "genre",
+        FrameAlias::Comment => "comment",
+    }
+}
+
+/// Returns the alias matching a name, as used on the command line (without the leading dashes).
+pub fn frame_alias_from_name(name: &str) -> Option<FrameAlias> {
+    ALL_FRAME_ALIASES.iter().copied().find(|&x| frame_alias_name(x) == name)
+}
+
+/// Returns the ID of the frame an alias stands for in a tag of the given version.
+pub fn frame_alias_id(alias: FrameAlias, version: Version) -> &'static str {
+    match alias {
+        FrameAlias::Artist => "TPE1",
+        FrameAlias::Title => "TIT2",
+        FrameAlias::Album => "TALB",
+        FrameAlias::AlbumArtist => "TPE2",
+        // The recording time frame was only introduced in ID3v2.4
+        FrameAlias::Year if version == Version::Id3v24 => "TDRC",
+        FrameAlias::Year => "TYER",
+        FrameAlias::Genre => "TCON",
+        FrameAlias::Comment => "COMM",
+    }
+}
+
+/// Constructs the frame an alias stands for in a tag of the given version, with the given value.
+/// With an empty value, the frame can be used as a query.
+pub fn frame_alias_frame(alias: FrameAlias, version: Version, value: String) -> Frame {
+    match frame_alias_id(alias, version) {
+        "COMM" => {
+            let comment = Comment {
+                lang: COMMENT_ALIAS_LANG.to_string(),
+                description: String::new(),
+                text: value,
+            };
+            Frame::with_content("COMM", Content::Comment(comment))
+        },
+        id => Frame::text(id, value),
+    }
+}
@@ -0,0 +1,98 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use anyhow::{anyhow, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Size of an APE tag header or footer, in bytes.
+const APE_FOOTER_SIZE: u64 = 32;
+/// Size of an ID3v1 tag, which may follow an APE tag at the end of the file.
+const ID3V1_SIZE: u64 = 128;
+/// Flag set in the APE footer if the tag is preceded by a header.
+const APE_FLAG_HAS_HEADER: u32 = 1 << 31;
+
+/// Represents the location of an APE tag within a file.
+#[derive(Debug, Copy, Clone)]
+pub struct ApeTag {
+    /// APE tag version, i.e. 1 for APEv1 and 2 for APEv2.
+    pub version: u32,
+    /// Offset of the first byte of the tag (including the header, if present).
+    pub offset: u64,
+    /// Size of the tag in bytes (including the header and footer).
+    pub size: u64,
+}
+
+/// Looks for an APE tag footer at a given offset from the end of the file.
+fn read_ape_footer(file: &mut File, file_len: u64, end_offset: u64) -> io::Result<Option<ApeTag>> {
+    if file_len < end_offset + APE_FOOTER_SIZE {
+        return Ok(None);
+    }
+    let footer_start = file_len - end_offset - APE_FOOTER_SIZE;
+    let mut footer = [0; APE_FOOTER_SIZE as usize];
+    file.seek(SeekFrom::Start(footer_start))?;
+    file.read_exact(&mut footer)?;
+    if &footer[0..8] != b"APETAGEX" {
+        return Ok(None);
+    }
+
+    let le_u32 = |i: usize| u32::from_le_bytes([footer[i], footer[i + 1], footer[i + 2], footer[i + 3]]);
+    let version = le_u32(8) / 1000;
+    // The size field counts all items and the footer, but not the header
+    let mut size = le_u32(12) as u64;
+    if le_u32(20) & APE_FLAG_HAS_HEADER != 0 {
+        size += APE_FOOTER_SIZE;
+    }
+    if size > footer_start + APE_FOOTER_SIZE {
+        return Ok(None);
+    }
+    Ok(Some(ApeTag {
+        version,
+        offset: footer_start + APE_FOOTER_SIZE - size,
+        size,
+    }))
+}
+
+/// Finds the APE tag at the end of a file, if it has one. The tag may be followed by an ID3v1 tag.
+pub fn find_ape_tag(fpath: &impl AsRef<Path>) -> Result<Option<ApeTag>> {
+    let result = File::open(fpath).and_then(|mut file| {
+        let file_len = file.seek(SeekFrom::End(0))?;
+        match read_ape_footer(&mut file, file_len, 0)? {
+            Some(ape_tag) => Ok(Some(ape_tag)),
+            None => read_ape_footer(&mut file, file_len, ID3V1_SIZE),
+        }
+    });
+    result.map_err(|e| anyhow!("Failed to look for APE tag in '{}': {e}", fpath.as_ref().display()))
+}
+
+/// Removes the APE tag from the end of a file, preserving any data that follows it.
+/// Returns whether the file had an APE tag.
+pub fn remove_ape_tag(fpath: &impl AsRef<Path>) -> Result<bool> {
+    let ape_tag = match find_ape_tag(fpath)? {
+        Some(x) => x,
+        None => return Ok(false),
+    };
+    let result = OpenOptions::new().read(true).write(true).open(fpath).and_then(|mut file| {
+        let mut trailing_data = vec![];
+        file.seek(SeekFrom::Start(ape_tag.offset + ape_tag.size))?;
+        file.read_to_end(&mut trailing_data)?;
+        file.seek(SeekFrom::Start(ape_tag.offset))?;
+        file.write_all(&trailing_data)?;
+        file.set_len(ape_tag.offset + trailing_data.len() as u64)
+    });
+    result.map_err(|e| anyhow!("Failed to remove APE tag from '{}': {e}", fpath.as_ref().display()))?;
+    Ok(true)
+}
@@ -0,0 +1,54 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::action::BinaryFormat;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+/// Encodes bytes as lowercase hexadecimal digits.
+pub fn encode_hex(data: &[u8]) -> String {
+    data.iter().map(|x| format!("{x:02x}")).collect()
+}
+
+/// Encodes binary frame data as text in the given format.
+pub fn encode_binary(data: &[u8], format: BinaryFormat) -> String {
+    match format {
+        BinaryFormat::Hex | BinaryFormat::Raw => encode_hex(data),
+        BinaryFormat::Base64 => STANDARD.encode(data),
+    }
+}
+
+/// Formats bytes as a hexdump, like `hexdump -C`: lines of 16 bytes, each starting with the offset
+/// of its first byte and ending with the bytes as ASCII, where printable.
+pub fn hexdump(data: &[u8]) -> String {
+    let mut lines = vec![];
+    for (i, chunk) in data.chunks(16).enumerate() {
+        let mut hex = String::new();
+        for j in 0..16 {
+            match chunk.get(j) {
+                Some(x) => hex.push_str(&format!("{x:02x} ")),
+                None => hex.push_str("   "),
+            }
+            if j == 7 {
+                hex.push(' ');
+            }
+        }
+        let ascii = chunk.iter()
+            .map(|&x| if x.is_ascii_graphic() || x == b' ' { x as char } else { '.' })
+            .collect::<String>();
+        lines.push(format!("{:08x}  {hex} |{ascii}|", i * 16));
+    }
+    lines.join("\n")
+}
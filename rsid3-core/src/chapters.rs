@@ -0,0 +1,464 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::action::ChaptersFormat;
+use crate::errors::{report_file_error, ErrorKind, Output};
+use anyhow::{anyhow, Result};
+use id3::{Frame, Tag, TagLike};
+use id3::frame::{Chapter, Content, TableOfContents};
+use std::fs::read_to_string;
+use std::io;
+use std::path::Path;
+
+/// Element ID of the table of contents generated by `--toc-auto`.
+pub const AUTO_TOC_ID: &str = "toc";
+
+/// Number of frames per second in cue sheet `mm:ss:ff` timestamps.
+const CUE_FRAMES_PER_SECOND: u32 = 75;
+
+/// Value of the CHAP byte offset fields meaning that the offsets are not used.
+const CHAPTER_NO_OFFSET: u32 = 0xffffffff;
+
+/// Parses a chapter time given either in milliseconds, or as `[[hh:]mm:]ss[.fff]`.
+pub fn parse_chapter_time(time: &str) -> Result<u32> {
+    let invalid = || anyhow!("Invalid chapter time: '{time}'");
+    if let Ok(ms) = time.parse() {
+        return Ok(ms);
+    }
+    let (time_s, fraction) = time.split_once('.').unwrap_or((time, ""));
+    if fraction.len() > 3 || !fraction.bytes().all(|x| x.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let fraction_ms = match fraction {
+        "" => 0,
+        x => x.parse::<u32>().map_err(|_| invalid())? * 10u32.pow(3 - x.len() as u32),
+    };
+    let parts = time_s.split(':').collect::<Vec<_>>();
+    if parts.len() > 3 {
+        return Err(invalid());
+    }
+    let mut seconds: u32 = 0;
+    for part in parts {
+        let part: u32 = part.parse().map_err(|_| invalid())?;
+        seconds = seconds.checked_mul(60).and_then(|x| x.checked_add(part)).ok_or_else(invalid)?;
+    }
+    seconds.checked_mul(1000).and_then(|x| x.checked_add(fraction_ms)).ok_or_else(invalid)
+}
+
+/// Formats a chapter time in milliseconds as `hh:mm:ss.fff`.
+pub fn format_chapter_time(time_ms: u32) -> String {
+    format!("{:02}:{:02}:{:02}.{:03}", time_ms / 3_600_000, time_ms / 60_000 % 60, time_ms / 1000 % 60,
+        time_ms % 1000)
+}
+
+/// Constructs a CHAP frame with the given element ID, times and title (stored in a TIT2 sub-frame).
+pub fn chapter_frame(element_id: String, start_time: u32, end_time: u32, title: &str) -> Frame {
+    let mut chapter = Chapter {
+        element_id,
+        start_time,
+        end_time,
+        start_offset: CHAPTER_NO_OFFSET,
+        end_offset: CHAPTER_NO_OFFSET,
+        frames: vec![],
+    };
+    if !title.is_empty() {
+        chapter.set_title(title);
+    }
+    Frame::from(chapter)
+}
+
+/// Returns the title of a chapter, i.e. the text of its TIT2 sub-frame, or an empty string.
+pub fn chapter_title(chapter: &Chapter) -> &str {
+    chapter.title().unwrap_or("")
+}
+
+/// Returns all chapters of a tag, sorted by their start time.
+pub fn sorted_chapters(tag: &Tag) -> Vec<&Chapter> {
+    let mut chapters = tag.chapters().collect::<Vec<_>>();
+    chapters.sort_by_key(|x| (x.start_time, x.end_time));
+    chapters
+}
+
+/// Prints all chapters of a tag to `output`, one per line: element ID, start time, end time and
+/// title, separated by tabs. `fpath` is only used for message prints.
+/// Returns whether the tag had any chapters.
+pub fn print_chapters(output: &mut dyn Output, tag: &Tag, fpath: &impl AsRef<Path>) -> io::Result<bool> {
+    let chapters = sorted_chapters(tag);
+    if chapters.is_empty() {
+        report_file_error(output, fpath, ErrorKind::NotFound, "Could not print chapters: Frame not found");
+        return Ok(false);
+    }
+    let lines = chapters.iter()
+        .map(|x| format!("{}\t{}\t{}\t{}", x.element_id, format_chapter_time(x.start_time),
+            format_chapter_time(x.end_time), chapter_title(x)))
+        .collect::<Vec<_>>();
+    write!(output.writer(), "{}", lines.join("\n"))?;
+    Ok(true)
+}
+
+/// Constructs an ordered CTOC frame with the given element ID and child element IDs.
+pub fn toc_frame(element_id: String, top_level: bool, elements: Vec<String>) -> Frame {
+    Frame::from(TableOfContents {
+        element_id,
+        top_level,
+        ordered: true,
+        elements,
+        frames: vec![],
+    })
+}
+
+/// Constructs a top-level CTOC frame listing all chapters of a tag, ordered by their start time.
+pub fn auto_toc_frame(tag: &Tag) -> Frame {
+    let elements = sorted_chapters(tag).into_iter().map(|x| x.element_id.clone()).collect();
+    toc_frame(AUTO_TOC_ID.to_string(), true, elements)
+}
+
+/// Adds a CTOC frame to a tag, replacing any CTOC with the same element ID.
+/// There may only be one top-level CTOC, so if the new one is top-level, all others are demoted.
+/// Returns whether a previous CTOC was replaced.
+pub fn add_toc(tag: &mut Tag, frame: Frame) -> bool {
+    let is_top_level = frame.content().table_of_contents().is_some_and(|x| x.top_level);
+    if is_top_level {
+        for old_frame in tag.remove("CTOC") {
+            match old_frame.content().table_of_contents() {
+                Some(toc) if toc.top_level => {
+                    let mut toc = toc.clone();
+                    toc.top_level = false;
+                    tag.add_frame(Frame::with_content("CTOC", Content::TableOfContents(toc)));
+                },
+                _ => {
+                    tag.add_frame(old_frame);
+                },
+            }
+        }
+    }
+    tag.add_frame(frame).is_some()
+}
+
+/// Prints all tables of contents of a tag to `output`, one per line: element ID, "top-level" or
+/// "nested", and comma-separated child element IDs, separated by tabs. `fpath` is only used for
+/// message prints. Returns whether the tag had any tables of contents.
+pub fn print_tocs(output: &mut dyn Output, tag: &Tag, fpath: &impl AsRef<Path>) -> io::Result<bool> {
+    let mut tocs = tag.tables_of_contents().collect::<Vec<_>>();
+    if tocs.is_empty() {
+        report_file_error(output, fpath, ErrorKind::NotFound, "Could not print tables of contents: Frame not found");
+        return Ok(false);
+    }
+    // The top-level table of contents goes first
+    tocs.sort_by_key(|x| !x.top_level);
+    let lines = tocs.iter()
+        .map(|x| format!("{}\t{}\t{}", x.element_id, if x.top_level { "top-level" } else { "nested" },
+            x.elements.join(",")))
+        .collect::<Vec<_>>();
+    write!(output.writer(), "{}", lines.join("\n"))?;
+    Ok(true)
+}
+
+/// Represents a chapter read from a cue sheet or an FFmpeg metadata file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChapterDef {
+    /// Start time in milliseconds.
+    pub start_time: u32,
+    /// End time in milliseconds, if the source defines it explicitly.
+    pub end_time: Option<u32>,
+    /// Title of the chapter, possibly empty.
+    pub title: String,
+}
+
+/// Parses a cue sheet `mm:ss:ff` timestamp (with 75 frames per second) into milliseconds.
+fn parse_cue_time(time: &str) -> Option<u32> {
+    let mut parts = time.split(':').map(|x| x.parse::<u32>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || seconds >= 60 || frames >= CUE_FRAMES_PER_SECOND {
+        return None;
+    }
+    minutes.checked_mul(60_000)?.checked_add(seconds * 1000 + frames * 1000 / CUE_FRAMES_PER_SECOND)
+}
+
+/// Removes the surrounding double quotes from a cue sheet value, if there are any.
+fn unquote_cue_value(value: &str) -> &str {
+    let value = value.trim();
+    value.strip_prefix('"').and_then(|x| x.strip_suffix('"')).unwrap_or(value)
+}
+
+/// Parses the contents of a cue sheet, turning every track into a chapter.
+/// Each track starts at its `INDEX 01` and ends where the next one starts.
+pub fn parse_cue(contents: &str) -> Result<Vec<ChapterDef>> {
+    let mut chapters = vec![];
+    // Start time and title of the track being parsed, if inside of a track
+    let mut track: Option<(Option<u32>, String)> = None;
+    for (i, line) in contents.trim_start_matches('\u{feff}').lines().enumerate() {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match (command.to_ascii_uppercase().as_str(), &mut track) {
+            ("TRACK", _) => {
+                if let Some((Some(start_time), title)) = track.take() {
+                    chapters.push(ChapterDef { start_time, end_time: None, title });
+                }
+                track = Some((None, String::new()));
+            },
+            ("TITLE", Some((_, title))) => {
+                *title = unquote_cue_value(rest).to_string();
+            },
+            ("INDEX", Some((start_time, _))) => {
+                let (number, time) = rest.trim().split_once(char::is_whitespace).unwrap_or((rest, ""));
+                if number.parse::<u32>().ok() == Some(1) {
+                    *start_time = Some(parse_cue_time(time.trim())
+                        .ok_or_else(|| anyhow!("Invalid cue sheet time on line {}: '{}'", i + 1, time.trim()))?);
+                }
+            },
+            _ => {},
+        }
+    }
+    if let Some((Some(start_time), title)) = track {
+        chapters.push(ChapterDef { start_time, end_time: None, title });
+    }
+    Ok(chapters)
+}
+
+/// Removes FFmpeg metadata escapes (a backslash before `=`, `;`, `#`, `\\` or a newline).
+fn unescape_ffmetadata(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.extend(chars.next()),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Represents a `[CHAPTER]` section of an FFmpeg metadata file, with times in time base units.
+struct FfmetadataChapter {
+    /// Numerator and denominator of the time base, in seconds.
+    timebase: (u64, u64),
+    start: Option<u64>,
+    end: Option<u64>,
+    title: String,
+}
+
+impl FfmetadataChapter {
+    /// Converts the section to a chapter, with times in milliseconds.
+    fn into_chapter_def(self) -> Result<ChapterDef> {
+        let (num, den) = self.timebase;
+        let to_ms = |x: u64| num.checked_mul(1000)
+            .and_then(|num| x.checked_mul(num))
+            .and_then(|x| u32::try_from(x / den).ok())
+            .ok_or_else(|| anyhow!("Chapter time out of range in FFmpeg metadata: {x}"));
+        Ok(ChapterDef {
+            start_time: to_ms(self.start.ok_or_else(|| anyhow!("Chapter without START in FFmpeg metadata"))?)?,
+            end_time: self.end.map(to_ms).transpose()?,
+            title: self.title,
+        })
+    }
+}
+
+/// Parses the contents of an FFmpeg metadata file (`;FFMETADATA1`), reading all of its chapters.
+pub fn parse_ffmetadata(contents: &str) -> Result<Vec<ChapterDef>> {
+    let mut chapters = vec![];
+    let mut chapter: Option<FfmetadataChapter> = None;
+    for (i, line) in contents.trim_start_matches('\u{feff}').lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            if let Some(x) = chapter.take() {
+                chapters.push(x.into_chapter_def()?);
+            }
+            if line.eq_ignore_ascii_case("[CHAPTER]") {
+                chapter = Some(FfmetadataChapter { timebase: (1, 1000), start: None, end: None, title: String::new() });
+            }
+            continue;
+        }
+        let (Some(chapter), Some((key, value))) = (&mut chapter, line.split_once('=')) else {
+            continue;
+        };
+        let invalid = || anyhow!("Invalid FFmpeg metadata on line {}: '{line}'", i + 1);
+        match key.to_ascii_uppercase().as_str() {
+            "TIMEBASE" => {
+                let (num, den) = value.split_once('/').ok_or_else(invalid)?;
+                chapter.timebase = (num.trim().parse().map_err(|_| invalid())?, den.trim().parse().map_err(|_| invalid())?);
+                if chapter.timebase.1 == 0 {
+                    return Err(invalid());
+                }
+            },
+            "START" => chapter.start = Some(value.trim().parse().map_err(|_| invalid())?),
+            "END" => chapter.end = Some(value.trim().parse().map_err(|_| invalid())?),
+            "TITLE" => chapter.title = unescape_ffmetadata(value),
+            _ => {},
+        }
+    }
+    if let Some(x) = chapter {
+        chapters.push(x.into_chapter_def()?);
+    }
+    Ok(chapters)
+}
+
+/// Reads chapters from a cue sheet or an FFmpeg metadata file, detected by its first line.
+pub fn read_chapters_file(fpath: &impl AsRef<Path>) -> Result<Vec<ChapterDef>> {
+    let contents = match read_to_string(fpath) {
+        Ok(x) => x,
+        Err(e) => return Err(anyhow!("Failed to read chapters from '{}': {e}", fpath.as_ref().display())),
+    };
+    let mut chapters = match contents.trim_start_matches('\u{feff}').starts_with(";FFMETADATA") {
+        true => parse_ffmetadata(&contents)?,
+        false => parse_cue(&contents)?,
+    };
+    if chapters.is_empty() {
+        return Err(anyhow!("No chapters found in '{}'", fpath.as_ref().display()));
+    }
+    chapters.sort_by_key(|x| x.start_time);
+    Ok(chapters)
+}
+
+/// Replaces all chapters and tables of contents of a tag with the given chapters, named
+/// `ch0`, `ch1`, etc., and a top-level table of contents listing them.
+/// Chapters without an end time end where the next one starts. The last one ends at the
+/// length given by TLEN, or if there is none, at its own start.
+pub fn import_chapters(tag: &mut Tag, chapters: &[ChapterDef]) {
+    let length = tag.get("TLEN")
+        .and_then(|x| x.content().text())
+        .and_then(|x| x.trim().parse::<u32>().ok());
+    tag.remove("CHAP");
+    tag.remove("CTOC");
+    for (i, chapter) in chapters.iter().enumerate() {
+        let end_time = chapter.end_time
+            .or_else(|| chapters.get(i + 1).map(|x| x.start_time))
+            .or(length)
+            .unwrap_or(chapter.start_time)
+            .max(chapter.start_time);
+        tag.add_frame(chapter_frame(format!("ch{i}"), chapter.start_time, end_time, &chapter.title));
+    }
+    let frame = auto_toc_frame(tag);
+    tag.add_frame(frame);
+}
+
+/// Formats a time in milliseconds as a cue sheet `mm:ss:ff` timestamp (with 75 frames per second).
+fn format_cue_time(time_ms: u32) -> String {
+    format!("{:02}:{:02}:{:02}", time_ms / 60_000, time_ms / 1000 % 60,
+        time_ms % 1000 * CUE_FRAMES_PER_SECOND / 1000)
+}
+
+/// Formats chapters as a cue sheet referring to the given audio file, one track per chapter.
+/// Cue sheets have no way of escaping double quotes, so they are replaced with single quotes.
+fn format_cue(chapters: &[&Chapter], fpath: &Path) -> String {
+    let quote = |x: &str| format!("\"{}\"", x.replace('"', "'"));
+    let file_name = fpath.file_name().unwrap_or(fpath.as_os_str()).to_string_lossy();
+    let mut lines = vec![format!("FILE {} MP3", quote(&file_name))];
+    for (i, chapter) in chapters.iter().enumerate() {
+        lines.push(format!("  TRACK {:02} AUDIO", i + 1));
+        lines.push(format!("    TITLE {}", quote(chapter_title(chapter))));
+        lines.push(format!("    INDEX 01 {}", format_cue_time(chapter.start_time)));
+    }
+    lines.join("\n")
+}
+
+/// Escapes the characters with a special meaning in FFmpeg metadata files.
+fn escape_ffmetadata(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Formats chapters as an FFmpeg metadata file, with times in milliseconds.
+fn format_ffmetadata(chapters: &[&Chapter]) -> String {
+    let mut lines = vec![";FFMETADATA1".to_string()];
+    for chapter in chapters {
+        lines.push(String::new());
+        lines.push("[CHAPTER]".to_string());
+        lines.push("TIMEBASE=1/1000".to_string());
+        lines.push(format!("START={}", chapter.start_time));
+        lines.push(format!("END={}", chapter.end_time));
+        lines.push(format!("title={}", escape_ffmetadata(chapter_title(chapter))));
+    }
+    lines.join("\n")
+}
+
+/// Prints all chapters of a tag to `output`, ordered by start time, in the given file format.
+/// `fpath` is used in the cue sheet's FILE command and for message prints.
+/// Returns whether the tag had any chapters.
+pub fn export_chapters(output: &mut dyn Output, tag: &Tag, fpath: &impl AsRef<Path>, format: ChaptersFormat) -> io::Result<bool> {
+    let chapters = sorted_chapters(tag);
+    if chapters.is_empty() {
+        report_file_error(output, fpath, ErrorKind::NotFound, "Could not export chapters: Frame not found");
+        return Ok(false);
+    }
+    let text = match format {
+        ChaptersFormat::Cue => format_cue(&chapters, fpath.as_ref()),
+        ChaptersFormat::Ffmetadata => format_ffmetadata(&chapters),
+    };
+    output.writer().write_all(text.as_bytes())?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a chapter definition without an explicit end time.
+    fn chapter(start_time: u32, title: &str) -> ChapterDef {
+        ChapterDef { start_time, end_time: None, title: title.to_string() }
+    }
+
+    #[test]
+    fn parses_chapter_times() {
+        assert_eq!(parse_chapter_time("1500").unwrap(), 1500);
+        assert_eq!(parse_chapter_time("01:30").unwrap(), 90_000);
+        assert_eq!(parse_chapter_time("01:02:03.5").unwrap(), 3_723_500);
+        assert_eq!(parse_chapter_time(&format_chapter_time(3_723_456)).unwrap(), 3_723_456);
+        for time in ["", "1:2:3:4", "01:30.1234", "01:3x", "-5", "1193:02:47.296", "99999999:00:00"] {
+            assert!(parse_chapter_time(time).is_err(), "{time}");
+        }
+    }
+
+    #[test]
+    fn parses_cue_times() {
+        assert_eq!(parse_cue_time("00:00:00"), Some(0));
+        assert_eq!(parse_cue_time("01:30:37"), Some(90_493));
+        assert_eq!(parse_cue_time(&format_cue_time(90_493)), Some(90_480));
+        for time in ["", "01:30", "01:60:00", "01:30:75", "01:30:00:00", "a:b:c", "99999:00:00", "71583:00:00"] {
+            assert_eq!(parse_cue_time(time), None, "{time}");
+        }
+        assert_eq!(parse_cue_time("71582:47:00"), Some(4_294_967_000));
+    }
+
+    #[test]
+    fn parses_cue_sheets() {
+        let chapters = parse_cue("\u{feff}TITLE \"Album\"\nFILE \"a.mp3\" MP3\n  TRACK 01 AUDIO\n    TITLE \"One\"\n    \
+            INDEX 00 00:00:00\n    INDEX 01 00:01:00\n  TRACK 02 AUDIO\n    INDEX 01 01:00:00\n  TRACK 03 AUDIO\n").unwrap();
+        assert_eq!(chapters, [chapter(1000, "One"), chapter(60_000, "")]);
+        let error = parse_cue("TRACK 01 AUDIO\n  INDEX 01 99999:00:00\n").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid cue sheet time on line 2: '99999:00:00'");
+    }
+
+    #[test]
+    fn parses_ffmetadata() {
+        let chapters = parse_ffmetadata(";FFMETADATA1\ntitle=Album\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=1500\n\
+            title=A\\=B\n[CHAPTER]\nTIMEBASE=1/44100\nSTART=88200\ntitle=Two\n[STREAM]\nTIMEBASE=1/1\n").unwrap();
+        assert_eq!(chapters, [
+            ChapterDef { start_time: 0, end_time: Some(1500), title: "A=B".to_string() },
+            chapter(2000, "Two"),
+        ]);
+        for contents in ["[CHAPTER]\nTIMEBASE=1/0\nSTART=0", "[CHAPTER]\nTIMEBASE=1\nSTART=0", "[CHAPTER]\nSTART=x",
+            "[CHAPTER]\nEND=5", "[CHAPTER]\nSTART=4294967296", "[CHAPTER]\nTIMEBASE=18446744073709551615/1\nSTART=1",
+            "[CHAPTER]\nTIMEBASE=18446744073709552/1\nSTART=0"] {
+            assert!(parse_ffmetadata(contents).is_err(), "{contents}");
+        }
+    }
+}
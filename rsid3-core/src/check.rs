@@ -0,0 +1,133 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::frame_table::is_frame_id_in_version;
+use crate::id3_helpers::frame_to_string;
+use crate::languages::is_valid_language;
+use crate::tag_header::TagLayout;
+use crate::terms_of_use::TermsOfUse;
+use crate::timestamps::parse_timestamp;
+use id3::{Content, Frame, Tag, Version};
+
+/// Represents a single problem found in a tag.
+#[derive(Debug, Clone)]
+pub struct Problem {
+    /// What the problem concerns, e.g. a frame (see `frame_to_string`).
+    pub subject: String,
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl Problem {
+    fn new(subject: impl Into<String>, message: impl Into<String>) -> Problem {
+        Problem { subject: subject.into(), message: message.into() }
+    }
+}
+
+/// Checks if a frame holds no value.
+pub fn is_frame_empty(frame: &Frame) -> bool {
+    let is_blank = |x: &str| x.trim_matches(|c: char| c == '\0' || c.is_whitespace()).is_empty();
+    match frame.content() {
+        Content::Text(x) | Content::Link(x) => is_blank(x),
+        Content::ExtendedText(x) => is_blank(&x.value),
+        Content::ExtendedLink(x) => is_blank(&x.link),
+        Content::Comment(x) => is_blank(&x.text),
+        Content::Lyrics(x) => is_blank(&x.text),
+        _ => false,
+    }
+}
+
+/// Returns the language of a frame, for frames which have one.
+fn frame_language(frame: &Frame) -> Option<String> {
+    match frame.content() {
+        Content::Comment(x) => Some(x.lang.clone()),
+        Content::Lyrics(x) => Some(x.lang.clone()),
+        Content::SynchronisedLyrics(x) => Some(x.lang.clone()),
+        _ if frame.id() == "USER" => TermsOfUse::from_frame(frame).ok().map(|x| x.lang),
+        _ => None,
+    }
+}
+
+/// Checks the value of a frame whose format is prescribed by the standard.
+/// Returns a description of the problem, if the value is malformed.
+fn check_frame_format(frame: &Frame) -> Option<String> {
+    let text = frame.content().text()?;
+    let is_digits = |x: &str, len: usize| x.len() == len && x.bytes().all(|x| x.is_ascii_digit());
+    match frame.id() {
+        "TRCK" | "TPOS" => {
+            let is_number = |x: &str| !x.is_empty() && x.bytes().all(|x| x.is_ascii_digit());
+            let is_valid = match text.split_once('/') {
+                Some((number, total)) => is_number(number) && is_number(total),
+                None => is_number(text),
+            };
+            (!is_valid).then(|| format!("Malformed number '{text}' (expected N or N/TOTAL)"))
+        },
+        "TDEN" | "TDOR" | "TDRC" | "TDRL" | "TDTG" => text.split('\0')
+            .find(|x| parse_timestamp(x).is_none())
+            .map(|x| format!("Malformed timestamp '{x}' (expected yyyy[-MM[-dd[THH[:mm[:ss]]]]])")),
+        "TYER" | "TORY" => (!is_digits(text, 4)).then(|| format!("Malformed year '{text}' (expected yyyy)")),
+        "TDAT" => (!is_digits(text, 4)).then(|| format!("Malformed date '{text}' (expected DDMM)")),
+        "TIME" => (!is_digits(text, 4)).then(|| format!("Malformed time '{text}' (expected HHMM)")),
+        _ => None,
+    }
+}
+
+/// Audits a tag against the standard and common conventions. `layout` is the raw layout of the
+/// tag as stored in the file, which is needed to find duplicate frames and invalid encodings.
+/// Returns all problems found.
+pub fn check_tag(tag: &Tag, layout: Option<&TagLayout>) -> Vec<Problem> {
+    let mut problems = vec![];
+    let version = tag.version();
+
+    for frame in tag.frames() {
+        let subject = frame_to_string(frame).unwrap_or_else(|_| frame.id().to_string());
+        if !is_frame_id_in_version(frame.id(), version) {
+            problems.push(Problem::new(&subject, format!("Frame is not defined in {version}")));
+        }
+        if is_frame_empty(frame) {
+            problems.push(Problem::new(&subject, "Frame is empty"));
+        }
+        if let Some(message) = check_frame_format(frame) {
+            problems.push(Problem::new(&subject, message));
+        }
+        if let Some(lang) = frame_language(frame).filter(|x| !is_valid_language(x)) {
+            problems.push(Problem::new(&subject, format!("Invalid language '{lang}' (expected an ISO 639-2/T code or XXX)")));
+        }
+    }
+
+    if let Some(layout) = layout {
+        // Frames which conflict with other frames are dropped when the tag is read, so any
+        // frames missing from the read tag are either such duplicates, or could not be read at all
+        let mut ids = layout.frames.iter().map(|x| x.id.as_str()).collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        for id in ids {
+            let stored = layout.frames.iter().filter(|x| x.id == id).count();
+            let read = tag.frames().filter(|x| x.id_for_version(version) == Some(id)).count();
+            if stored > read {
+                problems.push(Problem::new(id, format!("{} duplicate or unreadable frame(s), ignored when reading", stored - read)));
+            }
+        }
+
+        let max_encoding = if version == Version::Id3v24 { 3 } else { 1 };
+        for frame in &layout.frames {
+            if let Some(encoding) = frame.encoding.filter(|&x| x > max_encoding) {
+                problems.push(Problem::new(&frame.id, format!("Invalid text encoding {encoding} for {version}")));
+            }
+        }
+    }
+
+    problems
+}
@@ -0,0 +1,75 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::binary::encode_hex;
+use crate::file_format::{non_id3_chunk_ranges, FileFormat};
+use crate::mpeg_audio::audio_data_range;
+use anyhow::{anyhow, Result};
+use id3::{Content, Frame, Tag};
+use id3::frame::ExtendedText;
+use md5::{Digest, Md5};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+/// Description of the TXXX frame storing the MD5 digest of the audio payload.
+pub const AUDIO_MD5_DESCRIPTION: &str = "AUDIO_MD5";
+
+/// Returns the byte ranges of the audio payload of a file, i.e. everything that editing its tags
+/// must leave intact. For MPEG files, these are the bytes between the ID3v2 tag and the trailing
+/// ID3v1 and APE tags. For chunk-based files, these are all chunks other than the ID3 chunk.
+pub fn audio_payload_ranges(fpath: &impl AsRef<Path>) -> Result<Vec<Range<u64>>> {
+    match FileFormat::detect(fpath) {
+        FileFormat::Mpeg => Ok(vec![audio_data_range(fpath)?]),
+        format => File::open(fpath)
+            .and_then(|mut file| non_id3_chunk_ranges(&mut file, format))
+            .map_err(|e| anyhow!("Failed to read audio data of '{}': {e}", fpath.as_ref().display())),
+    }
+}
+
+/// Computes the MD5 digest of a file's audio payload (see [`audio_payload_ranges`]), as lowercase
+/// hexadecimal digits. The payload is streamed through the digest, rather than read as a whole.
+/// MD5 is long broken as a cryptographic hash, but it is what audio checksums are traditionally
+/// stored as, and it is good enough to catch accidental damage.
+pub fn audio_md5(fpath: &impl AsRef<Path>) -> Result<String> {
+    let ranges = audio_payload_ranges(fpath)?;
+    let mut hasher = Md5::new();
+    let result = File::open(fpath).and_then(|mut file| {
+        for range in ranges {
+            file.seek(SeekFrom::Start(range.start))?;
+            io::copy(&mut (&mut file).take(range.end - range.start), &mut hasher)?;
+        }
+        Ok(())
+    });
+    result.map_err(|e| anyhow!("Failed to read audio data of '{}': {e}", fpath.as_ref().display()))?;
+    Ok(encode_hex(&hasher.finalize()))
+}
+
+/// Returns the audio MD5 digest stored in a tag, if any.
+pub fn get_audio_md5(tag: &Tag) -> Option<&str> {
+    tag.extended_texts()
+        .find(|x| x.description == AUDIO_MD5_DESCRIPTION)
+        .map(|x| x.value.trim_end_matches('\0'))
+}
+
+/// Creates the TXXX frame storing an audio MD5 digest.
+pub fn audio_md5_frame(digest: &str) -> Frame {
+    let extended_text = ExtendedText {
+        description: AUDIO_MD5_DESCRIPTION.to_string(),
+        value: digest.to_string(),
+    };
+    Frame::with_content("TXXX", Content::ExtendedText(extended_text))
+}
@@ -0,0 +1,43 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use id3::{Tag, TagLike};
+
+/// The iTunes compilation flag frame. ID3v2.2 tags call it TCP, but the id3 crate reads and
+/// writes the ID3v2.3 and ID3v2.4 ID in both cases.
+const COMPILATION_ID: &str = "TCMP";
+
+/// Checks if a tag marks the file as part of a compilation. iTunes writes TCMP as "1" and removes
+/// it otherwise, but other taggers write "0", so any value other than a zero number counts.
+pub fn is_compilation(tag: &Tag) -> bool {
+    let value = match tag.get(COMPILATION_ID).and_then(|x| x.content().text()) {
+        Some(x) => x.trim_matches(|c: char| c.is_whitespace() || c == '\0'),
+        None => return false,
+    };
+    !value.is_empty() && value.parse::<u32>() != Ok(0)
+}
+
+/// Marks a tag as part of a compilation, setting TCMP to "1", or clears the mark by deleting TCMP,
+/// the way iTunes does. Returns whether the tag was changed.
+pub fn set_compilation(tag: &mut Tag, compilation: bool) -> bool {
+    let current = tag.get(COMPILATION_ID).and_then(|x| x.content().text()).map(str::to_string);
+    if compilation {
+        tag.set_text(COMPILATION_ID, "1");
+        current.as_deref() != Some("1")
+    } else {
+        tag.remove(COMPILATION_ID);
+        current.is_some()
+    }
+}
@@ -0,0 +1,73 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::id3_helpers::{decode_stored_frame, frame_to_string, frames_query_equal};
+use crate::tag_header::TagLayout;
+use id3::{Frame, Tag, TagLike};
+use std::collections::BTreeSet;
+
+/// Checks if only one frame with the given ID may exist per description, language, owner, etc.
+pub fn is_unique_id(id: &str) -> bool {
+    id.starts_with('T') || id.starts_with('W') || matches!(id,
+        "COMM" | "USLT" | "SYLT" | "POPM" | "PCNT" | "USER" | "GEOB" | "PRIV" | "UFID" | "CHAP" | "CTOC" | "GRP1" | "MVNM" | "MVIN")
+}
+
+/// Checks if a frame is made redundant by another one, i.e. they are identical, or they are both
+/// frames of which only one may exist (see `frames_query_equal`).
+fn is_duplicate(frame: &Frame, other: &Frame) -> bool {
+    frame == other || (is_unique_id(frame.id()) && frames_query_equal(frame, other).unwrap_or(false))
+}
+
+/// Removes redundant frames from a tag, keeping the last of each group of duplicates.
+/// `layout` is the raw layout of the tag as stored in the file, which is needed to find
+/// duplicates that were already dropped when reading the tag.
+/// Returns a description of each removal.
+pub fn dedupe_tag(tag: &mut Tag, layout: Option<&TagLayout>) -> Vec<String> {
+    let mut removals = vec![];
+
+    let frames = tag.frames().cloned().collect::<Vec<_>>();
+    let redundant = frames.iter()
+        .enumerate()
+        .filter(|(i, x)| frames[i + 1..].iter().any(|y| is_duplicate(x, y)))
+        .map(|(_, x)| x)
+        .collect::<Vec<_>>();
+    let ids = redundant.iter().map(|x| x.id()).collect::<BTreeSet<_>>();
+    for id in ids {
+        for frame in tag.remove(id) {
+            if !redundant.contains(&&frame) {
+                tag.add_frame(frame);
+            }
+        }
+    }
+    for frame in redundant {
+        removals.push(format!("Removed duplicate {}", frame_to_string(frame).unwrap_or_default()));
+    }
+
+    // Frames which conflict with a later one are dropped when the tag is read, but are still
+    // stored in the file until it is written
+    if let Some(layout) = layout {
+        let stored = layout.frames.iter().filter_map(|x| decode_stored_frame(layout, x)).collect::<Vec<_>>();
+        let ids = stored.iter().map(|x| x.id()).collect::<BTreeSet<_>>();
+        for id in ids {
+            let n_stored = stored.iter().filter(|x| x.id() == id).count();
+            let n_read = frames.iter().filter(|x| x.id() == id).count();
+            if n_stored > n_read {
+                removals.push(format!("Removed {} duplicate {id} frame(s) stored in the file", n_stored - n_read));
+            }
+        }
+    }
+
+    removals
+}
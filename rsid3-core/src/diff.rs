@@ -0,0 +1,55 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::dedupe::is_unique_id;
+use crate::id3_helpers::frames_query_equal;
+use id3::{Frame, Tag};
+
+/// Represents a difference between two tags in a single frame.
+#[derive(Debug)]
+pub enum FrameChange<'a> {
+    /// The frame exists only in the first tag.
+    Removed(&'a Frame),
+    /// The frame exists only in the second tag.
+    Added(&'a Frame),
+    /// The frame exists in both tags, but with different contents.
+    Changed(&'a Frame, &'a Frame),
+}
+
+/// Checks if two frames stand for the same frame of two tags, i.e. they are either identical, or
+/// they are both frames of which only one may exist (see `frames_query_equal`).
+fn frames_correspond(frame1: &Frame, frame2: &Frame) -> bool {
+    frame1.content() == frame2.content()
+        || (is_unique_id(frame1.id()) && frames_query_equal(frame1, frame2).unwrap_or(false))
+}
+
+/// Compares the frames of two tags, ignoring their order and text encodings.
+/// Returns all differences, in the order of the first tag's frames followed by added frames.
+pub fn diff_tags<'a>(tag1: &'a Tag, tag2: &'a Tag) -> Vec<FrameChange<'a>> {
+    let mut changes = vec![];
+    for frame1 in tag1.frames() {
+        match tag2.frames().find(|x| frames_correspond(frame1, x)) {
+            Some(frame2) if frame1.content() != frame2.content() => changes.push(FrameChange::Changed(frame1, frame2)),
+            Some(_) => {},
+            None => changes.push(FrameChange::Removed(frame1)),
+        }
+    }
+    for frame2 in tag2.frames() {
+        if !tag1.frames().any(|x| frames_correspond(x, frame2)) {
+            changes.push(FrameChange::Added(frame2));
+        }
+    }
+    changes
+}
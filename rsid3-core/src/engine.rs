@@ -0,0 +1,985 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! Execution of actions on the tags of files, which is what the rsid3 command line does for each
+//! file it is given.
+use crate::action::{Action, AutoTrackTotal, BinaryFormat, CaseChange, ConvertOpt, FrameFlag, NormalizationForm, NumberPart, PurgeOpt, RatingScale};
+use crate::aliases::frame_alias_frame;
+use crate::binary::hexdump;
+use crate::checksum::{audio_md5, audio_md5_frame, get_audio_md5, AUDIO_MD5_DESCRIPTION};
+use crate::errors::{report_file_error, ErrorKind, Output};
+use crate::file_format::FileFormat;
+use crate::id3_helpers::*;
+use crate::id3v1_helpers::*;
+use crate::mpeg_audio::read_file_audio_info;
+use crate::itunes_text::{get_itunes_text, is_itunes_text_id, itunes_text_frame};
+use crate::tag_header::TagLayout;
+use crate::terms_of_use::TermsOfUse;
+use crate::text_transforms::{frame_value, map_frame_text, map_frame_values, title_case, transform_tag_frames, trim_text, with_frame_value};
+use crate::{ape, chapters, check, compilation, dedupe, fix, involved_people, mojibake, normalization, numbering, play_count, rating, text_values};
+use std::fmt;
+use std::io::{self, Cursor};
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use id3::{Content, Encoding, Frame, Tag, TagLike, Version};
+
+/// Represents the options which affect how actions are executed, and how their output is printed.
+#[derive(Debug, Clone)]
+pub struct ExecOptions {
+    /// Printed between the outputs of print actions on the same file.
+    pub frame_sep: String,
+    /// Printed between the outputs of different files.
+    pub file_sep: String,
+    /// Separates multiple values of text frames, both when printing and when setting them.
+    pub value_sep: String,
+    /// Whether printed output is prefixed with the file's path.
+    pub with_filename: bool,
+    /// How binary frame data is printed.
+    pub binary_format: BinaryFormat,
+    /// Whether numeric genre references are resolved to genre names.
+    pub genre_names: bool,
+    /// Printed in place of frames which are not found, if any.
+    pub missing: Option<String>,
+    /// Whether frames which already exist are kept, rather than overwritten.
+    pub no_clobber: bool,
+    /// The width to which track and disc numbers are zero-padded, if any.
+    pub track_pad: Option<usize>,
+    /// The owner of the POPM frame holding the rating, or the first POPM frame if `None`.
+    pub rating_owner: Option<String>,
+    /// The scale on which POPM ratings are mapped to stars.
+    pub rating_scale: RatingScale,
+    /// The Unicode normalization form applied by `Action::NormalizeFrames`, or NFC if `None`.
+    pub normalize: Option<NormalizationForm>,
+    /// Whether corrupt frames are skipped when reading tags, rather than failing.
+    pub lenient: bool,
+    /// The version of tags created for files which have none.
+    pub default_version: Version,
+    /// The text encoding which modified tags are written with, or their frames' own if `None`.
+    pub encoding: Option<Encoding>,
+    /// The amount of padding which tags are written with, or an automatic amount if `None`.
+    pub padding: Option<usize>,
+    /// Whether tags are written to a temporary file first, which then replaces the original.
+    pub atomic: bool,
+    /// Whether modifications are only reported, rather than written.
+    pub dry_run: bool,
+    /// How much of what is done is reported as `ErrorKind::Info`, from 0 (nothing) to 2.
+    pub verbosity: u8,
+}
+
+impl Default for ExecOptions {
+    fn default() -> Self {
+        ExecOptions {
+            frame_sep: "\n".to_string(),
+            file_sep: "\n".to_string(),
+            value_sep: "\0".to_string(),
+            with_filename: false,
+            binary_format: BinaryFormat::Hex,
+            genre_names: false,
+            missing: None,
+            no_clobber: false,
+            track_pad: None,
+            rating_owner: None,
+            rating_scale: RatingScale::Wmp,
+            normalize: None,
+            lenient: false,
+            default_version: Version::Id3v24,
+            encoding: None,
+            padding: None,
+            atomic: false,
+            dry_run: false,
+            verbosity: 0,
+        }
+    }
+}
+
+/// Represents the tag of a file while actions are executed on it.
+#[derive(Debug, Clone)]
+pub struct FileTag {
+    /// The tag, with all modifications made so far.
+    pub tag: Tag,
+    /// The tag as stored in the file, or `None` if the file has no tag.
+    pub stored_tag: Option<Tag>,
+    /// Whether `tag` was modified, and needs to be written.
+    pub is_modified: bool,
+    /// Status flags to set (or clear) in the headers of frames with the given IDs, once the tag is
+    /// written, as the id3 crate cannot write them itself.
+    pub frame_flags: Vec<(String, FrameFlag, bool)>,
+}
+
+/// Represents the results of executing actions on a file which did not prevent the remaining
+/// actions from being executed.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// Whether a queried frame or field was not found.
+    pub not_found: bool,
+    /// Whether `Action::Check` found problems in the tag, or `Action::VerifyChecksum` found that
+    /// the audio data does not match its stored checksum.
+    pub problems_found: bool,
+}
+
+/// Represents an error which stopped the execution of actions on a file.
+#[derive(Debug)]
+pub enum ExecError {
+    /// An action cannot be executed at all, regardless of the file.
+    Usage(anyhow::Error),
+    /// The file or its tag could not be read or written.
+    File(anyhow::Error),
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecError::Usage(e) | ExecError::File(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ExecError {}
+
+/// Executes actions on the tags of a sequence of files, keeping track of what was printed so far,
+/// so that the outputs of different files are separated. Printed values and reports go to an
+/// [`Output`].
+#[derive(Debug)]
+pub struct Executor<O: Output> {
+    options: ExecOptions,
+    output: O,
+    file_count: usize,
+    is_first_file_print: bool,
+}
+
+/// Writes a frame into a tag. The previous value is overwritten, if any.
+/// On success, returns whether a previous value was overwritten.
+pub fn set_tag_frame(tag: &mut Tag, frame: Frame) -> Result<bool> {
+    match frame.id() {
+        "CTOC" => Ok(chapters::add_toc(tag, frame)),
+        "TIPL" | "TMCL" => {
+            let item = &get_content_people(&frame)?.items[0];
+            Ok(involved_people::set_person(tag, frame.id(), &item.involvement, &item.involvee))
+        },
+        "USER" => replace_tag_frame(tag, TermsOfUse::from_frame(&frame)?.to_frame()),
+        "PCNT" | "PCST" | "PRIV" => replace_tag_frame(tag, frame),
+        x if is_itunes_text_id(x) => replace_tag_frame(tag, itunes_text_frame(x, &get_itunes_text(&frame)?)),
+        x if x.starts_with('T') || x.starts_with('W') || matches!(x, "COMM" | "USLT" | "SYLT" | "CHAP" | "POPM" | "UFID" | "GEOB") => {
+            Ok(tag.add_frame(frame).is_some())
+        },
+        _ => Err(anyhow!("Writing to {frame} is not supported")),
+    }
+}
+
+/// Checks if the modifications made to a file's tag, as read into `stored_tag`, resulted in an
+/// identical tag, so that writing it would be pointless. Frames are compared regardless of their
+/// order, and text encodings only where both frames declare one. The stored tag must also not
+/// hold any frames which were dropped when reading it, e.g. corrupt or duplicate frames.
+pub fn is_tag_unchanged(fpath: &impl AsRef<Path>, stored_tag: &Tag, tag: &Tag) -> bool {
+    let is_frame_equal = |x: &Frame, y: &Frame| x.id() == y.id() && x.content() == y.content()
+        && (x.encoding().is_none() || y.encoding().is_none() || x.encoding() == y.encoding());
+    let n_frames = tag.frames().count();
+    stored_tag.version() == tag.version()
+        && stored_tag.frames().count() == n_frames
+        && stored_tag.frames().all(|x| tag.frames().any(|y| is_frame_equal(x, y)))
+        && read_tag_layout(fpath).is_ok_and(|x| x.is_some_and(|y| y.frames.len() == n_frames))
+}
+
+/// Checks if a tag already holds a frame matching a query. Involved people lists match only if
+/// they hold the queried role.
+pub fn tag_has_frame(tag: &Tag, frame: &Frame) -> bool {
+    match frame.id() {
+        "TIPL" | "TMCL" => get_content_people(frame).is_ok_and(|x|
+            !involved_people::get_people(tag, frame.id(), &x.items[0].involvement).is_empty()),
+        _ => tag.frames().any(|x| frames_query_equal(x, frame).unwrap_or(false)),
+    }
+}
+
+/// Converts a tag according to the given option.
+/// On success, returns whether any conversion happened (`false` iff the tag's version was already
+/// the same as the requested version).
+pub fn convert_tag(tag: &mut Tag, opt: ConvertOpt) -> Result<bool> {
+    let (tag_version, force) = opt.target();
+    if tag.version() == tag_version {
+        return Ok(false);
+    }
+    *tag = tag_with_version_from(tag, tag_version, force)?;
+    Ok(true)
+}
+
+/// Reads the raw layout of the ID3v2 tag of a file, unless the tag was modified since it was read,
+/// as the stored layout then no longer describes it.
+fn read_unmodified_tag_layout(fpath: &impl AsRef<Path>, tag_was_modified: bool) -> Result<Option<TagLayout>> {
+    match tag_was_modified {
+        false => read_tag_layout(fpath),
+        true => Ok(None),
+    }
+}
+
+/// Prints the raw layout of the ID3v2 tag stored in the file to `output`.
+pub fn print_tag_info(output: &mut dyn Output, fpath: &impl AsRef<Path>) -> Result<()> {
+    let layout = match read_tag_layout(fpath)? {
+        Some(x) => x,
+        None => {
+            report_file_error(output, fpath, ErrorKind::Warning, "No ID3v2 tag found");
+            return Ok(());
+        },
+    };
+    let header = &layout.header;
+    let flags = header.flag_names();
+    let out = output.writer();
+    writeln!(out, "{}: ID3v2.{}.{}, {} bytes", fpath.as_ref().display(),
+        header.major_version, header.revision, header.total_size())?;
+    writeln!(out, "flags: {}", if flags.is_empty() { "none".to_string() } else { flags.join(", ") })?;
+    if header.has_extended_header() {
+        writeln!(out, "extended header: {} bytes", layout.extended_header_size)?;
+    }
+    for frame in &layout.frames {
+        writeln!(out, "{}: {} bytes", frame.id, frame.size)?;
+    }
+    write!(out, "padding: {} bytes", layout.padding_size)?;
+    Ok(())
+}
+
+/// Wraps an error writing printed values to the output.
+fn write_error(e: io::Error) -> ExecError {
+    ExecError::File(anyhow!("Failed to write output: {e}"))
+}
+
+impl<O: Output> Executor<O> {
+    /// Creates an executor for a sequence of `file_count` files, which prints to `output`. The
+    /// count is needed to number tracks with `Action::AutoTrack`.
+    pub fn new(options: ExecOptions, file_count: usize, output: O) -> Self {
+        Executor {
+            options,
+            output,
+            file_count,
+            is_first_file_print: true,
+        }
+    }
+
+    /// Returns the options which actions are executed with.
+    pub fn options(&self) -> &ExecOptions {
+        &self.options
+    }
+
+    /// Returns the output which values are printed and reported to.
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    /// Returns the output mutably, e.g. to print something between the outputs of files.
+    pub fn output_mut(&mut self) -> &mut O {
+        &mut self.output
+    }
+
+    /// Consumes the executor, returning its output.
+    pub fn into_output(self) -> O {
+        self.output
+    }
+
+    /// Reads the tag of a file, which actions can then be executed on. A file without a tag is
+    /// given a new, empty tag of the default version.
+    pub fn read_file_tag(&mut self, fpath: &Path) -> Result<FileTag> {
+        let result = read_tag_for(&mut self.output, &fpath, self.options.lenient);
+        self.file_tag_from(fpath, result)
+    }
+
+    /// Like `read_file_tag`, but reads the tag of a file held in memory, e.g. read from stdin.
+    /// `fpath` only names the file in messages.
+    pub fn read_stream_tag(&mut self, fpath: &Path, bytes: &[u8]) -> Result<FileTag> {
+        self.file_tag_from(fpath, read_tag_from_bytes(bytes))
+    }
+
+    /// Prepares the tag read from a file for executing actions on it.
+    fn file_tag_from(&mut self, fpath: &Path, result: id3::Result<Tag>) -> Result<FileTag> {
+        match result {
+            Ok(tag) => {
+                verbose!(self.options, &mut self.output, 2, fpath, "Read {} tag with {} frame(s)", tag.version(), tag.frames().count());
+                Ok(FileTag { tag: tag.clone(), stored_tag: Some(tag), is_modified: false, frame_flags: vec![] })
+            },
+            Err(e) => match e.kind {
+                id3::ErrorKind::NoTag => {
+                    verbose!(self.options, &mut self.output, 2, fpath, "No tag found, starting a new {} tag", self.options.default_version);
+                    Ok(FileTag {
+                        tag: Tag::with_version(self.options.default_version), stored_tag: None, is_modified: false, frame_flags: vec![],
+                    })
+                },
+                _ => Err(anyhow!("Failed to read tag from file '{}': {e}", fpath.display())),
+            }
+        }
+    }
+
+    /// Executes actions on the tag of the file at `fpath`, which is the `index`-th file of the
+    /// sequence. Modifications are only made to `file`, except for actions which modify the file
+    /// directly (e.g. purging tags), before which `before_write` is called.
+    pub fn execute_actions(&mut self, fpath: &Path, index: usize, file: &mut FileTag, actions: &[Action],
+        before_write: &mut dyn FnMut() -> Result<()>) -> Result<Outcome, ExecError> {
+        let FileTag { tag, stored_tag, is_modified, frame_flags } = file;
+        let mut outcome = Outcome::default();
+        let mut is_first_frame_print = true;
+        let output: &mut dyn Output = &mut self.output;
+        for action in actions {
+            output.start_action(fpath, action);
+            // Aliases stand for different frames, depending on the tag's current version
+            let alias_action;
+            let action = match action {
+                Action::PrintAlias(alias) => {
+                    alias_action = Action::Print(frame_alias_frame(*alias, tag.version(), String::new()));
+                    &alias_action
+                },
+                Action::SetAlias(alias, value) => {
+                    alias_action = Action::Set(frame_alias_frame(*alias, tag.version(), value.clone()));
+                    &alias_action
+                },
+                Action::DeleteAlias(alias) => {
+                    alias_action = Action::Delete(frame_alias_frame(*alias, tag.version(), String::new()));
+                    &alias_action
+                },
+                x => x,
+            };
+            if action.is_print() {
+                if !is_first_frame_print {
+                    write!(output.writer(), "{}", self.options.frame_sep).map_err(write_error)?;
+                } else {
+                    is_first_frame_print = false;
+                    if !self.is_first_file_print {
+                        write!(output.writer(), "{}", self.options.file_sep).map_err(write_error)?;
+                    } else {
+                        self.is_first_file_print = false;
+                    }
+                }
+                if self.options.with_filename && !matches!(action, Action::TagInfo) {
+                    write!(output.writer(), "{}: ", fpath.display()).map_err(write_error)?;
+                }
+            }
+            match action {
+                Action::Print(frame) => {
+                    match print_tag_frame_query(output, tag, frame, &self.options, fpath) {
+                        Ok(true) => {
+                            verbose!(self.options, output, 2, fpath, "Printed {}", frame_to_string(frame).unwrap_or_default());
+                        },
+                        Ok(false) => {
+                            outcome.not_found = true;
+                        },
+                        Err(e) => return Err(ExecError::File(e)),
+                    }
+                },
+                Action::Set(frame) if self.options.no_clobber && tag_has_frame(tag, frame) => {
+                    verbose!(self.options, output, 2, fpath, "Kept existing {}", frame_to_string(frame).unwrap_or_default());
+                },
+                Action::Set(frame) => {
+                    let mut new_frame = text_values::split_text_values(frame.clone(), &self.options.value_sep);
+                    if self.options.genre_names {
+                        new_frame = resolve_genre_frame(new_frame);
+                    }
+                    match set_tag_frame(tag, new_frame) {
+                        Ok(overwritten) => {
+                            if let (Some(width), "TRCK" | "TPOS") = (self.options.track_pad, frame.id()) {
+                                numbering::pad_tag_numbers(tag, frame.id(), width);
+                            }
+                            verbose!(self.options, output, 1, fpath, "{} {}", if overwritten { "Overwrote" } else { "Created" },
+                                frame_to_string(frame).unwrap_or_default());
+                            *is_modified = true;
+                        },
+                        Err(e) => return Err(ExecError::Usage(e)),
+                    }
+                },
+                Action::Delete(frame) => {
+                    match delete_tag_frame(output, tag, frame, fpath) {
+                        Ok(modified) => {
+                            if modified {
+                                verbose!(self.options, output, 1, fpath, "Deleted {}", frame_to_string(frame).unwrap_or_default());
+                            }
+                            *is_modified |= modified;
+                        },
+                        Err(e) => return Err(ExecError::File(e)),
+                    }
+                },
+                Action::Convert(opt) => {
+                    let old_version = tag.version();
+                    match convert_tag(tag, *opt) {
+                        Ok(modified) => {
+                            if modified {
+                                verbose!(self.options, output, 1, fpath, "Converted tag from {old_version} to {}", tag.version());
+                            } else {
+                                verbose!(self.options, output, 2, fpath, "Tag is already {}, not converted", tag.version());
+                            }
+                            *is_modified |= modified;
+                        },
+                        Err(e) => return Err(ExecError::File(e)),
+                    }
+                },
+                Action::Purge(PurgeOpt::Id3v1) if self.options.dry_run => {
+                    verbose!(self.options, output, 1, fpath, "Would purge ID3v1 tag, if any");
+                },
+                Action::Purge(PurgeOpt::Ape) if self.options.dry_run => {
+                    verbose!(self.options, output, 1, fpath, "Would purge APE tag, if any");
+                },
+                Action::Purge(PurgeOpt::Id3v1) => {
+                    before_write().map_err(ExecError::File)?;
+                    match id3::v1::Tag::remove_from_path(fpath) {
+                        Ok(true) => verbose!(self.options, output, 1, fpath, "Purged ID3v1 tag"),
+                        Ok(false) => verbose!(self.options, output, 2, fpath, "No ID3v1 tag to purge"),
+                        Err(e) => return Err(ExecError::File(anyhow!("Failed to purge the ID3v1 tag of '{}': {e}", fpath.display()))),
+                    }
+                },
+                Action::Purge(PurgeOpt::Ape) => {
+                    before_write().map_err(ExecError::File)?;
+                    match ape::remove_ape_tag(&fpath) {
+                        Ok(true) => verbose!(self.options, output, 1, fpath, "Purged APE tag"),
+                        Ok(false) => verbose!(self.options, output, 2, fpath, "No APE tag to purge"),
+                        Err(e) => return Err(ExecError::File(e)),
+                    }
+                },
+                Action::Purge(opt) => {
+                    if match opt {
+                        PurgeOpt::Id3v22 => tag.version() == Version::Id3v22,
+                        PurgeOpt::Id3v23 => tag.version() == Version::Id3v23,
+                        PurgeOpt::Id3v24 => tag.version() == Version::Id3v24,
+                        PurgeOpt::All => true,
+                        PurgeOpt::Id3v1 | PurgeOpt::Ape => unreachable!(),
+                    } {
+                        if self.options.dry_run {
+                            verbose!(self.options, output, 1, fpath, "Would purge {} tag", tag.version());
+                            *tag = Tag::with_version(self.options.default_version);
+                            *stored_tag = None;
+                            *is_modified = false;
+                            continue;
+                        }
+                        before_write().map_err(ExecError::File)?;
+                        remove_tag(&fpath).map_err(ExecError::File)?;
+                        verbose!(self.options, output, 1, fpath, "Purged {} tag", tag.version());
+                        *tag = Tag::with_version(self.options.default_version);
+                        *stored_tag = None;
+                        *is_modified = false;
+                    }
+                },
+                Action::PrintId3v1(field) => {
+                    match read_id3v1_tag(&fpath) {
+                        Ok(v1_tag) => {
+                            if print_id3v1_field_query(output, v1_tag.as_ref(), *field, self.options.missing.as_deref(), fpath).map_err(write_error)? {
+                                verbose!(self.options, output, 2, fpath, "Printed ID3v1 {}", id3v1_field_name(*field));
+                            } else {
+                                outcome.not_found = true;
+                            }
+                        },
+                        Err(e) => return Err(ExecError::File(e)),
+                    }
+                },
+                Action::SetLength => {
+                    if FileFormat::detect(&fpath) != FileFormat::Mpeg {
+                        return Err(ExecError::File(anyhow!("Cannot compute the length of '{}': Only MPEG files are supported", fpath.display())));
+                    }
+                    let info = read_file_audio_info(&fpath).map_err(ExecError::File)?;
+                    let overwritten = set_tag_frame(tag, Frame::text("TLEN", info.duration_ms.to_string())).map_err(ExecError::Usage)?;
+                    verbose!(self.options, output, 1, fpath, "{} TLEN to {} ms", if overwritten { "Overwrote" } else { "Set" }, info.duration_ms);
+                    *is_modified = true;
+                },
+                Action::SetChecksum => {
+                    let digest = audio_md5(&fpath).map_err(ExecError::File)?;
+                    if get_audio_md5(tag) == Some(digest.as_str()) {
+                        verbose!(self.options, output, 2, fpath, "Audio checksum is up to date");
+                        continue;
+                    }
+                    let overwritten = set_tag_frame(tag, audio_md5_frame(&digest)).map_err(ExecError::Usage)?;
+                    verbose!(self.options, output, 1, fpath, "{} TXXX[{AUDIO_MD5_DESCRIPTION}] to {digest}", if overwritten { "Overwrote" } else { "Set" });
+                    *is_modified = true;
+                },
+                Action::VerifyChecksum => {
+                    let Some(stored) = get_audio_md5(tag).map(str::to_ascii_lowercase) else {
+                        verbose!(self.options, output, 1, fpath, "No audio checksum stored in TXXX[{AUDIO_MD5_DESCRIPTION}]");
+                        outcome.not_found = true;
+                        continue;
+                    };
+                    let digest = audio_md5(&fpath).map_err(ExecError::File)?;
+                    if digest == stored {
+                        verbose!(self.options, output, 1, fpath, "Audio checksum matches");
+                    } else {
+                        writeln!(output.writer(), "{}: audio checksum mismatch (stored {stored}, computed {digest})", fpath.display())
+                            .map_err(write_error)?;
+                        outcome.problems_found = true;
+                    }
+                },
+                Action::SyncId3v1 => {
+                    if FileFormat::detect(&fpath) != FileFormat::Mpeg {
+                        return Err(ExecError::File(anyhow!("Cannot write ID3v1 tag to '{}': Only MPEG files support ID3v1", fpath.display())));
+                    }
+                    if self.options.dry_run {
+                        verbose!(self.options, output, 1, fpath, "Would write ID3v1.1 tag");
+                        continue;
+                    }
+                    before_write().and_then(|_| write_id3v1_tag(output, &id3v1_tag_from(tag), &fpath)).map_err(ExecError::File)?;
+                    verbose!(self.options, output, 1, fpath, "Wrote ID3v1.1 tag");
+                },
+                Action::ExportLyrics(template) => {
+                    match export_lyrics(output, tag, &fpath, template.as_deref()) {
+                        Ok(true) => {
+                            verbose!(self.options, output, 1, fpath, "Exported lyrics to '{}'",
+                                lyrics_export_path(&fpath, template.as_deref()).display());
+                        },
+                        Ok(false) => {
+                            outcome.not_found = true;
+                        },
+                        Err(e) => return Err(ExecError::File(e)),
+                    }
+                },
+                Action::PrintChapters => {
+                    if chapters::print_chapters(output, tag, &fpath).map_err(write_error)? {
+                        verbose!(self.options, output, 2, fpath, "Printed chapters");
+                    } else {
+                        outcome.not_found = true;
+                    }
+                },
+                Action::ExportChapters(format) => {
+                    if chapters::export_chapters(output, tag, &fpath, *format).map_err(write_error)? {
+                        verbose!(self.options, output, 2, fpath, "Exported chapters");
+                    } else {
+                        outcome.not_found = true;
+                    }
+                },
+                Action::PrintRating(unit) => {
+                    if rating::print_rating(output, tag, self.options.rating_owner.as_deref(), self.options.rating_scale, *unit,
+                        self.options.missing.as_deref(), &fpath).map_err(write_error)? {
+                        verbose!(self.options, output, 2, fpath, "Printed rating");
+                    } else {
+                        outcome.not_found = true;
+                    }
+                },
+                Action::SetRating(rating) => {
+                    let overwritten = rating::set_rating(tag, self.options.rating_owner.as_deref(), self.options.rating_scale, *rating);
+                    verbose!(self.options, output, 1, fpath, "{} rating {rating}", if overwritten { "Overwrote" } else { "Set" });
+                    *is_modified = true;
+                },
+                Action::PrintCompilation => {
+                    write!(output.writer(), "{}", if compilation::is_compilation(tag) { "on" } else { "off" }).map_err(write_error)?;
+                    verbose!(self.options, output, 2, fpath, "Printed compilation flag");
+                },
+                Action::SetCompilation(value) => {
+                    if compilation::set_compilation(tag, *value) {
+                        verbose!(self.options, output, 1, fpath, "{} compilation flag", if *value { "Set" } else { "Cleared" });
+                        *is_modified = true;
+                    }
+                },
+                Action::IncrementPlayCount(with_popm) => {
+                    let popm_owner = with_popm.then_some(self.options.rating_owner.as_deref());
+                    match play_count::increment_play_count(tag, popm_owner) {
+                        Ok(counter) => {
+                            verbose!(self.options, output, 1, fpath, "Incremented play count to {counter}");
+                            *is_modified = true;
+                        },
+                        Err(e) => return Err(ExecError::File(e)),
+                    }
+                },
+                Action::AppendValue(frame) => {
+                    let frame = if self.options.genre_names { &resolve_genre_frame(frame.clone()) } else { frame };
+                    let values = get_content_text(frame).map(|x| x.split(self.options.value_sep.as_str()));
+                    match values {
+                        Ok(values) => {
+                            let existed = text_values::append_text_values(tag, frame.id(), values);
+                            verbose!(self.options, output, 1, fpath, "{} {}", if existed { "Appended to" } else { "Created" },
+                                frame_to_string(frame).unwrap_or_default());
+                            *is_modified = true;
+                        },
+                        Err(e) => return Err(ExecError::Usage(e)),
+                    }
+                },
+                Action::RemoveValue(frame) => {
+                    let value = match get_content_text(frame) {
+                        Ok(x) => x,
+                        Err(e) => return Err(ExecError::Usage(e)),
+                    };
+                    if text_values::remove_text_value(tag, frame.id(), value) {
+                        verbose!(self.options, output, 1, fpath, "Removed '{value}' from {}", frame.id());
+                        *is_modified = true;
+                    } else {
+                        report_file_error(output, fpath, ErrorKind::NotFound, format_args!("Could not remove '{value}' from {}: Value not found", frame.id()));
+                    }
+                },
+                Action::PrintNumberPart(part) => {
+                    if numbering::print_number_part(output, tag, *part, self.options.missing.as_deref(), fpath).map_err(write_error)? {
+                        verbose!(self.options, output, 2, fpath, "Printed {}", numbering::number_part_name(*part));
+                    } else {
+                        outcome.not_found = true;
+                    }
+                },
+                Action::SetNumberPart(part, value) => {
+                    let overwritten = numbering::set_number_part(tag, *part, Some(*value));
+                    if let Some(width) = self.options.track_pad {
+                        numbering::pad_tag_numbers(tag, numbering::number_part_frame_id(*part), width);
+                    }
+                    verbose!(self.options, output, 1, fpath, "{} {}", if overwritten { "Overwrote" } else { "Created" },
+                        numbering::number_part_name(*part));
+                    *is_modified = true;
+                },
+                Action::DeleteNumberPart(part) => {
+                    if numbering::set_number_part(tag, *part, None) {
+                        verbose!(self.options, output, 1, fpath, "Deleted {}", numbering::number_part_name(*part));
+                        *is_modified = true;
+                    } else {
+                        report_file_error(output, fpath, ErrorKind::NotFound, format_args!("Could not delete {}: Not found", numbering::number_part_name(*part)));
+                    }
+                },
+                Action::AutoTrack(start, total) => {
+                    let number = start + index as u32;
+                    numbering::set_number_part(tag, NumberPart::TrackNumber, Some(number));
+                    match total {
+                        Some(AutoTrackTotal::Fixed(x)) => {
+                            numbering::set_number_part(tag, NumberPart::TrackTotal, Some(*x));
+                        },
+                        Some(AutoTrackTotal::Auto) => {
+                            let total = start + self.file_count as u32 - 1;
+                            numbering::set_number_part(tag, NumberPart::TrackTotal, Some(total));
+                        },
+                        None => (),
+                    }
+                    if let Some(width) = self.options.track_pad {
+                        numbering::pad_tag_numbers(tag, "TRCK", width);
+                    }
+                    verbose!(self.options, output, 1, fpath, "Numbered track {}", tag.get("TRCK").map(|x| x.content().to_string()).unwrap_or_default());
+                    *is_modified = true;
+                },
+                Action::NormalizeNumbers => {
+                    let width = self.options.track_pad.unwrap_or(numbering::DEFAULT_TRACK_PAD);
+                    for id in ["TRCK", "TPOS"] {
+                        if numbering::pad_tag_numbers(tag, id, width) {
+                            verbose!(self.options, output, 1, fpath, "Padded {id} to {width} digits");
+                            *is_modified = true;
+                        }
+                    }
+                },
+                Action::PrintAlias(_) | Action::SetAlias(..) | Action::DeleteAlias(_) => {
+                    unreachable!("Aliases are resolved before handling actions");
+                },
+                Action::ExtractGeob(description, template) => {
+                    match extract_geob(output, tag, description, &fpath, template) {
+                        Ok(true) => {
+                            verbose!(self.options, output, 1, fpath, "Extracted GEOB[{description}] to '{}'",
+                                expand_path_template(&fpath, template).display());
+                        },
+                        Ok(false) => {
+                            outcome.not_found = true;
+                        },
+                        Err(e) => return Err(ExecError::File(e)),
+                    }
+                },
+                Action::PrintTocs => {
+                    if chapters::print_tocs(output, tag, &fpath).map_err(write_error)? {
+                        verbose!(self.options, output, 2, fpath, "Printed tables of contents");
+                    } else {
+                        outcome.not_found = true;
+                    }
+                },
+                Action::AutoToc => {
+                    let frame = chapters::auto_toc_frame(tag);
+                    let overwritten = chapters::add_toc(tag, frame);
+                    verbose!(self.options, output, 1, fpath, "{} CTOC[{}]", if overwritten { "Overwrote" } else { "Created" },
+                        chapters::AUTO_TOC_ID);
+                    *is_modified = true;
+                },
+                Action::ImportChapters(chapters) => {
+                    chapters::import_chapters(tag, chapters);
+                    verbose!(self.options, output, 1, fpath, "Imported {} chapters", chapters.len());
+                    *is_modified = true;
+                },
+                Action::TagInfo => {
+                    print_tag_info(output, &fpath).map_err(ExecError::File)?;
+                },
+                Action::PrintRaw(frame, binary) => {
+                    let layout = read_unmodified_tag_layout(&fpath, *is_modified).map_err(ExecError::File)?;
+                    match raw_frame_bytes(tag, layout.as_ref(), frame) {
+                        Ok(Some(bytes)) => {
+                            if *binary {
+                                output.writer().write_all(&bytes).map_err(write_error)?;
+                            } else {
+                                write!(output.writer(), "{}", hexdump(&bytes)).map_err(write_error)?;
+                            }
+                            verbose!(self.options, output, 2, fpath, "Printed raw {}", frame_to_string(frame).unwrap_or_default());
+                        },
+                        Ok(None) => {
+                            report_file_error(output, fpath, ErrorKind::NotFound,
+                                format_args!("Could not print raw {}: Frame not found", frame_to_string(frame).unwrap_or_default()));
+                            outcome.not_found = true;
+                        },
+                        Err(e) => return Err(ExecError::File(e)),
+                    }
+                },
+                Action::DumpTag(template) => {
+                    let bytes = read_raw_tag(&fpath)
+                        .map_err(|e| ExecError::File(anyhow!("Failed to read tag from file '{}': {e}", fpath.display())))?;
+                    if bytes.is_empty() {
+                        report_file_error(output, fpath, ErrorKind::NotFound, "Could not dump tag: File has no tag");
+                        outcome.not_found = true;
+                        continue;
+                    }
+                    let dest = expand_path_template(&fpath, template);
+                    if let Err(e) = std::fs::write(&dest, &bytes) {
+                        return Err(ExecError::File(anyhow!("Failed to dump tag to '{}': {e}", dest.display())));
+                    }
+                    verbose!(self.options, output, 1, fpath, "Dumped {} byte(s) of tag to '{}'", bytes.len(), dest.display());
+                },
+                Action::RestoreTag(template) => {
+                    let source = expand_path_template(&fpath, template);
+                    let bytes = std::fs::read(&source)
+                        .map_err(|e| ExecError::File(anyhow!("Failed to read tag from '{}': {e}", source.display())))?;
+                    let restored = Tag::read_from2(Cursor::new(&bytes))
+                        .map_err(|e| ExecError::File(anyhow!("Cannot restore tag from '{}': {e}", source.display())))?;
+                    if self.options.dry_run {
+                        verbose!(self.options, output, 1, fpath, "Would restore tag from '{}'", source.display());
+                        continue;
+                    }
+                    before_write().and_then(|_| write_raw_tag(&fpath, &bytes)).map_err(ExecError::File)?;
+                    verbose!(self.options, output, 1, fpath, "Restored tag from '{}'", source.display());
+                    *tag = restored.clone();
+                    *stored_tag = Some(restored);
+                    *is_modified = false;
+                },
+                Action::Fix(kinds) => {
+                    let layout = match read_unmodified_tag_layout(&fpath, *is_modified) {
+                        Ok(x) => x,
+                        Err(e) => return Err(ExecError::File(e)),
+                    };
+                    for fix in fix::fix_tag(tag, layout.as_ref(), kinds) {
+                        verbose!(self.options, output, 1, fpath, "{fix}");
+                        *is_modified = true;
+                    }
+                },
+                Action::NormalizeFrames => {
+                    let form = self.options.normalize.unwrap_or(NormalizationForm::Nfc);
+                    let normalize = |x: &Frame| map_frame_text(x, |y| normalization::normalize(y, form));
+                    for (frame, _) in transform_tag_frames(tag, normalize) {
+                        verbose!(self.options, output, 1, fpath, "Normalized {}", frame_to_string(&frame).unwrap_or_default());
+                        *is_modified = true;
+                    }
+                },
+                Action::ChangeCase(id, change) => {
+                    let change_case = |x: &str| match change {
+                        CaseChange::Title => title_case(x),
+                        CaseChange::Upper => x.to_uppercase(),
+                        CaseChange::Lower => x.to_lowercase(),
+                    };
+                    let transform = |x: &Frame| Some(x).filter(|y| y.id() == id).and_then(|y| map_frame_values(y, change_case));
+                    for (frame, _) in transform_tag_frames(tag, transform) {
+                        verbose!(self.options, output, 1, fpath, "Changed case of {}", frame_to_string(&frame).unwrap_or_default());
+                        *is_modified = true;
+                    }
+                },
+                Action::CopyFrom(source) => {
+                    *tag = (**source).clone();
+                    verbose!(self.options, output, 1, fpath, "Copied {} tag with {} frame(s)", tag.version(), tag.frames().count());
+                    *is_modified = true;
+                },
+                Action::SetFrameFlag(id, flag, value) => {
+                    if tag.get(id).is_none() {
+                        report_file_error(output, fpath, ErrorKind::NotFound, format_args!("Could not set flags of {id}: Frame not found"));
+                        outcome.not_found = true;
+                        continue;
+                    }
+                    frame_flags.push((id.clone(), *flag, *value));
+                },
+                Action::Where(filter) => {
+                    if !filter.matches(tag) {
+                        verbose!(self.options, output, 2, fpath, "Tag does not match --where, skipped remaining actions");
+                        break;
+                    }
+                },
+                Action::CopyFrame(query, _) | Action::CopyValue(_, query) | Action::MoveValue(_, query)
+                    if self.options.no_clobber && tag_has_frame(tag, query) => {
+                    verbose!(self.options, output, 2, fpath, "Kept existing {}", frame_to_string(query).unwrap_or_default());
+                },
+                Action::CopyFrame(query, frame) => {
+                    let frame = match frame {
+                        Some(x) => x.clone(),
+                        None => {
+                            report_file_error(output, fpath, ErrorKind::NotFound, format_args!("Could not copy {}: Frame not found in source file", frame_to_string(query).unwrap_or_default()));
+                            outcome.not_found = true;
+                            continue;
+                        },
+                    };
+                    match set_tag_frame(tag, frame) {
+                        Ok(overwritten) => {
+                            verbose!(self.options, output, 1, fpath, "{} {}", if overwritten { "Overwrote" } else { "Created" },
+                                frame_to_string(query).unwrap_or_default());
+                            *is_modified = true;
+                        },
+                        Err(e) => return Err(ExecError::Usage(e)),
+                    }
+                },
+                Action::CopyValue(from, to) | Action::MoveValue(from, to) => {
+                    let is_move = matches!(action, Action::MoveValue(..));
+                    let from_str = frame_to_string(from).unwrap_or_default();
+                    let to_str = frame_to_string(to).unwrap_or_default();
+                    let value = tag.frames()
+                        .find(|x| frames_query_equal(x, from).unwrap_or(false))
+                        .and_then(|x| frame_value(x).map(str::to_string));
+                    let value = match value {
+                        Some(x) => x,
+                        None => {
+                            report_file_error(output, fpath, ErrorKind::NotFound, format_args!("Could not {} {from_str}: Frame not found", if is_move { "move" } else { "copy" }));
+                            outcome.not_found = true;
+                            continue;
+                        },
+                    };
+                    if is_move {
+                        retain_tag_frames(tag, |x| !frames_query_equal(x, from).unwrap_or(false));
+                    }
+                    let frame = with_frame_value(to, &value).expect("--copy and --move only take frames with a value");
+                    set_tag_frame(tag, frame).map_err(ExecError::Usage)?;
+                    verbose!(self.options, output, 1, fpath, "{} {from_str} to {to_str}", if is_move { "Moved" } else { "Copied" });
+                    *is_modified = true;
+                },
+                Action::MergeFrom(source, overwrite) => {
+                    for frame in source.frames() {
+                        let frame_str = frame_to_string(frame).unwrap_or_default();
+                        if tag.frames().any(|x| x == frame) {
+                            verbose!(self.options, output, 2, fpath, "{frame_str} already present");
+                        } else if !conflicts_with_tag(tag, frame) {
+                            tag.add_frame(frame.clone());
+                            verbose!(self.options, output, 1, fpath, "Merged {frame_str}");
+                            *is_modified = true;
+                        } else if *overwrite {
+                            tag.add_frame(frame.clone());
+                            verbose!(self.options, output, 1, fpath, "Overwrote {frame_str}");
+                            *is_modified = true;
+                        } else {
+                            verbose!(self.options, output, 2, fpath, "Kept existing {frame_str}");
+                        }
+                    }
+                },
+                Action::Dedupe => {
+                    let layout = match read_unmodified_tag_layout(&fpath, *is_modified) {
+                        Ok(x) => x,
+                        Err(e) => return Err(ExecError::File(e)),
+                    };
+                    for removal in dedupe::dedupe_tag(tag, layout.as_ref()) {
+                        verbose!(self.options, output, 1, fpath, "{removal}");
+                        *is_modified = true;
+                    }
+                },
+                Action::StripEmpty => {
+                    let is_empty = |x: &Frame| check::is_frame_empty(x) && matches!(x.content(),
+                        Content::Text(_) | Content::Link(_) | Content::ExtendedText(_) | Content::ExtendedLink(_));
+                    for frame in retain_tag_frames(tag, |x| !is_empty(x)) {
+                        verbose!(self.options, output, 1, fpath, "Deleted empty {}", frame_to_string(&frame).unwrap_or_default());
+                        *is_modified = true;
+                    }
+                },
+                Action::Trim => {
+                    for (frame, _) in transform_tag_frames(tag, |x| map_frame_text(x, trim_text)) {
+                        verbose!(self.options, output, 1, fpath, "Trimmed {}", frame_to_string(&frame).unwrap_or_default());
+                        *is_modified = true;
+                    }
+                },
+                Action::FixEncoding => {
+                    for (frame, recovered) in transform_tag_frames(tag, mojibake::recover_frame_utf8) {
+                        verbose!(self.options, output, 1, fpath, "Fixed encoding of {}: '{}' -> '{}'",
+                            frame_to_string(&frame).unwrap_or_else(|_| frame.id().to_string()),
+                            frame.content(), recovered.content());
+                        *is_modified = true;
+                    }
+                },
+                Action::Check => {
+                    let layout = match read_unmodified_tag_layout(&fpath, *is_modified) {
+                        Ok(x) => x,
+                        Err(e) => return Err(ExecError::File(e)),
+                    };
+                    let problems = check::check_tag(tag, layout.as_ref());
+                    for problem in &problems {
+                        writeln!(output.writer(), "{}: {}: {}", fpath.display(), problem.subject, problem.message).map_err(write_error)?;
+                    }
+                    if problems.is_empty() {
+                        verbose!(self.options, output, 1, fpath, "No problems found");
+                    } else {
+                        outcome.problems_found = true;
+                    }
+                },
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Writes the tag of a file back to it, if it was modified, calling `before_write` first.
+    /// Nothing is written if the modifications resulted in an identical tag, unless padding was
+    /// requested explicitly. Frame flags are set afterwards, in the stored tag. Returns whether
+    /// the file was written.
+    pub fn write_file_tag(&mut self, fpath: &Path, file: &mut FileTag, actions: &[Action],
+        before_write: &mut dyn FnMut() -> Result<()>) -> Result<bool> {
+        let tag = &mut file.tag;
+        if let Some(encoding) = self.options.encoding.filter(|_| file.is_modified) {
+            set_text_encoding(tag, encoding);
+        }
+        // Explicit padding is a request to rewrite the tag, even if it is unchanged
+        let is_unchanged = file.is_modified && self.options.padding.is_none()
+            && file.stored_tag.as_ref().is_some_and(|x| is_tag_unchanged(&fpath, x, tag));
+        let mut is_written = false;
+        if is_unchanged {
+            verbose!(self.options, &mut self.output, 1, fpath, "Tag unchanged, nothing written");
+        } else if file.is_modified && self.options.dry_run {
+            verbose!(self.options, &mut self.output, 1, fpath, "Would write {} tag with {} frame(s)", tag.version(), tag.frames().count());
+        } else if file.is_modified {
+            before_write()?;
+            try_write_tag(tag, &fpath, tag.version(), self.options.padding, self.options.atomic)?;
+            verbose!(self.options, &mut self.output, 1, fpath, "Wrote {} tag with {} frame(s)", tag.version(), tag.frames().count());
+            is_written = true;
+        } else if file.frame_flags.is_empty() {
+            // Only worth reporting by default if something could have been written
+            let level = if actions.iter().all(Action::is_print) { 2 } else { 1 };
+            verbose!(self.options, &mut self.output, level, fpath, "Tag not modified, nothing written");
+        }
+        is_written |= self.write_frame_flags(fpath, &file.frame_flags, before_write)?;
+        Ok(is_written)
+    }
+
+    /// Like `write_file_tag`, but writes the tag to a file held in memory, e.g. to be written to
+    /// stdout. `fpath` only names the file in messages. Returns whether the tag was written.
+    pub fn write_stream_tag(&mut self, fpath: &Path, bytes: &mut Vec<u8>, file: &mut FileTag) -> Result<bool> {
+        let tag = &mut file.tag;
+        if !file.is_modified {
+            verbose!(self.options, &mut self.output, 2, fpath, "Tag not modified, nothing written");
+            return Ok(false);
+        }
+        if let Some(encoding) = self.options.encoding {
+            set_text_encoding(tag, encoding);
+        }
+        if self.options.dry_run {
+            verbose!(self.options, &mut self.output, 1, fpath, "Would write {} tag with {} frame(s)", tag.version(), tag.frames().count());
+            return Ok(false);
+        }
+        write_tag_to_bytes(tag, bytes, tag.version(), self.options.padding)?;
+        verbose!(self.options, &mut self.output, 1, fpath, "Wrote {} tag with {} frame(s)", tag.version(), tag.frames().count());
+        Ok(true)
+    }
+
+    /// Sets (or clears) frame header flags in the tag stored in a file, after it was written.
+    /// Returns whether any flags were changed.
+    fn write_frame_flags(&mut self, fpath: &Path, changes: &[(String, FrameFlag, bool)],
+        before_write: &mut dyn FnMut() -> Result<()>) -> Result<bool> {
+        if changes.is_empty() {
+            return Ok(false);
+        }
+        if self.options.dry_run {
+            for (id, flag, value) in changes {
+                verbose!(self.options, &mut self.output, 1, fpath, "Would {} {} flag of {id}", if *value { "set" } else { "clear" }, flag.name());
+            }
+            return Ok(false);
+        }
+        let patches = frame_flag_patches(&fpath, changes)?;
+        if patches.is_empty() {
+            verbose!(self.options, &mut self.output, 1, fpath, "Frame flags unchanged, nothing written");
+            return Ok(false);
+        }
+        before_write()?;
+        write_frame_flag_patches(&fpath, &patches)?;
+        for (id, flag, value) in changes {
+            verbose!(self.options, &mut self.output, 1, fpath, "{} {} flag of {id}", if *value { "Set" } else { "Cleared" }, flag.name());
+        }
+        Ok(true)
+    }
+}
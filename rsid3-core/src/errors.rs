@@ -0,0 +1,98 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::action::Action;
+use std::fmt::Display;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Represents the category of a reported error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A queried frame or field was not found.
+    NotFound,
+    /// The command line was invalid, or requested an unsupported operation.
+    Usage,
+    /// A file or its tag could not be read or written.
+    Io,
+    /// Something noteworthy happened, but processing went on as usual.
+    Warning,
+    /// Not an error, but a note on what is being done to a file, as requested by `verbosity`.
+    Info,
+}
+
+impl ErrorKind {
+    /// Returns the name of the kind, as included in JSON reports.
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::NotFound => "not-found",
+            ErrorKind::Usage => "usage",
+            ErrorKind::Io => "io",
+            ErrorKind::Warning => "warning",
+            ErrorKind::Info => "info",
+        }
+    }
+}
+
+/// Receives everything the engine outputs while executing actions: the values printed by print
+/// actions, and reports of errors which did not stop the execution, warnings, and notes on what
+/// is being done. The command line writes values to stdout and reports to stderr, whereas an
+/// application embedding the engine can collect them, e.g. with [`CollectedOutput`].
+pub trait Output {
+    /// Returns the writer which printed values are written to.
+    fn writer(&mut self) -> &mut dyn Write;
+
+    /// Receives a report concerning the given file, or no file in particular.
+    fn report(&mut self, fpath: Option<&Path>, kind: ErrorKind, message: &dyn Display);
+
+    /// Called before an action is executed on a file, e.g. to attribute subsequent reports to it.
+    fn start_action(&mut self, _fpath: &Path, _action: &Action) {}
+}
+
+/// Represents a report received by a [`CollectedOutput`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub fpath: Option<PathBuf>,
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+/// An output which collects printed values and reports in memory.
+#[derive(Debug, Default, Clone)]
+pub struct CollectedOutput {
+    /// All printed values, with the separators between them.
+    pub values: Vec<u8>,
+    pub reports: Vec<Report>,
+}
+
+impl Output for CollectedOutput {
+    fn writer(&mut self) -> &mut dyn Write {
+        &mut self.values
+    }
+
+    fn report(&mut self, fpath: Option<&Path>, kind: ErrorKind, message: &dyn Display) {
+        self.reports.push(Report { fpath: fpath.map(Path::to_path_buf), kind, message: message.to_string() });
+    }
+}
+
+/// Reports an error which is not specific to a file to an output.
+pub fn report_error(output: &mut dyn Output, kind: ErrorKind, message: impl Display) {
+    output.report(None, kind, &message);
+}
+
+/// Reports an error concerning the given file to an output.
+pub fn report_file_error(output: &mut dyn Output, fpath: impl AsRef<Path>, kind: ErrorKind, message: impl Display) {
+    output.report(Some(fpath.as_ref()), kind, &message);
+}
@@ -0,0 +1,117 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::chapters::format_chapter_time;
+use anyhow::{anyhow, Result};
+use id3::Frame;
+
+/// Names of the predefined ETCO event types, as listed in the ID3v2.4 standard.
+const EVENT_TYPE_NAMES: &[(u8, &str)] = &[
+    (0x00, "padding"),
+    (0x01, "end of initial silence"),
+    (0x02, "intro start"),
+    (0x03, "main part start"),
+    (0x04, "outro start"),
+    (0x05, "outro end"),
+    (0x06, "verse start"),
+    (0x07, "refrain start"),
+    (0x08, "interlude start"),
+    (0x09, "theme start"),
+    (0x0A, "variation start"),
+    (0x0B, "key change"),
+    (0x0C, "time change"),
+    (0x0D, "momentary unwanted noise"),
+    (0x0E, "sustained noise"),
+    (0x0F, "sustained noise end"),
+    (0x10, "intro end"),
+    (0x11, "main part end"),
+    (0x12, "verse end"),
+    (0x13, "refrain end"),
+    (0x14, "theme end"),
+    (0x15, "profanity"),
+    (0x16, "profanity end"),
+    (0xFD, "audio end"),
+    (0xFE, "audio file ends"),
+];
+
+/// The unit of the timestamps of an ETCO frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventTimestampFormat {
+    Mpeg,
+    Ms,
+}
+
+/// The decoded contents of an ETCO frame, which the id3 crate does not parse on its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventTimingCodes {
+    pub timestamp_format: EventTimestampFormat,
+    /// Event types and their timestamps, in the order they are stored.
+    pub events: Vec<(u8, u32)>,
+}
+
+impl EventTimingCodes {
+    /// Formats the timestamp of an event, either as `hh:mm:ss.fff` or as an MPEG frame number.
+    pub fn format_time(&self, time: u32) -> String {
+        match self.timestamp_format {
+            EventTimestampFormat::Ms => format_chapter_time(time),
+            EventTimestampFormat::Mpeg => format!("frame {time}"),
+        }
+    }
+
+    /// Formats the events one per line, as "TIME EVENT".
+    pub fn format_events(&self) -> String {
+        self.events.iter()
+            .map(|(event_type, time)| format!("{} {}", self.format_time(*time), event_type_name(*event_type)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Returns the human-readable name of an ETCO event type. Types which the standard reserves or
+/// leaves for the user are described by their number.
+pub fn event_type_name(event_type: u8) -> String {
+    if let Some((_, name)) = EVENT_TYPE_NAMES.iter().find(|(x, _)| *x == event_type) {
+        return name.to_string();
+    }
+    match event_type {
+        0xE0..=0xEF => format!("not predefined synch {:X}", event_type - 0xE0),
+        x => format!("reserved event 0x{x:02X}"),
+    }
+}
+
+/// Decodes the events of an ETCO frame.
+pub fn get_event_timing_codes(frame: &Frame) -> Result<EventTimingCodes> {
+    let data = &frame.content().to_unknown()?.data;
+    let timestamp_format = match data.first() {
+        Some(1) => EventTimestampFormat::Mpeg,
+        Some(2) => EventTimestampFormat::Ms,
+        Some(x) => return Err(anyhow!("ETCO frame has an invalid timestamp format ({x})")),
+        None => return Err(anyhow!("ETCO frame is empty")),
+    };
+    let mut events = vec![];
+    let mut rest = &data[1..];
+    while !rest.is_empty() {
+        // Event type 0xFF means that one more byte of event type follows, which is only
+        // meaningful to future versions of the standard, so the last byte is taken as the type
+        let type_len = rest.iter().position(|&x| x != 0xFF).map_or(rest.len(), |x| x + 1);
+        if rest.len() < type_len + 4 {
+            return Err(anyhow!("ETCO frame has a truncated event ({} bytes)", rest.len()));
+        }
+        let time = u32::from_be_bytes(rest[type_len..type_len + 4].try_into().unwrap());
+        events.push((rest[type_len - 1], time));
+        rest = &rest[type_len + 4..];
+    }
+    Ok(EventTimingCodes { timestamp_format, events })
+}
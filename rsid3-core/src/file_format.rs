@@ -0,0 +1,236 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+
+/// Extensions of files picked up when walking directories, in lowercase.
+pub const SUPPORTED_EXTENSIONS: &[&str] = &["mp3", "aif", "aiff", "aifc", "wav", "wave"];
+
+/// Size of the root chunk header of chunk-based formats (ID, size and form type).
+const ROOT_CHUNK_HEADER_SIZE: u64 = 12;
+/// Size of a chunk header of chunk-based formats (ID and size).
+const CHUNK_HEADER_SIZE: u64 = 8;
+
+/// Represents one of the audio file formats that can carry ID3v2 tags.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FileFormat {
+    /// MPEG audio, with the tag prepended to the audio stream.
+    Mpeg,
+    /// AIFF, with the tag stored in an "ID3 " chunk.
+    Aiff,
+    /// WAV, with the tag stored in an "ID3 " or "id3 " RIFF chunk.
+    Wav,
+}
+
+impl FileFormat {
+    /// Detects the format of a file by its magic bytes, falling back to its extension.
+    pub fn detect(fpath: &impl AsRef<Path>) -> FileFormat {
+        let mut magic = [0; 12];
+        let is_magic_read = File::open(fpath)
+            .and_then(|mut file| file.read_exact(&mut magic))
+            .is_ok();
+        if let Some(format) = FileFormat::from_magic(&magic).filter(|_| is_magic_read) {
+            return format;
+        }
+
+        let extension = fpath.as_ref()
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(|x| x.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("aif" | "aiff" | "aifc") => FileFormat::Aiff,
+            Some("wav" | "wave") => FileFormat::Wav,
+            _ => FileFormat::Mpeg,
+        }
+    }
+
+    /// Detects a chunk-based format by the magic bytes at the start of a file. MPEG audio has no
+    /// magic bytes of its own, so it is never detected.
+    pub fn from_magic(bytes: &[u8]) -> Option<FileFormat> {
+        match (bytes.get(0..4)?, bytes.get(8..12)?) {
+            (b"FORM", b"AIFF" | b"AIFC") => Some(FileFormat::Aiff),
+            (b"RIFF", b"WAVE") => Some(FileFormat::Wav),
+            _ => None,
+        }
+    }
+
+    /// Decodes a chunk size field, according to the format's byte order.
+    fn decode_chunk_size(self, bytes: [u8; 4]) -> u32 {
+        match self {
+            FileFormat::Aiff => u32::from_be_bytes(bytes),
+            FileFormat::Wav => u32::from_le_bytes(bytes),
+            FileFormat::Mpeg => unreachable!("MPEG files have no chunks"),
+        }
+    }
+
+    /// Encodes a chunk size field, according to the format's byte order.
+    fn encode_chunk_size(self, size: u32) -> [u8; 4] {
+        match self {
+            FileFormat::Aiff => size.to_be_bytes(),
+            FileFormat::Wav => size.to_le_bytes(),
+            FileFormat::Mpeg => unreachable!("MPEG files have no chunks"),
+        }
+    }
+}
+
+/// Checks if a file has one of the supported extensions (case-insensitive).
+pub fn has_supported_extension(fpath: &impl AsRef<Path>) -> bool {
+    fpath.as_ref()
+        .extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| SUPPORTED_EXTENSIONS.contains(&x.to_ascii_lowercase().as_str()))
+}
+
+/// Finds the ID3 chunk of a chunk-based file, if it has one.
+/// Returns the offset of the chunk's header and the chunk's total size, including the header.
+/// A chunk claiming to be larger than the rest of the file is cut short at the end of the file.
+pub fn find_id3_chunk(file: &mut File, format: FileFormat) -> io::Result<Option<(u64, u64)>> {
+    let file_len = file.seek(SeekFrom::End(0))?;
+    let mut pos = ROOT_CHUNK_HEADER_SIZE;
+    while pos + CHUNK_HEADER_SIZE <= file_len {
+        let mut header = [0; CHUNK_HEADER_SIZE as usize];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut header)?;
+        let size = format.decode_chunk_size([header[4], header[5], header[6], header[7]]) as u64;
+        // Chunks are always padded to an even number of bytes
+        let total_size = CHUNK_HEADER_SIZE + size + (size & 1);
+        if header[0..4].eq_ignore_ascii_case(b"ID3 ") {
+            return Ok(Some((pos, total_size.min(file_len - pos))));
+        }
+        pos += total_size;
+    }
+    Ok(None)
+}
+
+/// Returns the byte ranges of all chunks of a chunk-based file other than its ID3 chunk, i.e.
+/// everything but the root chunk's header, whose size field depends on the size of the ID3 chunk.
+pub fn non_id3_chunk_ranges(file: &mut File, format: FileFormat) -> io::Result<Vec<Range<u64>>> {
+    let file_len = file.seek(SeekFrom::End(0))?;
+    let start = ROOT_CHUNK_HEADER_SIZE.min(file_len);
+    // Without an ID3 chunk, the second range is empty
+    let (pos, size) = find_id3_chunk(file, format)?.unwrap_or((file_len, 0));
+    Ok(vec![start..pos, (pos + size)..file_len])
+}
+
+/// Returns the offset at which a file's ID3v2 tag is stored, according to the file's format.
+/// For chunk-based formats, returns `None` if the file has no ID3 chunk.
+pub fn id3_tag_offset(file: &mut File, format: FileFormat) -> io::Result<Option<u64>> {
+    match format {
+        FileFormat::Mpeg => Ok(Some(0)),
+        format => Ok(find_id3_chunk(file, format)?.map(|(pos, _)| pos + CHUNK_HEADER_SIZE)),
+    }
+}
+
+/// Removes the ID3 chunk from a chunk-based file, moving all subsequent chunks into its place.
+/// Returns whether the file had an ID3 chunk.
+pub fn remove_id3_chunk(fpath: &impl AsRef<Path>, format: FileFormat) -> io::Result<bool> {
+    let mut file = OpenOptions::new().read(true).write(true).open(fpath)?;
+    match find_id3_chunk(&mut file, format)? {
+        Some((pos, total_size)) => {
+            resize_file_region(&mut file, pos, total_size, 0)?;
+            update_root_chunk_size(&mut file, format, total_size, 0)?;
+            Ok(true)
+        },
+        None => Ok(false),
+    }
+}
+
+/// Replaces the contents of the ID3 chunk of a chunk-based file, moving all subsequent chunks as
+/// needed. If the file has no ID3 chunk, one is appended.
+pub fn replace_id3_chunk(fpath: &impl AsRef<Path>, format: FileFormat, contents: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new().read(true).write(true).open(fpath)?;
+    let (pos, old_size, id) = match find_id3_chunk(&mut file, format)? {
+        Some((pos, total_size)) => {
+            // Keep the case of the existing chunk's ID, which varies among WAV files
+            let mut id = [0; 4];
+            file.seek(SeekFrom::Start(pos))?;
+            file.read_exact(&mut id)?;
+            (pos, total_size, id)
+        },
+        None => (file.seek(SeekFrom::End(0))?, 0, *b"ID3 "),
+    };
+    let size = contents.len() as u64;
+    let mut chunk = id.to_vec();
+    chunk.extend(format.encode_chunk_size(size as u32));
+    chunk.extend(contents);
+    if size & 1 != 0 {
+        chunk.push(0);
+    }
+    resize_file_region(&mut file, pos, old_size, chunk.len() as u64)?;
+    file.seek(SeekFrom::Start(pos))?;
+    file.write_all(&chunk)?;
+    update_root_chunk_size(&mut file, format, old_size, chunk.len() as u64)
+}
+
+/// Moves all data from offset `from` until the end of file to offset `to`.
+/// The file is not truncated, if the data is moved to a lower offset.
+fn move_file_data(file: &mut File, mut from: u64, mut to: u64) -> io::Result<()> {
+    let mut buf = vec![0; 1 << 16];
+    if to <= from {
+        loop {
+            file.seek(SeekFrom::Start(from))?;
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                return Ok(());
+            }
+            file.seek(SeekFrom::Start(to))?;
+            file.write_all(&buf[..n])?;
+            from += n as u64;
+            to += n as u64;
+        }
+    } else {
+        // Moving to a higher offset must proceed from the end, to not overwrite unmoved data
+        let mut end = file.seek(SeekFrom::End(0))?;
+        while end > from {
+            let n = buf.len().min((end - from) as usize);
+            let start = end - n as u64;
+            file.seek(SeekFrom::Start(start))?;
+            file.read_exact(&mut buf[..n])?;
+            file.seek(SeekFrom::Start(start + (to - from)))?;
+            file.write_all(&buf[..n])?;
+            end = start;
+        }
+        Ok(())
+    }
+}
+
+/// Resizes a region of a file, shifting all data that follows it.
+/// The contents of the region itself are left unspecified.
+pub fn resize_file_region(file: &mut File, offset: u64, old_size: u64, new_size: u64) -> io::Result<()> {
+    if old_size == new_size {
+        return Ok(());
+    }
+    let file_len = file.seek(SeekFrom::End(0))?;
+    let new_len = offset.checked_add(old_size)
+        .filter(|x| *x <= file_len)
+        .and_then(|_| (file_len - old_size).checked_add(new_size))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "File region extends past the end of the file"))?;
+    move_file_data(file, offset + old_size, offset + new_size)?;
+    file.set_len(new_len)
+}
+
+/// Updates the size field of the root chunk after a chunk of `old_size` bytes was resized to
+/// `new_size` bytes.
+fn update_root_chunk_size(file: &mut File, format: FileFormat, old_size: u64, new_size: u64) -> io::Result<()> {
+    let mut size = [0; 4];
+    file.seek(SeekFrom::Start(4))?;
+    file.read_exact(&mut size)?;
+    let size = (format.decode_chunk_size(size) as u64 + new_size).saturating_sub(old_size) as u32;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&format.encode_chunk_size(size))
+}
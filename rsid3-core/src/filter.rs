@@ -0,0 +1,241 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::id3_helpers::frames_query_equal;
+use crate::text_transforms::frame_value;
+use anyhow::{anyhow, Result};
+use id3::{Frame, Tag};
+use std::cmp::Ordering;
+
+/// Represents a comparison operator of a filter expression.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+/// Represents a predicate over the frames of a tag, parsed from a `--where` expression.
+#[derive(Debug)]
+pub enum Filter {
+    /// The queried frame exists.
+    Exists(Frame),
+    /// A value of the queried frame compares to the given value as requested.
+    Compare(Frame, Comparison, String),
+    Not(Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+}
+
+/// Represents a single token of a filter expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LeftParen,
+    RightParen,
+    Not,
+    And,
+    Or,
+    Op(Comparison),
+    /// An unquoted word, i.e. a frame or a bare value.
+    Word(String),
+    /// A quoted string, with escapes already resolved.
+    Str(String),
+}
+
+/// Splits a filter expression into tokens.
+fn tokenize(expr: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut rest = expr.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let (token, len) = match (rest.get(..2).unwrap_or(""), c) {
+            ("&&", _) => (Token::And, 2),
+            ("||", _) => (Token::Or, 2),
+            ("==", _) => (Token::Op(Comparison::Equal), 2),
+            ("!=", _) => (Token::Op(Comparison::NotEqual), 2),
+            ("<=", _) => (Token::Op(Comparison::LessEqual), 2),
+            (">=", _) => (Token::Op(Comparison::GreaterEqual), 2),
+            (_, '<') => (Token::Op(Comparison::Less), 1),
+            (_, '>') => (Token::Op(Comparison::Greater), 1),
+            (_, '!') => (Token::Not, 1),
+            (_, '(') => (Token::LeftParen, 1),
+            (_, ')') => (Token::RightParen, 1),
+            (_, '"' | '\'') => {
+                let mut string = String::new();
+                let mut chars = rest.char_indices().skip(1);
+                let end = loop {
+                    match chars.next() {
+                        Some((_, '\\')) => string.push(chars.next().ok_or_else(|| anyhow!("unterminated string"))?.1),
+                        Some((i, x)) if x == c => break i,
+                        Some((_, x)) => string.push(x),
+                        None => return Err(anyhow!("unterminated string")),
+                    }
+                };
+                (Token::Str(string), end + 1)
+            },
+            _ => {
+                // Frames may be followed by a [DESC], which may contain anything but ']', and a (LANG)
+                let mut end = 0;
+                while let Some(x) = rest[end..].chars().next() {
+                    let closing = match x {
+                        '[' => ']',
+                        '(' if end > 0 => ')',
+                        x if x.is_whitespace() || "!=<>&|()".contains(x) => break,
+                        x => {
+                            end += x.len_utf8();
+                            continue;
+                        },
+                    };
+                    end += rest[end..].find(closing).ok_or_else(|| anyhow!("missing '{closing}'"))? + 1;
+                }
+                (Token::Word(rest[..end].to_string()), end)
+            },
+        };
+        tokens.push(token);
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser of filter expressions.
+struct Parser<'a, F: Fn(&str) -> Result<Frame>> {
+    tokens: &'a [Token],
+    pos: usize,
+    parse_frame: F,
+}
+
+impl<F: Fn(&str) -> Result<Frame>> Parser<'_, F> {
+    /// Returns the next token, without consuming it.
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Consumes the next token, if it is the given one.
+    fn accept(&mut self, token: &Token) -> bool {
+        let is_accepted = self.peek() == Some(token);
+        if is_accepted {
+            self.pos += 1;
+        }
+        is_accepted
+    }
+
+    /// Parses `and ('||' and)*`.
+    fn or(&mut self) -> Result<Filter> {
+        let mut filter = self.and()?;
+        while self.accept(&Token::Or) {
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    /// Parses `unary ('&&' unary)*`.
+    fn and(&mut self) -> Result<Filter> {
+        let mut filter = self.unary()?;
+        while self.accept(&Token::And) {
+            filter = Filter::And(Box::new(filter), Box::new(self.unary()?));
+        }
+        Ok(filter)
+    }
+
+    /// Parses `'!' unary | '(' or ')' | FRAME [OP VALUE]`.
+    fn unary(&mut self) -> Result<Filter> {
+        if self.accept(&Token::Not) {
+            return Ok(Filter::Not(Box::new(self.unary()?)));
+        }
+        if self.accept(&Token::LeftParen) {
+            let filter = self.or()?;
+            if !self.accept(&Token::RightParen) {
+                return Err(anyhow!("expected ')'"));
+            }
+            return Ok(filter);
+        }
+        let frame = match self.peek() {
+            Some(Token::Word(x)) => (self.parse_frame)(x)?,
+            Some(x) => return Err(anyhow!("expected a frame, found {x:?}")),
+            None => return Err(anyhow!("expected a frame, found the end")),
+        };
+        self.pos += 1;
+        let op = match self.peek() {
+            Some(&Token::Op(x)) => x,
+            _ => return Ok(Filter::Exists(frame)),
+        };
+        self.pos += 1;
+        match self.peek() {
+            Some(Token::Word(x) | Token::Str(x)) => {
+                let value = x.clone();
+                self.pos += 1;
+                Ok(Filter::Compare(frame, op, value))
+            },
+            _ => Err(anyhow!("expected a value after the comparison")),
+        }
+    }
+}
+
+impl Filter {
+    /// Parses a filter expression, e.g. `TPE1 == "Nirvana" && TCON != "Live"`.
+    /// Frames are parsed with `parse_frame`.
+    pub fn parse(expr: &str, parse_frame: impl Fn(&str) -> Result<Frame>) -> Result<Filter> {
+        let tokens = tokenize(expr).map_err(|e| anyhow!("Invalid --where expression '{expr}': {e}"))?;
+        let mut parser = Parser { tokens: &tokens, pos: 0, parse_frame };
+        let filter = parser.or().map_err(|e| anyhow!("Invalid --where expression '{expr}': {e}"))?;
+        if let Some(token) = parser.peek() {
+            return Err(anyhow!("Invalid --where expression '{expr}': unexpected {token:?}"));
+        }
+        Ok(filter)
+    }
+
+    /// Checks if a tag satisfies the filter. A comparison is satisfied if any value of a multi-value
+    /// text frame satisfies it, except for `!=`, which requires all values to differ. Frames that are
+    /// not found are equal to nothing.
+    pub fn matches(&self, tag: &Tag) -> bool {
+        match self {
+            Filter::Exists(query) => find_values(tag, query).is_some(),
+            Filter::Compare(query, Comparison::NotEqual, value) => {
+                !Filter::Compare(query.clone(), Comparison::Equal, value.clone()).matches(tag)
+            },
+            Filter::Compare(query, op, value) => find_values(tag, query).is_some_and(|x| x.iter().any(|y| {
+                let ordering = compare_values(y, value);
+                match op {
+                    Comparison::Equal => ordering == Ordering::Equal,
+                    Comparison::NotEqual => unreachable!(),
+                    Comparison::Less => ordering == Ordering::Less,
+                    Comparison::LessEqual => ordering != Ordering::Greater,
+                    Comparison::Greater => ordering == Ordering::Greater,
+                    Comparison::GreaterEqual => ordering != Ordering::Less,
+                }
+            })),
+            Filter::Not(x) => !x.matches(tag),
+            Filter::And(x, y) => x.matches(tag) && y.matches(tag),
+            Filter::Or(x, y) => x.matches(tag) || y.matches(tag),
+        }
+    }
+}
+
+/// Returns the values of the frame of a tag matching a query, if there is one.
+pub fn find_values(tag: &Tag, query: &Frame) -> Option<Vec<String>> {
+    let frame = tag.frames().find(|x| frames_query_equal(x, query).unwrap_or(false))?;
+    let value = frame_value(frame).map(str::to_string).unwrap_or_else(|| frame.content().to_string());
+    Some(value.split('\0').map(str::to_string).collect())
+}
+
+/// Compares two values as numbers, if they both are, or as strings otherwise.
+fn compare_values(value1: &str, value2: &str) -> Ordering {
+    match (value1.trim().parse::<f64>(), value2.trim().parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        _ => value1.cmp(value2),
+    }
+}
@@ -0,0 +1,160 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::check::is_frame_empty;
+use crate::action::FixKind;
+use crate::id3_helpers::{decode_stored_frame, frame_to_string, replace_exact_tag_frame, retain_tag_frames};
+use crate::id3v1_helpers::resolve_genre_frame;
+use crate::mojibake::recover_frame_utf8;
+use crate::tag_header::TagLayout;
+use crate::text_transforms::transform_tag_frames;
+use crate::text_values::is_multi_value_id;
+use id3::{Frame, Tag, TagLike};
+use std::collections::BTreeSet;
+
+/// All fix kinds, in the order in which they are applied.
+pub const ALL_FIX_KINDS: &[FixKind] = &[
+    FixKind::Corrupt,
+    FixKind::Encoding,
+    FixKind::Duplicates,
+    FixKind::Empty,
+    FixKind::Genres,
+    FixKind::Numbers,
+];
+
+/// Returns the name of a fix kind, as used on the command line.
+pub fn fix_kind_name(kind: FixKind) -> &'static str {
+    match kind {
+        FixKind::Corrupt => "corrupt",
+        FixKind::Encoding => "encoding",
+        FixKind::Duplicates => "duplicates",
+        FixKind::Empty => "empty",
+        FixKind::Genres => "genres",
+        FixKind::Numbers => "numbers",
+    }
+}
+
+/// Returns the fix kind matching a name, as used on the command line.
+pub fn fix_kind_from_name(name: &str) -> Option<FixKind> {
+    ALL_FIX_KINDS.iter().copied().find(|&x| fix_kind_name(x) == name)
+}
+
+/// Decodes every frame stored in a tag on its own, so that none of them are dropped in favor of
+/// conflicting frames. Each frame is returned along with its stored text encoding byte, if any.
+/// Frames which cannot be decoded are skipped.
+fn decode_stored_frames(layout: &TagLayout) -> Vec<(Frame, Option<u8>)> {
+    layout.frames.iter()
+        .filter_map(|x| Some((decode_stored_frame(layout, x)?, x.encoding)))
+        .collect()
+}
+
+/// Merges two frames that conflict with each other. The values of text frames are combined,
+/// otherwise the first frame is kept.
+fn merge_frames(first: Frame, second: Frame) -> Frame {
+    match (first.content().text(), second.content().text()) {
+        (Some(a), Some(b)) if is_multi_value_id(first.id()) => {
+            let mut values = a.split('\0').collect::<Vec<_>>();
+            for value in b.split('\0') {
+                if !values.contains(&value) {
+                    values.push(value);
+                }
+            }
+            Frame::text(first.id(), values.join("\0"))
+        },
+        _ => first,
+    }
+}
+
+/// Returns the name of a frame for reporting, e.g. "TXXX[desc]".
+fn frame_name(frame: &Frame) -> String {
+    frame_to_string(frame).unwrap_or_else(|_| frame.id().to_string())
+}
+
+/// Applies the given kinds of fixes to a tag. `layout` is the raw layout of the tag as stored in
+/// the file, without which duplicate frames and text encodings cannot be fixed.
+/// Returns a description of each fix applied.
+pub fn fix_tag(tag: &mut Tag, layout: Option<&TagLayout>, kinds: &[FixKind]) -> Vec<String> {
+    let mut fixes = vec![];
+    let mut stored_frames = layout.map(decode_stored_frames).unwrap_or_default();
+
+    if kinds.contains(&FixKind::Corrupt) {
+        // Corrupt frames are never part of the tag, as it can only be read leniently without them
+        for frame in layout.iter().flat_map(|x| x.frames.iter().filter(|y| decode_stored_frame(x, y).is_none())) {
+            fixes.push(format!("Removed corrupt {} frame", frame.id));
+        }
+    }
+
+    if kinds.contains(&FixKind::Encoding) {
+        for (frame, encoding) in &mut stored_frames {
+            let recovered = match recover_frame_utf8(frame) {
+                Some(x) if *encoding == Some(0) => x,
+                _ => continue,
+            };
+            // Stored frames which were dropped in favor of conflicting ones are not restored
+            if tag.frames().any(|x| x == frame) {
+                replace_exact_tag_frame(tag, frame, recovered.clone());
+            }
+            fixes.push(format!("Recovered UTF-8 text of {} stored as Latin-1", frame_name(&recovered)));
+            *frame = recovered;
+        }
+    }
+
+    if kinds.contains(&FixKind::Duplicates) {
+        let ids = stored_frames.iter().map(|(x, _)| x.id().to_string()).collect::<BTreeSet<_>>();
+        for id in ids {
+            let stored = stored_frames.iter().map(|(x, _)| x).filter(|x| x.id() == id).collect::<Vec<_>>();
+            if stored.len() <= tag.frames().filter(|x| x.id() == id).count() {
+                continue;
+            }
+            let mut merged = Tag::with_version(tag.version());
+            for frame in &stored {
+                if let Some(first) = merged.add_frame((*frame).clone()) {
+                    merged.add_frame(merge_frames(first, (*frame).clone()));
+                }
+            }
+            tag.remove(&id);
+            for frame in merged.frames() {
+                tag.add_frame(frame.clone());
+            }
+            fixes.push(format!("Merged {} duplicate {id} frame(s)", stored.len() - merged.frames().count()));
+        }
+    }
+
+    if kinds.contains(&FixKind::Empty) {
+        for frame in retain_tag_frames(tag, |x| !is_frame_empty(x)) {
+            fixes.push(format!("Removed empty {}", frame_name(&frame)));
+        }
+    }
+
+    if kinds.contains(&FixKind::Genres) {
+        let resolve = |x: &Frame| Some(resolve_genre_frame(x.clone())).filter(|y| y != x);
+        for (frame, _) in transform_tag_frames(tag, resolve) {
+            fixes.push(format!("Resolved genre references of {}", frame_name(&frame)));
+        }
+    }
+
+    if kinds.contains(&FixKind::Numbers) {
+        let trim = |x: &Frame| {
+            let text = x.content().text().filter(|_| matches!(x.id(), "TRCK" | "TPOS"))?;
+            let trimmed = text.split('/').map(str::trim).collect::<Vec<_>>().join("/");
+            (trimmed != text).then(|| Frame::text(x.id(), trimmed))
+        };
+        for (frame, _) in transform_tag_frames(tag, trim) {
+            fixes.push(format!("Removed whitespace from {}", frame_name(&frame)));
+        }
+    }
+
+    fixes
+}
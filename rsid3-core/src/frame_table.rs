@@ -0,0 +1,196 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use id3::{Frame, Version};
+
+/// All frames which rsid3 can read and write, with their descriptions. Some of them are written
+/// by dedicated options, which are referred to in their descriptions.
+pub const WRITABLE_FRAMES: &[(&str, &str)] = &[
+    ("CHAP", "Chapter (see --chap-add)"),
+    ("CTOC", "Table of contents (see --toc-set)"),
+    ("COMM", "User comment (DESC, LANG, TEXT)"),
+    ("GEOB", "General encapsulated object (see --geob-embed)"),
+    ("GRP1", "iTunes grouping"),
+    ("MVIN", "iTunes movement number/count"),
+    ("MVNM", "iTunes movement name"),
+    ("PCNT", "Play counter"),
+    ("PCST", "iTunes podcast flag (see --podcast)"),
+    ("POPM", "Popularimeter (OWNER, RATING, COUNTER)"),
+    ("TALB", "Album"),
+    ("TBPM", "Beats per minute"),
+    ("TCAT", "iTunes podcast category"),
+    ("TCMP", "iTunes compilation flag"),
+    ("TCOM", "Composer"),
+    ("TCON", "Content type (genre)"),
+    ("TCOP", "Copyright"),
+    ("TDAT", "Date of recording (DDMM)"),
+    ("TDEN", "Encoding time"),
+    ("TDES", "iTunes podcast description"),
+    ("TDLY", "Audio delay (ms)"),
+    ("TDOR", "Original release time"),
+    ("TDRC", "Recording time"),
+    ("TDRL", "Release time"),
+    ("TDTG", "Tagging time"),
+    ("TENC", "Encoder"),
+    ("TEXT", "Lyricist"),
+    ("TFLT", "File type"),
+    ("TGID", "iTunes podcast identifier"),
+    ("TIME", "Time of recording (HHMM)"),
+    ("TIPL", "Involved people list (ROLE, PERSON)"),
+    ("TIT1", "Content group description"),
+    ("TIT2", "Title"),
+    ("TIT3", "Subtitle/description refinement"),
+    ("TKEY", "Starting key"),
+    ("TKWD", "iTunes podcast keywords"),
+    ("TLAN", "Audio languages"),
+    ("TLEN", "Audio length (ms)"),
+    ("TMCL", "Musician credits list (INSTRUMENT, MUSICIAN)"),
+    ("TMED", "Source media type"),
+    ("TMOO", "Mood"),
+    ("TOAL", "Original album"),
+    ("TOFN", "Original filename"),
+    ("TOLY", "Original lyricist"),
+    ("TOPE", "Original artist/performer"),
+    ("TORY", "Original release year"),
+    ("TOWN", "Owner/Licensee"),
+    ("TPE1", "Lead artist/performer/soloist/group"),
+    ("TPE2", "Band/Orchestra/Accompaniment"),
+    ("TPE3", "Conductor"),
+    ("TPE4", "Interpreter/Remixer/Modifier"),
+    ("TPOS", "Part of set"),
+    ("TPRO", "Produced"),
+    ("TPUB", "Publisher"),
+    ("TRCK", "Track number"),
+    ("TRDA", "Recording dates"),
+    ("TRSN", "Internet radio station name"),
+    ("TRSO", "Internet radio station owner"),
+    ("TSIZ", "Size of audio data (bytes)"),
+    ("TSO2", "iTunes album artist sort"),
+    ("TSOA", "Album sort order key"),
+    ("TSOC", "iTunes composer sort"),
+    ("TSOP", "Performer sort order key"),
+    ("TSOT", "Title sort order key"),
+    ("TSRC", "International Standard Recording Code (ISRC)"),
+    ("TSSE", "Encoder settings"),
+    ("TSST", "Set subtitle"),
+    ("TXXX", "User-defined text data (DESC, TEXT)"),
+    ("TYER", "Year of recording"),
+    ("PRIV", "Private frame (OWNER, DATA)"),
+    ("SYLT", "Synchronised lyrics/text (DESC, LANG, LRC)"),
+    ("UFID", "Unique file identifier (OWNER, ID)"),
+    ("USER", "Terms of use (LANG, TEXT)"),
+    ("USLT", "Unsynchronised lyrics/text transcription (DESC, LANG, TEXT)"),
+    ("WCOM", "Commercial information"),
+    ("WCOP", "Copyright information"),
+    ("WFED", "iTunes podcast feed"),
+    ("WOAF", "Official file information"),
+    ("WOAR", "Official artist/performer information"),
+    ("WOAS", "Official source information"),
+    ("WORS", "Official internet radio information"),
+    ("WPAY", "Payment information"),
+    ("WPUB", "Official publisher information"),
+    ("WXXX", "User-defined URL data (DESC, URL)"),
+];
+
+/// All frames which rsid3 can only read, and print in a rudimentary form, with their descriptions.
+pub const READ_ONLY_FRAMES: &[(&str, &str)] = &[
+    ("AENC", "Audio encryption"),
+    ("APIC", "Attached (or linked) picture"),
+    ("ASPI", "Audio seek point index"),
+    ("COMR", "Commercial frame"),
+    ("ENCR", "Encryption method registration"),
+    ("EQU2", "Equalization 2"),
+    ("ETCO", "Event timing codes"),
+    ("GRID", "Group identification registration"),
+    ("IPLS", "Involved people list"),
+    ("LINK", "Linked information"),
+    ("MCDI", "Binary dump of CD's TOC"),
+    ("MLLT", "MPEG location lookup table"),
+    ("OWNE", "Ownership frame"),
+    ("POSS", "Position synchronisation frame"),
+    ("RBUF", "Recommended buffer size"),
+    ("RVA2", "Relative volume adjustment 2"),
+    ("RVAD", "Relative volume adjustment"),
+    ("RVRB", "Reverb"),
+    ("SEEK", "Seek frame"),
+    ("SIGN", "Signature frame"),
+    ("SYTC", "Synchronised tempo codes"),
+];
+
+/// IDs of frames that were introduced in ID3v2.4.
+const V24_ONLY_FRAMES: &[&str] = &[
+    "ASPI", "EQU2", "RVA2", "SEEK", "SIGN", "TDEN", "TDOR", "TDRC", "TDRL", "TDTG", "TIPL", "TMCL",
+    "TMOO", "TPRO", "TSOA", "TSOP", "TSOT", "TSST",
+];
+/// IDs of frames that were removed in ID3v2.4.
+const PRE_V24_ONLY_FRAMES: &[&str] = &["EQUA", "IPLS", "RVAD", "TDAT", "TIME", "TORY", "TRDA", "TSIZ", "TYER"];
+
+/// Checks if a frame ID is defined in the given version of the standard. ID3v2.2 frames are
+/// looked up under their ID3v2.3 IDs.
+pub fn is_frame_id_in_version(id: &str, version: Version) -> bool {
+    match version {
+        Version::Id3v24 => !PRE_V24_ONLY_FRAMES.contains(&id),
+        _ => !V24_ONLY_FRAMES.contains(&id) && Frame::text(id, "").id_for_version(version).is_some(),
+    }
+}
+
+/// Returns the parameters which a frame is set with on the command line, in order, e.g. DESC and
+/// TEXT for TXXX. Frames which are only written by dedicated options, or not at all, have none.
+pub fn frame_params(id: &str) -> &'static [&'static str] {
+    match id {
+        "COMM" | "USLT" => &["DESC", "LANG", "TEXT"],
+        "SYLT" => &["DESC", "LANG", "LRC"],
+        "TXXX" => &["DESC", "TEXT"],
+        "WXXX" => &["DESC", "URL"],
+        "TIPL" => &["ROLE", "PERSON"],
+        "TMCL" => &["INSTRUMENT", "MUSICIAN"],
+        "POPM" => &["OWNER", "RATING", "COUNTER"],
+        "PRIV" => &["OWNER", "DATA"],
+        "UFID" => &["OWNER", "ID"],
+        "USER" => &["LANG", "TEXT"],
+        "PCNT" => &["COUNTER"],
+        "GRP1" | "MVIN" | "MVNM" => &["TEXT"],
+        "CHAP" | "CTOC" | "GEOB" => &[],
+        x if WRITABLE_FRAMES.iter().any(|(y, _)| x == *y) && x.starts_with('T') => &["TEXT"],
+        x if WRITABLE_FRAMES.iter().any(|(y, _)| x == *y) && x.starts_with('W') => &["URL"],
+        _ => &[],
+    }
+}
+
+/// Returns the parameters which tell a frame apart from others with the same ID, and are thus
+/// passed when printing or deleting it, e.g. DESC and LANG for COMM. They are also the first
+/// parameters of `frame_params`, if the frame has any.
+pub fn frame_key_params(id: &str) -> &'static [&'static str] {
+    match id {
+        "COMM" | "USLT" | "SYLT" => &["DESC", "LANG"],
+        "TXXX" | "WXXX" | "GEOB" => &["DESC"],
+        "TIPL" => &["ROLE"],
+        "TMCL" => &["INSTRUMENT"],
+        "POPM" | "PRIV" | "UFID" => &["OWNER"],
+        "USER" => &["LANG"],
+        _ => &[],
+    }
+}
+
+/// Returns the human-readable name of a frame, i.e. its description without the parameters or
+/// options listed in parentheses, e.g. "User comment" for COMM.
+pub fn frame_name(id: &str) -> Option<&'static str> {
+    let (_, description) = WRITABLE_FRAMES.iter().chain(READ_ONLY_FRAMES).find(|(x, _)| *x == id)?;
+    let params = format!("({})", frame_params(id).join(", "));
+    match description.rsplit_once(" (") {
+        Some((name, rest)) if format!("({rest}") == params || rest.starts_with("see ") => Some(name),
+        _ => Some(description),
+    }
+}
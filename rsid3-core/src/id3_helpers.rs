@@ -0,0 +1,1288 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::binary::encode_binary;
+use crate::errors::{report_error, report_file_error, ErrorKind, Output};
+use crate::chapters::{chapter_title, format_chapter_time};
+use crate::engine::ExecOptions;
+use crate::id3v1_helpers::resolve_genre_references;
+use crate::event_timing::{event_type_name, get_event_timing_codes};
+use crate::itunes_text::{get_itunes_text, is_itunes_text_id};
+use crate::involved_people::{get_people, remove_involvement};
+use crate::lrc::{format_lrc, format_timestamp};
+use crate::play_count::get_play_counter;
+use crate::action::{BinaryFormat, FrameFlag};
+use crate::frame_table::frame_name;
+use crate::file_format::{FileFormat, id3_tag_offset, remove_id3_chunk, replace_id3_chunk, resize_file_region};
+use crate::terms_of_use::TermsOfUse;
+use crate::timestamps::convert_date_frames;
+use crate::tag_header::{encode_synchsafe, FrameLayout, TagHeader, TagLayout, FLAG_UNSYNCHRONISATION, TAG_HEADER_SIZE, tag_region_size};
+use anyhow::{anyhow, Result};
+use id3::{Content, Tag, TagLike, Frame, Version, Encoder, Encoding};
+use id3::frame::{Chapter, Comment, Lyrics, ExtendedText, ExtendedLink, EncapsulatedObject, InvolvedPeopleList, Popularimeter, Private, SynchronisedLyrics, TableOfContents, TimestampFormat, UniqueFileIdentifier};
+use std::collections::BTreeSet;
+use std::ffi::{OsStr, OsString};
+use std::fs::{copy, read, write, File, OpenOptions};
+use std::io::{self, empty, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Convenience wrapper for getting any simple text content.
+pub fn get_content_text(frame: &Frame) -> Result<&str> {
+    match frame.content().text() {
+        Some(x) => Ok(x),
+        None => Err(anyhow!("Frame claims to be {} with T but has no text content: {frame:?}", frame.id())),
+    }
+}
+
+/// Convenience wrapper for getting any link content.
+pub fn get_content_link(frame: &Frame) -> Result<&str> {
+    match frame.content().link() {
+        Some(x) => Ok(x),
+        None => Err(anyhow!("Frame claims to be {} with T but has no link content: {frame:?}", frame.id())),
+    }
+}
+
+/// Convenience wrapper for getting TXXX content.
+pub fn get_content_txxx(frame: &Frame) -> Result<&ExtendedText> {
+    match frame.content().extended_text() {
+        Some(x) => Ok(x),
+        None => Err(anyhow!("Frame claims to be TXXX but has no extended text content: {frame:?}")),
+    }
+}
+
+/// Convenience wrapper for getting WXXX content.
+pub fn get_content_wxxx(frame: &Frame) -> Result<&ExtendedLink> {
+    match frame.content().extended_link() {
+        Some(x) => Ok(x),
+        None => Err(anyhow!("Frame claims to be WXXX but has no extended link content: {frame:?}")),
+    }
+}
+
+/// Convenience wrapper for getting COMM content.
+pub fn get_content_comm(frame: &Frame) -> Result<&Comment> {
+    match frame.content().comment() {
+        Some(x) => Ok(x),
+        None => Err(anyhow!("Frame claims to be COMM but has no comment content: {frame:?}")),
+    }
+}
+
+/// Convenience wrapper for getting USLT content.
+pub fn get_content_uslt(frame: &Frame) -> Result<&Lyrics> {
+    match frame.content().lyrics() {
+        Some(x) => Ok(x),
+        None => Err(anyhow!("Frame claims to be USLT but has no lyrics content: {frame:?}")),
+    }
+}
+
+/// Convenience wrapper for getting SYLT content.
+pub fn get_content_sylt(frame: &Frame) -> Result<&SynchronisedLyrics> {
+    match frame.content().synchronised_lyrics() {
+        Some(x) => Ok(x),
+        None => Err(anyhow!("Frame claims to be SYLT but has no synchronised lyrics content: {frame:?}")),
+    }
+}
+
+/// Convenience wrapper for getting CHAP content.
+pub fn get_content_chap(frame: &Frame) -> Result<&Chapter> {
+    match frame.content().chapter() {
+        Some(x) => Ok(x),
+        None => Err(anyhow!("Frame claims to be CHAP but has no chapter content: {frame:?}")),
+    }
+}
+
+/// Convenience wrapper for getting POPM content.
+pub fn get_content_popm(frame: &Frame) -> Result<&Popularimeter> {
+    match frame.content().popularimeter() {
+        Some(x) => Ok(x),
+        None => Err(anyhow!("Frame claims to be POPM but has no popularimeter content: {frame:?}")),
+    }
+}
+
+/// Convenience wrapper for getting PRIV content.
+pub fn get_content_priv(frame: &Frame) -> Result<&Private> {
+    match frame.content() {
+        Content::Private(x) => Ok(x),
+        _ => Err(anyhow!("Frame claims to be PRIV but has no private content: {frame:?}")),
+    }
+}
+
+/// Convenience wrapper for getting GEOB content.
+pub fn get_content_geob(frame: &Frame) -> Result<&EncapsulatedObject> {
+    match frame.content().encapsulated_object() {
+        Some(x) => Ok(x),
+        None => Err(anyhow!("Frame claims to be GEOB but has no encapsulated object content: {frame:?}")),
+    }
+}
+
+/// Convenience wrapper for getting UFID content.
+pub fn get_content_ufid(frame: &Frame) -> Result<&UniqueFileIdentifier> {
+    match frame.content().unique_file_identifier() {
+        Some(x) => Ok(x),
+        None => Err(anyhow!("Frame claims to be UFID but has no unique file identifier content: {frame:?}")),
+    }
+}
+
+/// Convenience wrapper for getting TIPL, TMCL or IPLS content.
+pub fn get_content_people(frame: &Frame) -> Result<&InvolvedPeopleList> {
+    match frame.content().involved_people_list() {
+        Some(x) if !x.items.is_empty() || frame.id() == "IPLS" => Ok(x),
+        _ => Err(anyhow!("Frame claims to be {} but has no involved people list content: {frame:?}", frame.id())),
+    }
+}
+
+/// Convenience wrapper for getting CTOC content.
+pub fn get_content_ctoc(frame: &Frame) -> Result<&TableOfContents> {
+    match frame.content().table_of_contents() {
+        Some(x) => Ok(x),
+        None => Err(anyhow!("Frame claims to be CTOC but has no table of contents content: {frame:?}")),
+    }
+}
+
+/// Formats the entries of a SYLT frame as the contents of an LRC file.
+pub fn sylt_to_lrc(sylt: &SynchronisedLyrics) -> Result<String> {
+    if sylt.timestamp_format != TimestampFormat::Ms {
+        return Err(anyhow!("SYLT[{}]({}) uses MPEG frame timestamps, which cannot be converted to LRC",
+            sylt.description, sylt.lang));
+    }
+    Ok(format_lrc(&sylt.content))
+}
+
+/// Returns a string representation of a frame, WITHOUT CONTENT.
+pub fn frame_to_string(frame: &Frame) -> Result<String, anyhow::Error> {
+    let string = match frame.id() {
+        "WXXX" => format!("{}[{}]", frame.id(), get_content_wxxx(frame)?.description),
+        "TXXX" => format!("{}[{}]", frame.id(), get_content_txxx(frame)?.description),
+        "COMM" => {
+            let comment = get_content_comm(frame)?;
+            format!("{}[{}]({})", frame.id(), comment.description, comment.lang)
+        },
+        "USLT" => {
+            let lyrics = get_content_uslt(frame)?;
+            format!("{}[{}]({})", frame.id(), lyrics.description, lyrics.lang)
+        },
+        "SYLT" => {
+            let sylt = get_content_sylt(frame)?;
+            format!("{}[{}]({})", frame.id(), sylt.description, sylt.lang)
+        },
+        "POPM" => format!("{}[{}]", frame.id(), get_content_popm(frame)?.user),
+        "UFID" => format!("{}[{}]", frame.id(), get_content_ufid(frame)?.owner_identifier),
+        "PRIV" => format!("{}[{}]", frame.id(), get_content_priv(frame)?.owner_identifier),
+        "USER" => match TermsOfUse::from_frame(frame)?.lang {
+            x if x == TermsOfUse::ANY_LANGUAGE => format!("{}(first)", frame.id()),
+            x => format!("{}({x})", frame.id()),
+        },
+        "TIPL" | "TMCL" => format!("{}[{}]", frame.id(), get_content_people(frame)?.items[0].involvement),
+        "GEOB" => format!("{}[{}]", frame.id(), get_content_geob(frame)?.description),
+        "CHAP" => format!("{}[{}]", frame.id(), get_content_chap(frame)?.element_id),
+        "CTOC" => format!("{}[{}]", frame.id(), get_content_ctoc(frame)?.element_id),
+        x => x.to_string(),
+    };
+    Ok(string)
+}
+
+/// Attempts to find a tag frame matching a query and prints its contents as text to `output`.
+/// Binary data is printed in the `binary_format` of `options`, and multiple values of text frames
+/// are separated with its `value_sep`. Numeric genre references are resolved to names, if its
+/// `genre_names` is set. If no frame is found, its `missing` is printed instead, if given.
+/// `fpath` is only used for message prints. Returns whether a frame was found and printed.
+pub fn print_tag_frame_query(output: &mut dyn Output, tag: &Tag, frame: &Frame, options: &ExecOptions,
+    fpath: impl AsRef<Path>) -> Result<bool> {
+    let (binary_format, value_sep) = (options.binary_format, options.value_sep.as_str());
+    match frame.id() {
+        "TXXX" => {
+            let desc_query = &get_content_txxx(frame)?.description;
+
+            for txxx in tag.frames().filter(|&f| f.id() == "TXXX") {
+                let extended_text = match get_content_txxx(txxx) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        report_error(output, ErrorKind::Io, e);
+                        continue;
+                    },
+                };
+                if extended_text.description == *desc_query {
+                    write!(output.writer(), "{}", extended_text.value)?;
+                    return Ok(true);
+                }
+            }
+        },
+        "WXXX" => {
+            let desc_query = &get_content_wxxx(frame)?.description;
+            for wxxx in tag.frames().filter(|&f| f.id() == "WXXX") {
+                let extended_link = match get_content_wxxx(wxxx) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        report_error(output, ErrorKind::Io, e);
+                        continue;
+                    },
+                };
+                if extended_link.description == *desc_query {
+                    write!(output.writer(), "{}", extended_link.link)?;
+                    return Ok(true);
+                }
+            }
+        },
+        "COMM" => {
+            let comment_query = get_content_comm(frame)?;
+            let (desc_query, lang_query) = (&comment_query.description, &comment_query.lang);
+            for comm in tag.frames().filter(|&f| f.id() == "COMM") {
+                let comment = match get_content_comm(comm) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        report_error(output, ErrorKind::Io, e);
+                        continue;
+                    },
+                };
+                if comment.description == *desc_query && (comment.lang == *lang_query || *lang_query == "first") {
+                    write!(output.writer(), "{}", comment.text)?;
+                    return Ok(true);
+                }
+            }
+        },
+        "USLT" => {
+            let lyrics_query = get_content_uslt(frame)?;
+            let (desc_query, lang_query) = (&lyrics_query.description, &lyrics_query.lang);
+            for uslt in tag.frames().filter(|&f| f.id() == "USLT") {
+                let lyrics = match get_content_uslt(uslt) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        report_error(output, ErrorKind::Io, e);
+                        continue;
+                    },
+                };
+                if lyrics.description == *desc_query && (lyrics.lang == *lang_query || *lang_query == "first") {
+                    write!(output.writer(), "{}", lyrics.text)?;
+                    return Ok(true);
+                }
+            }
+        },
+        "SYLT" => {
+            let sylt_query = get_content_sylt(frame)?;
+            let (desc_query, lang_query) = (&sylt_query.description, &sylt_query.lang);
+            for sylt in tag.frames().filter(|&f| f.id() == "SYLT") {
+                let sylt = match get_content_sylt(sylt) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        report_error(output, ErrorKind::Io, e);
+                        continue;
+                    },
+                };
+                if sylt.description == *desc_query && (sylt.lang == *lang_query || *lang_query == "first") {
+                    write!(output.writer(), "{}", sylt_to_lrc(sylt)?)?;
+                    return Ok(true);
+                }
+            }
+        },
+        "TIPL" | "TMCL" => {
+            let people = get_people(tag, frame.id(), &get_content_people(frame)?.items[0].involvement);
+            if !people.is_empty() {
+                write!(output.writer(), "{}", people.join(", "))?;
+                return Ok(true);
+            }
+        },
+        "USER" => {
+            let lang_query = TermsOfUse::from_frame(frame)?.lang;
+            for user in tag.frames().filter(|&f| f.id() == "USER") {
+                let terms_of_use = match TermsOfUse::from_frame(user) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        report_error(output, ErrorKind::Io, e);
+                        continue;
+                    },
+                };
+                if terms_of_use.lang == lang_query || lang_query == TermsOfUse::ANY_LANGUAGE {
+                    write!(output.writer(), "{}", terms_of_use.text)?;
+                    return Ok(true);
+                }
+            }
+        },
+        "GEOB" => {
+            if let Some(object) = find_geob(tag, &get_content_geob(frame)?.description) {
+                print_binary(output.writer(), &object.data, binary_format)?;
+                return Ok(true);
+            }
+        },
+        "PRIV" => {
+            let owner_query = &get_content_priv(frame)?.owner_identifier;
+            for private in tag.frames().filter(|&f| f.id() == "PRIV") {
+                let private = match get_content_priv(private) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        report_error(output, ErrorKind::Io, e);
+                        continue;
+                    },
+                };
+                if private.owner_identifier == *owner_query {
+                    print_binary(output.writer(), &private.private_data, binary_format)?;
+                    return Ok(true);
+                }
+            }
+        },
+        "UFID" => {
+            let owner_query = &get_content_ufid(frame)?.owner_identifier;
+            for ufid in tag.frames().filter(|&f| f.id() == "UFID") {
+                let unique_file_identifier = match get_content_ufid(ufid) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        report_error(output, ErrorKind::Io, e);
+                        continue;
+                    },
+                };
+                if unique_file_identifier.owner_identifier == *owner_query {
+                    write!(output.writer(), "{}", String::from_utf8_lossy(&unique_file_identifier.identifier))?;
+                    return Ok(true);
+                }
+            }
+        },
+        "PCNT" => {
+            if let Some(frame) = tag.get("PCNT") {
+                write!(output.writer(), "{}", get_play_counter(frame)?)?;
+                return Ok(true);
+            }
+        },
+        "ETCO" => {
+            if let Some(frame) = tag.get("ETCO") {
+                write!(output.writer(), "{}", get_event_timing_codes(frame)?.format_events())?;
+                return Ok(true);
+            }
+        },
+        "POPM" => {
+            let owner_query = &get_content_popm(frame)?.user;
+            for popm in tag.frames().filter(|&f| f.id() == "POPM") {
+                let popularimeter = match get_content_popm(popm) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        report_error(output, ErrorKind::Io, e);
+                        continue;
+                    },
+                };
+                if popularimeter.user == *owner_query {
+                    write!(output.writer(), "{} {}", popularimeter.rating, popularimeter.counter)?;
+                    return Ok(true);
+                }
+            }
+        },
+        x if is_itunes_text_id(x) => {
+            if let Some(frame) = tag.get(x) {
+                write!(output.writer(), "{}", get_itunes_text(frame)?.replace('\0', value_sep))?;
+                return Ok(true);
+            }
+        },
+        x if x.starts_with('T') => {
+            if let Some(frame) = tag.get(x) {
+                let mut text = get_content_text(frame)?.to_string();
+                if options.genre_names && x == "TCON" {
+                    text = resolve_genre_references(&text);
+                }
+                write!(output.writer(), "{}", text.replace('\0', value_sep))?;
+                return Ok(true);
+            }
+        },
+        x if x.starts_with('W') => {
+            if let Some(frame) = tag.get(x) {
+                write!(output.writer(), "{}", get_content_link(frame)?)?;
+                return Ok(true);
+            }
+        },
+        x => {
+            if let Some(frame) = tag.get(x) {
+                match (binary_format, frame.content()) {
+                    (BinaryFormat::Raw, Content::Picture(picture)) => print_binary(output.writer(), &picture.data, binary_format)?,
+                    (BinaryFormat::Raw, Content::Unknown(unknown)) => print_binary(output.writer(), &unknown.data, binary_format)?,
+                    (_, content) => write!(output.writer(), "{content}")?,
+                }
+                return Ok(true);
+            }
+        },
+    }
+    // Frame not found
+    report_file_error(output, fpath, ErrorKind::NotFound, format_args!("Could not print {}: Frame not found", frame_to_string(frame)?));
+    if let Some(missing) = &options.missing {
+        write!(output.writer(), "{missing}")?;
+    }
+    Ok(false)
+}
+
+/// Prints binary frame data to `out` in the given format, or as it is, if the format is raw.
+fn print_binary(out: &mut dyn Write, data: &[u8], format: BinaryFormat) -> io::Result<()> {
+    match format {
+        BinaryFormat::Raw => out.write_all(data),
+        format => write!(out, "{}", encode_binary(data, format)),
+    }
+}
+
+/// ANSI escape sequence used for frame IDs in colored output.
+const COLOR_ID: &str = "\x1b[1;36m";
+/// ANSI escape sequence used for frame descriptions and languages in colored output.
+const COLOR_DESC: &str = "\x1b[33m";
+/// ANSI escape sequence used for frame values in colored output.
+const COLOR_VALUE: &str = "\x1b[32m";
+/// ANSI escape sequence resetting the color.
+const COLOR_RESET: &str = "\x1b[0m";
+
+/// Wraps text in the given color escape sequence, if `color` is set.
+fn paint(text: &str, code: &str, color: bool) -> String {
+    if color {
+        format!("{code}{text}{COLOR_RESET}")
+    } else {
+        text.to_string()
+    }
+}
+
+/// Pretty-prints a single frame's name and contents to `out`, optionally colored with ANSI escapes.
+/// Multiple values of text frames are separated with `value_sep`. If `describe` is set, the
+/// frame's ID is followed by its human-readable name, e.g. "TPE1 (Lead artist/performer/soloist/group)".
+pub fn print_frame_pretty(out: &mut dyn Write, frame: &Frame, color: bool, value_sep: &str, describe: bool) -> Result<()> {
+    writeln!(out, "{}", format_frame_pretty(frame, color, value_sep, describe)?)?;
+    Ok(())
+}
+
+/// Formats a single frame's name and contents like `print_frame_pretty`, without a trailing
+/// newline. Some frames span several lines.
+pub fn format_frame_pretty(frame: &Frame, color: bool, value_sep: &str, describe: bool) -> Result<String> {
+    let id = paint(frame.id(), COLOR_ID, color);
+    let id = match frame_name(frame.id()).filter(|_| describe) {
+        Some(name) => format!("{id} ({name})"),
+        None => id,
+    };
+    let desc = |x: &str| paint(x, COLOR_DESC, color);
+    let value = |x: &str| paint(x, COLOR_VALUE, color);
+    let string = match frame.id() {
+        "TXXX" => {
+            let extended_text = get_content_txxx(frame)?;
+            format!("{id}[{}]: {}", desc(&extended_text.description), value(&extended_text.value))
+        },
+        "WXXX" => {
+            let extended_link = get_content_wxxx(frame)?;
+            format!("{id}[{}]: {}", desc(&extended_link.description), value(&extended_link.link))
+        },
+        "COMM" => {
+            let comment = get_content_comm(frame)?;
+            format!("{id}[{}]({}): {}", desc(&comment.description), desc(&comment.lang), value(&comment.text))
+        },
+        "USLT" => {
+            let lyrics = get_content_uslt(frame)?;
+            format!("{id}[{}]({}): {}", desc(&lyrics.description), desc(&lyrics.lang), value(&lyrics.text))
+        },
+        "SYLT" => {
+            let sylt = get_content_sylt(frame)?;
+            let mut string = format!("{id}[{}]({}): {}", desc(&sylt.description), desc(&sylt.lang), sylt.content_type);
+            for (time, text) in &sylt.content {
+                let time = match sylt.timestamp_format {
+                    TimestampFormat::Ms => format_timestamp(*time),
+                    TimestampFormat::Mpeg => format!("frame {time}"),
+                };
+                string.push_str(&format!("\n  [{time}] {}", value(text)));
+            }
+            string
+        },
+        "TIPL" | "TMCL" | "IPLS" => {
+            let items = get_content_people(frame)?.items.iter()
+                .map(|x| format!("{}: {}", desc(&x.involvement), value(&x.involvee)))
+                .collect::<Vec<_>>();
+            format!("{id}: {}", items.join(", "))
+        },
+        "USER" => {
+            let terms_of_use = TermsOfUse::from_frame(frame)?;
+            format!("{id}({}): {}", desc(&terms_of_use.lang), value(&terms_of_use.text))
+        },
+        "GEOB" => {
+            let object = get_content_geob(frame)?;
+            format!("{id}[{}]: {}", desc(&object.description),
+                value(&format!("{} ({}), {} bytes", object.filename, object.mime_type, object.data.len())))
+        },
+        "PRIV" => {
+            let private = get_content_priv(frame)?;
+            format!("{id}[{}]: {}", desc(&private.owner_identifier),
+                value(&format!("{} bytes", private.private_data.len())))
+        },
+        "UFID" => {
+            let unique_file_identifier = get_content_ufid(frame)?;
+            format!("{id}[{}]: {}", desc(&unique_file_identifier.owner_identifier),
+                value(&String::from_utf8_lossy(&unique_file_identifier.identifier)))
+        },
+        "PCNT" => {
+            format!("{id}: {}", value(&get_play_counter(frame)?.to_string()))
+        },
+        "ETCO" => {
+            let codes = get_event_timing_codes(frame)?;
+            let mut string = format!("{id}:");
+            for (event_type, time) in &codes.events {
+                string.push_str(&format!("\n  [{}] {}", codes.format_time(*time), value(&event_type_name(*event_type))));
+            }
+            string
+        },
+        "POPM" => {
+            let popularimeter = get_content_popm(frame)?;
+            format!("{id}[{}]: {}", desc(&popularimeter.user),
+                value(&format!("rating {}/255, counter {}", popularimeter.rating, popularimeter.counter)))
+        },
+        "CHAP" => {
+            let chapter = get_content_chap(frame)?;
+            format!("{id}[{}]: {}-{}: {}", desc(&chapter.element_id), format_chapter_time(chapter.start_time),
+                format_chapter_time(chapter.end_time), value(chapter_title(chapter)))
+        },
+        "CTOC" => {
+            let toc = get_content_ctoc(frame)?;
+            format!("{id}[{}]: {}{}: {}", desc(&toc.element_id), if toc.top_level { "top-level" } else { "nested" },
+                if toc.ordered { ", ordered" } else { "" }, value(&toc.elements.join(", ")))
+        },
+        str if is_itunes_text_id(str) => {
+            format!("{id}: {}", value(&get_itunes_text(frame)?.replace('\0', value_sep)))
+        },
+        str if str.starts_with('T') => {
+            format!("{id}: {}", value(&get_content_text(frame)?.replace('\0', value_sep)))
+        },
+        str if str.starts_with('W') => {
+            format!("{id}: {}", value(get_content_link(frame)?))
+        },
+        _ => {
+            format!("{id}: {}", value(&frame.content().to_string()))
+        },
+    };
+    Ok(string)
+}
+
+/// Returns the path of the sidecar file to which the lyrics of a file are exported.
+/// See `expand_path_template` for the meaning of `template`. By default, the file's extension
+/// is replaced with `.txt`.
+pub fn lyrics_export_path(fpath: &impl AsRef<Path>, template: Option<&str>) -> PathBuf {
+    match template {
+        Some(x) => expand_path_template(fpath, x),
+        None => fpath.as_ref().with_extension("txt"),
+    }
+}
+
+/// Returns the path of a sidecar file of a file, according to a template.
+/// `template` may contain `{dir}` (the file's directory), `{name}` (the file name without its
+/// extension) and `{file}` (the full file name).
+pub fn expand_path_template(fpath: &impl AsRef<Path>, template: &str) -> PathBuf {
+    let fpath = fpath.as_ref();
+    let dir = match fpath.parent() {
+        Some(x) if !x.as_os_str().is_empty() => x.as_os_str(),
+        _ => OsStr::new("."),
+    };
+    let name = fpath.file_stem().unwrap_or_default();
+    let file = fpath.file_name().unwrap_or_default();
+    let mut result = OsString::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push(&rest[..start]);
+        rest = &rest[start..];
+        let (value, len) = if rest.starts_with("{dir}") {
+            (dir, "{dir}".len())
+        } else if rest.starts_with("{name}") {
+            (name, "{name}".len())
+        } else if rest.starts_with("{file}") {
+            (file, "{file}".len())
+        } else {
+            (OsStr::new("{"), 1)
+        };
+        result.push(value);
+        rest = &rest[len..];
+    }
+    result.push(rest);
+    PathBuf::from(result)
+}
+
+/// Writes the text of the first USLT frame of a tag to a sidecar file.
+/// `fpath` is the path of the tagged file. See `lyrics_export_path` for the meaning of `template`.
+/// Returns whether the tag had any lyrics to export.
+pub fn export_lyrics(output: &mut dyn Output, tag: &Tag, fpath: &impl AsRef<Path>, template: Option<&str>) -> Result<bool> {
+    let lyrics = match tag.lyrics().next() {
+        Some(x) => x,
+        None => {
+            report_file_error(output, fpath, ErrorKind::NotFound, "Could not export lyrics: Frame not found");
+            return Ok(false);
+        },
+    };
+    let dest = lyrics_export_path(fpath, template);
+    let mut text = lyrics.text.trim_end_matches('\0').to_string();
+    text.push('\n');
+    if let Err(e) = write(&dest, text) {
+        return Err(anyhow!("Failed to export lyrics to '{}': {e}", dest.display()));
+    }
+    Ok(true)
+}
+
+/// Finds the GEOB frame with the given description.
+fn find_geob<'a>(tag: &'a Tag, description: &str) -> Option<&'a EncapsulatedObject> {
+    tag.frames()
+        .filter_map(|x| x.content().encapsulated_object())
+        .find(|x| x.description == description)
+}
+
+/// Constructs a GEOB frame embedding a file, with the given description and MIME type.
+pub fn geob_frame_from_file(description: String, mime_type: String, fpath: &impl AsRef<Path>) -> Result<Frame> {
+    let fpath = fpath.as_ref();
+    let data = match read(fpath) {
+        Ok(x) => x,
+        Err(e) => return Err(anyhow!("Failed to read '{}': {e}", fpath.display())),
+    };
+    let object = EncapsulatedObject {
+        mime_type,
+        filename: fpath.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+        description,
+        data,
+    };
+    Ok(Frame::with_content("GEOB", Content::EncapsulatedObject(object)))
+}
+
+/// Writes the data of the GEOB frame with the given description to a file.
+/// `fpath` is the path of the tagged file. See `expand_path_template` for the meaning of `template`.
+/// Returns whether the tag had a matching GEOB to extract.
+pub fn extract_geob(output: &mut dyn Output, tag: &Tag, description: &str, fpath: &impl AsRef<Path>, template: &str) -> Result<bool> {
+    let object = match find_geob(tag, description) {
+        Some(x) => x,
+        None => {
+            report_file_error(output, fpath, ErrorKind::NotFound, format_args!("Could not extract GEOB[{description}]: Frame not found"));
+            return Ok(false);
+        },
+    };
+    let dest = expand_path_template(fpath, template);
+    if let Err(e) = write(&dest, &object.data) {
+        return Err(anyhow!("Failed to extract GEOB[{description}] to '{}': {e}", dest.display()));
+    }
+    Ok(true)
+}
+
+/// Removes all frames of a tag for which `keep` returns false, and returns them.
+pub fn retain_tag_frames(tag: &mut Tag, keep: impl Fn(&Frame) -> bool) -> Vec<Frame> {
+    let ids = tag.frames().filter(|x| !keep(x)).map(|x| x.id().to_string()).collect::<BTreeSet<_>>();
+    let mut removed = vec![];
+    for id in ids {
+        for frame in tag.remove(&id) {
+            if keep(&frame) {
+                tag.add_frame(frame);
+            } else {
+                removed.push(frame);
+            }
+        }
+    }
+    removed
+}
+
+/// Replaces a frame of a tag with a transformed version of it, which may differ in its description
+/// or language, and so would not replace it when simply added.
+pub fn replace_exact_tag_frame(tag: &mut Tag, old_frame: &Frame, new_frame: Frame) {
+    for frame in tag.remove(old_frame.id()) {
+        if frame != *old_frame {
+            tag.add_frame(frame);
+        }
+    }
+    tag.add_frame(new_frame);
+}
+
+/// Adds a frame to a tag, replacing all frames matching it as a query.
+/// This is needed for frames which the id3 crate only considers duplicates if their contents are
+/// identical as a whole. Returns whether any frame was replaced.
+pub fn replace_tag_frame(tag: &mut Tag, frame: Frame) -> Result<bool> {
+    let mut is_replaced = false;
+    for removed_frame in tag.remove(frame.id()) {
+        if frames_query_equal(&frame, &removed_frame)? {
+            is_replaced = true;
+        } else {
+            tag.add_frame(removed_frame);
+        }
+    }
+    tag.add_frame(frame);
+    Ok(is_replaced)
+}
+
+/// Deletes a frame matching a query from a tag.
+/// `fpath` is only used for message prints.
+/// Returns whether tag was modified.
+pub fn delete_tag_frame(output: &mut dyn Output, tag: &mut Tag, frame: &Frame, fpath: impl AsRef<Path>) -> Result<bool> {
+    let mut found = false;
+
+    if matches!(frame.id(), "TIPL" | "TMCL") {
+        // Involved people lists are edited entry by entry, rather than deleted as a whole
+        found = remove_involvement(tag, frame.id(), &get_content_people(frame)?.items[0].involvement);
+    } else {
+        // Not the most efficient approach, but the id3 crate does not seem to provide a nicer way
+        for removed_frame in tag.remove(frame.id()) {
+            if frames_query_equal(frame, &removed_frame)? {
+                // Remove this frame (i.e. don't add it back)
+                found = true
+            } else {
+                tag.add_frame(removed_frame);
+            }
+        }
+    }
+    if !found {
+        report_file_error(output, fpath, ErrorKind::NotFound, format_args!("Could not delete {}: Frame not found", frame_to_string(frame)?));
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Checks if adding a frame to a tag would replace any of the tag's frames.
+pub fn conflicts_with_tag(tag: &Tag, frame: &Frame) -> bool {
+    tag.clone().add_frame(frame.clone()).is_some()
+}
+
+/// Returns whether two frames are identical except for the relevant content component.
+/// E.g. two text types are equal iff their IDs match, but two COMMs are equal iff
+/// their IDs, descriptions and languages match.
+pub fn frames_query_equal(frame1: &Frame, frame2: &Frame) -> Result<bool, anyhow::Error> {
+    if frame1.id() != frame2.id() {
+        return Ok(false);
+    }
+    match frame1.id() {
+        "TXXX" => {
+            let extended_text1 = get_content_txxx(frame1)?;
+            let extended_text2 = get_content_txxx(frame2)?;
+            if extended_text1.description != extended_text2.description {
+                return Ok(false);
+            }
+        },
+        "WXXX" => {
+            let extended_link1 = get_content_wxxx(frame1)?;
+            let extended_link2 = get_content_wxxx(frame2)?;
+            if extended_link1.description != extended_link2.description {
+                return Ok(false);
+            }
+        },
+
+        "COMM" => {
+            let comment1 = get_content_comm(frame1)?;
+            let comment2 = get_content_comm(frame2)?;
+            if comment1.description != comment2.description || comment1.lang != comment2.lang {
+                return Ok(false);
+            }
+        },
+        "USLT" => {
+            let lyrics1 = get_content_uslt(frame1)?;
+            let lyrics2 = get_content_uslt(frame2)?;
+            if lyrics1.description != lyrics2.description || lyrics1.lang != lyrics2.lang {
+                return Ok(false);
+            }
+        },
+        "SYLT" => {
+            let sylt1 = get_content_sylt(frame1)?;
+            let sylt2 = get_content_sylt(frame2)?;
+            if sylt1.description != sylt2.description || sylt1.lang != sylt2.lang {
+                return Ok(false);
+            }
+        },
+        "POPM" => {
+            let popularimeter1 = get_content_popm(frame1)?;
+            let popularimeter2 = get_content_popm(frame2)?;
+            if popularimeter1.user != popularimeter2.user {
+                return Ok(false);
+            }
+        },
+        "USER" => {
+            let terms_of_use1 = TermsOfUse::from_frame(frame1)?;
+            let terms_of_use2 = TermsOfUse::from_frame(frame2)?;
+            if terms_of_use1.lang != terms_of_use2.lang {
+                return Ok(false);
+            }
+        },
+        "GEOB" => {
+            let object1 = get_content_geob(frame1)?;
+            let object2 = get_content_geob(frame2)?;
+            if object1.description != object2.description {
+                return Ok(false);
+            }
+        },
+        "PRIV" => {
+            let private1 = get_content_priv(frame1)?;
+            let private2 = get_content_priv(frame2)?;
+            if private1.owner_identifier != private2.owner_identifier {
+                return Ok(false);
+            }
+        },
+        "UFID" => {
+            let unique_file_identifier1 = get_content_ufid(frame1)?;
+            let unique_file_identifier2 = get_content_ufid(frame2)?;
+            if unique_file_identifier1.owner_identifier != unique_file_identifier2.owner_identifier {
+                return Ok(false);
+            }
+        },
+        "CHAP" => {
+            let chapter1 = get_content_chap(frame1)?;
+            let chapter2 = get_content_chap(frame2)?;
+            if chapter1.element_id != chapter2.element_id {
+                return Ok(false);
+            }
+        },
+        "CTOC" => {
+            let toc1 = get_content_ctoc(frame1)?;
+            let toc2 = get_content_ctoc(frame2)?;
+            if toc1.element_id != toc2.element_id {
+                return Ok(false);
+            }
+        },
+        _ => (),
+    }
+    Ok(true)
+}
+
+/// Create a new tag of the given version, from an existing tag.
+/// Date frames are converted to their equivalents in the target version (see `convert_date_frames`).
+/// If `force` is true, any other frames that cannot exist in the target version are simply omitted
+/// from the result. Otherwise, an error is returned.
+pub fn tag_with_version_from(tag: &Tag, target_version: Version, force: bool) -> Result<Tag> {
+    if tag.version() == target_version {
+        return Ok(tag.clone());
+    }
+
+    let mut new_tag = Tag::with_version(target_version);
+    let (date_frames, replaced_ids) = convert_date_frames(tag, target_version);
+    for frame in date_frames {
+        new_tag.add_frame(frame);
+    }
+    let frames = tag.frames().filter(|x| !replaced_ids.contains(&x.id()));
+    if force {
+        for frame in frames.filter(|x| x.id_for_version(target_version).is_some()) {
+            new_tag.add_frame(frame.clone());
+        }
+    } else {
+        let frames = frames.collect::<Vec<_>>();
+        let incompatible_frames = frames.iter()
+            .filter(|&x| x.id_for_version(target_version).is_none())
+            .map(|x| x.id())
+            .collect::<Vec<&str>>();
+        if !incompatible_frames.is_empty() {
+            return Err(anyhow!("Cannot convert tag from {} to {}: Incompatible frames: {}",
+                tag.version(), target_version, incompatible_frames.join(", ")));
+        }
+        for frame in frames {
+            new_tag.add_frame(frame.clone());
+        }
+    }
+    Ok(new_tag)
+}
+
+/// Sets the text encoding of all frames of a tag. UTF-16BE and UTF-8 are replaced with UTF-16 in
+/// tags older than ID3v2.4, as is Latin-1 for frames with text that it cannot represent.
+pub fn set_text_encoding(tag: &mut Tag, encoding: Encoding) {
+    let encoding = match (encoding, tag.version()) {
+        (Encoding::UTF16BE | Encoding::UTF8, Version::Id3v22 | Version::Id3v23) => Encoding::UTF16,
+        (x, _) => x,
+    };
+    let frames = tag.frames().cloned().collect::<Vec<_>>();
+    for frame in frames {
+        let is_latin1 = frame.content().to_string().chars().all(|x| x <= '\u{ff}');
+        let encoding = match encoding {
+            Encoding::Latin1 if !is_latin1 => Encoding::UTF16,
+            x => x,
+        };
+        tag.add_frame(frame.set_encoding(Some(encoding)));
+    }
+}
+
+/// Reads the ID3v2 tag of a file, respecting the file's format.
+#[allow(deprecated)] // The format-specific functions are deprecated in newer id3 versions
+pub fn read_tag(fpath: &impl AsRef<Path>) -> id3::Result<Tag> {
+    match FileFormat::detect(fpath) {
+        FileFormat::Mpeg => Tag::read_from_path(fpath),
+        FileFormat::Aiff => Tag::read_from_aiff_path(fpath),
+        FileFormat::Wav => Tag::read_from_wav_path(fpath),
+    }
+}
+
+/// Reads the ID3v2 tag of a file held in memory, e.g. read from stdin, respecting the file's
+/// format. Without a file name, MPEG audio is assumed unless magic bytes say otherwise.
+#[allow(deprecated)] // The format-specific functions are deprecated in newer id3 versions
+pub fn read_tag_from_bytes(bytes: &[u8]) -> id3::Result<Tag> {
+    match FileFormat::from_magic(bytes).unwrap_or(FileFormat::Mpeg) {
+        FileFormat::Mpeg => Tag::read_from2(Cursor::new(bytes)),
+        FileFormat::Aiff => Tag::read_from_aiff(Cursor::new(bytes)),
+        FileFormat::Wav => Tag::read_from_wav(Cursor::new(bytes)),
+    }
+}
+
+/// Size of the blocks in which the padding following a tag is read from a stream.
+const STREAM_BLOCK_SIZE: usize = 1 << 12;
+
+/// Reads the start of a file from a stream, up to and including its ID3v2 tag and the padding
+/// which follows it, so that the tag can be read with `read_tag_from_bytes` and rewritten with
+/// `write_tag_to_bytes` without holding the audio data in memory. The bytes read may run a little
+/// past the padding, and the rest of the file is left in the stream. Chunk-based formats may store
+/// their tag anywhere, so they are read as a whole.
+pub fn read_stream_head(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    // Enough for both the magic bytes of chunk-based formats and an ID3v2 tag header
+    let mut bytes = vec![];
+    reader.by_ref().take(12).read_to_end(&mut bytes)?;
+    if FileFormat::from_magic(&bytes).is_some() {
+        reader.read_to_end(&mut bytes)?;
+        return Ok(bytes);
+    }
+    let tag_size = match TagHeader::read_from(Cursor::new(&bytes), 0)? {
+        Some(x) => x.total_size(),
+        None => return Ok(bytes),
+    };
+    reader.by_ref().take(tag_size.saturating_sub(bytes.len() as u64)).read_to_end(&mut bytes)?;
+    // Zero bytes after the tag are padding as well, up to the first byte of audio data
+    let mut is_padding = bytes.get(tag_size as usize..).is_none_or(|x| x.iter().all(|&x| x == 0));
+    let mut block = [0; STREAM_BLOCK_SIZE];
+    while is_padding {
+        let n = match reader.read(&mut block) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        bytes.extend_from_slice(&block[..n]);
+        is_padding = block[..n].iter().all(|&x| x == 0);
+    }
+    Ok(bytes)
+}
+
+/// Reads the ID3v2 tag of a file whose frames are to be copied to other files.
+/// Unlike with `read_tag`, a missing tag is an error.
+pub fn read_source_tag(fpath: &impl AsRef<Path>) -> Result<Tag> {
+    read_tag(fpath).map_err(|e| match e.kind {
+        id3::ErrorKind::NoTag => anyhow!("Source file '{}' has no ID3v2 tag", fpath.as_ref().display()),
+        _ => anyhow!("Failed to read tag from file '{}': {e}", fpath.as_ref().display()),
+    })
+}
+
+/// Reads the ID3v2 tag of a file, skipping corrupt frames if `lenient` is set.
+/// Skipped frames are reported to `output`.
+pub fn read_tag_for(output: &mut dyn Output, fpath: &impl AsRef<Path>, lenient: bool) -> id3::Result<Tag> {
+    if !lenient {
+        return read_tag(fpath);
+    }
+    let (tag, skipped) = read_tag_lenient(fpath)?;
+    for id in skipped {
+        report_file_error(output, fpath, ErrorKind::Warning, format_args!("Skipped corrupt {id} frame"));
+    }
+    Ok(tag)
+}
+
+/// Reads the ID3v2 tag of a file like `read_tag`, but if the tag cannot be read as a whole, each
+/// of its stored frames is decoded on its own and corrupt frames are skipped.
+/// Returns the tag and the IDs of all skipped frames.
+pub fn read_tag_lenient(fpath: &impl AsRef<Path>) -> id3::Result<(Tag, Vec<String>)> {
+    let error = match read_tag(fpath) {
+        Ok(tag) => return Ok((tag, vec![])),
+        Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => return Err(e),
+        Err(e) => e,
+    };
+    let layout = match read_tag_layout(fpath) {
+        Ok(Some(x)) => x,
+        _ => return Err(error),
+    };
+    let mut tag = Tag::with_version(match layout.header.major_version {
+        2 => Version::Id3v22,
+        3 => Version::Id3v23,
+        4 => Version::Id3v24,
+        _ => return Err(error),
+    });
+    let mut skipped = vec![];
+    for frame in &layout.frames {
+        match decode_stored_frame(&layout, frame) {
+            Some(x) => { tag.add_frame(x); },
+            None => skipped.push(frame.id.clone()),
+        }
+    }
+    Ok((tag, skipped))
+}
+
+/// Decodes a single frame stored in a tag, regardless of any other frames.
+/// Returns `None` if the frame is corrupt.
+pub fn decode_stored_frame(layout: &TagLayout, frame: &FrameLayout) -> Option<Frame> {
+    // Wrap the frame in a tag of its own, as frames cannot be decoded separately
+    let mut bytes = b"ID3".to_vec();
+    bytes.extend([layout.header.major_version, 0, layout.header.flags & FLAG_UNSYNCHRONISATION]);
+    bytes.extend(encode_synchsafe(frame.bytes.len() as u32));
+    bytes.extend(&frame.bytes);
+    Tag::read_from2(Cursor::new(bytes)).ok()?.frames().next().cloned()
+}
+
+/// Encodes text the way the body of a text frame is stored, i.e. a text encoding byte followed by
+/// the text. ISO-8859-1 is used if possible, or UTF-16 otherwise, which are valid in any version.
+pub fn encode_text_body(text: &str) -> Vec<u8> {
+    let encoding = if text.chars().all(|x| x <= '\u{ff}') { Encoding::Latin1 } else { Encoding::UTF16 };
+    let frame = Frame::text("TIT2", text).set_encoding(Some(encoding));
+    // Text frames can always be encoded, and ID3v2.4 frame headers are 10 bytes long
+    encode_frame(&frame, Version::Id3v24).unwrap().split_off(10)
+}
+
+/// Decodes the body of a frame with the given ID which is stored like a text frame, i.e. a text
+/// encoding byte followed by the text.
+pub fn decode_text_body(id: &str, body: &[u8]) -> Result<String> {
+    // Let the id3 crate decode the body as that of a text frame, wrapped in a tag of its own
+    let mut bytes = b"ID3\x04\0\0".to_vec();
+    bytes.extend(encode_synchsafe(body.len() as u32 + 10));
+    bytes.extend(b"TIT2");
+    bytes.extend(encode_synchsafe(body.len() as u32));
+    bytes.extend([0, 0]);
+    bytes.extend(body);
+    let tag = Tag::read_from2(Cursor::new(bytes)).map_err(|e| anyhow!("{id} frame has invalid text: {e}"))?;
+    match tag.get("TIT2").and_then(|x| x.content().text()) {
+        Some(text) => Ok(text.to_string()),
+        None => Err(anyhow!("{id} frame has invalid text")),
+    }
+}
+
+/// Reads the raw layout of the ID3v2 tag of a file, respecting the file's format.
+pub fn read_tag_layout(fpath: &impl AsRef<Path>) -> Result<Option<TagLayout>> {
+    let result = File::open(fpath).and_then(|mut file| {
+        match id3_tag_offset(&mut file, FileFormat::detect(fpath))? {
+            Some(offset) => TagLayout::read_from(&mut file, offset),
+            None => Ok(None),
+        }
+    });
+    result.map_err(|e| anyhow!("Failed to read tag header from '{}': {e}", fpath.as_ref().display()))
+}
+
+/// Computes how the stored frame headers of a file change when the given status flags are set
+/// (or cleared), as the offsets of the flag bytes in the file and their new values. Frames whose
+/// flags are already as requested are skipped.
+pub fn frame_flag_patches(fpath: &impl AsRef<Path>, changes: &[(String, FrameFlag, bool)]) -> Result<Vec<(u64, [u8; 2])>> {
+    let fpath = fpath.as_ref();
+    let result = File::open(fpath).and_then(|mut file| {
+        match id3_tag_offset(&mut file, FileFormat::detect(&fpath))? {
+            Some(offset) => Ok(TagLayout::read_from(&mut file, offset)?.map(|x| (offset, x))),
+            None => Ok(None),
+        }
+    });
+    let (offset, layout) = match result {
+        Ok(Some(x)) => x,
+        Ok(None) => return Err(anyhow!("Cannot set frame flags of '{}': File has no tag", fpath.display())),
+        Err(e) => return Err(anyhow!("Failed to read tag header from '{}': {e}", fpath.display())),
+    };
+    if layout.header.flags & FLAG_UNSYNCHRONISATION != 0 {
+        return Err(anyhow!("Cannot set frame flags of '{}': Tag is unsynchronised", fpath.display()));
+    }
+
+    let mut patches = vec![];
+    let mut pos = offset + TAG_HEADER_SIZE + layout.extended_header_size;
+    for frame in &layout.frames {
+        let mut flags = frame.flags();
+        for (_, flag, value) in changes.iter().filter(|(x, _, _)| *x == frame.id) {
+            let Some(mask) = flag.mask(layout.header.major_version) else {
+                return Err(anyhow!("Cannot set frame flags of '{}': ID3v2.2 frames have no flags", fpath.display()));
+            };
+            match value {
+                true => flags |= mask,
+                false => flags &= !mask,
+            }
+        }
+        if flags != frame.flags() {
+            patches.push((pos + 8, flags.to_be_bytes()));
+        }
+        pos += frame.size;
+    }
+    Ok(patches)
+}
+
+/// Writes bytes computed by `frame_flag_patches` at their offsets in the file.
+pub fn write_frame_flag_patches(fpath: &impl AsRef<Path>, patches: &[(u64, [u8; 2])]) -> Result<()> {
+    let result = OpenOptions::new().write(true).open(fpath).and_then(|mut file| {
+        for (offset, bytes) in patches {
+            file.seek(SeekFrom::Start(*offset))?;
+            file.write_all(bytes)?;
+        }
+        Ok(())
+    });
+    result.map_err(|e| anyhow!("Failed to write frame flags to '{}': {e}", fpath.as_ref().display()))
+}
+
+/// Encodes a single frame, including its header, as it would be stored in a tag of the given
+/// version.
+pub fn encode_frame(frame: &Frame, version: Version) -> Result<Vec<u8>> {
+    let mut tag = Tag::with_version(version);
+    tag.add_frame(frame.clone());
+    let mut buf = vec![];
+    Encoder::new().version(version).padding(0).encode(&tag, &mut buf)
+        .map_err(|e| anyhow!("Failed to encode {}: {e}", frame.id()))?;
+    Ok(buf.split_off(TAG_HEADER_SIZE as usize))
+}
+
+/// Returns the raw bytes of the first frame of a tag matching the query, including its header.
+/// The bytes are taken from the stored tag, if its layout is given, so that frames which cannot
+/// be decoded are found by their ID as well. Otherwise, the frame is encoded in the tag's version.
+pub fn raw_frame_bytes(tag: &Tag, layout: Option<&TagLayout>, query: &Frame) -> Result<Option<Vec<u8>>> {
+    let Some(layout) = layout else {
+        for frame in tag.frames() {
+            if frames_query_equal(frame, query)? {
+                return encode_frame(frame, tag.version()).map(Some);
+            }
+        }
+        return Ok(None);
+    };
+    for stored in layout.frames.iter().filter(|x| x.id == query.id()) {
+        let is_match = match decode_stored_frame(layout, stored) {
+            Some(frame) => frames_query_equal(&frame, query)?,
+            None => true,
+        };
+        if is_match {
+            return Ok(Some(stored.bytes.clone()));
+        }
+    }
+    Ok(None)
+}
+
+/// Reads the raw bytes of a file's ID3v2 tag, including its header and footer.
+/// Returns no bytes if the file has no tag.
+pub fn read_raw_tag(fpath: &impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    let mut file = File::open(fpath)?;
+    let offset = match id3_tag_offset(&mut file, FileFormat::detect(fpath))? {
+        Some(x) => x,
+        None => return Ok(vec![]),
+    };
+    let header = match TagHeader::read_from(&mut file, offset)? {
+        Some(x) => x,
+        None => return Ok(vec![]),
+    };
+    let mut bytes = vec![];
+    file.seek(SeekFrom::Start(offset))?;
+    file.take(header.total_size()).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Replaces the ID3v2 tag of a file with the raw bytes of a tag, e.g. read by `read_raw_tag`,
+/// keeping them byte for byte. For MPEG files, any padding following the old tag is dropped.
+pub fn write_raw_tag(fpath: &impl AsRef<Path>, bytes: &[u8]) -> Result<()> {
+    let fpath = fpath.as_ref();
+    let is_tag = TagHeader::read_from(Cursor::new(bytes), 0)
+        .is_ok_and(|x| x.is_some_and(|y| y.total_size() == bytes.len() as u64));
+    if !is_tag {
+        return Err(anyhow!("Cannot restore the tag of '{}': Not a raw ID3v2 tag", fpath.display()));
+    }
+    let result = match FileFormat::detect(&fpath) {
+        FileFormat::Mpeg => OpenOptions::new().read(true).write(true).open(fpath).and_then(|mut file| {
+            let region_size = tag_region_size(&mut file)?;
+            resize_file_region(&mut file, 0, region_size, bytes.len() as u64)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(bytes)
+        }),
+        format => replace_id3_chunk(&fpath, format, bytes),
+    };
+    result.map_err(|e| anyhow!("Failed to restore the tag of '{}': {e}", fpath.display()))
+}
+
+/// Removes all ID3 tags from a file, respecting the file's format.
+pub fn remove_tag(fpath: &impl AsRef<Path>) -> Result<()> {
+    let result = match FileFormat::detect(fpath) {
+        FileFormat::Mpeg => id3::v1v2::remove_from_path(fpath).map(|_| ()).map_err(anyhow::Error::from),
+        format => remove_id3_chunk(fpath, format).map(|_| ()).map_err(anyhow::Error::from),
+    };
+    if let Err(e) = result {
+        return Err(anyhow!("Failed to purge the tag of '{}': {e}", fpath.as_ref().display()));
+    }
+    Ok(())
+}
+
+/// Encodes a tag to replace the region of `region_size` bytes at the start of an MPEG file,
+/// followed by `padding` bytes of padding. If `padding` is `None` and the new tag fits in the
+/// region, the remaining space is used as padding, so that the audio data need not be moved.
+fn encode_mpeg_tag(tag: &Tag, region_size: u64, version: Version, padding: Option<usize>) -> id3::Result<Vec<u8>> {
+    let mut buf = vec![];
+    Encoder::new().version(version).padding(padding.unwrap_or(0)).encode(tag, &mut buf)?;
+    if padding.is_none() && buf.len() as u64 <= region_size {
+        let padding = region_size as usize - buf.len();
+        buf.clear();
+        Encoder::new().version(version).padding(padding).encode(tag, &mut buf)?;
+    }
+    Ok(buf)
+}
+
+/// Writes a tag to an MPEG file, followed by `padding` bytes of padding.
+/// If `padding` is `None` and the new tag fits in the region occupied by the old tag and its
+/// padding, it is written in place with the remaining space used as padding. Otherwise, all audio
+/// data has to be moved, which can be very slow for large files.
+fn write_mpeg_tag(tag: &Tag, fpath: &impl AsRef<Path>, version: Version, padding: Option<usize>) -> id3::Result<()> {
+    let mut file = OpenOptions::new().read(true).write(true).open(fpath)?;
+    let region_size = tag_region_size(&mut file)?;
+    let buf = encode_mpeg_tag(tag, region_size, version, padding)?;
+    resize_file_region(&mut file, 0, region_size, buf.len() as u64)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&buf)?;
+    Ok(())
+}
+
+/// Writes a tag to a file held in memory, e.g. to be written to stdout, respecting the file's
+/// format. For MPEG audio, the tag is followed by `padding` bytes of padding, like with
+/// `write_mpeg_tag`.
+#[allow(deprecated)] // The format-specific functions are deprecated in newer id3 versions
+pub fn write_tag_to_bytes(tag: &Tag, bytes: &mut Vec<u8>, version: Version, padding: Option<usize>) -> Result<()> {
+    let result = match FileFormat::from_magic(bytes).unwrap_or(FileFormat::Mpeg) {
+        FileFormat::Mpeg => tag_region_size(Cursor::new(&bytes[..]))
+            .map_err(id3::Error::from)
+            .and_then(|region_size| {
+                let buf = encode_mpeg_tag(tag, region_size, version, padding)?;
+                bytes.splice(0..region_size as usize, buf);
+                Ok(())
+            }),
+        format => {
+            let mut cursor = Cursor::new(std::mem::take(bytes));
+            let result = match format {
+                FileFormat::Aiff => tag.write_to_aiff_file(&mut cursor, version),
+                _ => tag.write_to_wav_file(&mut cursor, version),
+            };
+            *bytes = cursor.into_inner();
+            result
+        },
+    };
+    result.map_err(|e| anyhow!("Failed to write tag: {e}"))
+}
+
+/// Writes a tag to a file of the given format.
+#[allow(deprecated)] // The format-specific functions are deprecated in newer id3 versions
+fn write_tag_to_path(tag: &Tag, fpath: &impl AsRef<Path>, format: FileFormat, version: Version, padding: Option<usize>) -> id3::Result<()> {
+    match format {
+        FileFormat::Mpeg => write_mpeg_tag(tag, fpath, version, padding),
+        FileFormat::Aiff => tag.write_to_aiff_path(fpath, version),
+        FileFormat::Wav => tag.write_to_wav_path(fpath, version),
+    }
+}
+
+/// Writes a tag to a copy of a file created in the same directory, and then renames the copy over
+/// the original file. This way the original file is never left in a half-written state.
+fn write_tag_atomically(tag: &Tag, fpath: &impl AsRef<Path>, format: FileFormat, version: Version, padding: Option<usize>) -> Result<()> {
+    let dir = match fpath.as_ref().parent() {
+        Some(x) if !x.as_os_str().is_empty() => x,
+        _ => Path::new("."),
+    };
+    let temp_file = NamedTempFile::new_in(dir)?;
+    copy(fpath, temp_file.path())?;
+    write_tag_to_path(tag, &temp_file.path(), format, version, padding)?;
+    temp_file.persist(fpath)?;
+    Ok(())
+}
+
+/// Attempt to write a tag to a file. `Tag.write_to_path()` does this, but it has the side-effect
+/// of deleting the tag from the target file in case of failure. This function is a wrapper that
+/// first tries to write the tag to an `std::io::Empty` dummy file, and will update the real file
+/// only if that trial write succeeded.
+/// `padding` controls the amount of padding written after the tag (MPEG files only); if `None`,
+/// the existing padding is reused whenever possible.
+/// If `atomic` is set, the tag is written to a temporary copy of the file, which then replaces
+/// the original file.
+pub fn try_write_tag(tag: &Tag, fpath: &impl AsRef<Path>, version: Version, padding: Option<usize>, atomic: bool) -> Result<()> {
+    if let Err(e) = tag.write_to(empty(), version) {
+        return Err(anyhow!("Failed to compose tag of '{}': {e}", fpath.as_ref().display()));
+    }
+    let format = FileFormat::detect(fpath);
+    let result = if atomic {
+        write_tag_atomically(tag, fpath, format, version, padding)
+    } else {
+        write_tag_to_path(tag, fpath, format, version, padding).map_err(anyhow::Error::from)
+    };
+    if let Err(e) = result {
+        // All errors caused by tag formats should have been caught in the previous if block.
+        // This should ideally only catch errors related to OS-level failures, e.g. insufficient
+        // storage, invalid path, etc.
+        return Err(anyhow!("Failed to write tag to '{}': {e}", fpath.as_ref().display()));
+    }
+    Ok(())
+}
@@ -0,0 +1,312 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::action::Id3v1Field;
+use crate::errors::{report_file_error, ErrorKind, Output};
+use anyhow::{anyhow, Result};
+use id3::{v1, Frame, Tag, TagLike};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Names of all ID3v1 genres (including Winamp extensions), indexed by their numeric ID.
+pub const ID3V1_GENRES: &[&str] = &[
+    "Blues", "Classic Rock", "Country", "Dance", "Disco", "Funk", "Grunge", "Hip-Hop", "Jazz",
+    "Metal", "New Age", "Oldies", "Other", "Pop", "R&B", "Rap", "Reggae", "Rock", "Techno",
+    "Industrial", "Alternative", "Ska", "Death Metal", "Pranks", "Soundtrack", "Euro-Techno",
+    "Ambient", "Trip-Hop", "Vocal", "Jazz+Funk", "Fusion", "Trance", "Classical", "Instrumental",
+    "Acid", "House", "Game", "Sound Clip", "Gospel", "Noise", "Alternative Rock", "Bass", "Soul",
+    "Punk", "Space", "Meditative", "Instrumental Pop", "Instrumental Rock", "Ethnic", "Gothic",
+    "Darkwave", "Techno-Industrial", "Electronic", "Pop-Folk", "Eurodance", "Dream",
+    "Southern Rock", "Comedy", "Cult", "Gangsta", "Top 40", "Christian Rap", "Pop/Funk", "Jungle",
+    "Native US", "Cabaret", "New Wave", "Psychadelic", "Rave", "Showtunes", "Trailer", "Lo-Fi",
+    "Tribal", "Acid Punk", "Acid Jazz", "Polka", "Retro", "Musical", "Rock & Roll", "Hard Rock",
+    "Folk", "Folk-Rock", "National Folk", "Swing", "Fast Fusion", "Bebob", "Latin", "Revival",
+    "Celtic", "Bluegrass", "Avantgarde", "Gothic Rock", "Progressive Rock", "Psychedelic Rock",
+    "Symphonic Rock", "Slow Rock", "Big Band", "Chorus", "Easy Listening", "Acoustic", "Humour",
+    "Speech", "Chanson", "Opera", "Chamber Music", "Sonata", "Symphony", "Booty Bass", "Primus",
+    "Porn Groove", "Satire", "Slow Jam", "Club", "Tango", "Samba", "Folklore", "Ballad",
+    "Power Ballad", "Rhytmic Soul", "Freestyle", "Duet", "Punk Rock", "Drum Solo", "Acapella",
+    "Euro-House", "Dance Hall", "Goa", "Drum & Bass", "Club-House", "Hardcore", "Terror", "Indie",
+    "BritPop", "Negerpunk", "Polsk Punk", "Beat", "Christian Gangsta", "Heavy Metal", "Black Metal",
+    "Crossover", "Contemporary C", "Christian Rock", "Merengue", "Salsa", "Thrash Metal", "Anime",
+    "JPop", "SynthPop",
+];
+
+/// Base letters of the Latin Extended-A block (U+0100 - U+017F), used for transliteration.
+const LATIN_EXTENDED_A_BASES: &str = concat!(
+    "AaAaAaCcCcCcCcDd", "DdEeEeEeEeEeGgGg", "GgGgHhHhIiIiIiIi", "IiIiJjKkkLlLlLlL",
+    "lLlNnNnNnnNnOoOo", "OoOoRrRrRrSsSsSs", "SsTtTtTtUuUuUuUu", "UuUuWwYyYZzZzZzs",
+);
+
+/// Reads the ID3v1 tag of a file, if it has one.
+pub fn read_id3v1_tag(fpath: &impl AsRef<Path>) -> Result<Option<v1::Tag>> {
+    match v1::Tag::read_from_path(fpath) {
+        Ok(tag) => Ok(Some(tag)),
+        Err(e) => match e.kind {
+            id3::ErrorKind::NoTag => Ok(None),
+            _ => Err(anyhow!("Failed to read ID3v1 tag from file '{}': {e}", fpath.as_ref().display())),
+        },
+    }
+}
+
+/// Returns the name of the ID3v1 revision a tag conforms to.
+pub fn id3v1_version_string(tag: &v1::Tag) -> &'static str {
+    // ID3v1.1 is distinguished from ID3v1 only by the presence of a track number
+    if tag.track.is_some() { "ID3v1.1" } else { "ID3v1" }
+}
+
+/// Returns the name of an ID3v1 field, as used on the command line.
+pub fn id3v1_field_name(field: Id3v1Field) -> &'static str {
+    match field {
+        Id3v1Field::Title => "title",
+        Id3v1Field::Artist => "artist",
+        Id3v1Field::Album => "album",
+        Id3v1Field::Year => "year",
+        Id3v1Field::Comment => "comment",
+        Id3v1Field::Track => "track",
+        Id3v1Field::Genre => "genre",
+    }
+}
+
+/// Returns the value of an ID3v1 field as text, or `None` if the field is unset.
+/// Text fields are always considered set, even if empty.
+pub fn get_id3v1_field(tag: &v1::Tag, field: Id3v1Field) -> Option<String> {
+    match field {
+        Id3v1Field::Title => Some(tag.title.clone()),
+        Id3v1Field::Artist => Some(tag.artist.clone()),
+        Id3v1Field::Album => Some(tag.album.clone()),
+        Id3v1Field::Year => Some(tag.year.clone()),
+        Id3v1Field::Comment => Some(tag.comment.clone()),
+        Id3v1Field::Track => tag.track.map(|x| x.to_string()),
+        Id3v1Field::Genre => tag.genre().map(|x| x.to_string()),
+    }
+}
+
+/// Prints the value of an ID3v1 field of a tag to `output`.
+/// If the field is not found, `missing` is printed instead, if given.
+/// `fpath` is only used for message prints, which are reported to `output`.
+/// Returns whether the field was found and printed.
+pub fn print_id3v1_field_query(output: &mut dyn Output, tag: Option<&v1::Tag>, field: Id3v1Field, missing: Option<&str>,
+    fpath: impl AsRef<Path>) -> io::Result<bool> {
+    match tag.and_then(|tag| get_id3v1_field(tag, field)) {
+        Some(value) => {
+            write!(output.writer(), "{value}")?;
+            Ok(true)
+        },
+        None => {
+            report_file_error(output, fpath, ErrorKind::NotFound, format_args!("Could not print ID3v1 {}: Field not found", id3v1_field_name(field)));
+            if let Some(missing) = missing {
+                write!(output.writer(), "{missing}")?;
+            }
+            Ok(false)
+        },
+    }
+}
+
+/// Pretty-prints all set fields of an ID3v1 tag to `out`.
+pub fn print_id3v1_tag_pretty(out: &mut dyn Write, tag: &v1::Tag) -> io::Result<()> {
+    for field in [
+        Id3v1Field::Title,
+        Id3v1Field::Artist,
+        Id3v1Field::Album,
+        Id3v1Field::Year,
+        Id3v1Field::Comment,
+        Id3v1Field::Track,
+        Id3v1Field::Genre,
+    ] {
+        if let Some(value) = get_id3v1_field(tag, field) {
+            writeln!(out, "{}: {value}", id3v1_field_name(field))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the ID3v1 genre ID matching a TCON value. The value can be a genre name,
+/// or a numeric reference, either bare (e.g. "17") or in parentheses (e.g. "(17)").
+pub fn id3v1_genre_id(genre: &str) -> Option<u8> {
+    let genre = genre.trim();
+    let numeric = genre.strip_prefix('(')
+        .and_then(|x| x.strip_suffix(')'))
+        .unwrap_or(genre);
+    if let Ok(id) = numeric.parse::<u8>() {
+        return if (id as usize) < ID3V1_GENRES.len() { Some(id) } else { None };
+    }
+    ID3V1_GENRES.iter()
+        .position(|x| x.eq_ignore_ascii_case(genre))
+        .map(|x| x as u8)
+}
+
+/// Returns the genre name of a numeric reference (e.g. "17"), or of the special ID3v2.3
+/// references "RX" (remix) and "CR" (cover).
+fn genre_reference_name(reference: &str) -> Option<&'static str> {
+    match reference {
+        "RX" => Some("Remix"),
+        "CR" => Some("Cover"),
+        x => ID3V1_GENRES.get(x.parse::<usize>().ok()?).copied(),
+    }
+}
+
+/// Resolves the numeric references in a single TCON value to genre names.
+/// The value can be a bare reference (e.g. "17"), or a list of references in parentheses
+/// (e.g. "(17)(6)"), optionally followed by a refinement of the last one (e.g. "(4)Eurodisco").
+/// Values without any references are returned as they are.
+fn resolve_genre(value: &str) -> Vec<String> {
+    let trimmed = value.trim();
+    if let Some(name) = trimmed.parse::<u8>().ok().and_then(|_| genre_reference_name(trimmed)) {
+        return vec![name.to_string()];
+    }
+    let mut names = vec![];
+    let mut rest = trimmed;
+    while let Some(after) = rest.strip_prefix('(') {
+        // "((" escapes a literal opening parenthesis
+        if after.starts_with('(') {
+            rest = after;
+            break;
+        }
+        match after.find(')').and_then(|end| Some((genre_reference_name(&after[..end])?, end))) {
+            Some((name, end)) => {
+                names.push(name.to_string());
+                rest = &after[end + 1..];
+            },
+            None => break,
+        }
+    }
+    if names.is_empty() {
+        return vec![value.to_string()];
+    }
+    if !rest.is_empty() {
+        names.pop();
+        names.push(rest.to_string());
+    }
+    names
+}
+
+/// Resolves the numeric references in all values of a (null-separated) TCON text to genre names.
+pub fn resolve_genre_references(text: &str) -> String {
+    text.split('\0')
+        .flat_map(resolve_genre)
+        .collect::<Vec<_>>()
+        .join("\0")
+}
+
+/// Resolves the numeric genre references of a TCON frame to genre names.
+/// Other frames are returned as they are.
+pub fn resolve_genre_frame(frame: Frame) -> Frame {
+    match frame.content().text() {
+        Some(text) if frame.id() == "TCON" => Frame::text("TCON", resolve_genre_references(text)),
+        _ => frame,
+    }
+}
+
+/// Composes an ID3v1.1 tag from the frames of an ID3v2 tag.
+pub fn id3v1_tag_from(tag: &Tag) -> v1::Tag {
+    let year = tag.year()
+        .or_else(|| tag.date_recorded().map(|x| x.year))
+        .map(|x| x.to_string())
+        .unwrap_or_default();
+    // Prefer the comment without a description, as that is what most players display
+    let comment = tag.comments()
+        .find(|x| x.description.is_empty())
+        .or_else(|| tag.comments().next())
+        .map(|x| x.text.clone())
+        .unwrap_or_default();
+    v1::Tag {
+        title: tag.title().unwrap_or_default().to_string(),
+        artist: tag.artist().unwrap_or_default().to_string(),
+        album: tag.album().unwrap_or_default().to_string(),
+        year,
+        comment,
+        track: tag.track().and_then(|x| u8::try_from(x).ok()),
+        genre_id: tag.genre().and_then(id3v1_genre_id).unwrap_or(u8::MAX),
+        ..v1::Tag::default()
+    }
+}
+
+/// Returns an ASCII approximation of a character which is not representable in Latin-1.
+fn transliterate_char(c: char) -> &'static str {
+    match c {
+        '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{2032}' => "'",
+        '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{2033}' => "\"",
+        '\u{2010}'..='\u{2015}' | '\u{2212}' => "-",
+        '\u{2026}' => "...",
+        '\u{20AC}' => "EUR",
+        '\u{0152}' => "OE",
+        '\u{0153}' => "oe",
+        '\u{0100}'..='\u{017F}' => {
+            let i = c as usize - 0x100;
+            &LATIN_EXTENDED_A_BASES[i..(i + 1)]
+        },
+        _ => "?",
+    }
+}
+
+/// Encodes a text field of an ID3v1 tag into a fixed-size, zero-padded Latin-1 buffer.
+/// Characters outside Latin-1 are transliterated, and text that does not fit is truncated.
+/// `fpath` is only used for message prints, which are reported to `output`.
+fn encode_id3v1_text(output: &mut dyn Output, buf: &mut [u8], text: &str, field: Id3v1Field, fpath: &impl AsRef<Path>) {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut transliterated = false;
+    for c in text.chars() {
+        match u8::try_from(c) {
+            Ok(x) => bytes.push(x),
+            Err(_) => {
+                bytes.extend(transliterate_char(c).bytes());
+                transliterated = true;
+            },
+        }
+    }
+    if transliterated {
+        report_file_error(output, fpath, ErrorKind::Warning, format_args!("ID3v1 {} was transliterated to Latin-1", id3v1_field_name(field)));
+    }
+    if bytes.len() > buf.len() {
+        report_file_error(output, fpath, ErrorKind::Warning, format_args!("ID3v1 {} was truncated to {} characters", id3v1_field_name(field), buf.len()));
+        bytes.truncate(buf.len());
+    }
+    buf.fill(0);
+    buf[..bytes.len()].copy_from_slice(&bytes);
+}
+
+/// Encodes an ID3v1.1 tag into its 128-byte binary representation.
+/// `fpath` is only used for message prints, which are reported to `output`.
+pub fn encode_id3v1_tag(output: &mut dyn Output, tag: &v1::Tag, fpath: &impl AsRef<Path>) -> [u8; 128] {
+    let mut buf = [0; 128];
+    buf[0..3].copy_from_slice(b"TAG");
+    encode_id3v1_text(output, &mut buf[3..33], &tag.title, Id3v1Field::Title, fpath);
+    encode_id3v1_text(output, &mut buf[33..63], &tag.artist, Id3v1Field::Artist, fpath);
+    encode_id3v1_text(output, &mut buf[63..93], &tag.album, Id3v1Field::Album, fpath);
+    encode_id3v1_text(output, &mut buf[93..97], &tag.year, Id3v1Field::Year, fpath);
+    encode_id3v1_text(output, &mut buf[97..125], &tag.comment, Id3v1Field::Comment, fpath);
+    // buf[125] must stay zero to mark the tag as ID3v1.1
+    buf[126] = tag.track.unwrap_or(0);
+    buf[127] = tag.genre_id;
+    buf
+}
+
+/// Writes an ID3v1.1 tag to a file, replacing the existing ID3v1 tag, if any.
+pub fn write_id3v1_tag(output: &mut dyn Output, tag: &v1::Tag, fpath: &impl AsRef<Path>) -> Result<()> {
+    let buf = encode_id3v1_tag(output, tag, fpath);
+    if let Err(e) = v1::Tag::remove_from_path(fpath) {
+        return Err(anyhow!("Failed to remove old ID3v1 tag from '{}': {e}", fpath.as_ref().display()));
+    }
+    let result = OpenOptions::new()
+        .append(true)
+        .open(fpath)
+        .and_then(|mut file| file.write_all(&buf));
+    if let Err(e) = result {
+        return Err(anyhow!("Failed to write ID3v1 tag to '{}': {e}", fpath.as_ref().display()));
+    }
+    Ok(())
+}
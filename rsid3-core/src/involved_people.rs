@@ -0,0 +1,86 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use id3::{Content, Frame, Tag, TagLike, Version};
+use id3::frame::{InvolvedPeopleList, InvolvedPeopleListItem};
+
+/// Returns the ID of the frame which holds the given involved people list in a tag.
+/// Before ID3v2.4, the involved people list (TIPL) is stored in IPLS. The musician credits list
+/// (TMCL) has no earlier equivalent, so it is always stored as is.
+pub fn people_list_id(tag: &Tag, id: &str) -> &'static str {
+    match id {
+        "TIPL" if tag.version() != Version::Id3v24 => "IPLS",
+        "TIPL" => "TIPL",
+        _ => "TMCL",
+    }
+}
+
+/// Constructs an involved people list frame with a single entry, e.g. to be used as a query.
+pub fn people_list_frame(id: &str, involvement: String, involvee: String) -> Frame {
+    let list = InvolvedPeopleList {
+        items: vec![InvolvedPeopleListItem { involvement, involvee }],
+    };
+    Frame::with_content(id, Content::InvolvedPeopleList(list))
+}
+
+/// Returns all people listed under an involvement (e.g. a role) in an involved people list of a tag.
+pub fn get_people<'a>(tag: &'a Tag, id: &str, involvement: &str) -> Vec<&'a str> {
+    tag.get(people_list_id(tag, id))
+        .and_then(|x| x.content().involved_people_list())
+        .map(|list| list.items.iter()
+            .filter(|x| x.involvement == involvement)
+            .map(|x| x.involvee.as_str())
+            .collect())
+        .unwrap_or_default()
+}
+
+/// Sets the person listed under an involvement in an involved people list of a tag, replacing all
+/// people previously listed under it. Returns whether any entry was replaced.
+pub fn set_person(tag: &mut Tag, id: &str, involvement: &str, involvee: &str) -> bool {
+    let id = people_list_id(tag, id);
+    let mut items = tag.get(id)
+        .and_then(|x| x.content().involved_people_list())
+        .map(|x| x.items.clone())
+        .unwrap_or_default();
+    let old_len = items.len();
+    // The entry is kept at the position of the first one it replaces, so that the order is stable
+    let position = items.iter().position(|x| x.involvement == involvement).unwrap_or(items.len());
+    items.retain(|x| x.involvement != involvement);
+    let is_replaced = items.len() != old_len;
+    let item = InvolvedPeopleListItem { involvement: involvement.to_string(), involvee: involvee.to_string() };
+    items.insert(position.min(items.len()), item);
+    tag.add_frame(Frame::with_content(id, Content::InvolvedPeopleList(InvolvedPeopleList { items })));
+    is_replaced
+}
+
+/// Removes all people listed under an involvement from an involved people list of a tag.
+/// The frame is removed altogether once it becomes empty. Returns whether any entry was removed.
+pub fn remove_involvement(tag: &mut Tag, id: &str, involvement: &str) -> bool {
+    let id = people_list_id(tag, id);
+    let mut items = match tag.get(id).and_then(|x| x.content().involved_people_list()) {
+        Some(x) => x.items.clone(),
+        None => return false,
+    };
+    let old_len = items.len();
+    items.retain(|x| x.involvement != involvement);
+    if items.len() == old_len {
+        return false;
+    }
+    tag.remove(id);
+    if !items.is_empty() {
+        tag.add_frame(Frame::with_content(id, Content::InvolvedPeopleList(InvolvedPeopleList { items })));
+    }
+    true
+}
@@ -0,0 +1,48 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::id3_helpers::{decode_text_body, encode_text_body};
+use anyhow::{anyhow, Result};
+use id3::{Content, Frame, Version};
+use id3::frame::Unknown;
+
+/// Checks if a frame ID belongs to one of the text frames introduced by iTunes whose IDs do not
+/// start with T: grouping (GRP1), movement name (MVNM) and movement number/count (MVIN).
+pub fn is_itunes_text_id(id: &str) -> bool {
+    matches!(id, "GRP1" | "MVNM" | "MVIN")
+}
+
+/// Returns the text of an iTunes text frame. The id3 crate only decodes GRP1 on its own, so the
+/// bodies of the other frames are decoded here.
+pub fn get_itunes_text(frame: &Frame) -> Result<String> {
+    if let Some(text) = frame.content().text() {
+        return Ok(text.to_string());
+    }
+    let data = &frame.content().to_unknown()?.data;
+    if data.is_empty() {
+        return Err(anyhow!("{} frame is empty", frame.id()));
+    }
+    Ok(decode_text_body(frame.id(), data)?.trim_end_matches('\0').to_string())
+}
+
+/// Constructs an iTunes text frame with the given text. Frames which the id3 crate cannot encode
+/// as text are stored as ISO-8859-1 or UTF-16, like iTunes does.
+pub fn itunes_text_frame(id: &str, text: &str) -> Frame {
+    if id == "GRP1" {
+        return Frame::text(id, text);
+    }
+    let data = encode_text_body(text);
+    Frame::with_content(id, Content::Unknown(Unknown { data, version: Version::Id3v24 }))
+}
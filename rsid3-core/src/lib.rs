@@ -0,0 +1,98 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! The tag reading, querying and editing engine of rsid3, a command line ID3v2 tag editor.
+//!
+//! Everything the command line does to a file's tag is represented by an [`Action`], and executed
+//! by an [`Executor`]. Frames are queried with empty frames, which identify the frame by its ID
+//! and, where needed, its description and language (see [`query::parse_frame_query`]).
+//!
+//! Printed values and reports of errors which do not stop the execution go to an
+//! [`errors::Output`], e.g. an [`errors::CollectedOutput`], which keeps them in memory:
+//!
+//! ```
+//! use rsid3_core::{Action, ExecOptions, Executor};
+//! use rsid3_core::errors::{CollectedOutput, ErrorKind};
+//! use rsid3_core::query::parse_frame_query;
+//! use std::path::Path;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let fpath = Path::new("../tests/samples/sample_TIT2.mp3");
+//! let actions = [Action::Print(parse_frame_query("TIT2")?), Action::Print(parse_frame_query("TALB")?)];
+//! let mut executor = Executor::new(ExecOptions::default(), 1, CollectedOutput::default());
+//! let mut file = executor.read_file_tag(fpath)?;
+//! let outcome = executor.execute_actions(fpath, 0, &mut file, &actions, &mut || Ok(()))?;
+//! executor.write_file_tag(fpath, &mut file, &actions, &mut || Ok(()))?;
+//!
+//! let output = executor.into_output();
+//! assert!(outcome.not_found);
+//! assert_eq!(output.reports[0].kind, ErrorKind::NotFound);
+//! assert!(String::from_utf8(output.values)?.starts_with("Sample Title\n"));
+//! # Ok(())
+//! # }
+//! ```
+
+/// Reports what is being done to a file to `$output` (an [`errors::Output`]), if the verbosity
+/// level of `$options` (anything with a `verbosity` field) is at least `level`.
+#[macro_export]
+macro_rules! verbose {
+    ($options:expr, $output:expr, $level:expr, $fpath:expr, $($arg:tt)*) => {
+        if $options.verbosity >= $level {
+            $crate::errors::report_file_error($output, &$fpath, $crate::errors::ErrorKind::Info, format_args!($($arg)*));
+        }
+    };
+}
+
+pub mod action;
+pub mod aliases;
+pub mod ape;
+pub mod binary;
+pub mod chapters;
+pub mod check;
+pub mod checksum;
+pub mod compilation;
+pub mod dedupe;
+pub mod diff;
+pub mod engine;
+pub mod errors;
+pub mod event_timing;
+pub mod file_format;
+pub mod filter;
+pub mod fix;
+pub mod frame_table;
+pub mod id3_helpers;
+pub mod id3v1_helpers;
+pub mod involved_people;
+pub mod itunes_text;
+pub mod languages;
+pub mod lrc;
+pub mod mojibake;
+pub mod mpeg_audio;
+pub mod musicbrainz;
+pub mod normalization;
+pub mod numbering;
+pub mod play_count;
+pub mod podcast;
+pub mod query;
+pub mod rating;
+pub mod replaygain;
+pub mod tag_header;
+pub mod terms_of_use;
+pub mod text_transforms;
+pub mod text_values;
+pub mod timestamps;
+
+pub use action::Action;
+pub use engine::{ExecError, ExecOptions, Executor, FileTag, Outcome};
@@ -0,0 +1,183 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use anyhow::{anyhow, Result};
+use std::fs::read_to_string;
+use std::path::Path;
+
+/// Represents a single timed line of an LRC file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LrcLine {
+    /// Time at which the line starts, in milliseconds.
+    pub time_ms: u32,
+    /// Text of the line, without any timestamps.
+    pub text: String,
+}
+
+/// Parses an LRC timestamp of the form `mm:ss`, `mm:ss.xx` or `mm:ss.xxx` into milliseconds.
+fn parse_timestamp(timestamp: &str) -> Option<u32> {
+    let (minutes, seconds) = timestamp.split_once(':')?;
+    let (seconds, fraction) = seconds.split_once(['.', ':']).unwrap_or((seconds, "0"));
+    if fraction.is_empty() || fraction.len() > 3 || !fraction.bytes().all(|x| x.is_ascii_digit()) {
+        return None;
+    }
+    let minutes: u32 = minutes.trim().parse().ok()?;
+    let seconds: u32 = seconds.parse().ok()?;
+    // The fraction is in hundredths of a second most of the time, but milliseconds are also common
+    let fraction_ms = fraction.parse::<u32>().ok()? * 10u32.pow(3 - fraction.len() as u32);
+    minutes.checked_mul(60_000)?
+        .checked_add(seconds.checked_mul(1000)?)?
+        .checked_add(fraction_ms)
+}
+
+/// Removes enhanced LRC word timestamps (e.g. `<00:12.34>`) from a line of text.
+fn strip_word_timestamps(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        match rest[start..].find('>') {
+            Some(end) if parse_timestamp(&rest[start + 1..start + end]).is_some() => {
+                result.push_str(&rest[..start]);
+                rest = &rest[start + end + 1..];
+            },
+            _ => {
+                result.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            },
+        }
+    }
+    result.push_str(rest);
+    result.trim().to_string()
+}
+
+/// Parses the contents of an LRC file into timed lines, sorted by time.
+/// Lines with several timestamps are repeated at each of them, and the `[offset:]` tag is applied.
+/// Other metadata tags and untimed lines are skipped.
+pub fn parse_lrc(contents: &str) -> Vec<LrcLine> {
+    let mut lines = vec![];
+    let mut offset_ms: i64 = 0;
+    for line in contents.trim_start_matches('\u{feff}').lines() {
+        let mut rest = line.trim();
+        let mut times = vec![];
+        while let Some(tag) = rest.strip_prefix('[') {
+            let end = match tag.find(']') {
+                Some(x) => x,
+                None => break,
+            };
+            let (tag, after) = (&tag[..end], &tag[end + 1..]);
+            match parse_timestamp(tag) {
+                Some(time) => times.push(time),
+                None => {
+                    if let Some(offset) = tag.strip_prefix("offset:") {
+                        offset_ms = offset.trim().parse().unwrap_or(0);
+                    }
+                },
+            }
+            rest = after;
+        }
+        let text = strip_word_timestamps(rest);
+        for time in times {
+            lines.push(LrcLine { time_ms: time, text: text.clone() });
+        }
+    }
+    // A positive offset means the lyrics should appear sooner
+    for line in &mut lines {
+        line.time_ms = (line.time_ms as i64).saturating_sub(offset_ms).clamp(0, u32::MAX as i64) as u32;
+    }
+    lines.sort_by_key(|x| x.time_ms);
+    lines
+}
+
+/// Formats a time in milliseconds as an LRC timestamp (`mm:ss.xx`).
+pub fn format_timestamp(time_ms: u32) -> String {
+    format!("{:02}:{:02}.{:02}", time_ms / 60_000, time_ms / 1000 % 60, time_ms % 1000 / 10)
+}
+
+/// Formats timed lines as the contents of an LRC file.
+pub fn format_lrc(lines: &[(u32, String)]) -> String {
+    lines.iter()
+        .map(|(time_ms, text)| format!("[{}]{text}", format_timestamp(*time_ms)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reads the contents of an LRC file.
+pub fn read_lrc_file(fpath: &impl AsRef<Path>) -> Result<String> {
+    match read_to_string(fpath) {
+        Ok(x) => Ok(x),
+        Err(e) => Err(anyhow!("Failed to read lyrics from '{}': {e}", fpath.as_ref().display())),
+    }
+}
+
+/// Reads an LRC file and returns its lyrics as plain text, with all timestamps stripped.
+/// Files without any timestamps are returned as they are.
+pub fn read_lrc_as_text(fpath: &impl AsRef<Path>) -> Result<String> {
+    let contents = read_lrc_file(fpath)?;
+    let lines = parse_lrc(&contents);
+    if lines.is_empty() {
+        return Ok(contents.trim_start_matches('\u{feff}').trim_end().to_string());
+    }
+    Ok(lines.into_iter().map(|x| x.text).collect::<Vec<_>>().join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("01:02"), Some(62_000));
+        assert_eq!(parse_timestamp("01:02.3"), Some(62_300));
+        assert_eq!(parse_timestamp("01:02.34"), Some(62_340));
+        assert_eq!(parse_timestamp("01:02.345"), Some(62_345));
+        assert_eq!(parse_timestamp("01:02:34"), Some(62_340));
+        assert_eq!(parse_timestamp("100:00.00"), Some(6_000_000));
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        for timestamp in ["", ":", "01", "ab:cd", "01:02.", "01:02.3456", "01:02.x", "-1:02", "01:-2", "ti:Title"] {
+            assert_eq!(parse_timestamp(timestamp), None, "{timestamp}");
+        }
+    }
+
+    #[test]
+    fn rejects_overflowing_timestamps() {
+        assert_eq!(parse_timestamp("100000:00.00"), None);
+        assert_eq!(parse_timestamp("71582:47.295"), Some(u32::MAX));
+        assert_eq!(parse_timestamp("71582:47.296"), None);
+        assert_eq!(parse_timestamp("0:4294968"), None);
+        assert_eq!(parse_timestamp("99999999999:00"), None);
+    }
+
+    #[test]
+    fn parses_lrc() {
+        let lines = parse_lrc("\u{feff}[ti:Title]\n[offset:500]\n[00:02.00][00:01.00]<00:01.00>Repeated <00:01.50>line\n\
+            untimed\n[100000:00.00]overflowing\n[00:00.20]early");
+        assert_eq!(lines, [
+            LrcLine { time_ms: 0, text: "early".to_string() },
+            LrcLine { time_ms: 500, text: "Repeated line".to_string() },
+            LrcLine { time_ms: 1500, text: "Repeated line".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn clamps_extreme_offsets() {
+        let lines = parse_lrc(&format!("[offset:{}]\n[00:01.00]a", i64::MIN));
+        assert_eq!(lines[0].time_ms, u32::MAX);
+        let lines = parse_lrc(&format!("[offset:{}]\n[00:01.00]a", i64::MAX));
+        assert_eq!(lines[0].time_ms, 0);
+    }
+}
@@ -0,0 +1,54 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::text_transforms::map_frame_text;
+use id3::Frame;
+
+/// Characters of the Windows-1252 code page in the range 0x80-0x9F, where it differs from Latin-1.
+const CP1252_CHARS: &[(u8, char)] = &[
+    (0x80, '€'), (0x82, '‚'), (0x83, 'ƒ'), (0x84, '„'), (0x85, '…'), (0x86, '†'), (0x87, '‡'),
+    (0x88, 'ˆ'), (0x89, '‰'), (0x8a, 'Š'), (0x8b, '‹'), (0x8c, 'Œ'), (0x8e, 'Ž'), (0x91, '‘'),
+    (0x92, '’'), (0x93, '“'), (0x94, '”'), (0x95, '•'), (0x96, '–'), (0x97, '—'), (0x98, '˜'),
+    (0x99, '™'), (0x9a, 'š'), (0x9b, '›'), (0x9c, 'œ'), (0x9e, 'ž'), (0x9f, 'Ÿ'),
+];
+
+/// Returns the byte a character was decoded from, assuming Latin-1 or Windows-1252.
+fn mojibake_byte(c: char) -> Option<u8> {
+    u8::try_from(c).ok().or_else(|| CP1252_CHARS.iter().find(|x| x.1 == c).map(|x| x.0))
+}
+
+/// Recovers UTF-8 text that was decoded as Latin-1 or Windows-1252, i.e. one character per byte,
+/// such as "CafÃ©" for "Café". Text that was mis-decoded several times over is recovered fully.
+/// Returns `None` if the text is not such mojibake.
+pub fn recover_utf8(text: &str) -> Option<String> {
+    let mut recovered = None;
+    loop {
+        let current = recovered.as_deref().unwrap_or(text);
+        let bytes = match current.chars().map(mojibake_byte).collect::<Option<Vec<_>>>() {
+            Some(x) if !x.is_ascii() => x,
+            _ => return recovered,
+        };
+        match String::from_utf8(bytes) {
+            Ok(x) => recovered = Some(x),
+            Err(_) => return recovered,
+        }
+    }
+}
+
+/// Recovers UTF-8 text in all text of a frame (see `recover_utf8`).
+/// Returns `None` if there was nothing to recover.
+pub fn recover_frame_utf8(frame: &Frame) -> Option<Frame> {
+    map_frame_text(frame, |x| recover_utf8(x).unwrap_or_else(|| x.to_string()))
+}
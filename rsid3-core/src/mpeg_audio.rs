@@ -0,0 +1,322 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::ape::find_ape_tag;
+use crate::tag_header::tag_region_size;
+use anyhow::{anyhow, Result};
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+/// Size of an MPEG audio frame header, in bytes.
+const FRAME_HEADER_SIZE: usize = 4;
+/// Size of the start of a frame which holds a Xing, Info or VBRI header, if any.
+const INFO_FRAME_PREFIX_SIZE: usize = FRAME_HEADER_SIZE + 36;
+/// Size of an ID3v1 tag, at the end of the file.
+const ID3V1_SIZE: u64 = 128;
+
+/// Bitrates in kbps, indexed by the bitrate bits of the frame header (0 being free format, and
+/// 15 invalid), for MPEG-1 layers I, II and III, and MPEG-2/2.5 layer I and layers II and III.
+const BITRATES: [[u32; 15]; 5] = [
+    [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+    [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+/// Sample rates in Hz of MPEG-1, indexed by the sample rate bits of the frame header. MPEG-2 and
+/// MPEG-2.5 use a half and a quarter of them, respectively.
+const SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+/// Represents one of the versions of the MPEG audio standard.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MpegVersion {
+    Mpeg1,
+    Mpeg2,
+    Mpeg25,
+}
+
+impl MpegVersion {
+    /// Returns the version's name, e.g. "MPEG-2.5".
+    pub fn name(self) -> &'static str {
+        match self {
+            MpegVersion::Mpeg1 => "MPEG-1",
+            MpegVersion::Mpeg2 => "MPEG-2",
+            MpegVersion::Mpeg25 => "MPEG-2.5",
+        }
+    }
+}
+
+/// Represents the header of an MPEG audio frame.
+#[derive(Debug, Copy, Clone)]
+struct FrameHeader {
+    version: MpegVersion,
+    layer: u8,
+    /// Bitrate in kbps.
+    bitrate: u32,
+    /// Sample rate in Hz.
+    sample_rate: u32,
+    padding: bool,
+    channels: u8,
+}
+
+impl FrameHeader {
+    /// Parses the frame header at the start of `bytes`. Free format frames, whose size cannot be
+    /// told from the header, are not supported.
+    fn parse(bytes: &[u8]) -> Option<FrameHeader> {
+        let header = bytes.get(..FRAME_HEADER_SIZE)?;
+        if header[0] != 0xff || header[1] & 0xe0 != 0xe0 {
+            return None;
+        }
+        let version = match (header[1] >> 3) & 0x03 {
+            0 => MpegVersion::Mpeg25,
+            2 => MpegVersion::Mpeg2,
+            3 => MpegVersion::Mpeg1,
+            _ => return None,
+        };
+        let layer = match (header[1] >> 1) & 0x03 {
+            0 => return None,
+            x => 4 - x,
+        };
+        let bitrates = match (version, layer) {
+            (MpegVersion::Mpeg1, x) => &BITRATES[x as usize - 1],
+            (_, 1) => &BITRATES[3],
+            _ => &BITRATES[4],
+        };
+        let bitrate = *bitrates.get((header[2] >> 4) as usize).filter(|&&x| x != 0)?;
+        let sample_rate = *SAMPLE_RATES.get(((header[2] >> 2) & 0x03) as usize)?;
+        let sample_rate = match version {
+            MpegVersion::Mpeg1 => sample_rate,
+            MpegVersion::Mpeg2 => sample_rate / 2,
+            MpegVersion::Mpeg25 => sample_rate / 4,
+        };
+        Some(FrameHeader {
+            version,
+            layer,
+            bitrate,
+            sample_rate,
+            padding: header[2] & 0x02 != 0,
+            channels: if header[3] >> 6 == 3 { 1 } else { 2 },
+        })
+    }
+
+    /// Returns the number of samples per channel in the frame.
+    fn samples(&self) -> u32 {
+        match (self.layer, self.version) {
+            (1, _) => 384,
+            (3, MpegVersion::Mpeg2 | MpegVersion::Mpeg25) => 576,
+            _ => 1152,
+        }
+    }
+
+    /// Returns the size of the whole frame, including the header, in bytes.
+    fn size(&self) -> usize {
+        let size = match self.layer {
+            1 => (12 * self.bitrate * 1000 / self.sample_rate + self.padding as u32) * 4,
+            _ => self.samples() / 8 * self.bitrate * 1000 / self.sample_rate + self.padding as u32,
+        };
+        size as usize
+    }
+
+    /// Checks if another frame belongs to the same stream, so that it is not mistaken for a frame
+    /// header, where there is only garbage which happens to look like one.
+    fn is_same_stream(&self, other: &FrameHeader) -> bool {
+        self.version == other.version && self.layer == other.layer && self.sample_rate == other.sample_rate
+    }
+
+    /// Checks if the frame holds a Xing, Info or VBRI header instead of audio, as the first frame
+    /// of a stream written by encoders such as LAME does.
+    fn is_info_frame(&self, frame: &[u8]) -> bool {
+        if self.layer != 3 {
+            return false;
+        }
+        let side_info_size = match (self.version, self.channels) {
+            (MpegVersion::Mpeg1, 1) => 17,
+            (MpegVersion::Mpeg1, _) => 32,
+            (_, 1) => 9,
+            _ => 17,
+        };
+        let xing = frame.get(FRAME_HEADER_SIZE + side_info_size..FRAME_HEADER_SIZE + side_info_size + 4);
+        let vbri = frame.get(FRAME_HEADER_SIZE + 32..FRAME_HEADER_SIZE + 36);
+        matches!(xing, Some(b"Xing" | b"Info")) || vbri == Some(b"VBRI")
+    }
+}
+
+/// Represents the properties of an MPEG audio stream.
+#[derive(Debug, Clone)]
+pub struct AudioInfo {
+    pub version: MpegVersion,
+    pub layer: u8,
+    /// Sample rate in Hz.
+    pub sample_rate: u32,
+    pub channels: u8,
+    /// Average bitrate in kbps.
+    pub bitrate: u32,
+    /// Whether the frames have differing bitrates.
+    pub is_vbr: bool,
+    /// Number of audio frames.
+    pub frame_count: u64,
+    /// Duration in milliseconds.
+    pub duration_ms: u64,
+}
+
+/// Returns the byte range of the audio data of an MPEG file, i.e. everything between the ID3v2
+/// tag at its start and the APE and ID3v1 tags at its end.
+pub fn audio_data_range(fpath: &impl AsRef<Path>) -> Result<Range<u64>> {
+    let result = File::open(fpath).and_then(|mut file| -> io::Result<Range<u64>> {
+        let start = tag_region_size(&mut file)?;
+        let mut end = file.seek(SeekFrom::End(0))?;
+        if end >= start + ID3V1_SIZE {
+            let mut magic = [0; 3];
+            file.seek(SeekFrom::Start(end - ID3V1_SIZE))?;
+            file.read_exact(&mut magic)?;
+            if &magic == b"TAG" {
+                end -= ID3V1_SIZE;
+            }
+        }
+        Ok(start..end)
+    });
+    let range = result.map_err(|e| anyhow!("Failed to read audio data of '{}': {e}", fpath.as_ref().display()))?;
+    match find_ape_tag(fpath)? {
+        Some(ape_tag) if ape_tag.offset >= range.start => Ok(range.start..ape_tag.offset.min(range.end)),
+        _ => Ok(range),
+    }
+}
+
+/// Reads bytes at arbitrary offsets within a range of a reader. Seeks within the buffer keep it,
+/// as scanning MPEG frames mostly moves forward by small steps.
+struct RangeReader<R> {
+    reader: BufReader<R>,
+    /// The offset which the reader is at.
+    pos: u64,
+    /// The end of the range, past which nothing is read.
+    end: u64,
+}
+
+impl<R: Read + Seek> RangeReader<R> {
+    fn new(mut reader: R, range: Range<u64>) -> io::Result<RangeReader<R>> {
+        reader.seek(SeekFrom::Start(range.start))?;
+        Ok(RangeReader { reader: BufReader::new(reader), pos: range.start, end: range.end })
+    }
+
+    /// Reads bytes at `pos` into `buf`, fewer if the range ends before `buf` is full.
+    /// Returns the bytes read.
+    fn read_at<'a>(&mut self, pos: u64, buf: &'a mut [u8]) -> io::Result<&'a [u8]> {
+        let n = buf.len().min(self.end.saturating_sub(pos) as usize);
+        self.reader.seek_relative(pos as i64 - self.pos as i64)?;
+        self.pos = pos;
+        self.reader.read_exact(&mut buf[..n])?;
+        self.pos += n as u64;
+        Ok(&buf[..n])
+    }
+
+    /// Parses the frame header at `pos`, if there is one.
+    fn read_header(&mut self, pos: u64) -> io::Result<Option<FrameHeader>> {
+        let mut buf = [0; FRAME_HEADER_SIZE];
+        Ok(FrameHeader::parse(self.read_at(pos, &mut buf)?))
+    }
+
+    /// Finds the first frame of an MPEG audio stream at or after `pos`. Unless it is the last
+    /// one, the frame must be followed by another frame of the same stream.
+    fn find_first_frame(&mut self, mut pos: u64) -> io::Result<Option<(u64, FrameHeader)>> {
+        while pos + FRAME_HEADER_SIZE as u64 <= self.end {
+            if let Some(header) = self.read_header(pos)? {
+                let next = pos + header.size() as u64;
+                if next == self.end || self.read_header(next)?.is_some_and(|x| header.is_same_stream(&x)) {
+                    return Ok(Some((pos, header)));
+                }
+            }
+            pos += 1;
+        }
+        Ok(None)
+    }
+}
+
+/// Computes the properties of the MPEG audio stream in a range of a reader by going through all
+/// of its frame headers, which is accurate for VBR streams as well. Garbage between frames is
+/// skipped, and only the headers are read. Returns `None` if there are no MPEG audio frames.
+fn scan_audio_info(reader: impl Read + Seek, range: Range<u64>) -> io::Result<Option<AudioInfo>> {
+    let mut reader = RangeReader::new(reader, range)?;
+    let (mut pos, first) = match reader.find_first_frame(reader.pos)? {
+        Some(x) => x,
+        None => return Ok(None),
+    };
+    let mut frame_count = 0;
+    let mut samples = 0;
+    let mut bytes = 0;
+    let mut is_vbr = false;
+    let mut bitrate = None;
+    while pos + FRAME_HEADER_SIZE as u64 <= reader.end {
+        let header = match reader.read_header(pos)?.filter(|x| first.is_same_stream(x)) {
+            Some(x) => x,
+            None => {
+                pos += 1;
+                continue;
+            },
+        };
+        let size = header.size() as u64;
+        if pos + size > reader.end {
+            // The last frame is truncated
+            break;
+        }
+        // Only the start of the first frame is needed to tell if it is an info frame
+        let is_info_frame = frame_count == 0 && {
+            let mut buf = [0; INFO_FRAME_PREFIX_SIZE];
+            let n = INFO_FRAME_PREFIX_SIZE.min(size as usize);
+            header.is_info_frame(reader.read_at(pos, &mut buf[..n])?)
+        };
+        if !is_info_frame {
+            frame_count += 1;
+            samples += header.samples() as u64;
+            bytes += size;
+            is_vbr |= bitrate.is_some_and(|x| x != header.bitrate);
+            bitrate = Some(header.bitrate);
+        }
+        pos += size;
+    }
+    let duration_ms = samples * 1000 / first.sample_rate as u64;
+    let bitrate = match (is_vbr, bitrate) {
+        (false, Some(x)) => x,
+        _ if duration_ms > 0 => (bytes * 8 / duration_ms) as u32,
+        _ => 0,
+    };
+    Ok(Some(AudioInfo {
+        version: first.version,
+        layer: first.layer,
+        sample_rate: first.sample_rate,
+        channels: first.channels,
+        bitrate,
+        is_vbr,
+        frame_count,
+        duration_ms,
+    }))
+}
+
+/// Computes the properties of an MPEG audio stream held in memory, e.g. read from stdin (see
+/// `read_file_audio_info`). Returns `None` if there are no MPEG audio frames.
+pub fn read_audio_info(data: &[u8]) -> Option<AudioInfo> {
+    // Reading from memory cannot fail
+    scan_audio_info(Cursor::new(data), 0..data.len() as u64).ok().flatten()
+}
+
+/// Reads the properties of the audio stream of an MPEG file, scanning the frame headers of its
+/// audio data (see `audio_data_range`) without reading the audio itself.
+pub fn read_file_audio_info(fpath: &impl AsRef<Path>) -> Result<AudioInfo> {
+    let range = audio_data_range(fpath)?;
+    let result = File::open(fpath).and_then(|file| scan_audio_info(file, range));
+    result.map_err(|e| anyhow!("Failed to read audio data of '{}': {e}", fpath.as_ref().display()))?
+        .ok_or_else(|| anyhow!("No MPEG audio frames found in '{}'", fpath.as_ref().display()))
+}
@@ -0,0 +1,55 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use id3::{Content, Frame};
+use id3::frame::{ExtendedText, UniqueFileIdentifier};
+
+/// Owner of the UFID frame holding the MusicBrainz recording ID.
+pub const MUSICBRAINZ_UFID_OWNER: &str = "http://musicbrainz.org";
+
+/// Names of the MusicBrainz identifiers (as in `--mb-NAME`) and the TXXX descriptions they are
+/// stored under by MusicBrainz Picard. The recording ID is stored in UFID instead.
+const MUSICBRAINZ_TXXX_DESCRIPTIONS: &[(&str, &str)] = &[
+    ("releaseid", "MusicBrainz Album Id"),
+    ("artistid", "MusicBrainz Artist Id"),
+    ("releaseartistid", "MusicBrainz Album Artist Id"),
+    ("releasegroupid", "MusicBrainz Release Group Id"),
+    ("releasetrackid", "MusicBrainz Release Track Id"),
+    ("workid", "MusicBrainz Work Id"),
+    ("discid", "MusicBrainz Disc Id"),
+];
+
+/// Returns the names of all supported MusicBrainz identifiers.
+pub fn musicbrainz_names() -> impl Iterator<Item = &'static str> {
+    std::iter::once("recordingid").chain(MUSICBRAINZ_TXXX_DESCRIPTIONS.iter().map(|(name, _)| *name))
+}
+
+/// Constructs the frame storing the MusicBrainz identifier of the given name, with the given value.
+/// Returns `None` if the name is unknown.
+pub fn musicbrainz_frame(name: &str, value: &str) -> Option<Frame> {
+    if name == "recordingid" {
+        let ufid = UniqueFileIdentifier {
+            owner_identifier: MUSICBRAINZ_UFID_OWNER.to_string(),
+            identifier: value.as_bytes().to_vec(),
+        };
+        return Some(Frame::with_content("UFID", Content::UniqueFileIdentifier(ufid)));
+    }
+    let (_, description) = MUSICBRAINZ_TXXX_DESCRIPTIONS.iter().find(|(x, _)| *x == name)?;
+    let extended_text = ExtendedText {
+        description: description.to_string(),
+        value: value.to_string(),
+    };
+    Some(Frame::with_content("TXXX", Content::ExtendedText(extended_text)))
+}
@@ -0,0 +1,89 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::action::NormalizationForm;
+use crate::text_transforms::map_frame_text;
+use id3::Frame;
+use unicode_normalization::UnicodeNormalization;
+
+/// Converts a text to the given Unicode normalization form.
+pub fn normalize(text: &str, form: NormalizationForm) -> String {
+    // Most text is plain ASCII, which is the same in all forms
+    if text.is_ascii() {
+        return text.to_string();
+    }
+    match form {
+        NormalizationForm::Nfc => text.nfc().collect(),
+        NormalizationForm::Nfd => text.nfd().collect(),
+    }
+}
+
+/// Converts all text of a frame to the given Unicode normalization form.
+pub fn normalize_frame(frame: Frame, form: NormalizationForm) -> Frame {
+    map_frame_text(&frame, |x| normalize(x, form)).unwrap_or(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks that a text in NFC and NFD has the given forms, in both directions.
+    fn assert_forms(nfc: &str, nfd: &str) {
+        assert_eq!(normalize(nfc, NormalizationForm::Nfd), nfd, "NFD of {nfc:?}");
+        assert_eq!(normalize(nfd, NormalizationForm::Nfc), nfc, "NFC of {nfd:?}");
+        assert_eq!(normalize(nfc, NormalizationForm::Nfc), nfc, "NFC of {nfc:?}");
+        assert_eq!(normalize(nfd, NormalizationForm::Nfd), nfd, "NFD of {nfd:?}");
+    }
+
+    #[test]
+    fn normalizes_latin() {
+        assert_forms("Café", "Cafe\u{301}");
+        assert_forms("Beyonc\u{e9} Kn\u{f6}wles", "Beyonce\u{301} Kno\u{308}wles");
+        assert_forms("\u{1d6}", "u\u{308}\u{304}");
+        assert_forms("plain ASCII", "plain ASCII");
+    }
+
+    #[test]
+    fn orders_combining_marks() {
+        assert_eq!(normalize("a\u{301}\u{323}", NormalizationForm::Nfd), "a\u{323}\u{301}");
+        assert_eq!(normalize("a\u{301}\u{323}", NormalizationForm::Nfc), "\u{1ea1}\u{301}");
+        // Marks of the same class keep their order, and the second one is blocked by the first
+        assert_eq!(normalize("a\u{301}\u{300}", NormalizationForm::Nfc), "\u{e1}\u{300}");
+        assert_eq!(normalize("a\u{300}\u{301}", NormalizationForm::Nfc), "\u{e0}\u{301}");
+    }
+
+    #[test]
+    fn normalizes_hangul() {
+        assert_forms("\u{d55c}\u{ae00}", "\u{1112}\u{1161}\u{11ab}\u{1100}\u{1173}\u{11af}");
+        assert_forms("\u{ac00}", "\u{1100}\u{1161}");
+        assert_eq!(normalize("\u{ac00}\u{11a8}", NormalizationForm::Nfc), "\u{ac01}");
+    }
+
+    #[test]
+    fn handles_singletons_and_exclusions() {
+        assert_eq!(normalize("\u{212b}", NormalizationForm::Nfd), "A\u{30a}");
+        assert_eq!(normalize("\u{212b}", NormalizationForm::Nfc), "\u{c5}");
+        assert_eq!(normalize("\u{958}", NormalizationForm::Nfc), "\u{915}\u{93c}");
+        assert_eq!(normalize("\u{344}", NormalizationForm::Nfc), "\u{308}\u{301}");
+    }
+
+    #[test]
+    fn normalizes_long_text() {
+        let nfd = "Cafe\u{301} \u{1112}\u{1161}\u{11ab} a\u{323}\u{301} ".repeat(10_000);
+        let nfc = normalize(&nfd, NormalizationForm::Nfc);
+        assert_eq!(nfc, "Caf\u{e9} \u{d55c} \u{1ea1}\u{301} ".repeat(10_000));
+        assert_eq!(normalize(&nfc, NormalizationForm::Nfd), nfd);
+    }
+}
@@ -0,0 +1,159 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::action::NumberPart;
+use crate::errors::{report_file_error, ErrorKind, Output};
+use id3::{Tag, TagLike};
+use std::io;
+use std::path::Path;
+
+/// Width to which numbers are padded by `--normalize-numbers`, if `--track-pad` is not given.
+pub const DEFAULT_TRACK_PAD: usize = 2;
+
+/// All parts of TRCK and TPOS which can be accessed on their own.
+pub const ALL_NUMBER_PARTS: &[NumberPart] = &[
+    NumberPart::TrackNumber,
+    NumberPart::TrackTotal,
+    NumberPart::DiscNumber,
+    NumberPart::DiscTotal,
+];
+
+/// Returns the ID of the frame holding a number part: TRCK for track numbers, TPOS for disc numbers.
+pub fn number_part_frame_id(part: NumberPart) -> &'static str {
+    match part {
+        NumberPart::TrackNumber | NumberPart::TrackTotal => "TRCK",
+        NumberPart::DiscNumber | NumberPart::DiscTotal => "TPOS",
+    }
+}
+
+/// Returns the name of a number part, as used on the command line (without the leading dashes).
+pub fn number_part_name(part: NumberPart) -> &'static str {
+    match part {
+        NumberPart::TrackNumber => "track-number",
+        NumberPart::TrackTotal => "track-total",
+        NumberPart::DiscNumber => "disc-number",
+        NumberPart::DiscTotal => "disc-total",
+    }
+}
+
+/// Returns the number part matching a name, as used on the command line (without the leading dashes).
+pub fn number_part_from_name(name: &str) -> Option<NumberPart> {
+    ALL_NUMBER_PARTS.iter().copied().find(|&x| number_part_name(x) == name)
+}
+
+/// Checks if a number part is the total (the part after the slash, e.g. 13 in "7/13").
+fn is_total(part: NumberPart) -> bool {
+    matches!(part, NumberPart::TrackTotal | NumberPart::DiscTotal)
+}
+
+/// Splits the value of a TRCK or TPOS frame (e.g. "7/13") into the number and the total.
+/// Both parts are trimmed, and empty parts are returned as `None`.
+pub fn split_number_pair(text: &str) -> (Option<&str>, Option<&str>) {
+    let (number, total) = match text.split_once('/') {
+        Some((number, total)) => (number, Some(total)),
+        None => (text, None),
+    };
+    let number = Some(number.trim()).filter(|x| !x.is_empty());
+    let total = total.map(str::trim).filter(|x| !x.is_empty());
+    (number, total)
+}
+
+/// Joins a number and a total into the value of a TRCK or TPOS frame (e.g. "7/13").
+/// A total without a number is kept as "/13", so that it is not lost.
+pub fn join_number_pair(number: Option<&str>, total: Option<&str>) -> String {
+    match (number, total) {
+        (Some(number), Some(total)) => format!("{number}/{total}"),
+        (Some(number), None) => number.to_string(),
+        (None, Some(total)) => format!("/{total}"),
+        (None, None) => String::new(),
+    }
+}
+
+/// Pads both parts of the value of a TRCK or TPOS frame with leading zeros to `width` digits
+/// (e.g. "7/13" to "07/13"), removing any excess leading zeros. Non-numeric parts are left as they are.
+pub fn pad_number_pair(text: &str, width: usize) -> String {
+    let pad = |x: &str| match x.parse::<u32>() {
+        Ok(n) => format!("{n:0width$}"),
+        Err(_) => x.to_string(),
+    };
+    let (number, total) = split_number_pair(text);
+    join_number_pair(number.map(pad).as_deref(), total.map(pad).as_deref())
+}
+
+/// Pads the numbers of a TRCK or TPOS frame of a tag to `width` digits (see `pad_number_pair`).
+/// Returns whether the frame was changed.
+pub fn pad_tag_numbers(tag: &mut Tag, id: &str, width: usize) -> bool {
+    let text = match tag.get(id).and_then(|x| x.content().text()) {
+        Some(x) => x.to_string(),
+        None => return false,
+    };
+    let padded = pad_number_pair(&text, width);
+    if padded == text {
+        return false;
+    }
+    tag.set_text(id, padded);
+    true
+}
+
+/// Returns a number part of a tag, if it is set and numeric.
+pub fn get_number_part(tag: &Tag, part: NumberPart) -> Option<u32> {
+    let text = tag.get(number_part_frame_id(part))?.content().text()?;
+    let (number, total) = split_number_pair(text);
+    if is_total(part) { total } else { number }?.parse().ok()
+}
+
+/// Prints a number part of a tag to `output`. If the part is not found, `missing` is printed
+/// instead, if given. `fpath` is only used for message prints.
+/// Returns whether the part was found and printed.
+pub fn print_number_part(output: &mut dyn Output, tag: &Tag, part: NumberPart, missing: Option<&str>,
+    fpath: impl AsRef<Path>) -> io::Result<bool> {
+    match get_number_part(tag, part) {
+        Some(x) => {
+            write!(output.writer(), "{x}")?;
+            Ok(true)
+        },
+        None => {
+            report_file_error(output, fpath, ErrorKind::NotFound, format_args!("Could not print {}: Not found", number_part_name(part)));
+            if let Some(missing) = missing {
+                write!(output.writer(), "{missing}")?;
+            }
+            Ok(false)
+        },
+    }
+}
+
+/// Sets (or with `None`, removes) a number part of a tag, keeping the other part of the frame
+/// intact. The frame is removed once both of its parts are gone. Returns whether the part was set before.
+pub fn set_number_part(tag: &mut Tag, part: NumberPart, value: Option<u32>) -> bool {
+    let id = number_part_frame_id(part);
+    let old_text = tag.get(id)
+        .and_then(|x| x.content().text())
+        .unwrap_or_default()
+        .to_string();
+    let (mut number, mut total) = split_number_pair(&old_text);
+    let value = value.map(|x| x.to_string());
+    let old_value = if is_total(part) {
+        std::mem::replace(&mut total, value.as_deref())
+    } else {
+        std::mem::replace(&mut number, value.as_deref())
+    };
+    let text = join_number_pair(number, total);
+    if text.is_empty() {
+        tag.remove(id);
+    } else {
+        tag.set_text(id, text);
+    }
+    old_value.is_some()
+}
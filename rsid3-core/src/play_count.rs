@@ -0,0 +1,66 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::id3_helpers::replace_tag_frame;
+use crate::rating::DEFAULT_RATING_OWNER;
+use anyhow::{anyhow, Result};
+use id3::{Frame, Tag, TagLike, Version};
+use id3::frame::{Content, Popularimeter, Unknown};
+
+/// Minimum size of the PCNT counter, in bytes. Larger counters take as many bytes as they need.
+const PCNT_MIN_SIZE: usize = 4;
+
+/// Decodes the play counter of a PCNT frame, which the id3 crate does not parse on its own.
+pub fn get_play_counter(frame: &Frame) -> Result<u64> {
+    let data = frame.content().to_unknown()?.data.clone();
+    if data.len() < PCNT_MIN_SIZE {
+        return Err(anyhow!("PCNT frame is too short ({} bytes)", data.len()));
+    }
+    // Leading zero bytes do not change the value, so only too large counters are rejected
+    let significant = &data[data.iter().position(|&x| x != 0).unwrap_or(data.len())..];
+    if significant.len() > 8 {
+        return Err(anyhow!("PCNT counter is too large ({} bytes)", data.len()));
+    }
+    Ok(significant.iter().fold(0, |acc, &x| (acc << 8) | x as u64))
+}
+
+/// Constructs a PCNT frame with the given play counter.
+pub fn play_counter_frame(counter: u64) -> Frame {
+    let bytes = counter.to_be_bytes();
+    let start = bytes.iter().position(|&x| x != 0).unwrap_or(bytes.len()).min(bytes.len() - PCNT_MIN_SIZE);
+    let unknown = Unknown { data: bytes[start..].to_vec(), version: Version::Id3v24 };
+    Frame::with_content("PCNT", Content::Unknown(unknown))
+}
+
+/// Increments the play counter of a tag, creating PCNT if it does not exist.
+/// If `popm_owner` is given, the counter of that owner's POPM frame is incremented as well
+/// (`None` being the default rating owner). Returns the new PCNT value.
+pub fn increment_play_count(tag: &mut Tag, popm_owner: Option<Option<&str>>) -> Result<u64> {
+    let counter = match tag.get("PCNT") {
+        Some(frame) => get_play_counter(frame)?.saturating_add(1),
+        None => 1,
+    };
+    // The id3 crate never considers frames it cannot decode duplicates, so PCNT is replaced by hand
+    replace_tag_frame(tag, play_counter_frame(counter))?;
+    if let Some(owner) = popm_owner {
+        let user = owner.unwrap_or(DEFAULT_RATING_OWNER).to_string();
+        let popm = match tag.frames().filter_map(|x| x.content().popularimeter()).find(|x| x.user == user) {
+            Some(x) => Popularimeter { counter: x.counter.saturating_add(1), ..x.clone() },
+            None => Popularimeter { user, rating: 0, counter: 1 },
+        };
+        tag.add_frame(Frame::with_content("POPM", Content::Popularimeter(popm)));
+    }
+    Ok(counter)
+}
@@ -0,0 +1,53 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use id3::{Content, Frame, Version};
+use id3::frame::Unknown;
+
+/// The podcast metadata set by `--podcast`, in the iTunes podcast frames. Unset fields are left
+/// as they are in the tag.
+#[derive(Debug, Clone, Default)]
+pub struct Podcast {
+    /// The feed URL (WFED).
+    pub feed: Option<String>,
+    /// The episode's identifier, usually its GUID in the feed (TGID).
+    pub id: Option<String>,
+    /// The episode's description (TDES).
+    pub description: Option<String>,
+    /// The comma-separated keywords of the episode (TKWD).
+    pub keywords: Option<String>,
+}
+
+impl Podcast {
+    /// Checks if any of the fields are set.
+    pub fn is_empty(&self) -> bool {
+        self.feed.is_none() && self.id.is_none() && self.description.is_none() && self.keywords.is_none()
+    }
+
+    /// Constructs the frames to be set: the podcast flag, followed by the set fields.
+    pub fn frames(&self) -> Vec<Frame> {
+        let mut frames = vec![podcast_flag_frame()];
+        let fields = [("TGID", &self.id), ("TDES", &self.description), ("TKWD", &self.keywords)];
+        frames.extend(fields.into_iter().filter_map(|(id, value)| value.as_ref().map(|x| Frame::text(id, x))));
+        frames.extend(self.feed.as_ref().map(|x| Frame::link("WFED", x)));
+        frames
+    }
+}
+
+/// Constructs the PCST frame marking a file as a podcast episode. iTunes writes it with 4 zero bytes.
+pub fn podcast_flag_frame() -> Frame {
+    let unknown = Unknown { data: vec![0; 4], version: Version::Id3v24 };
+    Frame::with_content("PCST", Content::Unknown(unknown))
+}
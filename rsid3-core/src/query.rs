@@ -0,0 +1,113 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::terms_of_use::TermsOfUse;
+use anyhow::{anyhow, Result};
+use id3::{Content, Frame};
+use id3::frame::{Comment, EncapsulatedObject, ExtendedLink, ExtendedText, Lyrics, Popularimeter, Private, SynchronisedLyrics, SynchronisedLyricsType, TimestampFormat, UniqueFileIdentifier};
+
+/// Constructs an empty POPM frame content, matching the rating of the given user (an e-mail address).
+pub fn popm_query(user: String) -> Popularimeter {
+    Popularimeter {
+        user,
+        rating: 0,
+        counter: 0,
+    }
+}
+
+/// Constructs an empty GEOB frame content, matching the object with the given description.
+pub fn geob_query(description: String) -> EncapsulatedObject {
+    EncapsulatedObject {
+        mime_type: String::new(),
+        filename: String::new(),
+        description,
+        data: vec![],
+    }
+}
+
+/// Constructs an empty PRIV frame content, matching the data of the given owner.
+pub fn priv_query(owner_identifier: String) -> Private {
+    Private {
+        owner_identifier,
+        private_data: vec![],
+    }
+}
+
+/// Constructs an empty UFID frame content, matching the identifier of the given owner.
+pub fn ufid_query(owner_identifier: String) -> UniqueFileIdentifier {
+    UniqueFileIdentifier {
+        owner_identifier,
+        identifier: vec![],
+    }
+}
+
+/// Constructs an empty SYLT frame content, matching the lyrics with the given description and language.
+pub fn sylt_query(description: String, lang: String) -> SynchronisedLyrics {
+    SynchronisedLyrics {
+        lang,
+        timestamp_format: TimestampFormat::Ms,
+        content_type: SynchronisedLyricsType::Lyrics,
+        description,
+        content: vec![],
+    }
+}
+
+/// Constructs an empty query frame from its specification, which is written like frames are named
+/// in messages: an ID followed by a [DESC] and/or a (LANG), where the frame needs them (e.g. TIT2,
+/// TXXX[Mood] or COMM[](eng)).
+pub fn parse_frame_query(spec: &str) -> Result<Frame> {
+    let invalid = || anyhow!("Invalid frame: '{spec}' (expected e.g. TIT2, TXXX[DESC] or COMM[DESC](LANG))");
+    let (id, rest) = spec.split_at(spec.find(['[', '(']).unwrap_or(spec.len()));
+    let (rest, lang) = match rest.strip_suffix(')').and_then(|x| x.rsplit_once('(')) {
+        Some((rest, lang)) => (rest, Some(lang.to_string())),
+        None => (rest, None),
+    };
+    let description = match rest {
+        "" => None,
+        x => Some(x.strip_prefix('[').and_then(|y| y.strip_suffix(']')).ok_or_else(invalid)?.to_string()),
+    };
+    let frame = match (id, description, lang) {
+        ("TXXX", Some(description), None) => Frame::with_content(id, Content::ExtendedText(ExtendedText {
+            description,
+            value: String::new(),
+        })),
+        ("WXXX", Some(description), None) => Frame::with_content(id, Content::ExtendedLink(ExtendedLink {
+            description,
+            link: String::new(),
+        })),
+        ("COMM", Some(description), Some(lang)) => Frame::with_content(id, Content::Comment(Comment {
+            description,
+            lang,
+            text: String::new(),
+        })),
+        ("USLT", Some(description), Some(lang)) => Frame::with_content(id, Content::Lyrics(Lyrics {
+            description,
+            lang,
+            text: String::new(),
+        })),
+        ("SYLT", Some(description), Some(lang)) => Frame::with_content(id, Content::SynchronisedLyrics(sylt_query(description, lang))),
+        ("POPM", Some(user), None) => Frame::with_content(id, Content::Popularimeter(popm_query(user))),
+        ("GEOB", Some(description), None) => Frame::with_content(id, Content::EncapsulatedObject(geob_query(description))),
+        ("PRIV", Some(owner), None) => Frame::with_content(id, Content::Private(priv_query(owner))),
+        ("UFID", Some(owner), None) => Frame::with_content(id, Content::UniqueFileIdentifier(ufid_query(owner))),
+        ("USER", None, Some(lang)) => TermsOfUse::query_frame(&lang, "")?,
+        (id, None, None) if id.len() == 4 && id.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+            && (id.starts_with(['T', 'W']) || id == "PCNT") && !matches!(id, "TXXX" | "WXXX" | "TIPL" | "TMCL") => {
+            Frame::text(id, "")
+        },
+        _ => return Err(invalid()),
+    };
+    Ok(frame)
+}
@@ -0,0 +1,149 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::action::RatingScale;
+use crate::errors::{report_file_error, ErrorKind, Output};
+use anyhow::{anyhow, Result};
+use id3::{Frame, Tag, TagLike};
+use id3::frame::{Content, Popularimeter};
+use std::io;
+use std::path::Path;
+
+/// Owner of the POPM frame used for ratings, unless configured otherwise.
+/// Windows Media Player's owner is the one most other players recognize.
+pub const DEFAULT_RATING_OWNER: &str = "Windows Media Player 9 Series";
+
+/// POPM ratings corresponding to 1-5 stars, as written by Windows Media Player.
+const WMP_STAR_RATINGS: [u8; 5] = [1, 64, 128, 196, 255];
+
+/// Represents a unit in which ratings are printed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RatingUnit {
+    Stars,
+    Percent,
+}
+
+/// Represents a rating in one of the units it can be passed in on the command line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rating {
+    /// Number of stars, 0-5 (0 meaning unrated).
+    Stars(u8),
+    /// Percentage, 0-100 (0 meaning unrated).
+    Percent(u8),
+}
+
+impl Rating {
+    /// Parses a rating given either as stars (`4`) or as a percentage (`80%`).
+    pub fn parse(value: &str) -> Result<Rating> {
+        let invalid = || anyhow!("Invalid rating (expected 0-5 stars or 0-100%): '{value}'");
+        match value.strip_suffix('%') {
+            Some(percent) => match percent.parse() {
+                Ok(x) if x <= 100 => Ok(Rating::Percent(x)),
+                _ => Err(invalid()),
+            },
+            None => match value.parse() {
+                Ok(x) if x <= 5 => Ok(Rating::Stars(x)),
+                _ => Err(invalid()),
+            },
+        }
+    }
+
+    /// Converts the rating to the 0-255 POPM scale.
+    pub fn to_popm(self, scale: RatingScale) -> u8 {
+        match (self, scale) {
+            (Rating::Stars(0) | Rating::Percent(0), _) => 0,
+            (Rating::Stars(x), RatingScale::Wmp) => WMP_STAR_RATINGS[x as usize - 1],
+            (Rating::Stars(x), RatingScale::Linear) => x * 51,
+            (Rating::Percent(x), _) => ((x as u32 * 255 + 50) / 100) as u8,
+        }
+    }
+
+    /// Converts a 0-255 POPM rating to stars, rounding to the nearest one.
+    pub fn stars_from_popm(rating: u8, scale: RatingScale) -> Rating {
+        let stars = match scale {
+            _ if rating == 0 => 0,
+            // Values in between those written by WMP are rounded to the closest one
+            RatingScale::Wmp => WMP_STAR_RATINGS.iter()
+                .enumerate()
+                .min_by_key(|(_, &x)| x.abs_diff(rating))
+                .map_or(0, |(i, _)| i as u8 + 1),
+            RatingScale::Linear => ((rating as u32 + 25) / 51) as u8,
+        };
+        Rating::Stars(stars)
+    }
+
+    /// Converts a 0-255 POPM rating to a percentage.
+    pub fn percent_from_popm(rating: u8) -> Rating {
+        Rating::Percent(((rating as u32 * 100 + 127) / 255) as u8)
+    }
+}
+
+impl std::fmt::Display for Rating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rating::Stars(x) => write!(f, "{x}"),
+            Rating::Percent(x) => write!(f, "{x}%"),
+        }
+    }
+}
+
+/// Finds the POPM frame holding the rating. If no owner is configured, the default owner's
+/// frame is preferred, but any other one is accepted.
+fn find_rating_popm<'a>(tag: &'a Tag, owner: Option<&str>) -> Option<&'a Popularimeter> {
+    let mut popms = tag.frames().filter_map(|x| x.content().popularimeter());
+    match owner {
+        Some(owner) => popms.find(|x| x.user == owner),
+        None => {
+            let popms = popms.collect::<Vec<_>>();
+            popms.iter().find(|x| x.user == DEFAULT_RATING_OWNER).or(popms.first()).copied()
+        },
+    }
+}
+
+/// Prints the rating of a tag to `output` in the given unit.
+/// If there is no rating, `missing` is printed instead, if given. `fpath` is only used for message prints.
+/// Returns whether a rating was found and printed.
+pub fn print_rating(output: &mut dyn Output, tag: &Tag, owner: Option<&str>, scale: RatingScale, unit: RatingUnit,
+    missing: Option<&str>, fpath: &impl AsRef<Path>) -> io::Result<bool> {
+    match find_rating_popm(tag, owner) {
+        Some(popm) => {
+            let rating = match unit {
+                RatingUnit::Stars => Rating::stars_from_popm(popm.rating, scale),
+                RatingUnit::Percent => Rating::percent_from_popm(popm.rating),
+            };
+            write!(output.writer(), "{rating}")?;
+            Ok(true)
+        },
+        None => {
+            report_file_error(output, fpath, ErrorKind::NotFound, "Could not print rating: Frame not found");
+            if let Some(missing) = missing {
+                write!(output.writer(), "{missing}")?;
+            }
+            Ok(false)
+        },
+    }
+}
+
+/// Sets the rating of a tag, keeping the play counter of an existing POPM frame of the same owner.
+/// Returns whether an existing rating was overwritten.
+pub fn set_rating(tag: &mut Tag, owner: Option<&str>, scale: RatingScale, rating: Rating) -> bool {
+    let user = owner.unwrap_or(DEFAULT_RATING_OWNER).to_string();
+    let counter = tag.frames()
+        .filter_map(|x| x.content().popularimeter())
+        .find(|x| x.user == user)
+        .map_or(0, |x| x.counter);
+    let popm = Popularimeter { user, rating: rating.to_popm(scale), counter };
+    tag.add_frame(Frame::with_content("POPM", Content::Popularimeter(popm))).is_some()
+}
@@ -0,0 +1,60 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use anyhow::{anyhow, Result};
+use id3::{Content, Frame};
+use id3::frame::ExtendedText;
+
+/// Names of the ReplayGain values (as in `--rg-NAME`) and the TXXX descriptions players read
+/// them from.
+const REPLAYGAIN_TXXX_DESCRIPTIONS: &[(&str, &str)] = &[
+    ("track-gain", "REPLAYGAIN_TRACK_GAIN"),
+    ("track-peak", "REPLAYGAIN_TRACK_PEAK"),
+    ("album-gain", "REPLAYGAIN_ALBUM_GAIN"),
+    ("album-peak", "REPLAYGAIN_ALBUM_PEAK"),
+];
+
+/// Returns the names of all ReplayGain values.
+pub fn replaygain_names() -> impl Iterator<Item = &'static str> {
+    REPLAYGAIN_TXXX_DESCRIPTIONS.iter().map(|(name, _)| *name)
+}
+
+/// Normalizes a ReplayGain value to the conventional format: gains as "-6.50 dB" (the unit may
+/// be omitted, and is matched case-insensitively), peaks as a non-negative number with 6 decimals.
+pub fn normalize_replaygain_value(name: &str, value: &str) -> Result<String> {
+    let is_gain = name.ends_with("gain");
+    let number = match value.trim().strip_suffix(['B', 'b']).and_then(|x| x.strip_suffix(['D', 'd'])) {
+        Some(x) if is_gain => x.trim_end(),
+        _ => value.trim(),
+    };
+    let number = number.parse::<f64>().ok().filter(|x| x.is_finite() && (is_gain || *x >= 0.0));
+    match number {
+        Some(x) if is_gain => Ok(format!("{x:.2} dB")),
+        Some(x) => Ok(format!("{x:.6}")),
+        None if is_gain => Err(anyhow!("Invalid ReplayGain {name}: '{value}' (expected e.g. '-6.50 dB')")),
+        None => Err(anyhow!("Invalid ReplayGain {name}: '{value}' (expected e.g. '0.988547')")),
+    }
+}
+
+/// Constructs the TXXX frame storing the ReplayGain value of the given name, with the given
+/// (already normalized) value. Returns `None` if the name is unknown.
+pub fn replaygain_frame(name: &str, value: &str) -> Option<Frame> {
+    let (_, description) = REPLAYGAIN_TXXX_DESCRIPTIONS.iter().find(|(x, _)| *x == name)?;
+    let extended_text = ExtendedText {
+        description: description.to_string(),
+        value: value.to_string(),
+    };
+    Some(Frame::with_content("TXXX", Content::ExtendedText(extended_text)))
+}
@@ -0,0 +1,275 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+
+/// Size of the ID3v2 tag header (and footer), in bytes.
+pub const TAG_HEADER_SIZE: u64 = 10;
+/// Header flag indicating that the tag is unsynchronised.
+pub const FLAG_UNSYNCHRONISATION: u8 = 0x80;
+/// Header flag indicating that the tag is compressed (ID3v2.2 only).
+const FLAG_COMPRESSION: u8 = 0x40;
+/// Header flag indicating that the tag has an extended header (ID3v2.3 and later).
+const FLAG_EXTENDED_HEADER: u8 = 0x40;
+/// Header flag indicating that the tag is experimental (ID3v2.3 and later).
+const FLAG_EXPERIMENTAL: u8 = 0x20;
+/// Header flag indicating that the tag is followed by a footer (ID3v2.4 only).
+const FLAG_FOOTER: u8 = 0x10;
+
+/// Represents the raw header of an ID3v2 tag.
+#[derive(Debug, Copy, Clone)]
+pub struct TagHeader {
+    /// Major version, e.g. 4 for ID3v2.4.
+    pub major_version: u8,
+    /// Revision number, e.g. 0 for ID3v2.4.0.
+    pub revision: u8,
+    /// Raw header flags.
+    pub flags: u8,
+    /// Size of the tag excluding the header and footer, but including any declared padding.
+    pub size: u64,
+}
+
+impl TagHeader {
+    /// Reads a tag header from the given offset of the reader, if there is one.
+    pub fn read_from(mut reader: impl Read + Seek, offset: u64) -> io::Result<Option<TagHeader>> {
+        let mut header = [0; TAG_HEADER_SIZE as usize];
+        reader.seek(SeekFrom::Start(offset))?;
+        if let Err(e) = reader.read_exact(&mut header) {
+            return match e.kind() {
+                io::ErrorKind::UnexpectedEof => Ok(None),
+                _ => Err(e),
+            };
+        }
+        if &header[0..3] != b"ID3" {
+            return Ok(None);
+        }
+        Ok(Some(TagHeader {
+            major_version: header[3],
+            revision: header[4],
+            flags: header[5],
+            size: decode_synchsafe(&header[6..10]),
+        }))
+    }
+
+    /// Checks if the tag is followed by a footer.
+    pub fn has_footer(&self) -> bool {
+        self.major_version == 4 && self.flags & FLAG_FOOTER != 0
+    }
+
+    /// Checks if the tag has an extended header.
+    pub fn has_extended_header(&self) -> bool {
+        self.major_version >= 3 && self.flags & FLAG_EXTENDED_HEADER != 0
+    }
+
+    /// Returns the names of all flags set in the header, according to the tag's version.
+    pub fn flag_names(&self) -> Vec<&'static str> {
+        let mut names = vec![];
+        if self.flags & FLAG_UNSYNCHRONISATION != 0 {
+            names.push("unsynchronisation");
+        }
+        if self.major_version == 2 && self.flags & FLAG_COMPRESSION != 0 {
+            names.push("compression");
+        }
+        if self.has_extended_header() {
+            names.push("extended header");
+        }
+        if self.major_version >= 3 && self.flags & FLAG_EXPERIMENTAL != 0 {
+            names.push("experimental");
+        }
+        if self.has_footer() {
+            names.push("footer");
+        }
+        names
+    }
+
+    /// Returns the total size of the tag, including the header and footer.
+    pub fn total_size(&self) -> u64 {
+        TAG_HEADER_SIZE + self.size + if self.has_footer() { TAG_HEADER_SIZE } else { 0 }
+    }
+}
+
+/// Frame flags of ID3v2.3 that change how the frame's body is stored.
+const FRAME_FORMAT_FLAGS_V23: u8 = 0xe0;
+/// Frame flags of ID3v2.4 that change how the frame's body is stored.
+const FRAME_FORMAT_FLAGS_V24: u8 = 0x4f;
+
+/// Frame header flags of ID3v2.3 (status byte, then format byte), with their names.
+const FRAME_FLAG_NAMES_V23: &[(u16, &str)] = &[
+    (0x8000, "tag alter preservation"),
+    (0x4000, "file alter preservation"),
+    (0x2000, "read-only"),
+    (0x0080, "compression"),
+    (0x0040, "encryption"),
+    (0x0020, "grouping"),
+];
+/// Frame header flags of ID3v2.4 (status byte, then format byte), with their names.
+const FRAME_FLAG_NAMES_V24: &[(u16, &str)] = &[
+    (0x4000, "tag alter preservation"),
+    (0x2000, "file alter preservation"),
+    (0x1000, "read-only"),
+    (0x0040, "grouping"),
+    (0x0008, "compression"),
+    (0x0004, "encryption"),
+    (0x0002, "unsynchronisation"),
+    (0x0001, "data length indicator"),
+];
+
+/// Represents the layout of a single frame of an ID3v2 tag, as stored in a file.
+#[derive(Debug, Clone)]
+pub struct FrameLayout {
+    /// The frame's ID, as stored (i.e. 3 characters long in ID3v2.2).
+    pub id: String,
+    /// Size of the frame, including the frame header.
+    pub size: u64,
+    /// The text encoding byte of frames that begin with one, unless the frame's body is stored
+    /// compressed, encrypted or otherwise transformed.
+    pub encoding: Option<u8>,
+    /// The frame's raw bytes, including the frame header.
+    pub bytes: Vec<u8>,
+}
+
+impl FrameLayout {
+    /// Returns the raw flags of the frame header, or 0 in ID3v2.2, which has no frame flags.
+    pub fn flags(&self) -> u16 {
+        match self.id.len() {
+            3 => 0,
+            _ => u16::from_be_bytes([self.bytes[8], self.bytes[9]]),
+        }
+    }
+
+    /// Returns the names of all flags set in the frame header, according to the tag's version.
+    pub fn flag_names(&self, major_version: u8) -> Vec<&'static str> {
+        let names = match major_version {
+            3 => FRAME_FLAG_NAMES_V23,
+            4 => FRAME_FLAG_NAMES_V24,
+            _ => &[],
+        };
+        names.iter().filter(|(x, _)| self.flags() & x != 0).map(|(_, x)| *x).collect()
+    }
+}
+
+/// Checks if the body of a frame begins with a text encoding byte.
+fn has_encoding_byte(id: &str) -> bool {
+    id.starts_with('T') || matches!(id,
+        "COMM" | "USLT" | "SYLT" | "USER" | "WXXX" | "APIC" | "GEOB" | "IPLS" | "GRP1" | "MVNM" | "MVIN" |
+        "COM" | "ULT" | "SLT" | "WXX" | "PIC" | "GEO" | "IPL")
+}
+
+/// Represents the layout of an ID3v2 tag, as stored in a file.
+#[derive(Debug, Clone)]
+pub struct TagLayout {
+    /// The tag's header.
+    pub header: TagHeader,
+    /// Size of the extended header in bytes, or 0 if there is none.
+    pub extended_header_size: u64,
+    /// Layouts of all frames, in the order they are stored.
+    pub frames: Vec<FrameLayout>,
+    /// Size of the padding declared in the header, i.e. everything that follows the last frame.
+    pub padding_size: u64,
+}
+
+impl TagLayout {
+    /// Reads the layout of a tag stored at the given offset of the reader, if there is one.
+    /// Frames are walked until the first byte of padding or anything that is not a valid frame.
+    pub fn read_from(mut reader: impl Read + Seek, offset: u64) -> io::Result<Option<TagLayout>> {
+        let header = match TagHeader::read_from(&mut reader, offset)? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let mut body = vec![];
+        reader.take(header.size).read_to_end(&mut body)?;
+
+        let mut pos = 0;
+        let mut extended_header_size = 0;
+        if header.has_extended_header() && body.len() >= 4 {
+            // In ID3v2.3 the size excludes itself, while in ID3v2.4 it is synchsafe and does not
+            extended_header_size = match header.major_version {
+                3 => 4 + u32::from_be_bytes([body[0], body[1], body[2], body[3]]) as u64,
+                _ => decode_synchsafe(&body[0..4]),
+            }.min(body.len() as u64);
+            pos = extended_header_size as usize;
+        }
+
+        let (id_len, frame_header_len) = match header.major_version {
+            2 => (3, 6),
+            _ => (4, 10),
+        };
+        let mut frames = vec![];
+        while pos + frame_header_len <= body.len() {
+            let id = &body[pos..pos + id_len];
+            if !id.iter().all(|x| x.is_ascii_uppercase() || x.is_ascii_digit()) {
+                break;
+            }
+            let size_bytes = &body[pos + id_len..pos + 2 * id_len];
+            let size = match header.major_version {
+                2 | 3 => size_bytes.iter().fold(0, |acc, &x| (acc << 8) | x as u64),
+                _ => decode_synchsafe(size_bytes),
+            };
+            let frame_size = frame_header_len as u64 + size;
+            if pos as u64 + frame_size > body.len() as u64 {
+                break;
+            }
+            let id = String::from_utf8_lossy(id).into_owned();
+            let format_flags = match header.major_version {
+                2 => 0,
+                3 => body[pos + 9] & FRAME_FORMAT_FLAGS_V23,
+                _ => body[pos + 9] & FRAME_FORMAT_FLAGS_V24,
+            };
+            let encoding = Some(body[pos + frame_header_len])
+                .filter(|_| size > 0 && format_flags == 0 && has_encoding_byte(&id));
+            let bytes = body[pos..pos + frame_size as usize].to_vec();
+            frames.push(FrameLayout { id, size: frame_size, encoding, bytes });
+            pos += frame_size as usize;
+        }
+
+        Ok(Some(TagLayout {
+            header,
+            extended_header_size,
+            frames,
+            padding_size: header.size - pos as u64,
+        }))
+    }
+}
+
+/// Decodes a big-endian "synchsafe" integer, in which the most significant bit of each byte is
+/// ignored.
+fn decode_synchsafe(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |acc, &x| (acc << 7) | (x & 0x7f) as u64)
+}
+
+/// Encodes a big-endian "synchsafe" integer, in which the most significant bit of each byte is
+/// zero.
+pub fn encode_synchsafe(value: u32) -> [u8; 4] {
+    [(value >> 21) as u8 & 0x7f, (value >> 14) as u8 & 0x7f, (value >> 7) as u8 & 0x7f, value as u8 & 0x7f]
+}
+
+/// Returns the size of the region at the start of an MPEG file occupied by the ID3v2 tag,
+/// including any zero bytes of padding which follow the tag without being declared in its header.
+/// If the file has no tag, returns 0.
+pub fn tag_region_size(mut reader: impl Read + Seek) -> io::Result<u64> {
+    let header = match TagHeader::read_from(&mut reader, 0)? {
+        Some(x) => x,
+        None => return Ok(0),
+    };
+    let mut size = header.total_size();
+    reader.seek(SeekFrom::Start(size))?;
+    for byte in BufReader::new(reader).bytes() {
+        if byte? != 0 {
+            break;
+        }
+        size += 1;
+    }
+    Ok(size)
+}
@@ -0,0 +1,69 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::id3_helpers::{decode_text_body, encode_text_body};
+use anyhow::{anyhow, Result};
+use id3::{Content, Frame, Version};
+use id3::frame::Unknown;
+
+/// Represents the contents of a USER (terms of use) frame, which the id3 crate does not parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TermsOfUse {
+    pub lang: String,
+    pub text: String,
+}
+
+impl TermsOfUse {
+    /// Language of query frames which match the first USER frame found, whatever its language.
+    /// It stands for the "first" language of queries, and no valid language is blank.
+    pub const ANY_LANGUAGE: &'static str = "   ";
+
+    /// Decodes the contents of a USER frame.
+    pub fn from_frame(frame: &Frame) -> Result<TermsOfUse> {
+        let data = &frame.content().to_unknown()?.data;
+        if data.len() < 4 {
+            return Err(anyhow!("USER frame is too short ({} bytes)", data.len()));
+        }
+        let lang = String::from_utf8_lossy(&data[1..4]).into_owned();
+        let mut body = vec![data[0]];
+        body.extend(&data[4..]);
+        let text = decode_text_body("USER", &body)?;
+        Ok(TermsOfUse { lang, text: text.trim_end_matches('\0').to_string() })
+    }
+
+    /// Encodes the terms of use as a USER frame, which is valid in any ID3v2 version.
+    /// The text is stored as ISO-8859-1 if possible, or as UTF-16 otherwise.
+    pub fn to_frame(&self) -> Frame {
+        let mut lang = self.lang.bytes().chain(std::iter::repeat(b' ')).take(3).collect::<Vec<_>>();
+        lang.iter_mut().filter(|x| !x.is_ascii()).for_each(|x| *x = b' ');
+        let mut data = encode_text_body(&self.text);
+        data.splice(1..1, lang);
+        Frame::with_content("USER", Content::Unknown(Unknown { data, version: Version::Id3v24 }))
+    }
+
+    /// Constructs a USER frame from a language and text passed by the user, e.g. for printing,
+    /// setting or deleting. The language "first" is stored as `ANY_LANGUAGE`, and languages which
+    /// do not fit in a USER frame are rejected.
+    pub fn query_frame(lang: &str, text: &str) -> Result<Frame> {
+        let lang = match lang {
+            "first" => TermsOfUse::ANY_LANGUAGE,
+            x if x.len() > 3 || !x.is_ascii() => {
+                return Err(anyhow!("Invalid language for USER: '{x}' (expected up to 3 ASCII characters)"));
+            },
+            x => x,
+        };
+        Ok(TermsOfUse { lang: lang.to_string(), text: text.to_string() }.to_frame())
+    }
+}
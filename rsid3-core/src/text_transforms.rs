@@ -0,0 +1,199 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::id3_helpers::replace_exact_tag_frame;
+use id3::{Content, Frame, Tag};
+use id3::frame::{Comment, ExtendedLink, ExtendedText, InvolvedPeopleList, InvolvedPeopleListItem, Lyrics, SynchronisedLyrics};
+
+/// Applies a transformation to all text of a frame: values, descriptions and names, but not
+/// links, languages or binary data. Returns `None` if the frame has no such text, or if the
+/// transformation did not change it.
+pub fn map_frame_text(frame: &Frame, f: impl Fn(&str) -> String) -> Option<Frame> {
+    let content = match frame.content() {
+        Content::Text(x) => Content::Text(f(x)),
+        Content::ExtendedText(x) => Content::ExtendedText(ExtendedText {
+            description: f(&x.description),
+            value: f(&x.value),
+        }),
+        Content::ExtendedLink(x) => Content::ExtendedLink(ExtendedLink {
+            description: f(&x.description),
+            link: x.link.clone(),
+        }),
+        Content::Comment(x) => Content::Comment(Comment {
+            lang: x.lang.clone(),
+            description: f(&x.description),
+            text: f(&x.text),
+        }),
+        Content::Lyrics(x) => Content::Lyrics(Lyrics {
+            lang: x.lang.clone(),
+            description: f(&x.description),
+            text: f(&x.text),
+        }),
+        Content::SynchronisedLyrics(x) => Content::SynchronisedLyrics(SynchronisedLyrics {
+            lang: x.lang.clone(),
+            timestamp_format: x.timestamp_format,
+            content_type: x.content_type,
+            description: f(&x.description),
+            content: x.content.iter().map(|(time, text)| (*time, f(text))).collect(),
+        }),
+        Content::InvolvedPeopleList(x) => Content::InvolvedPeopleList(InvolvedPeopleList {
+            items: x.items.iter().map(|x| InvolvedPeopleListItem {
+                involvement: f(&x.involvement),
+                involvee: f(&x.involvee),
+            }).collect(),
+        }),
+        _ => return None,
+    };
+    (content != *frame.content()).then(|| Frame::with_content(frame.id(), content))
+}
+
+/// Applies a transformation to the values of a frame, i.e. its text, but not its descriptions.
+/// Returns `None` if the frame has no such value, or if the transformation did not change it.
+pub fn map_frame_values(frame: &Frame, f: impl Fn(&str) -> String) -> Option<Frame> {
+    let content = match frame.content() {
+        Content::Text(x) => Content::Text(f(x)),
+        Content::ExtendedText(x) => Content::ExtendedText(ExtendedText {
+            description: x.description.clone(),
+            value: f(&x.value),
+        }),
+        Content::Comment(x) => Content::Comment(Comment {
+            lang: x.lang.clone(),
+            description: x.description.clone(),
+            text: f(&x.text),
+        }),
+        Content::Lyrics(x) => Content::Lyrics(Lyrics {
+            lang: x.lang.clone(),
+            description: x.description.clone(),
+            text: f(&x.text),
+        }),
+        _ => return None,
+    };
+    (content != *frame.content()).then(|| Frame::with_content(frame.id(), content))
+}
+
+/// Returns the value of a frame, i.e. its text or link, but not its description.
+pub fn frame_value(frame: &Frame) -> Option<&str> {
+    match frame.content() {
+        Content::Text(x) | Content::Link(x) => Some(x),
+        Content::ExtendedText(x) => Some(&x.value),
+        Content::ExtendedLink(x) => Some(&x.link),
+        Content::Comment(x) => Some(&x.text),
+        Content::Lyrics(x) => Some(&x.text),
+        _ => None,
+    }
+}
+
+/// Returns a copy of a frame with its value (see `frame_value`) replaced, or `None` if the frame
+/// has no such value.
+pub fn with_frame_value(frame: &Frame, value: &str) -> Option<Frame> {
+    let content = match frame.content() {
+        Content::Text(_) if frame.id().starts_with('W') => Content::Link(value.to_string()),
+        Content::Text(_) => Content::Text(value.to_string()),
+        Content::Link(_) => Content::Link(value.to_string()),
+        Content::ExtendedText(x) => Content::ExtendedText(ExtendedText {
+            description: x.description.clone(),
+            value: value.to_string(),
+        }),
+        Content::ExtendedLink(x) => Content::ExtendedLink(ExtendedLink {
+            description: x.description.clone(),
+            link: value.to_string(),
+        }),
+        Content::Comment(x) => Content::Comment(Comment {
+            lang: x.lang.clone(),
+            description: x.description.clone(),
+            text: value.to_string(),
+        }),
+        Content::Lyrics(x) => Content::Lyrics(Lyrics {
+            lang: x.lang.clone(),
+            description: x.description.clone(),
+            text: value.to_string(),
+        }),
+        _ => return None,
+    };
+    Some(Frame::with_content(frame.id(), content))
+}
+
+/// Replaces every frame of a tag for which `f` returns a new frame.
+/// Returns the replaced frames, each along with its replacement.
+pub fn transform_tag_frames(tag: &mut Tag, f: impl Fn(&Frame) -> Option<Frame>) -> Vec<(Frame, Frame)> {
+    let transformed = tag.frames().filter_map(|x| Some((x.clone(), f(x)?))).collect::<Vec<_>>();
+    for (old_frame, new_frame) in &transformed {
+        replace_exact_tag_frame(tag, old_frame, new_frame.clone());
+    }
+    transformed
+}
+
+/// Removes leading and trailing whitespace from each value (separated by null bytes) and each
+/// line of a text, and collapses runs of whitespace within lines into a single space.
+/// Empty lines at the start and the end of each value are removed.
+pub fn trim_text(text: &str) -> String {
+    text.split('\0')
+        .map(|value| value
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim_matches('\n')
+            .to_string())
+        .collect::<Vec<_>>()
+        .join("\0")
+}
+
+/// Short words which are not capitalized in title case, unless they come first or last.
+const TITLE_CASE_EXCEPTIONS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "by", "for", "in", "nor", "of", "on", "or", "the", "to", "vs", "with",
+];
+
+/// Uppercases the first letter of a word. The rest is lowercased if the word is in all caps, but
+/// is otherwise kept as it is, so that e.g. "McCartney" is left intact.
+fn capitalize(word: &str) -> String {
+    let rest = match word.chars().any(char::is_lowercase) {
+        true => word.to_string(),
+        false => word.to_lowercase(),
+    };
+    match rest.char_indices().find(|(_, x)| x.is_alphabetic()) {
+        Some((i, c)) => format!("{}{}{}", &rest[..i], c.to_uppercase(), &rest[i + c.len_utf8()..]),
+        None => rest,
+    }
+}
+
+/// Converts each value (separated by null bytes) of a text to title case.
+pub fn title_case(text: &str) -> String {
+    text.split('\0')
+        .map(|value| {
+            let n_words = value.split_whitespace().count();
+            let mut i_word = 0;
+            let mut result = String::with_capacity(value.len());
+            // Keep the original whitespace, by splitting on the boundaries of words
+            for part in value.split_inclusive(char::is_whitespace) {
+                let word = part.trim_end();
+                if word.is_empty() {
+                    result.push_str(part);
+                    continue;
+                }
+                let is_exception = TITLE_CASE_EXCEPTIONS.contains(&word.to_lowercase().as_str());
+                if is_exception && i_word != 0 && i_word + 1 != n_words {
+                    result.push_str(&word.to_lowercase());
+                } else {
+                    result.push_str(&capitalize(word));
+                }
+                result.push_str(&part[word.len()..]);
+                i_word += 1;
+            }
+            result
+        })
+        .collect::<Vec<_>>()
+        .join("\0")
+}
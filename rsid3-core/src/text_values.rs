@@ -0,0 +1,66 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use id3::{Content, Frame, Tag, TagLike};
+
+/// Checks if a frame ID belongs to a simple text frame, which may hold multiple values.
+pub fn is_multi_value_id(id: &str) -> bool {
+    id.starts_with('T') && !matches!(id, "TXXX" | "TIPL" | "TMCL")
+}
+
+/// Splits the text of a simple text frame into multiple values, wherever `value_sep` occurs.
+/// Other frames are returned as they are.
+pub fn split_text_values(frame: Frame, value_sep: &str) -> Frame {
+    match frame.content().text() {
+        Some(text) if is_multi_value_id(frame.id()) && !value_sep.is_empty() => {
+            Frame::with_content(frame.id(), Content::new_text_values(text.split(value_sep)))
+        },
+        _ => frame,
+    }
+}
+
+/// Returns all values of a simple text frame of a tag.
+fn get_text_values(tag: &Tag, id: &str) -> Vec<String> {
+    tag.get(id)
+        .and_then(|x| x.content().text_values())
+        .map(|x| x.map(|x| x.to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// Appends values to a simple text frame of a tag, creating the frame if it does not exist.
+/// Returns whether the frame already existed.
+pub fn append_text_values<'a>(tag: &mut Tag, id: &str, new_values: impl IntoIterator<Item = &'a str>) -> bool {
+    let mut values = get_text_values(tag, id);
+    let existed = !values.is_empty();
+    values.extend(new_values.into_iter().map(|x| x.to_string()));
+    tag.add_frame(Frame::with_content(id, Content::new_text_values(values)));
+    existed
+}
+
+/// Removes all occurrences of a value from a simple text frame of a tag.
+/// The frame is removed altogether once it has no values left. Returns whether any value was removed.
+pub fn remove_text_value(tag: &mut Tag, id: &str, value: &str) -> bool {
+    let mut values = get_text_values(tag, id);
+    let old_len = values.len();
+    values.retain(|x| x != value);
+    if values.len() == old_len {
+        return false;
+    }
+    tag.remove(id);
+    if !values.is_empty() {
+        tag.add_frame(Frame::with_content(id, Content::new_text_values(values)));
+    }
+    true
+}
@@ -0,0 +1,119 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use id3::{Frame, Tag, TagLike, Timestamp, Version};
+
+/// Parses an ID3v2.4 timestamp of the form `yyyy[-MM[-dd[THH[:mm[:ss]]]]]`, strictly following
+/// the grammar and the ranges of each field.
+pub fn parse_timestamp(text: &str) -> Option<Timestamp> {
+    // Each field is preceded by its separator and has its own length and range
+    const FIELDS: [(u8, u8, u8); 5] = [(b'-', 1, 12), (b'-', 1, 31), (b'T', 0, 23), (b':', 0, 59), (b':', 0, 59)];
+    let bytes = text.as_bytes();
+    if bytes.len() < 4 || !bytes[..4].iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let mut values = [None; 5];
+    let mut rest = &bytes[4..];
+    for (value, (sep, min, max)) in values.iter_mut().zip(FIELDS) {
+        match rest {
+            [] => break,
+            [x, a, b, tail @ ..] if *x == sep && a.is_ascii_digit() && b.is_ascii_digit() => {
+                let n = (a - b'0') * 10 + (b - b'0');
+                if n < min || n > max {
+                    return None;
+                }
+                *value = Some(n);
+                rest = tail;
+            },
+            _ => return None,
+        }
+    }
+    if !rest.is_empty() {
+        return None;
+    }
+    Some(Timestamp {
+        year: text[..4].parse().ok()?,
+        month: values[0],
+        day: values[1],
+        hour: values[2],
+        minute: values[3],
+        second: values[4],
+    })
+}
+
+/// Composes a timestamp from the ID3v2.3 date frames: TYER (`yyyy`), TDAT (`DDMM`) and TIME (`HHMM`).
+/// TDAT and TIME are only used if all the preceding frames are valid.
+fn timestamp_from_v23(year: &str, date: Option<&str>, time: Option<&str>) -> Option<Timestamp> {
+    let mut timestamp = parse_timestamp(year.trim()).filter(|x| x.month.is_none())?;
+    let split_pair = |x: &str| Some((x.get(..2)?.to_string(), x.get(2..)?.to_string())).filter(|_| x.len() == 4);
+    if let Some((day, month)) = date.and_then(split_pair) {
+        let date = parse_timestamp(&format!("{:04}-{month}-{day}", timestamp.year))?;
+        timestamp.month = date.month;
+        timestamp.day = date.day;
+        if let Some((hour, minute)) = time.and_then(split_pair) {
+            let time = parse_timestamp(&format!("{date}T{hour}:{minute}"))?;
+            timestamp.hour = time.hour;
+            timestamp.minute = time.minute;
+        }
+    }
+    Some(timestamp)
+}
+
+/// Converts the date frames of a tag to their equivalents in the target version: TYER, TDAT and
+/// TIME are merged into TDRC (and TORY into TDOR) when upgrading to ID3v2.4, and split back when
+/// downgrading. Returns the converted frames, and the IDs of the frames they replace.
+/// Frames that are not valid dates are not converted.
+pub fn convert_date_frames(tag: &Tag, target_version: Version) -> (Vec<Frame>, Vec<&'static str>) {
+    let text = |id| tag.get(id).and_then(|x| x.content().text());
+    let mut frames = vec![];
+    let mut replaced_ids = vec![];
+    match (tag.version(), target_version) {
+        (Version::Id3v24, Version::Id3v24) => (),
+        (_, Version::Id3v24) => {
+            if let Some(timestamp) = text("TYER").and_then(|x| timestamp_from_v23(x, text("TDAT"), text("TIME"))) {
+                frames.push(Frame::text("TDRC", timestamp.to_string()));
+                replaced_ids.push("TYER");
+                if timestamp.month.is_some() {
+                    replaced_ids.push("TDAT");
+                }
+                if timestamp.hour.is_some() {
+                    replaced_ids.push("TIME");
+                }
+            }
+            if let Some(timestamp) = text("TORY").and_then(|x| timestamp_from_v23(x, None, None)) {
+                frames.push(Frame::text("TDOR", timestamp.to_string()));
+                replaced_ids.push("TORY");
+            }
+        },
+        (Version::Id3v24, _) => {
+            if let Some(timestamp) = text("TDRC").and_then(parse_timestamp) {
+                frames.push(Frame::text("TYER", format!("{:04}", timestamp.year)));
+                if let (Some(month), Some(day)) = (timestamp.month, timestamp.day) {
+                    frames.push(Frame::text("TDAT", format!("{day:02}{month:02}")));
+                }
+                if let (Some(hour), Some(minute)) = (timestamp.hour, timestamp.minute) {
+                    frames.push(Frame::text("TIME", format!("{hour:02}{minute:02}")));
+                }
+                replaced_ids.push("TDRC");
+            }
+            if let Some(timestamp) = text("TDOR").and_then(parse_timestamp) {
+                frames.push(Frame::text("TORY", format!("{:04}", timestamp.year)));
+                replaced_ids.push("TDOR");
+            }
+        },
+        _ => (),
+    }
+    (frames, replaced_ids)
+}
@@ -0,0 +1,50 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::cli::BackupOpt;
+use anyhow::{anyhow, Result};
+use std::ffi::OsString;
+use std::fs::{copy, create_dir_all};
+use std::path::{Path, PathBuf};
+
+/// Returns the path under which the backup of a file is stored.
+fn backup_path(fpath: &Path, backup: &BackupOpt) -> Result<PathBuf> {
+    let file_name = match fpath.file_name() {
+        Some(x) => x,
+        None => return Err(anyhow!("Cannot back up '{}': Not a file", fpath.display())),
+    };
+    Ok(match backup {
+        BackupOpt::Suffix(suffix) => {
+            let mut backup_name = OsString::from(file_name);
+            backup_name.push(suffix);
+            fpath.with_file_name(backup_name)
+        },
+        BackupOpt::Dir(dir) => Path::new(dir).join(file_name),
+    })
+}
+
+/// Copies a file aside, according to the backup option. Existing backups are overwritten.
+pub fn backup_file(fpath: &impl AsRef<Path>, backup: &BackupOpt) -> Result<()> {
+    let fpath = fpath.as_ref();
+    let dest = backup_path(fpath, backup)?;
+    let result = match backup {
+        BackupOpt::Dir(dir) => create_dir_all(dir).and_then(|_| copy(fpath, &dest)),
+        BackupOpt::Suffix(_) => copy(fpath, &dest),
+    };
+    if let Err(e) = result {
+        return Err(anyhow!("Failed to back up '{}' to '{}': {e}", fpath.display(), dest.display()));
+    }
+    Ok(())
+}
@@ -0,0 +1,69 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use anyhow::{anyhow, Result};
+
+/// Splits a batch command into arguments, like a POSIX shell would: arguments are separated by
+/// whitespace, unless it is quoted with '...' (taken literally) or "..." (where \" and \\ are
+/// escapes), or escaped with a backslash.
+pub fn split_command(command: &str) -> Result<Vec<String>> {
+    let mut args = vec![];
+    let mut arg: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(x) = arg.take() {
+                    args.push(x);
+                }
+            },
+            '\'' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(x) => arg.push(x),
+                        None => return Err(anyhow!("Unterminated quote in command: {command}")),
+                    }
+                }
+            },
+            '"' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(x @ ('"' | '\\')) => arg.push(x),
+                            Some(x) => {
+                                arg.push('\\');
+                                arg.push(x);
+                            },
+                            None => return Err(anyhow!("Unterminated quote in command: {command}")),
+                        },
+                        Some(x) => arg.push(x),
+                        None => return Err(anyhow!("Unterminated quote in command: {command}")),
+                    }
+                }
+            },
+            '\\' => match chars.next() {
+                Some(x) => arg.get_or_insert_with(String::new).push(x),
+                None => return Err(anyhow!("Trailing backslash in command: {command}")),
+            },
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(arg);
+    Ok(args)
+}
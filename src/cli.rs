@@ -13,10 +13,41 @@
 // You should have received a copy of the GNU General Public License along
 // with this program; if not, write to the Free Software Foundation, Inc.,
 // 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
-use std::env::args;
+use rsid3_core::action::{Action, AutoTrackTotal, BinaryFormat, CaseChange, ChaptersFormat, ConvertOpt, FrameAlias, FrameFlag, Id3v1Field, NormalizationForm, PurgeOpt, RatingScale};
+use rsid3_core::aliases::frame_alias_from_name;
+use crate::completions::is_builtin_option;
+use crate::config::Config;
+use rsid3_core::chapters::{chapter_frame, parse_chapter_time, read_chapters_file, toc_frame};
+use rsid3_core::file_format::has_supported_extension;
+use crate::errors::json_string;
+use rsid3_core::frame_table::{frame_params, is_frame_id_in_version, READ_ONLY_FRAMES, WRITABLE_FRAMES};
+use rsid3_core::filter::Filter;
+use rsid3_core::fix::{fix_kind_from_name, ALL_FIX_KINDS};
+use rsid3_core::rating::{Rating, RatingUnit};
+use rsid3_core::numbering::{number_part_from_name, number_part_name};
+use rsid3_core::musicbrainz::{musicbrainz_frame, musicbrainz_names};
+use rsid3_core::replaygain::{normalize_replaygain_value, replaygain_frame, replaygain_names};
+use rsid3_core::podcast::Podcast;
+use rsid3_core::play_count::play_counter_frame;
+use rsid3_core::id3_helpers::{frame_to_string, frames_query_equal, geob_frame_from_file, read_source_tag};
+use rsid3_core::query::{geob_query, parse_frame_query, popm_query, priv_query, sylt_query, ufid_query};
+use rsid3_core::terms_of_use::TermsOfUse;
+use rsid3_core::languages::is_valid_language;
+use rsid3_core::timestamps::parse_timestamp;
+use rsid3_core::involved_people::people_list_frame;
+use rsid3_core::text_values::is_multi_value_id;
+use rsid3_core::normalization::{normalize, normalize_frame};
+use rsid3_core::lrc::{parse_lrc, read_lrc_as_text, read_lrc_file};
+use rsid3_core::text_transforms::{frame_value, with_frame_value};
+use std::env::args_os;
+use std::ffi::{OsStr, OsString};
+use std::fs::{read, read_dir};
+use std::io::{stdin, Read};
+use std::path::{Path, PathBuf};
 use anyhow::{anyhow, Result};
-use id3::{Frame, Content};
-use id3::frame::{Comment, Lyrics, ExtendedText, ExtendedLink};
+use id3::{Encoding, Frame, Content, Version};
+use id3::frame::{Comment, Lyrics, ExtendedText, ExtendedLink, EncapsulatedObject, Popularimeter, Private, SynchronisedLyrics, UniqueFileIdentifier};
+use regex::Regex;
 
 /// Represents all options passed to the program on the command line.
 #[derive(Debug)]
@@ -24,42 +55,98 @@ pub struct Cli {
     pub help: bool,
     pub version: bool,
     pub list_frames: bool,
+    pub help_frame: Option<String>,
+    pub completions: Option<Shell>,
+    pub diff: bool,
+    pub interactive: bool,
+    pub edit: bool,
+    pub undo: bool,
+    pub grep: Option<(Frame, Regex)>,
+    pub find_duplicates: Option<Vec<Frame>>,
+    pub batch: Option<u8>,
+    pub stdin: bool,
     pub frame_sep: Option<String>,
     pub file_sep: Option<String>,
     pub frame_sep_null: bool,
     pub file_sep_null: bool,
+    pub escape_seps: bool,
+    pub value_sep: Option<String>,
+    pub genre_names: bool,
+    pub describe: bool,
+    pub show_flags: bool,
+    pub audio_info: bool,
+    pub lenient: bool,
+    pub keep_going: bool,
+    pub no_clobber: bool,
+    pub normalize: Option<NormalizationForm>,
+    pub track_pad: Option<usize>,
+    pub with_filename: bool,
+    pub missing: Option<String>,
+    pub verbosity: u8,
+    pub quiet: bool,
+    pub color: ColorOpt,
+    pub errors: ErrorFormat,
+    pub output: OutputFormat,
+    pub binary_format: BinaryFormat,
+    pub rating_owner: Option<String>,
+    pub rating_scale: RatingScale,
+    pub padding: Option<usize>,
+    pub encoding: Option<Encoding>,
+    pub default_version: Version,
+    pub backup: Option<BackupOpt>,
+    pub atomic: bool,
+    pub force_write: bool,
+    pub verify_audio: bool,
+    pub no_journal: bool,
+    pub dry_run: bool,
+    pub preserve_mtime: bool,
+    pub preserve_atime: bool,
     pub actions: Vec<Action>,
-    pub files: Vec<String>,
+    pub files: Vec<PathBuf>,
 }
 
-/// Represents a single action passed by the user on the command line.
-#[derive(Debug)]
-pub enum Action {
-    Print(Frame),
-    Set(Frame),
-    Delete(Frame),
-    Convert(ConvertOpt),
-    Purge(PurgeOpt),
+/// Represents when to color pretty-printed output, as passed on the command line.
+#[derive(Debug, Copy, Clone)]
+pub enum ColorOpt {
+    /// Color the output only if stdout is a terminal and `NO_COLOR` is not set.
+    Auto,
+    Always,
+    Never,
 }
 
-/// Represents one of convert options passed to the program on the command line.
-#[derive(Debug, Copy, Clone)]
-pub enum ConvertOpt {
-    Id3v22,
-    Id3v23,
-    Id3v24,
-    Id3v22Force,
-    Id3v23Force,
-    Id3v24Force,
+/// Represents the format in which errors are reported on stderr, as passed on the command line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Plain messages, prefixed with the file path or "rsid3".
+    Text,
+    /// One JSON object per line, with the file, action, kind and message of the error.
+    Json,
+}
+
+/// Represents the format of listings, e.g. of --list-frames, as passed on the command line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A table meant for humans, with one item per line.
+    Text,
+    /// A JSON array of objects, one per item.
+    Json,
 }
 
-/// Represents one of purge options passed to the program on the command line.
+/// Represents a shell for which a completion script is generated, as passed on the command line.
 #[derive(Debug, Copy, Clone)]
-pub enum PurgeOpt {
-    Id3v22,
-    Id3v23,
-    Id3v24,
-    All,
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Represents where backups of modified files are stored, as passed on the command line.
+#[derive(Debug, Clone)]
+pub enum BackupOpt {
+    /// Store the backup next to the original file, with the given suffix appended to its name.
+    Suffix(String),
+    /// Store the backup in the given directory, under the original file's name.
+    Dir(String),
 }
 
 impl Cli {
@@ -67,27 +154,211 @@ impl Cli {
     pub fn print_usage() {
         println!("Usage:  rsid3 [OPTION] [--] FILE...");
         println!();
-        println!("Reads or writes ID3v2 tags in mp3 files.");
-        println!("Supported standards: ID3v2.2, ID3v2.3, ID3v2.4.");
+        println!("Reads or writes ID3v2 tags in mp3, AIFF and WAV files.");
+        println!("Supported standards: ID3v2.2, ID3v2.3, ID3v2.4, ID3v1, ID3v1.1.");
         println!();
         println!("Options:");
         println!("  -h, --help               Show this help and exit.");
         println!("  -V, --version            Print version information.");
-        println!("  -L, --list-frames        List all supported frames.");
+        println!("  -L, --list-frames [json] List all supported frames, as a table or as JSON (see --output).");
+        println!("                           Followed by --id3v2.2, --id3v2.3 or --id3v2.4, list only the");
+        println!("                           frames defined in that version.");
+        println!("  --help-frame ID          Explain a frame, e.g. COMM: its parameters, how to print, set");
+        println!("                           and delete it, and an example.");
+        println!("  --completions SHELL      Print a completion script for bash, zsh or fish.");
+        println!("  -v, --verbose            Report modifications and writes on stderr (-vv: all actions).");
+        println!("  -q, --quiet              Do not show progress on stderr, which is otherwise shown when");
+        println!("                           processing many files in a terminal.");
         println!("  -d SEP, --frame-sep SEP  Separate printed frames with SEP (default: \\n).");
         println!("  -D SEP, --file-sep SEP   Separate printed files with SEP (default: \\n).");
         println!("  -0d, --frame-sep-null    Separate printed frames with the null byte.");
         println!("  -0D, --file-sep-null     Separate printed files with the null byte.");
+        println!("  -H, --with-filename      Prefix each printed value with the file path and ': '.");
+        println!("  --no-filename            Do not prefix printed values with the file path (default).");
+        println!("  --binary-format FORMAT   Print binary frame data (PRIV, GEOB) as hex (default), base64");
+        println!("                           or raw, i.e. written to stdout as it is.");
+        println!("  --binary                 Same as --binary-format raw, which also writes the data of APIC");
+        println!("                           and MCDI, e.g. to pipe it to file, xxd or an image decoder.");
+        println!("  --color[=WHEN]           Color frame summaries: auto (default), always or never.");
+        println!("  --errors FORMAT          Report errors on stderr as text (default) or json, one object");
+        println!("                           per line with the file, action, kind and message.");
+        println!("  --output FORMAT          Print listings (--list-frames) as text (default) or json, an");
+        println!("                           array of objects with the ID, description, parameters,");
+        println!("                           read-write status and ID3v2 versions of each frame.");
+        println!("  --missing STR            Print STR in place of frames that are not found.");
+        println!("  --escape-seps            Interpret backslash escapes (\\t, \\n, \\0, \\xHH, ...) in SEP.");
+        println!("  --value-sep SEP          Separate multiple values of text frames with SEP, when printing");
+        println!("                           and setting them (default: the null byte, as stored).");
+        println!("  --no-validate            Do not reject malformed timestamps in date frames, nor");
+        println!("                           languages other than ISO 639-2/T codes (e.g. eng) or XXX.");
+        println!("  --track-pad N            Pad the numbers in TRCK and TPOS to N digits when setting them.");
+        println!("  --genre-names            Resolve numeric genre references in TCON, e.g. (17) or 17, to");
+        println!("                           genre names (e.g. Rock), when printing and setting them.");
+        println!("  --describe               Follow frame IDs with their names in frame summaries, e.g.");
+        println!("                           TPE1 (Lead artist/performer/soloist/group): Nirvana.");
+        println!("  --show-flags             Follow each frame in frame summaries by the flags set in its");
+        println!("                           header, e.g. read-only, compression, encryption or grouping");
+        println!("                           (implied by -v).");
+        println!("  --audio-info             Follow file summaries by the duration, bitrate (and whether");
+        println!("                           it is constant or variable), sample rate and channels of the");
+        println!("                           audio, parsed from the MPEG frame headers.");
+        println!("  --lenient                If a tag cannot be read as a whole, decode each of its frames");
+        println!("                           on its own, skipping (and reporting) corrupt frames.");
+        println!("  -k, --keep-going         Go on with the remaining files after a file fails, reporting");
+        println!("                           how many failed at the end.");
+        println!("  --fail-fast              Stop at the first file that fails (default).");
+        println!("  --no-clobber             Never overwrite existing frames when setting or copying them.");
+        println!("  --normalize FORM         Convert set values to the Unicode normalization form nfc (as");
+        println!("                           on most systems) or nfd (as in macOS file names).");
+        println!("  --where EXPR             Apply all following actions only to files whose tag matches");
+        println!("                           EXPR, e.g. 'TPE1 == \"Nirvana\" && TCON != \"Live\"'. Frames are");
+        println!("                           written like TXXX[DESC] (see --copy-frame) and compared with");
+        println!("                           ==, !=, <, <=, > or >= (numerically, if both sides are numbers),");
+        println!("                           or tested for existence on their own. Combine with !, && and ||.");
+        println!("  -r, --recursive          Process supported files found in directories recursively.");
+        println!("  --files-from LIST        Also process files listed in LIST, one per line ('-' for stdin).");
+        println!("  --files-from0 LIST       Same as --files-from, but the list is null-delimited.");
+        println!("  --batch                  Run commands read from stdin, one per line, without starting");
+        println!("                           a new process for each. Commands consist of options and files,");
+        println!("                           quoted like in a shell, and any other arguments passed along");
+        println!("                           with --batch are prepended to each. After the output of each");
+        println!("                           command, 'exit CODE' is printed on its own line.");
+        println!("  --batch0                 Same as --batch, but commands (and the output's line breaks");
+        println!("                           around 'exit CODE') are null-delimited.");
+        println!("  --stdin                  Read a file from stdin, given as the FILE '-', and write it to");
+        println!("                           stdout with the modified tag, e.g. in a transcoding pipeline.");
+        println!("                           Actions which print or access the file itself are not allowed.");
+        println!("  --padding N              Write N bytes of padding after modified tags (mp3 only).");
+        println!("  --no-padding             Write no padding after modified tags (mp3 only).");
+        println!("  --encoding ENC           Encode all text of modified tags as latin1, utf16 (with BOM),");
+        println!("                           utf16be or utf8. The latter two only exist in ID3v2.4, so");
+        println!("                           utf16 is used in older tags, as it is for text which does not");
+        println!("                           fit in latin1.");
+        println!("  --default-version VER    Create new tags as ID3v2.VER, i.e. 2.2, 2.3 or 2.4 (default).");
+        println!("  --no-config              Ignore the configuration file.");
+        println!("  --alias NAME=FRAME       Define --NAME, --NAME= VALUE and --NAME- to print, set and delete");
+        println!("                           FRAME, e.g. mood=TXXX[MOOD] or note=COMM[Note](eng).");
+        println!("  --backup[=SUFFIX|DIR]    Copy each file aside before modifying it, appending SUFFIX to");
+        println!("                           its name (default: .bak) or storing it in DIR (ending in '/').");
+        println!("  -n, --dry-run            Do not modify any files, only report what would be done (implies -v).");
+        println!("  --atomic                 Write modified tags to a temporary copy of the file, which");
+        println!("                           then replaces the original (purges are done in place).");
+        println!("  --force-write            Make read-only files writable while modifying them, restoring");
+        println!("                           their permissions afterwards. Without it, read-only files are");
+        println!("                           reported as failed before any actions are executed on them.");
+        println!("  --verify-audio           Hash the audio data of each file before and after writing its");
+        println!("                           tag, and report the file as failed if the data changed.");
+        println!("  --undo                   Restore the tag each FILE had before it was last written by");
+        println!("                           rsid3. Undoing again goes further back. Tags are recorded in");
+        println!("                           ~/.cache/rsid3/journal (or in $XDG_CACHE_HOME/rsid3), pictures");
+        println!("                           included, taking up to 64 MiB of disk space. The oldest tags are");
+        println!("                           dropped once it is full, after which they cannot be undone.");
+        println!("  --no-journal             Do not record tags in the journal before writing them, e.g.");
+        println!("                           to keep older tags when modifying many files with large pictures.");
+        println!("  --preserve-mtime         Restore the modification time of files after modifying them.");
+        println!("  --preserve-atime         Restore the access time of files after modifying them.");
+        println!("  --glob                   Expand wildcards (*, ?, [...]) in FILE arguments (default on Windows).");
         println!();
         println!("  --FRAME                  Print the value of FRAME.");
-        println!("  --FRAME DESC             Print the value of FRAME (TXXX, WXXX).");
-        println!("  --FRAME DESC LANG        Print the value of FRAME (COMM, USLT).");
+        println!("  --FRAME DESC             Print the value of FRAME (TXXX, WXXX, UFID, PRIV, GEOB).");
+        println!("  --FRAME DESC LANG        Print the value of FRAME (COMM, USLT, SYLT).");
+        println!("  --USER LANG              Print the terms of use in LANG.");
+        println!("  --TIPL ROLE              Print the person(s) involved in ROLE (e.g. producer).");
+        println!("  --TMCL INSTRUMENT        Print the musician(s) playing INSTRUMENT.");
         println!("  --FRAME= TEXT            Set the value of FRAME.");
-        println!("  --FRAME= DESC TEXT       Set the value of FRAME (TXXX, WXXX).");
-        println!("  --FRAME= DESC LANG TEXT  Set the value of FRAME (COMM, USLT).");
+        println!("  --FRAME+ TEXT            Append a value to a text FRAME, keeping the other values.");
+        println!("  --FRAME-value TEXT       Remove a value from a text FRAME, keeping the other values.");
+        println!("  --FRAME= DESC TEXT       Set the value of FRAME (TXXX, WXXX, UFID, PRIV).");
+        println!("  --FRAME= DESC LANG TEXT  Set the value of FRAME (COMM, USLT, SYLT).");
+        println!("  --USER= LANG TEXT        Set the terms of use in LANG.");
+        println!("  --TIPL= ROLE PERSON      Set the person involved in ROLE, keeping other roles.");
+        println!("  --TMCL= INSTRUMENT MUSICIAN");
+        println!("                           Set the musician playing INSTRUMENT, keeping other instruments.");
+        println!("  --POPM OWNER             Print the rating and play counter of POPM.");
+        println!("  --POPM= OWNER RATING COUNTER");
+        println!("                           Set POPM, with RATING 0-255 (0 meaning unknown).");
+        println!("  --play-count-inc         Increment the play counter (PCNT), starting from 1.");
+        println!("  --play-count-inc-popm    Same as --play-count-inc, but also increment the POPM counter");
+        println!("                           of the rating owner (see --rating-owner).");
+        println!("  --rating                 Print the rating in stars (0-5, 0 meaning unrated).");
+        println!("  --rating%                Print the rating as a percentage.");
+        println!("  --rating= RATING         Set the rating, given in stars (e.g. 4) or as a percentage");
+        println!("                           (e.g. 80%). The rating is stored in POPM.");
+        println!("  --rating-owner OWNER     Use the POPM of OWNER for ratings (default: Windows Media");
+        println!("                           Player's, or any other when printing).");
+        println!("  --rating-scale SCALE     Map stars onto POPM like Windows Media Player (wmp, default)");
+        println!("                           or linearly (linear).");
+        println!("  --compilation            Print whether the file is part of a compilation (on, off).");
+        println!("  --compilation= on|off    Mark the file as part of a compilation (TCMP set to 1), or");
+        println!("                           clear the mark (TCMP deleted).");
         println!("  --FRAME-                 Delete FRAME.");
-        println!("  --FRAME- DESC            Delete FRAME (TXXX, WXXX).");
-        println!("  --FRAME- DESC LANG       Delete FRAME (COMM, USLT).");
+        println!("  --FRAME- DESC            Delete FRAME (TXXX, WXXX, POPM, UFID, PRIV, GEOB).");
+        println!("  --FRAME- DESC LANG       Delete FRAME (COMM, USLT, SYLT).");
+        println!("  --USER- LANG             Delete the terms of use in LANG.");
+        println!("  --TIPL- ROLE             Delete ROLE from the involved people list.");
+        println!("  --TMCL- INSTRUMENT       Delete INSTRUMENT from the musician credits list.");
+        println!("  --ALIAS, --ALIAS= TEXT, --ALIAS-");
+        println!("                           Print, set or delete the frame ALIAS stands for: artist (TPE1),");
+        println!("                           title (TIT2), album (TALB), album-artist (TPE2), genre (TCON),");
+        println!("                           year (TDRC, or TYER before ID3v2.4) or comment (COMM without");
+        println!("                           a description, in English).");
+        println!("  --auto-track[=START[/TOTAL]]");
+        println!("                           Number the tracks (TRCK) of all files in order, starting from");
+        println!("                           START (default: 1). TOTAL can be a number, or 'auto' for the");
+        println!("                           number of the last file.");
+        println!("  --normalize-numbers      Pad the numbers in TRCK and TPOS to the --track-pad width");
+        println!("                           (default: 2), e.g. 7/13 to 07/13.");
+        println!("  --PART, --PART= N, --PART-");
+        println!("                           Print, set or delete one part of TRCK or TPOS, keeping the other");
+        println!("                           intact: track-number, track-total, disc-number or disc-total");
+        println!("                           (e.g. 7 and 13 of TRCK \"7/13\").");
+        println!();
+        println!("  --lyrics-import DESC LANG FILE");
+        println!("                           Set USLT from an LRC (or plain text) file, stripping timestamps.");
+        println!("  --chapters               Print all chapters (ID, start, end, title), one per line.");
+        println!("  --chap-add ID START END TITLE");
+        println!("                           Add or replace chapter ID. Times are in ms or [hh:]mm:ss[.fff].");
+        println!("  --chap-del ID            Delete chapter ID.");
+        println!("  --chapters-import FILE   Replace all chapters and tables of contents with chapters");
+        println!("                           read from a cue sheet or an FFmpeg metadata file.");
+        println!("  --chapters-export FORMAT Print all chapters as a cue sheet (FORMAT 'cue') or an FFmpeg");
+        println!("                           metadata file (FORMAT 'ffmetadata').");
+        println!("  --tocs                   Print all tables of contents (ID, top-level or nested,");
+        println!("                           comma-separated child IDs), one per line.");
+        println!("  --toc-set ID CHILDREN    Set the top-level table of contents ID, listing the");
+        println!("                           comma-separated CHILDREN chapter/table IDs in order.");
+        println!("  --toc-set-nested ID CHILDREN");
+        println!("                           Same as --toc-set, but the table is not top-level.");
+        println!("  --toc-auto               Set the top-level table of contents 'toc' to all chapters,");
+        println!("                           ordered by start time.");
+        println!("  --toc-del ID             Delete table of contents ID.");
+        println!("  --podcast                Mark the file as a podcast episode (PCST), and set the frames");
+        println!("                           given by the following options, wherever they appear:");
+        println!("  --podcast-feed URL       The podcast's feed URL (WFED).");
+        println!("  --podcast-id ID          The episode's identifier, e.g. its GUID in the feed (TGID).");
+        println!("  --podcast-description TEXT");
+        println!("                           The episode's description (TDES).");
+        println!("  --podcast-keywords KEYWORDS");
+        println!("                           The episode's comma-separated keywords (TKWD).");
+        println!("  --copy-from SOURCE       Replace the whole tag with a copy of the tag of SOURCE,");
+        println!("                           including its version.");
+        println!("  --copy-frame FRAME SOURCE");
+        println!("                           Set FRAME to its value in SOURCE. FRAME is written like in");
+        println!("                           messages, e.g. TIT2, TXXX[DESC] or COMM[DESC](LANG).");
+        println!("  --copy FROM TO           Set frame TO to the value of frame FROM (see --copy-frame).");
+        println!("  --move FROM TO           Same as --copy, but FROM is deleted afterwards.");
+        println!("  --merge-from SOURCE      Copy all frames of SOURCE which are missing in the tag.");
+        println!("  --merge-overwrite SOURCE Copy all frames of SOURCE, overwriting existing frames.");
+        println!("  --geob-embed DESC MIME FILE");
+        println!("                           Embed FILE as GEOB[DESC], with the given MIME type.");
+        println!("  --geob-extract DESC DEST Write the data of GEOB[DESC] to DEST, which may contain");
+        println!("                           {{dir}}, {{name}} and {{file}} (see --lyrics-export).");
+        println!("  --lyrics-export[=DEST]   Write the first USLT to DEST (default: the file's name with");
+        println!("                           a .txt extension). DEST may contain {{dir}}, {{name}} and {{file}}.");
+        println!("  --dump-tag DEST          Write the tag exactly as stored in the file, byte for byte, to");
+        println!("                           DEST, which may contain {{dir}}, {{name}} and {{file}}.");
+        println!("  --restore-tag SRC        Replace the tag with one written by --dump-tag to SRC, byte for");
+        println!("                           byte (padding after the old tag is dropped in mp3 files).");
         println!();
         println!("  --id3v2.2                Convert tags to ID3v2.2 (lossless; may fail).");
         println!("  --id3v2.3                Convert tags to ID3v2.3 (lossless; may fail).");
@@ -99,6 +370,100 @@ impl Cli {
         println!("  --purge-id3v2.3          Purge ID3v2.3 tags, if present.");
         println!("  --purge-id3v2.4          Purge ID3v2.4 tags, if present.");
         println!("  --purge-all              Purge all ID3v2 tags, if present.");
+        println!("  --purge-id3v1            Purge the ID3v1 tag, if present (leaves ID3v2 intact).");
+        println!("  --purge-ape              Purge the APE tag, if present (leaves ID3 intact).");
+        println!();
+        println!("  --id3v1 FIELD            Print FIELD of the ID3v1 tag (title, artist, album,");
+        println!("                           year, comment, track, genre).");
+        println!("  --sync-id3v1             Write an ID3v1.1 tag derived from the ID3v2 tag.");
+        println!("  --set-length             Set TLEN to the duration of the audio in ms, computed from");
+        println!("                           the MPEG frame headers (MPEG files only).");
+        println!("  --checksum-set           Store the MD5 digest of the audio data (everything but the");
+        println!("                           tags) in TXXX[AUDIO_MD5].");
+        println!("  --checksum-verify        Compare the MD5 digest of the audio data to TXXX[AUDIO_MD5],");
+        println!("                           printing the files whose audio data does not match.");
+        println!();
+        println!("  --mb-NAME                Print the MusicBrainz identifier NAME.");
+        println!("  --mb-NAME= ID            Set the MusicBrainz identifier NAME.");
+        println!("  --mb-NAME-               Delete the MusicBrainz identifier NAME.");
+        println!("                           NAME is one of: recordingid, releaseid, artistid,");
+        println!("                           releaseartistid, releasegroupid, releasetrackid, workid,");
+        println!("                           discid. They are stored in the same frames as Picard does.");
+        println!();
+        println!("  --rg-NAME                Print the ReplayGain value NAME.");
+        println!("  --rg-NAME= VALUE         Set the ReplayGain value NAME.");
+        println!("  --rg-NAME-               Delete the ReplayGain value NAME.");
+        println!("                           NAME is one of: track-gain, track-peak, album-gain,");
+        println!("                           album-peak. Gains are stored as e.g. '-6.50 dB', peaks as");
+        println!("                           e.g. '0.988547', in the REPLAYGAIN_* TXXX frames.");
+        println!();
+        println!("  --diff FILE1 FILE2       Print the frames which differ between the tags of FILE1 and");
+        println!("                           FILE2: '-' lines for FILE1, '+' lines for FILE2. Exits with 1");
+        println!("                           if the tags differ, like diff(1).");
+        println!("  --interactive            Edit the tag of each FILE in turn with commands typed at a");
+        println!("                           prompt: list (l), edit (e N VALUE), add (a FRAME VALUE) and");
+        println!("                           delete (d N) frames, write (w), next file (n), quit (q).");
+        println!("  --edit                   Open the tag of each FILE in turn in $VISUAL or $EDITOR, as");
+        println!("                           FRAME=VALUE lines, and write the edited tag on exit. Deleted");
+        println!("                           lines delete frames, and added lines add frames.");
+        println!("  --grep FRAME REGEX       Print the paths of all files in which a value of FRAME (see");
+        println!("                           --copy-frame) matches REGEX, separated by the file separator.");
+        println!("                           Exits with 1 if no file matches, like grep(1). REGEX uses the");
+        println!("                           syntax of the Rust regex crate, e.g. (?i) makes it case-insensitive.");
+        println!("  --find-duplicates FRAME,...");
+        println!("                           Print groups of files which share the values of all FRAMEs");
+        println!("                           (see --copy-frame), ignoring case and surrounding whitespace.");
+        println!("                           Files are separated by the file separator, and groups by an");
+        println!("                           empty entry. Exits with 1 if there are no duplicates.");
+        println!("  --tag-info               Print the version, size, padding, flags and frame sizes");
+        println!("                           of the ID3v2 tag, as stored in the file.");
+        println!("  --raw FRAME              Print a hexdump of the bytes of FRAME (e.g. TXXX[DESC]),");
+        println!("                           including its header, as stored in the file (or as it would be");
+        println!("                           stored, if modified by earlier actions).");
+        println!("  --raw-binary FRAME       Same as --raw, but write the bytes as they are.");
+        println!("  --check                  Audit the tag against the standard: frames from the wrong");
+        println!("                           version, duplicate or empty frames, invalid encodings and");
+        println!("                           languages, and malformed numbers and dates. Each problem is");
+        println!("                           printed on its own line.");
+        println!("  --fix[=KIND,...]         Apply safe fixes to the tag, either all or the given KINDs:");
+        println!("                           corrupt     remove frames which cannot be decoded (see --lenient)");
+        println!("                           encoding    recover UTF-8 text stored as Latin-1");
+        println!("                           duplicates  merge conflicting frames, e.g. two TALB");
+        println!("                           empty       remove frames which hold no value");
+        println!("                           genres      resolve numeric genre references in TCON");
+        println!("                           numbers     remove whitespace from TRCK and TPOS");
+        println!("                           The corrupt, encoding and duplicates fixes need the tag as");
+        println!("                           stored, so they must come before other modifications.");
+        println!("  --titlecase FRAME        Capitalize each word in the values of FRAME, except for short");
+        println!("                           words such as a, of and the, unless they come first or last.");
+        println!("  --upper FRAME            Convert the values of FRAME to uppercase.");
+        println!("  --lower FRAME            Convert the values of FRAME to lowercase.");
+        println!("  --flag FRAME +FLAG       Set (+) or clear (-) a status flag in the header of all FRAME");
+        println!("  --flag FRAME -FLAG       frames, e.g. --flag TIT2 +read_only. FLAG is one of:");
+        println!("                           read_only             the frame should not be changed");
+        println!("                           tag_alter_preserve    discard the frame if the tag is altered");
+        println!("                           file_alter_preserve   discard the frame if the audio is altered");
+        println!("                           Flags are lost when the tag is later rewritten, as they are");
+        println!("                           not read back from the stored frames. ID3v2.2 has no flags.");
+        println!("  --dedupe                 Delete identical frames, and frames of which only one may exist");
+        println!("                           (e.g. COMM with the same description and language), keeping");
+        println!("                           the last one.");
+        println!("  --strip-empty            Delete all text and link frames whose value is empty or only");
+        println!("                           whitespace.");
+        println!("  --trim                   Strip leading and trailing whitespace from the text of all");
+        println!("                           frames, and collapse runs of whitespace within lines.");
+        println!("  --normalize-frames       Convert the text of all frames to the normalization form given");
+        println!("                           by --normalize (default: nfc).");
+        println!("  --fix-encoding           Repair UTF-8 text that was mis-decoded as Latin-1 or Windows-1252");
+        println!("                           (e.g. CafÃ© for Café) in text frames, whatever their encoding.");
+        println!();
+        println!("The DESC of UFID is its owner, e.g. 'http://musicbrainz.org'.");
+        println!("The DESC of PRIV is its owner, and its TEXT is binary data, read from a file");
+        println!("if it is '@' followed by the file's path.");
+        println!("POPM is printed as 'RATING COUNTER', and its DESC is the rating owner's e-mail address.");
+        println!();
+        println!("SYLT is printed in the LRC format, and set from LRC text or from an LRC file,");
+        println!("if TEXT is '@' followed by the file's path.");
         println!();
         println!("If the value of LANG is irrelevant when printing a frame, 'first'");
         println!("can be passed instead, in which case the first frame with a matching");
@@ -110,6 +475,22 @@ impl Cli {
         println!("for chaining many operations under a single command.");
         println!("If no convert options are passed, rsid3 keeps the existing tag versions,");
         println!("or defaults to ID3v2.4 when creating new tags from scratch.");
+        println!();
+        println!("Defaults for some options can be set in ~/.config/rsid3/config.toml (or in");
+        println!("$XDG_CONFIG_HOME/rsid3), e.g. 'frame_sep = \"\\t\"'. The supported options are");
+        println!("frame_sep, file_sep, value_sep, default_version, encoding, color and binary_format.");
+        println!("Aliases can be defined in an [aliases] table, e.g. 'mood = \"TXXX[MOOD]\"'.");
+        println!("Each of them can also be set in an environment variable named like it in uppercase,");
+        println!("prefixed with RSID3_, e.g. RSID3_FRAME_SEP, which overrides the configuration file.");
+        println!("Options passed on the command line take precedence over both.");
+        println!();
+        println!("Exit status:");
+        println!("  0  Success.");
+        println!("  1  A printed frame or field was not found.");
+        println!("  2  Invalid command line or unsupported operation.");
+        println!("  3  A file or its tag could not be read or written.");
+        println!("  4  Same as 3, but some files were processed successfully.");
+        println!("  5  --check found problems in a tag, or --checksum-verify found a mismatch.");
     }
 
     /// Prints the current version of rsid3.
@@ -120,158 +501,383 @@ impl Cli {
             env!("VERGEN_BUILD_TIMESTAMP"));
     }
 
-    /// Prints the available frames.
-    pub fn print_all_frames() {
+    /// Prints the available frames, in the given format. If a version is given, only frames
+    /// defined in that version are printed.
+    pub fn print_all_frames(format: OutputFormat, version: Option<Version>) {
+        let is_listed = |id: &&str| version.is_none_or(|x| is_frame_id_in_version(id, x));
+        let writable = WRITABLE_FRAMES.iter().filter(|(id, _)| is_listed(id));
+        let read_only = READ_ONLY_FRAMES.iter().filter(|(id, _)| is_listed(id));
+        if format == OutputFormat::Json {
+            Cli::print_all_frames_json(writable.map(|x| (x, true)).chain(read_only.map(|x| (x, false))).collect());
+            return;
+        }
         println!("Read-write frames:");
-        println!("COMM	User comment (DESC, LANG, TEXT)");
-        println!("TALB	Album");
-        println!("TBPM	Beats per minute");
-        println!("TCAT	iTunes podcast category");
-        println!("TCMP	iTunes compilation flag");
-        println!("TCOM	Composer");
-        println!("TCON	Content type (genre)");
-        println!("TCOP	Copyright");
-        println!("TDAT	Date of recording (DDMM)");
-        println!("TDEN	Encoding time");
-        println!("TDES	iTunes podcast description");
-        println!("TDLY	Audio delay (ms)");
-        println!("TDOR	Original release time");
-        println!("TDRC	Recording time");
-        println!("TDRL	Release time");
-        println!("TDTG	Tagging time");
-        println!("TENC	Encoder");
-        println!("TEXT	Lyricist");
-        println!("TFLT	File type");
-        println!("TGID	iTunes podcast identifier");
-        println!("TIME	Time of recording (HHMM)");
-        println!("TIPL	Involved people list");
-        println!("TIT1	Content group description");
-        println!("TIT2	Title");
-        println!("TIT3	Subtitle/description refinement");
-        println!("TKEY	Starting key");
-        println!("TKWD	iTunes podcast keywords");
-        println!("TLAN	Audio languages");
-        println!("TLEN	Audio length (ms)");
-        println!("TMCL	Musicians credits list");
-        println!("TMED	Source media type");
-        println!("TMOO	Mood");
-        println!("TOAL	Original album");
-        println!("TOFN	Original filename");
-        println!("TOLY	Original lyricist");
-        println!("TOPE	Original artist/performer");
-        println!("TORY	Original release year");
-        println!("TOWN	Owner/Licensee");
-        println!("TPE1	Lead artist/performer/soloist/group");
-        println!("TPE2	Band/Orchestra/Accompaniment");
-        println!("TPE3	Conductor");
-        println!("TPE4	Interpreter/Remixer/Modifier");
-        println!("TPOS	Part of set");
-        println!("TPRO	Produced");
-        println!("TPUB	Publisher");
-        println!("TRCK	Track number");
-        println!("TRDA	Recording dates");
-        println!("TRSN	Internet radio station name");
-        println!("TRSO	Internet radio station owner");
-        println!("TSIZ	Size of audio data (bytes)");
-        println!("TSO2	iTunes album artist sort");
-        println!("TSOA	Album sort order key");
-        println!("TSOC	iTunes composer sort");
-        println!("TSOP	Performer sort order key");
-        println!("TSOT	Title sort order key");
-        println!("TSRC	International Standard Recording Code (ISRC)");
-        println!("TSSE	Encoder settings");
-        println!("TSST	Set subtitle");
-        println!("TXXX	User-defined text data (DESC, TEXT)");
-        println!("TYER	Year of recording");
-        println!("USLT	Unsynchronised lyrics/text transcription (DESC, LANG, TEXT)");
-        println!("WCOM	Commercial information");
-        println!("WCOP	Copyright information");
-        println!("WFED	iTunes podcast feed");
-        println!("WOAF	Official file information");
-        println!("WOAR	Official artist/performer information");
-        println!("WOAS	Official source information");
-        println!("WORS	Official internet radio information");
-        println!("WPAY	Payment information");
-        println!("WPUB	Official publisher information");
-        println!("WXXX	User-defined URL data (DESC, URL)");
+        for (id, description) in writable {
+            println!("{id}\t{description}");
+        }
         println!();
         println!("Read-only frames (rudimentary support):");
-        println!("AENC	Audio encryption");
-        println!("APIC	Attached (or linked) picture");
-        println!("ASPI	Audio seek point index");
-        println!("CHAP	Chapter");
-        println!("COMR	Commercial frame");
-        println!("CTOC	Table of contents");
-        println!("ENCR	Encryption method registration");
-        println!("EQU2	Equalization 2");
-        println!("ETCO	Event timing codes");
-        println!("GEOB	General encapsulated object");
-        println!("GRID	Group identification registration");
-        println!("GRP1	iTunes grouping");
-        println!("IPLS	Involved people list");
-        println!("LINK	Linked information");
-        println!("MCDI	Binary dump of CD's TOC");
-        println!("MLLT	MPEG location lookup table");
-        println!("MVIN	iTunes movement number/count");
-        println!("MVNM	iTunes movement name");
-        println!("OWNE	Ownership frame");
-        println!("PCNT	Play counter");
-        println!("PCST	iTunes podcast flag");
-        println!("POPM	Popularimeter");
-        println!("POSS	Position synchronisation frame");
-        println!("PRIV	Private frame");
-        println!("RBUF	Recommended buffer size");
-        println!("RVA2	Relative volume adjustment 2");
-        println!("RVAD	Relative volume adjustment");
-        println!("RVRB	Reverb");
-        println!("SEEK	Seek frame");
-        println!("SIGN	Signature frame");
-        println!("SYLT	Synchronised lyrics/text");
-        println!("SYTC	Synchronised tempo codes");
-        println!("UFID	Unique file identifier");
-        println!("USER	Terms of use");
+        for (id, description) in read_only {
+            println!("{id}\t{description}");
+        }
+    }
+
+    /// Prints frames with their descriptions and whether they are writable as a JSON array, with
+    /// one object per line.
+    fn print_all_frames_json(frames: Vec<(&(&str, &str), bool)>) {
+        let json_list = |items: &mut dyn Iterator<Item = &str>| {
+            items.map(json_string).collect::<Vec<_>>().join(", ")
+        };
+        println!("[");
+        for (i, ((id, description), writable)) in frames.iter().enumerate() {
+            let mut versions = [(Version::Id3v22, "2.2"), (Version::Id3v23, "2.3"), (Version::Id3v24, "2.4")].into_iter()
+                .filter(|(x, _)| is_frame_id_in_version(id, *x))
+                .map(|(_, x)| x);
+            println!("  {{\"id\": {}, \"description\": {}, \"params\": [{}], \"writable\": {writable}, \"versions\": [{}]}}{}",
+                json_string(id), json_string(description), json_list(&mut frame_params(id).iter().copied()),
+                json_list(&mut versions), if i + 1 < frames.len() { "," } else { "" });
+        }
+        println!("]");
     }
 
     /// Construct a Cli object representing passed command-line arguments.
     pub fn parse_args() -> Result<Self> {
-        let args: Vec<String> = args().collect();
+        Cli::parse_args_from(args_os().collect())
+    }
+
+    /// Construct a Cli object representing the given arguments, the first of which is the name
+    /// of the program.
+    pub fn parse_args_from(args: Vec<OsString>) -> Result<Self> {
         let mut help = false;
         let mut version = false;
         let mut list_frames = false;
+        let mut help_frame = None;
+        let mut completions = None;
+        let mut diff = false;
+        let mut interactive = false;
+        let mut edit = false;
+        let mut undo = false;
+        let mut grep = None;
+        let mut find_duplicates = None;
+        let mut batch = None;
+        let mut stdin = false;
         let mut frame_sep: Option<String> = None;
         let mut file_sep: Option<String> = None;
         let mut frame_sep_null = false;
         let mut file_sep_null = false;
+        let mut escape_seps = false;
+        let mut value_sep = None;
+        let mut genre_names = false;
+        let mut describe = false;
+        let mut show_flags = false;
+        let mut audio_info = false;
+        let mut lenient = false;
+        let mut keep_going = false;
+        let mut no_clobber = false;
+        let mut normalize_form = None;
+        let mut track_pad = None;
+        let mut no_validate = false;
+        let mut with_filename = false;
+        let mut missing: Option<String> = None;
+        let mut verbosity: u8 = 0;
+        let mut quiet = false;
+        let mut color = None;
+        let mut errors = ErrorFormat::Text;
+        let mut output = OutputFormat::Text;
+        let mut binary_format = None;
+        let mut rating_owner: Option<String> = None;
+        let mut rating_scale = RatingScale::Wmp;
+        let mut podcast = Podcast::default();
+        let mut podcast_index: Option<usize> = None;
+        let mut recursive = false;
+        let mut files_from: Option<(OsString, u8)> = None;
+        let mut padding: Option<usize> = None;
+        let mut encoding: Option<Encoding> = None;
+        let mut default_version = None;
+        let mut backup: Option<BackupOpt> = None;
+        let mut atomic = false;
+        let mut force_write = false;
+        let mut verify_audio = false;
+        let mut no_journal = false;
+        let mut dry_run = false;
+        let mut preserve_mtime = false;
+        let mut preserve_atime = false;
+        // Windows shells do not expand wildcards, so it is up to the program
+        let mut glob = cfg!(windows);
         let mut actions = vec![];
+
+        // Options which are not passed on the command line default to the environment, and then
+        // to the configuration file. The latter is read first, as it can define aliases
+        let no_config = args.iter().skip(1).take_while(|x| *x != "--").any(|x| x == "--no-config");
+        let mut config = if no_config { Config::default() } else { Config::read()? };
+        config.read_env()?;
+        let mut aliases = vec![];
+        for (name, spec) in &config.aliases {
+            aliases.push(Cli::user_alias_def(name, spec)?);
+        }
+
         let mut i = 1;
         while i < args.len() {
-            let arg = args[i].as_str();
+            // Options are always valid UTF-8, so anything else must be the first file
+            let arg = match args[i].to_str() {
+                Some(x) => x,
+                None => break,
+            };
+            if let Some((frame, suffix)) = Cli::user_alias_arg(arg, &aliases) {
+                match suffix {
+                    "=" => {
+                        if i + 1 >= args.len() {
+                            return Err(anyhow!("1 argument expected after {arg}"));
+                        }
+                        let value = Cli::text_arg(&args[i + 1])?;
+                        // Aliases only stand for frames with a value, as checked by `user_alias_def`
+                        actions.push(Action::Set(with_frame_value(frame, &value).unwrap()));
+                        i += 1;
+                    },
+                    "-" => actions.push(Action::Delete(frame.clone())),
+                    _ => actions.push(Action::Print(frame.clone())),
+                }
+                i += 1;
+                continue;
+            }
             match arg {
                 "-h" | "--help" => { help = true; },
                 "-V" | "--version" => { version = true; },
-                "-L" | "--list-frames" => { list_frames = true; },
+                "-L" | "--list-frames" => {
+                    list_frames = true;
+                    // The format can also be given right after the option, e.g. -L json
+                    if let Some(format) = args.get(i + 1).and_then(|x| Cli::output_format_arg(&x.to_string_lossy()).ok()) {
+                        output = format;
+                        i += 1;
+                    }
+                },
+                "--diff" => { diff = true; },
+                "--interactive" => { interactive = true; },
+                "--edit" => { edit = true; },
+                "--undo" => { undo = true; },
+                "--completions" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --completions"));
+                    }
+                    completions = match Cli::text_arg(&args[i + 1])?.as_str() {
+                        "bash" => Some(Shell::Bash),
+                        "zsh" => Some(Shell::Zsh),
+                        "fish" => Some(Shell::Fish),
+                        x => return Err(anyhow!("Unsupported shell: '{x}' (expected bash, zsh or fish)")),
+                    };
+                    i += 1;
+                },
+                "--find-duplicates" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --find-duplicates"));
+                    }
+                    let frames = Cli::frame_list_arg(&Cli::text_arg(&args[i + 1])?)?;
+                    find_duplicates = Some(frames);
+                    i += 1;
+                },
+                "--grep" => {
+                    if i + 2 >= args.len() {
+                        return Err(anyhow!("2 arguments expected after --grep"));
+                    }
+                    grep = Some((Cli::frame_query_arg(&args[i + 1])?, Cli::regex_arg(&args[i + 2])?));
+                    i += 2;
+                },
+                "-v" | "--verbose" => { verbosity = verbosity.saturating_add(1); },
+                "-vv" => { verbosity = verbosity.saturating_add(2); },
+                "-q" | "--quiet" => { quiet = true; },
                 "-d" | "--frame-sep" => {
                     if i + 1 >= args.len() {
                         return Err(anyhow!("1 argument expected after --frame-sep"));
                     }
-                    frame_sep = Some(args[i + 1].clone());
+                    frame_sep = Some(Cli::text_arg(&args[i + 1])?);
                     i += 1;
                 },
                 str if str.starts_with("-d") => {
-                    frame_sep = Some(((args[i])[2..]).to_string());
+                    frame_sep = Some(arg[2..].to_string());
                 },
                 "-D" | "--file-sep" => {
                     if i + 1 >= args.len() {
                         return Err(anyhow!("1 argument expected after --file-sep"));
                     }
-                    file_sep = Some(args[i + 1].clone());
+                    file_sep = Some(Cli::text_arg(&args[i + 1])?);
                     i += 1;
                 },
                 str if str.starts_with("-D") => {
-                    file_sep = Some(((args[i])[2..]).to_string());
+                    file_sep = Some(arg[2..].to_string());
                 },
                 "-0d" | "--frame-sep-null" => { frame_sep_null = true; },
                 "-0D" | "--file-sep-null" => { file_sep_null = true; },
+                "--escape-seps" => { escape_seps = true; },
+                "-H" | "--with-filename" => { with_filename = true; },
+                "--no-filename" => { with_filename = false; },
+                "--color" => { color = Some(ColorOpt::Always); },
+                str if str.starts_with("--color=") => {
+                    color = Some(Cli::color_arg(&str["--color=".len()..])?);
+                },
+                "--value-sep" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --value-sep"));
+                    }
+                    value_sep = Some(Cli::text_arg(&args[i + 1])?);
+                    i += 1;
+                },
+                "--genre-names" => { genre_names = true; },
+                "--describe" => { describe = true; },
+                "--show-flags" => { show_flags = true; },
+                "--audio-info" => { audio_info = true; },
+                "--normalize" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --normalize"));
+                    }
+                    normalize_form = match Cli::text_arg(&args[i + 1])?.as_str() {
+                        "nfc" => Some(NormalizationForm::Nfc),
+                        "nfd" => Some(NormalizationForm::Nfd),
+                        x => return Err(anyhow!("Invalid normalization form: '{x}'")),
+                    };
+                    i += 1;
+                },
+                "--lenient" => { lenient = true; },
+                "-k" | "--keep-going" => { keep_going = true; },
+                "--fail-fast" => { keep_going = false; },
+                "--no-clobber" => { no_clobber = true; },
+                "--no-validate" => { no_validate = true; },
+                "--track-pad" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --track-pad"));
+                    }
+                    let value = Cli::text_arg(&args[i + 1])?;
+                    track_pad = Some(value.parse().map_err(|_| anyhow!("Invalid track padding: '{value}'"))?);
+                    i += 1;
+                },
+                "--errors" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --errors"));
+                    }
+                    errors = match Cli::text_arg(&args[i + 1])?.as_str() {
+                        "text" => ErrorFormat::Text,
+                        "json" => ErrorFormat::Json,
+                        x => return Err(anyhow!("Invalid error format: '{x}'")),
+                    };
+                    i += 1;
+                },
+                "--help-frame" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --help-frame"));
+                    }
+                    help_frame = Some(Cli::text_arg(&args[i + 1])?);
+                    i += 1;
+                },
+                "--output" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --output"));
+                    }
+                    output = Cli::output_format_arg(&Cli::text_arg(&args[i + 1])?)?;
+                    i += 1;
+                },
+                "--binary" => { binary_format = Some(BinaryFormat::Raw); },
+                "--binary-format" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --binary-format"));
+                    }
+                    binary_format = Some(Cli::binary_format_arg(&Cli::text_arg(&args[i + 1])?)?);
+                    i += 1;
+                },
+                "--rating-owner" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --rating-owner"));
+                    }
+                    rating_owner = Some(Cli::text_arg(&args[i + 1])?);
+                    i += 1;
+                },
+                "--rating-scale" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --rating-scale"));
+                    }
+                    rating_scale = match Cli::text_arg(&args[i + 1])?.as_str() {
+                        "wmp" => RatingScale::Wmp,
+                        "linear" => RatingScale::Linear,
+                        x => return Err(anyhow!("Invalid rating scale: '{x}'")),
+                    };
+                    i += 1;
+                },
+                "--missing" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --missing"));
+                    }
+                    missing = Some(Cli::text_arg(&args[i + 1])?);
+                    i += 1;
+                },
+                "-r" | "--recursive" => { recursive = true; },
+                "--glob" => { glob = true; },
+                "--encoding" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --encoding"));
+                    }
+                    encoding = Some(Cli::encoding_arg(&Cli::text_arg(&args[i + 1])?)?);
+                    i += 1;
+                },
+                "--default-version" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --default-version"));
+                    }
+                    default_version = Some(Cli::version_arg(&Cli::text_arg(&args[i + 1])?)?);
+                    i += 1;
+                },
+                "--no-config" => {},
+                "--alias" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --alias"));
+                    }
+                    let def = Cli::text_arg(&args[i + 1])?;
+                    let (name, spec) = def.split_once('=')
+                        .ok_or_else(|| anyhow!("Invalid alias: '{def}' (expected NAME=FRAME)"))?;
+                    let alias = Cli::user_alias_def(name, spec)?;
+                    // Later definitions replace earlier ones, e.g. from the configuration file
+                    aliases.retain(|(x, _)| *x != alias.0);
+                    aliases.push(alias);
+                    i += 1;
+                },
+                "--padding" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --padding"));
+                    }
+                    let value = Cli::text_arg(&args[i + 1])?;
+                    padding = match value.parse() {
+                        Ok(x) => Some(x),
+                        Err(_) => return Err(anyhow!("Invalid padding size: '{value}'")),
+                    };
+                    i += 1;
+                },
+                "--no-padding" => { padding = Some(0); },
+                "--atomic" => { atomic = true; },
+                "--force-write" => { force_write = true; },
+                "--verify-audio" => { verify_audio = true; },
+                "--no-journal" => { no_journal = true; },
+                "-n" | "--dry-run" => { dry_run = true; },
+                "--preserve-mtime" => { preserve_mtime = true; },
+                "--preserve-atime" => { preserve_atime = true; },
+                "--backup" => { backup = Some(BackupOpt::Suffix(".bak".to_string())); },
+                str if str.starts_with("--backup=") => {
+                    let value = &str["--backup=".len()..];
+                    backup = Some(match value {
+                        "" => return Err(anyhow!("Backup suffix or directory must not be empty")),
+                        x if x.ends_with(std::path::is_separator) || Path::new(x).is_dir() => {
+                            BackupOpt::Dir(x.to_string())
+                        },
+                        x => BackupOpt::Suffix(x.to_string()),
+                    });
+                },
+                "--batch" => { batch = Some(b'\n'); },
+                "--batch0" => { batch = Some(b'\0'); },
+                "--stdin" => { stdin = true; },
+                "--files-from" | "--files-from0" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after {arg}"));
+                    }
+                    let delimiter = if arg == "--files-from0" { b'\0' } else { b'\n' };
+                    files_from = Some((args[i + 1].clone(), delimiter));
+                    i += 1;
+                },
                 "--" => { i += 1; break; },
 
                 "--COMM" => {
@@ -279,8 +885,8 @@ impl Cli {
                         return Err(anyhow!("2 arguments expected after --COMM"));
                     }
                     let comment = Comment {
-                        description: args[i + 1].clone(),
-                        lang: args[i + 2].clone(),
+                        description: Cli::text_arg(&args[i + 1])?,
+                        lang: Cli::text_arg(&args[i + 2])?,
                         text: "".to_string(),
                     };
                     actions.push(Action::Print(Frame::with_content("COMM", Content::Comment(comment))));
@@ -291,21 +897,104 @@ impl Cli {
                         return Err(anyhow!("2 arguments expected after --USLT"));
                     }
                     let lyrics = Lyrics {
-                        description: args[i + 1].clone(),
-                        lang: args[i + 2].clone(),
+                        description: Cli::text_arg(&args[i + 1])?,
+                        lang: Cli::text_arg(&args[i + 2])?,
                         text: "".to_string(),
                     };
                     actions.push(Action::Print(Frame::with_content("USLT", Content::Lyrics(lyrics))));
                     i += 2;
                 },
 
+                "--rating" => {
+                    actions.push(Action::PrintRating(RatingUnit::Stars));
+                },
+                "--rating%" => {
+                    actions.push(Action::PrintRating(RatingUnit::Percent));
+                },
+                "--rating=" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --rating="));
+                    }
+                    actions.push(Action::SetRating(Rating::parse(&Cli::text_arg(&args[i + 1])?)?));
+                    i += 1;
+                },
+                "--compilation" => {
+                    actions.push(Action::PrintCompilation);
+                },
+                "--compilation=" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --compilation="));
+                    }
+                    let value = match Cli::text_arg(&args[i + 1])?.as_str() {
+                        "on" | "1" => true,
+                        "off" | "0" => false,
+                        x => return Err(anyhow!("Invalid compilation flag: '{x}' (expected 'on' or 'off')")),
+                    };
+                    actions.push(Action::SetCompilation(value));
+                    i += 1;
+                },
+                "--POPM" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --POPM"));
+                    }
+                    let popm = Cli::popm_query(&args[i + 1])?;
+                    actions.push(Action::Print(Frame::with_content("POPM", Content::Popularimeter(popm))));
+                    i += 1;
+                },
+                "--GEOB" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --GEOB"));
+                    }
+                    let object = Cli::geob_query(&args[i + 1])?;
+                    actions.push(Action::Print(Frame::with_content("GEOB", Content::EncapsulatedObject(object))));
+                    i += 1;
+                },
+                "--PRIV" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --PRIV"));
+                    }
+                    let private = Cli::priv_query(&args[i + 1])?;
+                    actions.push(Action::Print(Frame::with_content("PRIV", Content::Private(private))));
+                    i += 1;
+                },
+                "--UFID" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --UFID"));
+                    }
+                    let ufid = Cli::ufid_query(&args[i + 1])?;
+                    actions.push(Action::Print(Frame::with_content("UFID", Content::UniqueFileIdentifier(ufid))));
+                    i += 1;
+                },
+                "--USER" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --USER"));
+                    }
+                    actions.push(Action::Print(TermsOfUse::query_frame(&Cli::text_arg(&args[i + 1])?, "")?));
+                    i += 1;
+                },
+                "--TIPL" | "--TMCL" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after {arg}"));
+                    }
+                    actions.push(Action::Print(people_list_frame(&arg[2..], Cli::text_arg(&args[i + 1])?, String::new())));
+                    i += 1;
+                },
+                "--SYLT" => {
+                    if i + 2 >= args.len() {
+                        return Err(anyhow!("2 arguments expected after --SYLT"));
+                    }
+                    let sylt = Cli::sylt_query(&args[i + 1], &args[i + 2])?;
+                    actions.push(Action::Print(Frame::with_content("SYLT", Content::SynchronisedLyrics(sylt))));
+                    i += 2;
+                },
+
                 "--TXXX" => {
                     if i + 1 >= args.len() {
                         return Err(anyhow!("1 argument expected after --TXXX"));
                     }
                     let extended_text = ExtendedText {
                         value: "".to_string(),
-                        description: args[i + 1].clone(),
+                        description: Cli::text_arg(&args[i + 1])?,
                     };
                     actions.push(Action::Print(Frame::with_content("TXXX", Content::ExtendedText(extended_text))));
                     i += 1;
@@ -316,7 +1005,7 @@ impl Cli {
                     }
                     let extended_link = ExtendedLink {
                         link: "".to_string(),
-                        description: args[i + 1].clone(),
+                        description: Cli::text_arg(&args[i + 1])?,
                     };
                     actions.push(Action::Print(Frame::with_content("WXXX", Content::ExtendedLink(extended_link))));
                     i += 1;
@@ -332,9 +1021,9 @@ impl Cli {
                         return Err(anyhow!("3 arguments expected after --COMM="));
                     }
                     let comment = Comment {
-                        description: args[i + 1].clone(),
-                        lang: args[i + 2].clone(),
-                        text: args[i + 3].clone(),
+                        description: Cli::text_arg(&args[i + 1])?,
+                        lang: Cli::text_arg(&args[i + 2])?,
+                        text: Cli::text_arg(&args[i + 3])?,
                     };
                     actions.push(Action::Set(Frame::with_content("COMM", Content::Comment(comment))));
                     i += 3;
@@ -344,21 +1033,220 @@ impl Cli {
                         return Err(anyhow!("3 arguments expected after --USLT="));
                     }
                     let lyrics = Lyrics {
-                        description: args[i + 1].clone(),
-                        lang: args[i + 2].clone(),
-                        text: args[i + 3].clone(),
+                        description: Cli::text_arg(&args[i + 1])?,
+                        lang: Cli::text_arg(&args[i + 2])?,
+                        text: Cli::text_arg(&args[i + 3])?,
                     };
                     actions.push(Action::Set(Frame::with_content("USLT", Content::Lyrics(lyrics))));
                     i += 3;
                 }
 
+                "--PCNT=" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --PCNT="));
+                    }
+                    let counter = Cli::text_arg(&args[i + 1])?;
+                    let counter = counter.parse().map_err(|_| anyhow!("Invalid PCNT counter: '{counter}'"))?;
+                    actions.push(Action::Set(play_counter_frame(counter)));
+                    i += 1;
+                },
+                "--play-count-inc" => {
+                    actions.push(Action::IncrementPlayCount(false));
+                },
+                "--play-count-inc-popm" => {
+                    actions.push(Action::IncrementPlayCount(true));
+                },
+                "--POPM=" => {
+                    if i + 3 >= args.len() {
+                        return Err(anyhow!("3 arguments expected after --POPM="));
+                    }
+                    let mut popm = Cli::popm_query(&args[i + 1])?;
+                    let rating = Cli::text_arg(&args[i + 2])?;
+                    popm.rating = rating.parse().map_err(|_| anyhow!("Invalid POPM rating (expected 0-255): '{rating}'"))?;
+                    let counter = Cli::text_arg(&args[i + 3])?;
+                    popm.counter = counter.parse().map_err(|_| anyhow!("Invalid POPM counter: '{counter}'"))?;
+                    actions.push(Action::Set(Frame::with_content("POPM", Content::Popularimeter(popm))));
+                    i += 3;
+                },
+                "--PRIV=" => {
+                    if i + 2 >= args.len() {
+                        return Err(anyhow!("2 arguments expected after --PRIV="));
+                    }
+                    let mut private = Cli::priv_query(&args[i + 1])?;
+                    private.private_data = Cli::binary_arg(&args[i + 2])?;
+                    actions.push(Action::Set(Frame::with_content("PRIV", Content::Private(private))));
+                    i += 2;
+                },
+                "--UFID=" => {
+                    if i + 2 >= args.len() {
+                        return Err(anyhow!("2 arguments expected after --UFID="));
+                    }
+                    let mut ufid = Cli::ufid_query(&args[i + 1])?;
+                    ufid.identifier = Cli::text_arg(&args[i + 2])?.into_bytes();
+                    actions.push(Action::Set(Frame::with_content("UFID", Content::UniqueFileIdentifier(ufid))));
+                    i += 2;
+                },
+                "--USER=" => {
+                    if i + 2 >= args.len() {
+                        return Err(anyhow!("2 arguments expected after --USER="));
+                    }
+                    let lang = Cli::text_arg(&args[i + 1])?;
+                    actions.push(Action::Set(TermsOfUse::query_frame(&lang, &Cli::text_arg(&args[i + 2])?)?));
+                    i += 2;
+                },
+                "--TIPL=" | "--TMCL=" => {
+                    if i + 2 >= args.len() {
+                        return Err(anyhow!("2 arguments expected after {arg}"));
+                    }
+                    let role = Cli::text_arg(&args[i + 1])?;
+                    let person = Cli::text_arg(&args[i + 2])?;
+                    actions.push(Action::Set(people_list_frame(&arg[2..6], role, person)));
+                    i += 2;
+                },
+                "--SYLT=" => {
+                    if i + 3 >= args.len() {
+                        return Err(anyhow!("3 arguments expected after --SYLT="));
+                    }
+                    let mut sylt = Cli::sylt_query(&args[i + 1], &args[i + 2])?;
+                    let lrc = Cli::text_arg(&args[i + 3])?;
+                    let lrc = match lrc.strip_prefix('@') {
+                        Some(lrc_path) => read_lrc_file(&lrc_path)?,
+                        None => lrc,
+                    };
+                    sylt.content = parse_lrc(&lrc).into_iter().map(|x| (x.time_ms, x.text)).collect();
+                    if sylt.content.is_empty() {
+                        return Err(anyhow!("No timed lines found in the LRC passed to --SYLT="));
+                    }
+                    actions.push(Action::Set(Frame::with_content("SYLT", Content::SynchronisedLyrics(sylt))));
+                    i += 3;
+                },
+
+                "--lyrics-import" => {
+                    if i + 3 >= args.len() {
+                        return Err(anyhow!("3 arguments expected after --lyrics-import"));
+                    }
+                    let lyrics = Lyrics {
+                        description: Cli::text_arg(&args[i + 1])?,
+                        lang: Cli::text_arg(&args[i + 2])?,
+                        text: read_lrc_as_text(&Path::new(&args[i + 3]))?,
+                    };
+                    actions.push(Action::Set(Frame::with_content("USLT", Content::Lyrics(lyrics))));
+                    i += 3;
+                },
+
+                "--normalize-numbers" => {
+                    actions.push(Action::NormalizeNumbers);
+                },
+                "--auto-track" => {
+                    actions.push(Action::AutoTrack(1, None));
+                },
+                str if str.starts_with("--auto-track=") => {
+                    let (start, total) = Cli::auto_track_arg(&str["--auto-track=".len()..])?;
+                    actions.push(Action::AutoTrack(start, total));
+                },
+
+                "--lyrics-export" => {
+                    actions.push(Action::ExportLyrics(None));
+                },
+                str if str.starts_with("--lyrics-export=") => {
+                    actions.push(Action::ExportLyrics(Some(str["--lyrics-export=".len()..].to_string())));
+                },
+
+                "--chapters" => {
+                    actions.push(Action::PrintChapters);
+                },
+                "--chap-add" => {
+                    if i + 4 >= args.len() {
+                        return Err(anyhow!("4 arguments expected after --chap-add"));
+                    }
+                    let start_time = parse_chapter_time(&Cli::text_arg(&args[i + 2])?)?;
+                    let end_time = parse_chapter_time(&Cli::text_arg(&args[i + 3])?)?;
+                    if end_time < start_time {
+                        return Err(anyhow!("Chapter cannot end before it starts"));
+                    }
+                    let frame = chapter_frame(Cli::text_arg(&args[i + 1])?, start_time, end_time,
+                        &Cli::text_arg(&args[i + 4])?);
+                    actions.push(Action::Set(frame));
+                    i += 4;
+                },
+                "--chap-del" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --chap-del"));
+                    }
+                    actions.push(Action::Delete(chapter_frame(Cli::text_arg(&args[i + 1])?, 0, 0, "")));
+                    i += 1;
+                },
+
+                "--tocs" => {
+                    actions.push(Action::PrintTocs);
+                },
+                "--toc-set" | "--toc-set-nested" => {
+                    if i + 2 >= args.len() {
+                        return Err(anyhow!("2 arguments expected after {arg}"));
+                    }
+                    let elements = Cli::text_arg(&args[i + 2])?
+                        .split(',')
+                        .filter(|x| !x.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                    let frame = toc_frame(Cli::text_arg(&args[i + 1])?, arg == "--toc-set", elements);
+                    actions.push(Action::Set(frame));
+                    i += 2;
+                },
+                "--chapters-import" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --chapters-import"));
+                    }
+                    actions.push(Action::ImportChapters(read_chapters_file(&Path::new(&args[i + 1]))?));
+                    i += 1;
+                },
+                "--chapters-export" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --chapters-export"));
+                    }
+                    let format = match Cli::text_arg(&args[i + 1])?.as_str() {
+                        "cue" => ChaptersFormat::Cue,
+                        "ffmetadata" => ChaptersFormat::Ffmetadata,
+                        x => return Err(anyhow!("Invalid chapters format: '{x}'")),
+                    };
+                    actions.push(Action::ExportChapters(format));
+                    i += 1;
+                },
+                "--toc-auto" => {
+                    actions.push(Action::AutoToc);
+                },
+                "--toc-del" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --toc-del"));
+                    }
+                    actions.push(Action::Delete(toc_frame(Cli::text_arg(&args[i + 1])?, false, vec![])));
+                    i += 1;
+                },
+                "--podcast" => {
+                    // The frames are only known once all --podcast-* options have been read
+                    podcast_index.get_or_insert(actions.len());
+                },
+                "--podcast-feed" | "--podcast-id" | "--podcast-description" | "--podcast-keywords" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after {arg}"));
+                    }
+                    let value = Some(Cli::text_arg(&args[i + 1])?);
+                    match arg {
+                        "--podcast-feed" => podcast.feed = value,
+                        "--podcast-id" => podcast.id = value,
+                        "--podcast-description" => podcast.description = value,
+                        _ => podcast.keywords = value,
+                    }
+                    i += 1;
+                },
+
                 "--TXXX=" => {
                     if i + 2 >= args.len() {
                         return Err(anyhow!("2 arguments expected after --TXXX="));
                     }
                     let extended_text = ExtendedText {
-                        description: args[i + 1].clone(),
-                        value: args[i + 2].clone(),
+                        description: Cli::text_arg(&args[i + 1])?,
+                        value: Cli::text_arg(&args[i + 2])?,
                     };
                     actions.push(Action::Set(Frame::with_content("TXXX", Content::ExtendedText(extended_text))));
                     i += 2;
@@ -368,8 +1256,8 @@ impl Cli {
                         return Err(anyhow!("2 arguments expected after --WXXX="));
                     }
                     let extended_link = ExtendedLink {
-                        description: args[i + 1].clone(),
-                        link: args[i + 2].clone(),
+                        description: Cli::text_arg(&args[i + 1])?,
+                        link: Cli::text_arg(&args[i + 2])?,
                     };
                     actions.push(Action::Set(Frame::with_content("WXXX", Content::ExtendedLink(extended_link))));
                     i += 2;
@@ -378,10 +1266,15 @@ impl Cli {
                 // All parameterless setters
                 str if Cli::is_setter_arg(str) => {
                     if i + 1 >= args.len() {
-                        return Err(anyhow!("1 argument expected after {str}"));
+                        return Err(anyhow!("1 argument expected after {arg}"));
                     }
-                    let text = args[i + 1].clone();
-                    actions.push(Action::Set(Frame::text(&str[2..(str.len() - 1)], text)));
+                    let text = Cli::text_arg(&args[i + 1])?;
+                    let id = &str[2..(str.len() - 1)];
+                    let frame = match id.starts_with('W') {
+                        true => Frame::link(id, text),
+                        false => Frame::text(id, text),
+                    };
+                    actions.push(Action::Set(frame));
                     i += 1;
                 },
 
@@ -390,8 +1283,8 @@ impl Cli {
                         return Err(anyhow!("2 arguments expected after --COMM"));
                     }
                     let comment = Comment {
-                        description: args[i + 1].clone(),
-                        lang: args[i + 2].clone(),
+                        description: Cli::text_arg(&args[i + 1])?,
+                        lang: Cli::text_arg(&args[i + 2])?,
                         text: "".to_string(),
                     };
                     actions.push(Action::Delete(Frame::with_content("COMM", Content::Comment(comment))));
@@ -402,21 +1295,151 @@ impl Cli {
                         return Err(anyhow!("2 arguments expected after --USLT"));
                     }
                     let lyrics = Lyrics {
-                        description: args[i + 1].clone(),
-                        lang: args[i + 2].clone(),
+                        description: Cli::text_arg(&args[i + 1])?,
+                        lang: Cli::text_arg(&args[i + 2])?,
                         text: "".to_string(),
                     };
                     actions.push(Action::Delete(Frame::with_content("USLT", Content::Lyrics(lyrics))));
                     i += 2;
                 },
 
+                "--POPM-" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --POPM-"));
+                    }
+                    let popm = Cli::popm_query(&args[i + 1])?;
+                    actions.push(Action::Delete(Frame::with_content("POPM", Content::Popularimeter(popm))));
+                    i += 1;
+                },
+                "--GEOB-" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --GEOB-"));
+                    }
+                    let object = Cli::geob_query(&args[i + 1])?;
+                    actions.push(Action::Delete(Frame::with_content("GEOB", Content::EncapsulatedObject(object))));
+                    i += 1;
+                },
+                "--copy-from" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --copy-from"));
+                    }
+                    actions.push(Action::CopyFrom(Box::new(read_source_tag(&Path::new(&args[i + 1]))?)));
+                    i += 1;
+                },
+                "--copy-frame" => {
+                    if i + 2 >= args.len() {
+                        return Err(anyhow!("2 arguments expected after --copy-frame"));
+                    }
+                    let query = Cli::frame_query_arg(&args[i + 1])?;
+                    let source = read_source_tag(&Path::new(&args[i + 2]))?;
+                    let frame = source.frames().find(|x| frames_query_equal(x, &query).unwrap_or(false)).cloned();
+                    actions.push(Action::CopyFrame(query, frame));
+                    i += 2;
+                },
+                "--where" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --where"));
+                    }
+                    let expr = Cli::text_arg(&args[i + 1])?;
+                    actions.push(Action::Where(Filter::parse(&expr, |x| Cli::frame_query_arg(OsStr::new(x)))?));
+                    i += 1;
+                },
+                "--copy" | "--move" => {
+                    if i + 2 >= args.len() {
+                        return Err(anyhow!("2 arguments expected after {arg}"));
+                    }
+                    let from = Cli::frame_query_arg(&args[i + 1])?;
+                    let to = Cli::frame_query_arg(&args[i + 2])?;
+                    for frame in [&from, &to] {
+                        if frame.id() == "PCNT" || frame_value(frame).is_none() {
+                            return Err(anyhow!("Invalid frame for {arg}: '{}' (expected a text or link frame, COMM or USLT)",
+                                frame_to_string(frame)?));
+                        }
+                    }
+                    actions.push(if arg == "--copy" { Action::CopyValue(from, to) } else { Action::MoveValue(from, to) });
+                    i += 2;
+                },
+                "--merge-from" | "--merge-overwrite" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after {arg}"));
+                    }
+                    let source = read_source_tag(&Path::new(&args[i + 1]))?;
+                    actions.push(Action::MergeFrom(Box::new(source), arg == "--merge-overwrite"));
+                    i += 1;
+                },
+                "--geob-embed" => {
+                    if i + 3 >= args.len() {
+                        return Err(anyhow!("3 arguments expected after --geob-embed"));
+                    }
+                    let frame = geob_frame_from_file(Cli::text_arg(&args[i + 1])?, Cli::text_arg(&args[i + 2])?,
+                        &Path::new(&args[i + 3]))?;
+                    actions.push(Action::Set(frame));
+                    i += 3;
+                },
+                "--dump-tag" | "--restore-tag" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after {arg}"));
+                    }
+                    let template = Cli::text_arg(&args[i + 1])?;
+                    actions.push(match arg {
+                        "--dump-tag" => Action::DumpTag(template),
+                        _ => Action::RestoreTag(template),
+                    });
+                    i += 1;
+                },
+                "--geob-extract" => {
+                    if i + 2 >= args.len() {
+                        return Err(anyhow!("2 arguments expected after --geob-extract"));
+                    }
+                    actions.push(Action::ExtractGeob(Cli::text_arg(&args[i + 1])?, Cli::text_arg(&args[i + 2])?));
+                    i += 2;
+                },
+                "--PRIV-" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --PRIV-"));
+                    }
+                    let private = Cli::priv_query(&args[i + 1])?;
+                    actions.push(Action::Delete(Frame::with_content("PRIV", Content::Private(private))));
+                    i += 1;
+                },
+                "--UFID-" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --UFID-"));
+                    }
+                    let ufid = Cli::ufid_query(&args[i + 1])?;
+                    actions.push(Action::Delete(Frame::with_content("UFID", Content::UniqueFileIdentifier(ufid))));
+                    i += 1;
+                },
+                "--USER-" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --USER-"));
+                    }
+                    actions.push(Action::Delete(TermsOfUse::query_frame(&Cli::text_arg(&args[i + 1])?, "")?));
+                    i += 1;
+                },
+                "--TIPL-" | "--TMCL-" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after {arg}"));
+                    }
+                    actions.push(Action::Delete(people_list_frame(&arg[2..6], Cli::text_arg(&args[i + 1])?, String::new())));
+                    i += 1;
+                },
+                "--SYLT-" => {
+                    if i + 2 >= args.len() {
+                        return Err(anyhow!("2 arguments expected after --SYLT-"));
+                    }
+                    let sylt = Cli::sylt_query(&args[i + 1], &args[i + 2])?;
+                    actions.push(Action::Delete(Frame::with_content("SYLT", Content::SynchronisedLyrics(sylt))));
+                    i += 2;
+                },
+
                 "--TXXX-" => {
                     if i + 1 >= args.len() {
                         return Err(anyhow!("1 argument expected after --TXXX"));
                     }
                     let extended_text = ExtendedText {
                         value: "".to_string(),
-                        description: args[i + 1].clone(),
+                        description: Cli::text_arg(&args[i + 1])?,
                     };
                     actions.push(Action::Delete(Frame::with_content("TXXX", Content::ExtendedText(extended_text))));
                     i += 1;
@@ -427,12 +1450,58 @@ impl Cli {
                     }
                     let extended_link = ExtendedLink {
                         link: "".to_string(),
-                        description: args[i + 1].clone(),
+                        description: Cli::text_arg(&args[i + 1])?,
                     };
                     actions.push(Action::Delete(Frame::with_content("WXXX", Content::ExtendedLink(extended_link))));
                     i += 1;
                 },
 
+                str if Cli::is_alias_arg(str) => {
+                    let name = &str[2..];
+                    if let Some(alias) = name.strip_suffix('=').and_then(frame_alias_from_name) {
+                        if i + 1 >= args.len() {
+                            return Err(anyhow!("1 argument expected after {str}"));
+                        }
+                        actions.push(Action::SetAlias(alias, Cli::text_arg(&args[i + 1])?));
+                        i += 1;
+                    } else if let Some(alias) = name.strip_suffix('-').and_then(frame_alias_from_name) {
+                        actions.push(Action::DeleteAlias(alias));
+                    } else if let Some(alias) = frame_alias_from_name(name) {
+                        actions.push(Action::PrintAlias(alias));
+                    }
+                },
+                str if Cli::is_number_part_arg(str) => {
+                    let name = &str[2..];
+                    if let Some(part) = name.strip_suffix('=').and_then(number_part_from_name) {
+                        if i + 1 >= args.len() {
+                            return Err(anyhow!("1 argument expected after {str}"));
+                        }
+                        let value = Cli::text_arg(&args[i + 1])?;
+                        let value = value.trim().parse()
+                            .map_err(|_| anyhow!("Invalid {}: '{value}'", number_part_name(part)))?;
+                        actions.push(Action::SetNumberPart(part, value));
+                        i += 1;
+                    } else if let Some(part) = name.strip_suffix('-').and_then(number_part_from_name) {
+                        actions.push(Action::DeleteNumberPart(part));
+                    } else if let Some(part) = number_part_from_name(name) {
+                        actions.push(Action::PrintNumberPart(part));
+                    }
+                },
+                str if Cli::is_append_arg(str) => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after {str}"));
+                    }
+                    actions.push(Action::AppendValue(Frame::text(&str[2..6], Cli::text_arg(&args[i + 1])?)));
+                    i += 1;
+                },
+                str if Cli::is_remove_value_arg(str) => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after {str}"));
+                    }
+                    actions.push(Action::RemoveValue(Frame::text(&str[2..6], Cli::text_arg(&args[i + 1])?)));
+                    i += 1;
+                },
+
                 // All parameterless delete args
                 str if Cli::is_delete_arg(str) => {
                     actions.push(Action::Delete(Frame::text(&str[2..(str.len() - 1)], "")));
@@ -470,9 +1539,160 @@ impl Cli {
                 "--purge-all" => {
                     actions.push(Action::Purge(PurgeOpt::All));
                 },
+                "--purge-id3v1" => {
+                    actions.push(Action::Purge(PurgeOpt::Id3v1));
+                },
+                "--purge-ape" => {
+                    actions.push(Action::Purge(PurgeOpt::Ape));
+                },
+
+                "--id3v1" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after --id3v1"));
+                    }
+                    let field = match Cli::text_arg(&args[i + 1])?.as_str() {
+                        "title" => Id3v1Field::Title,
+                        "artist" => Id3v1Field::Artist,
+                        "album" => Id3v1Field::Album,
+                        "year" => Id3v1Field::Year,
+                        "comment" => Id3v1Field::Comment,
+                        "track" => Id3v1Field::Track,
+                        "genre" => Id3v1Field::Genre,
+                        x => return Err(anyhow!("Unknown ID3v1 field: '{x}'")),
+                    };
+                    actions.push(Action::PrintId3v1(field));
+                    i += 1;
+                },
+                "--sync-id3v1" => {
+                    actions.push(Action::SyncId3v1);
+                },
+                "--set-length" => {
+                    actions.push(Action::SetLength);
+                },
+                "--checksum-set" => {
+                    actions.push(Action::SetChecksum);
+                },
+                "--checksum-verify" => {
+                    actions.push(Action::VerifyChecksum);
+                },
+
+                "--tag-info" => {
+                    actions.push(Action::TagInfo);
+                },
+                "--raw" | "--raw-binary" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after {arg}"));
+                    }
+                    // Frames unknown to rsid3 can still be found by their ID
+                    let query = match Cli::frame_query_arg(&args[i + 1]) {
+                        Ok(x) => x,
+                        Err(_) if Cli::is_frame_id(&Cli::text_arg(&args[i + 1])?) => {
+                            Frame::with_content(Cli::text_arg(&args[i + 1])?, Content::Text(String::new()))
+                        },
+                        Err(e) => return Err(e),
+                    };
+                    actions.push(Action::PrintRaw(query, arg == "--raw-binary"));
+                    i += 1;
+                },
+                "--check" => {
+                    actions.push(Action::Check);
+                },
+                "--titlecase" | "--upper" | "--lower" => {
+                    if i + 1 >= args.len() {
+                        return Err(anyhow!("1 argument expected after {arg}"));
+                    }
+                    let id = Cli::text_arg(&args[i + 1])?;
+                    if id.starts_with('W') || !Cli::is_setter_arg(&format!("--{id}=")) {
+                        return Err(anyhow!("Invalid frame for {arg}: '{id}' (expected a text frame, COMM or USLT)"));
+                    }
+                    let change = match arg {
+                        "--titlecase" => CaseChange::Title,
+                        "--upper" => CaseChange::Upper,
+                        _ => CaseChange::Lower,
+                    };
+                    actions.push(Action::ChangeCase(id, change));
+                    i += 1;
+                },
+                "--flag" => {
+                    if i + 2 >= args.len() {
+                        return Err(anyhow!("2 arguments expected after --flag"));
+                    }
+                    let id = Cli::text_arg(&args[i + 1])?;
+                    if !Cli::is_frame_id(&id) {
+                        return Err(anyhow!("Invalid frame ID for --flag: '{id}'"));
+                    }
+                    let spec = Cli::text_arg(&args[i + 2])?;
+                    let (value, name) = match spec.split_at_checked(1) {
+                        Some(("+", x)) => (true, x),
+                        Some(("-", x)) => (false, x),
+                        _ => return Err(anyhow!("Invalid flag for --flag: '{spec}' (expected +FLAG or -FLAG)")),
+                    };
+                    let flag = match name {
+                        "read_only" => FrameFlag::ReadOnly,
+                        "tag_alter_preserve" | "tag_alter_preservation" => FrameFlag::TagAlterPreservation,
+                        "file_alter_preserve" | "file_alter_preservation" => FrameFlag::FileAlterPreservation,
+                        _ => return Err(anyhow!("Invalid flag for --flag: '{name}' (expected read_only, tag_alter_preserve or file_alter_preserve)")),
+                    };
+                    actions.push(Action::SetFrameFlag(id, flag, value));
+                    i += 2;
+                },
+                "--dedupe" => {
+                    actions.push(Action::Dedupe);
+                },
+                "--strip-empty" => {
+                    actions.push(Action::StripEmpty);
+                },
+                "--trim" => {
+                    actions.push(Action::Trim);
+                },
+                "--normalize-frames" => {
+                    actions.push(Action::NormalizeFrames);
+                },
+                "--fix-encoding" => {
+                    actions.push(Action::FixEncoding);
+                },
+                "--fix" => {
+                    actions.push(Action::Fix(ALL_FIX_KINDS.to_vec()));
+                },
+                str if str.starts_with("--fix=") => {
+                    let kinds = str["--fix=".len()..].split(',')
+                        .map(|x| fix_kind_from_name(x).ok_or_else(|| anyhow!("Invalid --fix kind: '{x}'")))
+                        .collect::<Result<Vec<_>>>()?;
+                    actions.push(Action::Fix(kinds));
+                },
+
+                str if str.starts_with("--mb-") => {
+                    let name = &str["--mb-".len()..];
+                    if let Some(name) = name.strip_suffix('=') {
+                        if i + 1 >= args.len() {
+                            return Err(anyhow!("1 argument expected after {arg}"));
+                        }
+                        actions.push(Action::Set(Cli::musicbrainz_arg(name, &Cli::text_arg(&args[i + 1])?)?));
+                        i += 1;
+                    } else if let Some(name) = name.strip_suffix('-') {
+                        actions.push(Action::Delete(Cli::musicbrainz_arg(name, "")?));
+                    } else {
+                        actions.push(Action::Print(Cli::musicbrainz_arg(name, "")?));
+                    }
+                },
+                str if str.starts_with("--rg-") => {
+                    let name = &str["--rg-".len()..];
+                    if let Some(name) = name.strip_suffix('=') {
+                        if i + 1 >= args.len() {
+                            return Err(anyhow!("1 argument expected after {arg}"));
+                        }
+                        let value = normalize_replaygain_value(name, &Cli::text_arg(&args[i + 1])?)?;
+                        actions.push(Action::Set(Cli::replaygain_arg(name, &value)?));
+                        i += 1;
+                    } else if let Some(name) = name.strip_suffix('-') {
+                        actions.push(Action::Delete(Cli::replaygain_arg(name, "")?));
+                    } else {
+                        actions.push(Action::Print(Cli::replaygain_arg(name, "")?));
+                    }
+                },
 
                 str => {
-                    if str.starts_with('-') {
+                    if str.starts_with('-') && str != "-" {
                         return Err(anyhow!("Unknown option: '{arg}'"));
                     }
                     break;
@@ -481,23 +1701,441 @@ impl Cli {
             i += 1;
         }
 
-        let files = (i..args.len())
-            .map(|x| args[x].clone())
-            .collect();
+        match podcast_index {
+            Some(index) => {
+                actions.splice(index..index, podcast.frames().into_iter().map(Action::Set));
+            },
+            None if !podcast.is_empty() => return Err(anyhow!("--podcast-* options require --podcast")),
+            None => {},
+        }
+
+        let mut files = vec![];
+        for fpath in &args[i..] {
+            match fpath.to_str() {
+                Some(pattern) if glob && Cli::is_glob_pattern(pattern) => {
+                    for fpath in Cli::expand_glob(pattern)? {
+                        Cli::add_file(&fpath, recursive, &mut files)?;
+                    }
+                },
+                _ => Cli::add_file(Path::new(fpath), recursive, &mut files)?,
+            }
+        }
+        if let Some((list_path, delimiter)) = files_from {
+            for fpath in Cli::read_file_list(&list_path, delimiter)? {
+                Cli::add_file(&fpath, recursive, &mut files)?;
+            }
+        }
+        if stdin && (files.len() != 1 || files[0] != Path::new("-")) {
+            return Err(anyhow!("--stdin expects '-' as the only file"));
+        }
+        if !stdin && files.iter().any(|x| x == Path::new("-")) {
+            return Err(anyhow!("Reading a file from stdin ('-') requires --stdin"));
+        }
+        if !no_validate {
+            Cli::validate_actions(&actions)?;
+        }
+
+        if frame_sep.is_none() && !frame_sep_null {
+            frame_sep = config.frame_sep;
+        }
+        if file_sep.is_none() && !file_sep_null {
+            file_sep = config.file_sep;
+        }
+        if let Some(form) = normalize_form {
+            Cli::normalize_actions(&mut actions, form);
+        }
 
         Ok(Cli {
             help,
             version,
             list_frames,
+            help_frame,
+            completions,
+            diff,
+            interactive,
+            edit,
+            undo,
+            grep,
+            find_duplicates,
+            batch,
+            stdin,
             frame_sep,
             file_sep,
             frame_sep_null,
             file_sep_null,
+            escape_seps,
+            value_sep: value_sep.or(config.value_sep),
+            genre_names,
+            describe,
+            show_flags,
+            audio_info,
+            lenient,
+            keep_going,
+            no_clobber,
+            normalize: normalize_form,
+            track_pad,
+            with_filename,
+            missing,
+            // A dry run is only useful if it reports what would be done
+            verbosity: if dry_run { verbosity.max(1) } else { verbosity },
+            quiet,
+            color: color.or(config.color).unwrap_or(ColorOpt::Auto),
+            errors,
+            output,
+            binary_format: binary_format.or(config.binary_format).unwrap_or(BinaryFormat::Hex),
+            rating_owner,
+            rating_scale,
+            padding,
+            encoding: encoding.or(config.encoding),
+            default_version: default_version.or(config.default_version).unwrap_or(Version::Id3v24),
+            backup,
+            atomic,
+            force_write,
+            verify_audio,
+            no_journal,
+            dry_run,
+            preserve_mtime,
+            preserve_atime,
             actions,
             files,
         })
     }
 
+    /// Converts the values set by actions to the given Unicode normalization form.
+    fn normalize_actions(actions: &mut [Action], form: NormalizationForm) {
+        for action in actions {
+            match action {
+                Action::Set(frame) | Action::AppendValue(frame) | Action::RemoveValue(frame) => {
+                    *frame = normalize_frame(frame.clone(), form);
+                },
+                Action::SetAlias(_, value) => *value = normalize(value, form),
+                _ => {},
+            }
+        }
+    }
+
+    /// Checks the values set by actions against the rules of the ID3v2 standard, so that malformed
+    /// values are rejected before any file is modified.
+    fn validate_actions(actions: &[Action]) -> Result<()> {
+        for action in actions {
+            if let Action::Set(frame) = action {
+                let lang = match frame.content() {
+                    Content::Comment(x) => Some(x.lang.clone()),
+                    Content::Lyrics(x) => Some(x.lang.clone()),
+                    Content::SynchronisedLyrics(x) => Some(x.lang.clone()),
+                    _ if frame.id() == "USER" => Some(TermsOfUse::from_frame(frame)?.lang),
+                    _ => None,
+                };
+                if let Some(lang) = lang.filter(|x| !is_valid_language(x)) {
+                    return Err(anyhow!("Invalid language for {}: '{lang}' (expected an ISO 639-2/T code, e.g. eng, \
+                        or XXX, or pass --no-validate)", frame.id()));
+                }
+            }
+            let (id, text) = match action {
+                Action::Set(frame) => match frame.content().text() {
+                    Some(text) => (frame.id(), text),
+                    None => continue,
+                },
+                Action::SetAlias(FrameAlias::Year, text) => ("TDRC", text.as_str()),
+                _ => continue,
+            };
+            if matches!(id, "TDRC" | "TDEN" | "TDOR" | "TDRL" | "TDTG") && parse_timestamp(text).is_none() {
+                return Err(anyhow!("Invalid timestamp for {id}: '{text}' (expected yyyy[-MM[-dd[THH[:mm[:ss]]]]], \
+                    or pass --no-validate)"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Constructs an empty POPM frame content from an OWNER command-line argument.
+    fn popm_query(owner: &OsStr) -> Result<Popularimeter> {
+        Ok(popm_query(Cli::text_arg(owner)?))
+    }
+
+    /// Constructs the frame of a MusicBrainz identifier passed as `--mb-NAME`.
+    fn musicbrainz_arg(name: &str, value: &str) -> Result<Frame> {
+        musicbrainz_frame(name, value).ok_or_else(|| anyhow!("Unknown MusicBrainz identifier: '{name}' (expected one of: {})",
+            musicbrainz_names().collect::<Vec<_>>().join(", ")))
+    }
+
+    /// Constructs the frame of a ReplayGain value passed as `--rg-NAME`.
+    fn replaygain_arg(name: &str, value: &str) -> Result<Frame> {
+        replaygain_frame(name, value).ok_or_else(|| anyhow!("Unknown ReplayGain value: '{name}' (expected one of: {})",
+            replaygain_names().collect::<Vec<_>>().join(", ")))
+    }
+
+    /// Parses the START[/TOTAL] argument of --auto-track, where TOTAL can also be "auto".
+    fn auto_track_arg(arg: &str) -> Result<(u32, Option<AutoTrackTotal>)> {
+        let invalid = || anyhow!("Invalid --auto-track value: '{arg}' (expected START[/TOTAL])");
+        let (start, total) = match arg.split_once('/') {
+            Some((start, "auto")) => (start, Some(AutoTrackTotal::Auto)),
+            Some((start, total)) => (start, Some(AutoTrackTotal::Fixed(total.parse().map_err(|_| invalid())?))),
+            None => (arg, None),
+        };
+        Ok((start.parse().map_err(|_| invalid())?, total))
+    }
+
+    /// Constructs an empty query frame from a FRAME command-line argument (see `parse_frame_query`).
+    pub fn frame_query_arg(arg: &OsStr) -> Result<Frame> {
+        parse_frame_query(&Cli::text_arg(arg)?)
+    }
+
+    /// Compiles a REGEX command-line argument. Only the last line of the parser's error message is
+    /// kept, which is the one describing the error.
+    fn regex_arg(arg: &OsStr) -> Result<Regex> {
+        let pattern = Cli::text_arg(arg)?;
+        Regex::new(&pattern).map_err(|e| {
+            let message = e.to_string();
+            let message = message.lines().last().unwrap_or_default();
+            anyhow!("Invalid regular expression '{pattern}': {}", message.strip_prefix("error: ").unwrap_or(message))
+        })
+    }
+
+    /// Constructs query frames from a comma-separated list of FRAMEs (see `frame_query_arg`).
+    /// Commas inside a [DESC] or (LANG) do not separate frames.
+    fn frame_list_arg(arg: &str) -> Result<Vec<Frame>> {
+        let mut frames = vec![];
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in arg.char_indices().chain([(arg.len(), ',')]) {
+            match c {
+                '[' | '(' => depth += 1,
+                ']' | ')' => depth -= 1,
+                ',' if depth <= 0 => {
+                    frames.push(Cli::frame_query_arg(OsStr::new(&arg[start..i]))?);
+                    start = i + 1;
+                },
+                _ => {},
+            }
+        }
+        Ok(frames)
+    }
+
+    /// Constructs an empty GEOB frame content from a DESC command-line argument.
+    fn geob_query(description: &OsStr) -> Result<EncapsulatedObject> {
+        Ok(geob_query(Cli::text_arg(description)?))
+    }
+
+    /// Constructs an empty PRIV frame content from an OWNER command-line argument.
+    fn priv_query(owner: &OsStr) -> Result<Private> {
+        Ok(priv_query(Cli::text_arg(owner)?))
+    }
+
+    /// Constructs an empty UFID frame content from an OWNER command-line argument.
+    fn ufid_query(owner: &OsStr) -> Result<UniqueFileIdentifier> {
+        Ok(ufid_query(Cli::text_arg(owner)?))
+    }
+
+    /// Constructs an empty SYLT frame content from DESC and LANG command-line arguments.
+    fn sylt_query(description: &OsStr, lang: &OsStr) -> Result<SynchronisedLyrics> {
+        Ok(sylt_query(Cli::text_arg(description)?, Cli::text_arg(lang)?))
+    }
+
+    /// Returns a command-line argument which is used as binary data: the contents of a file, if
+    /// the argument is '@' followed by the file's path, or the argument's own bytes otherwise.
+    fn binary_arg(arg: &OsStr) -> Result<Vec<u8>> {
+        let bytes = arg.as_encoded_bytes();
+        match bytes.strip_prefix(b"@") {
+            Some(fpath) => {
+                let fpath = Cli::path_from_bytes(fpath)?;
+                read(&fpath).map_err(|e| anyhow!("Failed to read '{}': {e}", fpath.display()))
+            },
+            None => Ok(bytes.to_vec()),
+        }
+    }
+
+    /// Parses the definition of a user alias, e.g. name "mood" and spec "TXXX[MOOD]".
+    /// Aliases may not shadow built-in options or frame IDs, with or without a suffix.
+    fn user_alias_def(name: &str, spec: &str) -> Result<(String, Frame)> {
+        if name.is_empty() || name.starts_with('-') || name.ends_with(['=', '-']) || name.contains(char::is_whitespace) {
+            return Err(anyhow!("Invalid alias name: '{name}'"));
+        }
+        let arg = format!("--{name}");
+        if Cli::is_getter_arg(&arg) || ["", "=", "-"].iter().any(|x| is_builtin_option(&format!("{arg}{x}"))) {
+            return Err(anyhow!("Invalid alias name: '{name}' (already an option)"));
+        }
+        let frame = Cli::frame_query_arg(OsStr::new(spec))?;
+        if with_frame_value(&frame, "").is_none() {
+            return Err(anyhow!("Invalid alias {name}: {} has no text value to stand for", frame.id()));
+        }
+        Ok((name.to_string(), frame))
+    }
+
+    /// Matches a command-line argument against user aliases, e.g. "--rating=".
+    /// Returns the frame the alias stands for, and the suffix of the argument ("", "=" or "-").
+    fn user_alias_arg<'a>(arg: &'a str, aliases: &'a [(String, Frame)]) -> Option<(&'a Frame, &'a str)> {
+        let name = arg.strip_prefix("--")?;
+        aliases.iter().find_map(|(alias, frame)| {
+            match name.strip_prefix(alias.as_str())? {
+                suffix @ ("" | "=" | "-") => Some((frame, suffix)),
+                _ => None,
+            }
+        })
+    }
+
+    /// Parses the name of a color mode, as passed to --color.
+    pub fn color_arg(name: &str) -> Result<ColorOpt> {
+        match name {
+            "auto" => Ok(ColorOpt::Auto),
+            "always" => Ok(ColorOpt::Always),
+            "never" => Ok(ColorOpt::Never),
+            x => Err(anyhow!("Invalid color mode: '{x}'")),
+        }
+    }
+
+    /// Parses the name of an output format, as passed to --output.
+    pub fn output_format_arg(name: &str) -> Result<OutputFormat> {
+        match name {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            x => Err(anyhow!("Invalid output format: '{x}'")),
+        }
+    }
+
+    /// Parses the name of a binary format, as passed to --binary-format.
+    pub fn binary_format_arg(name: &str) -> Result<BinaryFormat> {
+        match name {
+            "hex" => Ok(BinaryFormat::Hex),
+            "base64" => Ok(BinaryFormat::Base64),
+            "raw" => Ok(BinaryFormat::Raw),
+            x => Err(anyhow!("Invalid binary format: '{x}'")),
+        }
+    }
+
+    /// Parses the name of a text encoding, as passed to --encoding.
+    pub fn encoding_arg(name: &str) -> Result<Encoding> {
+        match name {
+            "latin1" => Ok(Encoding::Latin1),
+            "utf16" => Ok(Encoding::UTF16),
+            "utf16be" => Ok(Encoding::UTF16BE),
+            "utf8" => Ok(Encoding::UTF8),
+            x => Err(anyhow!("Invalid encoding: '{x}'")),
+        }
+    }
+
+    /// Parses an ID3v2 version, as passed to --default-version (e.g. 2.4).
+    pub fn version_arg(name: &str) -> Result<Version> {
+        match name {
+            "2.2" => Ok(Version::Id3v22),
+            "2.3" => Ok(Version::Id3v23),
+            "2.4" => Ok(Version::Id3v24),
+            x => Err(anyhow!("Invalid ID3v2 version: '{x}' (expected 2.2, 2.3 or 2.4)")),
+        }
+    }
+
+    /// Returns a command-line argument which is used as text, e.g. a frame value.
+    fn text_arg(arg: &OsStr) -> Result<String> {
+        match arg.to_str() {
+            Some(x) => Ok(x.to_string()),
+            None => Err(anyhow!("Argument is not valid UTF-8: {arg:?}")),
+        }
+    }
+
+    /// Checks if a string is a well-formed ID3v2.3/ID3v2.4 frame ID, whether rsid3 knows the frame
+    /// or not.
+    fn is_frame_id(id: &str) -> bool {
+        id.len() == 4 && id.chars().all(|x| x.is_ascii_uppercase() || x.is_ascii_digit())
+    }
+
+    /// Appends a file passed by the user to the list of files to process.
+    /// Directories are expanded, if `recursive` is set.
+    fn add_file(fpath: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<()> {
+        if fpath.is_dir() {
+            if !recursive {
+                return Err(anyhow!("'{}' is a directory (use --recursive to process it)", fpath.display()));
+            }
+            Cli::collect_dir_files(fpath, files)
+        } else {
+            files.push(fpath.to_path_buf());
+            Ok(())
+        }
+    }
+
+    /// Checks if a file argument contains any wildcard characters.
+    fn is_glob_pattern(arg: &str) -> bool {
+        arg.contains(['*', '?', '['])
+    }
+
+    /// Expands a wildcard pattern into the sorted list of matching paths. A pattern which matches
+    /// nothing, or is not a valid pattern at all, still names a file whose name contains wildcard
+    /// characters, e.g. "Song [Live].mp3", if that file exists.
+    fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+        let literal = Path::new(pattern);
+        let paths = match glob::glob(pattern) {
+            Ok(x) => x,
+            Err(_) if literal.exists() => return Ok(vec![literal.to_path_buf()]),
+            Err(e) => return Err(anyhow!("Invalid pattern '{pattern}': {e}")),
+        };
+        let mut files = vec![];
+        for path in paths {
+            match path {
+                Ok(x) => files.push(x),
+                Err(e) => return Err(anyhow!("Failed to expand pattern '{pattern}': {e}")),
+            }
+        }
+        if files.is_empty() {
+            if literal.exists() {
+                return Ok(vec![literal.to_path_buf()]);
+            }
+            return Err(anyhow!("No files match pattern '{pattern}'"));
+        }
+        Ok(files)
+    }
+
+    /// Reads a list of delimited file paths from a file, or from stdin if `list_path` is "-".
+    /// Empty entries are skipped.
+    fn read_file_list(list_path: &OsStr, delimiter: u8) -> Result<Vec<PathBuf>> {
+        let contents = if list_path == "-" {
+            let mut buf = vec![];
+            stdin().read_to_end(&mut buf).map(|_| buf)
+        } else {
+            read(list_path)
+        };
+        let contents = match contents {
+            Ok(x) => x,
+            Err(e) => return Err(anyhow!("Failed to read file list from '{}': {e}", Path::new(list_path).display())),
+        };
+        contents.split(|&x| x == delimiter)
+            .filter(|x| !x.is_empty())
+            .map(Cli::path_from_bytes)
+            .collect()
+    }
+
+    /// Converts raw bytes read from a file list into a path. Paths are arbitrary bytes on Unix,
+    /// but elsewhere they have to be valid UTF-8.
+    fn path_from_bytes(bytes: &[u8]) -> Result<PathBuf> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            Ok(PathBuf::from(OsStr::from_bytes(bytes)))
+        }
+        #[cfg(not(unix))]
+        {
+            String::from_utf8(bytes.to_vec())
+                .map(PathBuf::from)
+                .map_err(|_| anyhow!("Path is not valid UTF-8: {:?}", String::from_utf8_lossy(bytes)))
+        }
+    }
+
+    /// Recursively collects all files with supported extensions from a directory, in sorted order.
+    fn collect_dir_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+        let entries = read_dir(dir)
+            .and_then(|x| x.map(|entry| entry.map(|x| x.path())).collect::<Result<Vec<_>, _>>());
+        let mut entries = match entries {
+            Ok(x) => x,
+            Err(e) => return Err(anyhow!("Failed to read directory '{}': {e}", dir.display())),
+        };
+        entries.sort();
+        for path in entries {
+            if path.is_dir() {
+                Cli::collect_dir_files(&path, files)?;
+            } else if has_supported_extension(&path) {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
     /// Checks if a command-line argument is a getter argument.
     fn is_getter_arg(arg: &str) -> bool {
         arg.starts_with("--") && (arg[2..]).chars()
@@ -507,10 +2145,11 @@ impl Cli {
     /// Checks if a command-line argument is a setter argument.
     fn is_setter_arg(arg: &str) -> bool {
         arg.starts_with("--") && arg.ends_with('=') && matches!(&arg[2..(arg.len() - 1)],
-            "COMM" | "TALB" | "TBPM" | "TCAT" | "TCMP" | "TCOM" | "TCON" | "TCOP" |
+            "COMM" | "GRP1" | "MVIN" | "MVNM" |
+            "TALB" | "TBPM" | "TCAT" | "TCMP" | "TCOM" | "TCON" | "TCOP" |
             "TDAT" | "TDEN" | "TDES" | "TDLY" | "TDOR" | "TDRC" | "TDRL" | "TDTG" |
-            "TENC" | "TEXT" | "TFLT" | "TGID" | "TIME" | "TIPL" | "TIT1" | "TIT2" |
-            "TIT3" | "TKEY" | "TKWD" | "TLAN" | "TLEN" | "TMCL" | "TMED" | "TMOO" |
+            "TENC" | "TEXT" | "TFLT" | "TGID" | "TIME" | "TIT1" | "TIT2" |
+            "TIT3" | "TKEY" | "TKWD" | "TLAN" | "TLEN" | "TMED" | "TMOO" |
             "TOAL" | "TOFN" | "TOLY" | "TOPE" | "TORY" | "TOWN" | "TPE1" | "TPE2" |
             "TPE3" | "TPE4" | "TPOS" | "TPRO" | "TPUB" | "TRCK" | "TRDA" | "TRSN" |
             "TRSO" | "TSIZ" | "TSO2" | "TSOA" | "TSOC" | "TSOP" | "TSOT" | "TSRC" |
@@ -518,6 +2157,40 @@ impl Cli {
             "WOAF" | "WOAR" | "WOAS" | "WORS" | "WPAY" | "WPUB" | "WXXX")
     }
 
+    /// Checks if a frame can be set with a --FRAME= option.
+    pub fn is_settable_frame(id: &str) -> bool {
+        Cli::is_setter_arg(&format!("--{id}="))
+            || matches!(id, "PCNT" | "POPM" | "PRIV" | "SYLT" | "TIPL" | "TMCL" | "UFID" | "USER")
+    }
+
+    /// Checks if a command-line argument is a frame alias getter, setter or delete argument.
+    fn is_alias_arg(arg: &str) -> bool {
+        arg.strip_prefix("--").is_some_and(|name| {
+            let name = name.strip_suffix(['=', '-']).unwrap_or(name);
+            frame_alias_from_name(name).is_some()
+        })
+    }
+
+    /// Checks if a command-line argument is a TRCK or TPOS component getter, setter or delete argument.
+    fn is_number_part_arg(arg: &str) -> bool {
+        arg.strip_prefix("--").is_some_and(|name| {
+            let name = name.strip_suffix(['=', '-']).unwrap_or(name);
+            number_part_from_name(name).is_some()
+        })
+    }
+
+    /// Checks if a command-line argument appends a value to a multi-valued text frame.
+    fn is_append_arg(arg: &str) -> bool {
+        arg.len() == 7 && arg.starts_with("--") && arg.ends_with('+') && is_multi_value_id(&arg[2..6])
+        && Cli::is_getter_arg(&arg[..6])
+    }
+
+    /// Checks if a command-line argument removes a value from a multi-valued text frame.
+    fn is_remove_value_arg(arg: &str) -> bool {
+        arg.len() == 12 && arg.starts_with("--") && arg.ends_with("-value") && is_multi_value_id(&arg[2..6])
+        && Cli::is_getter_arg(&arg[..6])
+    }
+
     /// Checks if a command-line argument is a delete argument.
     fn is_delete_arg(arg: &str) -> bool {
         arg.len() > 3 && arg.starts_with("--") && arg.ends_with('-')
@@ -0,0 +1,183 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use rsid3_core::aliases::{frame_alias_name, ALL_FRAME_ALIASES};
+use crate::cli::{Cli, Shell};
+use rsid3_core::fix::{fix_kind_name, ALL_FIX_KINDS};
+use rsid3_core::frame_table::{READ_ONLY_FRAMES, WRITABLE_FRAMES};
+use rsid3_core::musicbrainz::musicbrainz_names;
+use rsid3_core::replaygain::replaygain_names;
+use rsid3_core::numbering::{number_part_name, ALL_NUMBER_PARTS};
+use rsid3_core::text_values::is_multi_value_id;
+
+/// All options which are not generated from the tables of frames, aliases and the like.
+const OPTIONS: &[&str] = &[
+    "-h", "--help", "-V", "--version", "-L", "--list-frames", "--help-frame", "-v", "--verbose",
+    "-q", "--quiet", "-d", "--frame-sep", "-D", "--file-sep", "-0d", "--frame-sep-null", "-0D",
+    "--file-sep-null", "-H", "--with-filename", "--no-filename", "--color", "--color=auto",
+    "--color=always", "--color=never", "--missing", "--escape-seps", "--binary", "--value-sep",
+    "--no-validate", "--track-pad", "--genre-names", "--describe", "--show-flags", "--audio-info",
+    "--lenient", "-k", "--keep-going", "--fail-fast", "--no-clobber", "--no-config", "--alias",
+    "--padding", "--no-padding", "--backup", "--backup=", "-n", "--dry-run", "--atomic",
+    "--force-write", "--verify-audio", "--undo", "--no-journal", "--preserve-mtime",
+    "--preserve-atime", "-r", "--recursive", "--glob", "--files-from", "--files-from0", "--batch",
+    "--batch0", "--stdin", "--diff", "--interactive", "--grep", "--find-duplicates", "--where",
+    "--edit", "--id3v2.2", "--id3v2.3", "--id3v2.4", "--force-id3v2.2", "--force-id3v2.3",
+    "--force-id3v2.4", "--purge-id3v2.2", "--purge-id3v2.3", "--purge-id3v2.4", "--purge-all",
+    "--purge-id3v1", "--purge-ape", "--id3v1", "--sync-id3v1", "--set-length", "--checksum-set",
+    "--checksum-verify", "--tag-info", "--raw", "--raw-binary", "--lyrics-import",
+    "--lyrics-export", "--lyrics-export=", "--chapters", "--tocs", "--toc-auto", "--toc-set",
+    "--toc-set-nested", "--toc-del", "--podcast", "--podcast-feed", "--podcast-id",
+    "--podcast-description", "--podcast-keywords", "--chap-add", "--chap-del", "--chapters-import",
+    "--rating", "--rating%", "--rating=", "--compilation", "--rating-owner", "--play-count-inc",
+    "--play-count-inc-popm", "--geob-embed", "--geob-extract", "--dump-tag", "--restore-tag",
+    "--auto-track", "--auto-track=", "--normalize-numbers", "--check", "--fix", "--fix-encoding",
+    "--normalize-frames", "--trim", "--strip-empty", "--dedupe", "--lower", "--upper",
+    "--titlecase", "--flag", "--copy-from", "--merge-from", "--merge-overwrite", "--copy-frame",
+    "--copy", "--move",
+];
+
+/// Options whose argument is one of a fixed set of values, with those values.
+const VALUE_OPTIONS: &[(&str, &[&str])] = &[
+    ("--binary-format", &["hex", "base64", "raw"]),
+    ("--errors", &["text", "json"]),
+    ("--output", &["text", "json"]),
+    ("--normalize", &["nfc", "nfd"]),
+    ("--default-version", &["2.2", "2.3", "2.4"]),
+    ("--encoding", &["latin1", "utf16", "utf16be", "utf8"]),
+    ("--rating-scale", &["wmp", "linear"]),
+    ("--compilation=", &["on", "off"]),
+    ("--chapters-export", &["cue", "ffmetadata"]),
+    ("--completions", &["bash", "zsh", "fish"]),
+];
+
+/// Returns all options to be completed, other than those in `VALUE_OPTIONS`, with descriptions
+/// of the frames they refer to.
+fn completed_options() -> Vec<(String, &'static str)> {
+    let mut options: Vec<(String, &str)> = OPTIONS.iter().map(|x| (x.to_string(), "")).collect();
+    for (id, description) in WRITABLE_FRAMES.iter().chain(READ_ONLY_FRAMES) {
+        options.push((format!("--{id}"), description));
+        options.push((format!("--{id}-"), description));
+        if Cli::is_settable_frame(id) {
+            options.push((format!("--{id}="), description));
+        }
+        if is_multi_value_id(id) {
+            options.push((format!("--{id}+"), description));
+            options.push((format!("--{id}-value"), description));
+        }
+    }
+    let names = ALL_FRAME_ALIASES.iter().map(|&x| frame_alias_name(x))
+        .chain(ALL_NUMBER_PARTS.iter().map(|&x| number_part_name(x)));
+    for name in names {
+        options.extend(["", "=", "-"].map(|x| (format!("--{name}{x}"), "")));
+    }
+    for name in musicbrainz_names() {
+        options.extend(["", "=", "-"].map(|x| (format!("--mb-{name}{x}"), "")));
+    }
+    for name in replaygain_names() {
+        options.extend(["", "=", "-"].map(|x| (format!("--rg-{name}{x}"), "")));
+    }
+    options.extend(ALL_FIX_KINDS.iter().map(|&x| (format!("--fix={}", fix_kind_name(x)), "")));
+    options
+}
+
+/// Checks if a command-line argument is one of the built-in options, e.g. "--help" or "--TIT2=".
+pub fn is_builtin_option(arg: &str) -> bool {
+    VALUE_OPTIONS.iter().any(|(x, _)| *x == arg) || completed_options().iter().any(|(x, _)| x == arg)
+}
+
+/// Quotes a string for bash and zsh, in single quotes.
+fn posix_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Quotes a string for fish, in single quotes.
+fn fish_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Returns a completion script for the given shell.
+pub fn completion_script(shell: Shell) -> String {
+    let options = completed_options();
+    let mut script = String::new();
+    match shell {
+        Shell::Bash => {
+            script.push_str("_rsid3() {\n");
+            script.push_str("    local cur=\"${COMP_WORDS[COMP_CWORD]}\" prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n");
+            script.push_str("    case \"$prev\" in\n");
+            for (option, values) in VALUE_OPTIONS {
+                script.push_str(&format!("        {option}) COMPREPLY=($(compgen -W {} -- \"$cur\")); return;;\n",
+                    posix_quote(&values.join(" "))));
+            }
+            script.push_str("    esac\n");
+            script.push_str("    if [[ \"$cur\" == -* ]]; then\n");
+            let names = options.iter().map(|(x, _)| x.as_str())
+                .chain(VALUE_OPTIONS.iter().map(|(x, _)| *x))
+                .collect::<Vec<_>>();
+            script.push_str(&format!("        COMPREPLY=($(compgen -W {} -- \"$cur\"))\n", posix_quote(&names.join(" "))));
+            script.push_str("    fi\n");
+            script.push_str("}\n");
+            // Files are completed by default, if no option matches
+            script.push_str("complete -o bashdefault -o default -F _rsid3 rsid3\n");
+        },
+        Shell::Zsh => {
+            script.push_str("#compdef rsid3\n\n");
+            script.push_str("_rsid3() {\n");
+            script.push_str("    local -a options\n");
+            script.push_str("    options=(\n");
+            for (option, description) in &options {
+                let option = option.replace(':', "\\:");
+                script.push_str(&format!("        {}\n", posix_quote(&match description.is_empty() {
+                    true => option,
+                    false => format!("{option}:{description}"),
+                })));
+            }
+            for (option, _) in VALUE_OPTIONS {
+                script.push_str(&format!("        {}\n", posix_quote(option)));
+            }
+            script.push_str("    )\n");
+            script.push_str("    case $words[CURRENT-1] in\n");
+            for (option, values) in VALUE_OPTIONS {
+                script.push_str(&format!("        {option}) compadd -- {}; return;;\n", values.join(" ")));
+            }
+            script.push_str("    esac\n");
+            script.push_str("    if [[ $PREFIX == -* ]]; then\n");
+            script.push_str("        _describe -t options 'option' options\n");
+            script.push_str("    else\n");
+            script.push_str("        _files\n");
+            script.push_str("    fi\n");
+            script.push_str("}\n\n");
+            script.push_str("_rsid3 \"$@\"\n");
+        },
+        Shell::Fish => {
+            // Frame options do not fit fish's notion of long options (e.g. --TIT2= VALUE), so all
+            // options are completed as plain words, whenever the current token starts with a dash
+            script.push_str("function __rsid3_completing_option\n");
+            script.push_str("    string match -q -- '-*' (commandline -ct)\n");
+            script.push_str("end\n");
+            for (option, description) in &options {
+                script.push_str(&format!("complete -c rsid3 -n __rsid3_completing_option -a {}", fish_quote(option)));
+                if !description.is_empty() {
+                    script.push_str(&format!(" -d {}", fish_quote(description)));
+                }
+                script.push('\n');
+            }
+            for (option, values) in VALUE_OPTIONS {
+                script.push_str(&format!("complete -c rsid3 -l {} -x -a {}\n", &option[2..], fish_quote(&values.join(" "))));
+            }
+        },
+    }
+    script
+}
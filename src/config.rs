@@ -0,0 +1,126 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::cli::{Cli, ColorOpt};
+use rsid3_core::action::BinaryFormat;
+use anyhow::{anyhow, Result};
+use id3::{Encoding, Version};
+use std::env::var_os;
+use std::fs::read_to_string;
+use std::io;
+use std::path::PathBuf;
+
+/// Names of all options which can be set in the configuration file, or in environment variables
+/// named like them in uppercase and prefixed with `RSID3_`, e.g. `RSID3_FRAME_SEP`.
+pub const CONFIG_OPTIONS: &[&str] = &[
+    "frame_sep", "file_sep", "value_sep", "default_version", "encoding", "color", "binary_format",
+];
+
+/// Represents the defaults read from the configuration file and the environment. Each of them is
+/// overridden by the corresponding command-line option.
+#[derive(Debug, Default)]
+pub struct Config {
+    pub frame_sep: Option<String>,
+    pub file_sep: Option<String>,
+    pub value_sep: Option<String>,
+    pub default_version: Option<Version>,
+    pub encoding: Option<Encoding>,
+    pub color: Option<ColorOpt>,
+    pub binary_format: Option<BinaryFormat>,
+    /// User-defined aliases, as names and frame specifications, e.g. `mood = "TXXX[MOOD]"`.
+    pub aliases: Vec<(String, String)>,
+}
+
+/// Returns the path of the configuration file, i.e. `rsid3/config.toml` in the user's
+/// configuration directory, if it can be determined.
+pub fn config_path() -> Option<PathBuf> {
+    let config_dir = if cfg!(windows) {
+        var_os("APPDATA").map(PathBuf::from)
+    } else {
+        var_os("XDG_CONFIG_HOME")
+            .filter(|x| !x.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| var_os("HOME").map(|x| PathBuf::from(x).join(".config")))
+    };
+    config_dir.map(|x| x.join("rsid3").join("config.toml"))
+}
+
+impl Config {
+    /// Reads the configuration file. If there is none, all defaults are left unset.
+    pub fn read() -> Result<Config> {
+        let fpath = match config_path() {
+            Some(x) => x,
+            None => return Ok(Config::default()),
+        };
+        let contents = match read_to_string(&fpath) {
+            Ok(x) => x,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(anyhow!("Failed to read config file '{}': {e}", fpath.display())),
+        };
+        Config::parse(&contents).map_err(|e| anyhow!("Invalid config file '{}': {e}", fpath.display()))
+    }
+
+    /// Parses the contents of a configuration file. Options are named like their command-line
+    /// counterparts, with underscores in place of dashes, e.g. `frame_sep = "\t"`.
+    pub fn parse(contents: &str) -> Result<Config> {
+        let table: toml::Table = contents.parse().map_err(|e: toml::de::Error| anyhow!("{}", e.message()))?;
+        let mut config = Config::default();
+        for (key, value) in table {
+            if let ("aliases", toml::Value::Table(aliases)) = (key.as_str(), &value) {
+                for (name, spec) in aliases {
+                    let spec = spec.as_str().ok_or_else(|| anyhow!("Alias '{name}' must be a string"))?;
+                    config.aliases.push((name.clone(), spec.to_string()));
+                }
+                continue;
+            }
+            // Versions are likely to be written as numbers, e.g. 2.3
+            let value = match value {
+                toml::Value::String(x) => x,
+                toml::Value::Float(x) if key == "default_version" => x.to_string(),
+                _ => return Err(anyhow!("Value of '{key}' must be a string")),
+            };
+            config.set(&key, value)?;
+        }
+        Ok(config)
+    }
+
+    /// Overrides the defaults with the values of environment variables, e.g. `RSID3_FRAME_SEP`.
+    pub fn read_env(&mut self) -> Result<()> {
+        for key in CONFIG_OPTIONS {
+            let name = format!("RSID3_{}", key.to_ascii_uppercase());
+            if let Some(value) = var_os(&name) {
+                let value = value.into_string()
+                    .map_err(|x| anyhow!("Value of {name} is not valid UTF-8: {x:?}"))?;
+                self.set(key, value).map_err(|e| anyhow!("Invalid {name}: {e}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the option named `key` from its textual value.
+    fn set(&mut self, key: &str, value: String) -> Result<()> {
+        match key {
+            "frame_sep" => self.frame_sep = Some(value),
+            "file_sep" => self.file_sep = Some(value),
+            "value_sep" => self.value_sep = Some(value),
+            "default_version" => self.default_version = Some(Cli::version_arg(&value)?),
+            "encoding" => self.encoding = Some(Cli::encoding_arg(&value)?),
+            "color" => self.color = Some(Cli::color_arg(&value)?),
+            "binary_format" => self.binary_format = Some(Cli::binary_format_arg(&value)?),
+            x => return Err(anyhow!("Unknown option: '{x}'")),
+        }
+        Ok(())
+    }
+}
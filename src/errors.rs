@@ -0,0 +1,119 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::progress::clear_progress;
+use rsid3_core::errors::{ErrorKind, Output};
+use rsid3_core::Action;
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::io::{stdout, Stdout, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether errors are reported as JSON objects, rather than as plain messages.
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// The file and action being processed, which errors are attributed to in JSON reports.
+    static CONTEXT: RefCell<(Option<PathBuf>, Option<String>)> = const { RefCell::new((None, None)) };
+}
+
+/// Sets whether errors are reported as JSON objects, one per line.
+pub fn set_json_errors(json: bool) {
+    JSON_ERRORS.store(json, Ordering::Relaxed);
+}
+
+/// Sets the file and action which subsequently reported errors are attributed to.
+pub fn set_error_context(fpath: Option<&Path>, action: Option<String>) {
+    CONTEXT.with(|x| *x.borrow_mut() = (fpath.map(Path::to_path_buf), action));
+}
+
+/// Quotes a string as a JSON string literal.
+pub fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Reports an error on stderr, either as "FILE: MESSAGE" (or "rsid3: MESSAGE" if no file is
+/// given), or as a JSON object with the file, action, kind and message of the error. Reports of
+/// what is being done to files are always plain messages.
+fn report_with(fpath: Option<&Path>, kind: ErrorKind, message: &dyn Display) {
+    clear_progress();
+    if !JSON_ERRORS.load(Ordering::Relaxed) || kind == ErrorKind::Info {
+        match fpath {
+            Some(fpath) => eprintln!("{}: {message}", fpath.display()),
+            None => eprintln!("rsid3: {message}"),
+        }
+        return;
+    }
+    let (file, action) = CONTEXT.with(|x| {
+        let context = x.borrow();
+        let file = fpath.or(context.0.as_deref()).map(|x| json_string(&x.to_string_lossy()));
+        (file, context.1.as_deref().map(json_string))
+    });
+    eprintln!("{{\"file\":{},\"action\":{},\"kind\":{},\"message\":{}}}",
+        file.as_deref().unwrap_or("null"),
+        action.as_deref().unwrap_or("null"),
+        json_string(kind.name()),
+        json_string(&message.to_string()));
+}
+
+/// Reports an error which is not specific to a file on stderr.
+pub fn report_error(kind: ErrorKind, message: impl Display) {
+    report_with(None, kind, &message);
+}
+
+/// Reports an error concerning the given file on stderr.
+pub fn report_file_error(fpath: impl AsRef<Path>, kind: ErrorKind, message: impl Display) {
+    report_with(Some(fpath.as_ref()), kind, &message);
+}
+
+/// The output of the command line, which writes printed values to stdout and reports to stderr.
+pub struct CliOutput {
+    stdout: Stdout,
+}
+
+impl CliOutput {
+    pub fn new() -> CliOutput {
+        CliOutput { stdout: stdout() }
+    }
+}
+
+impl Output for CliOutput {
+    fn writer(&mut self) -> &mut dyn Write {
+        &mut self.stdout
+    }
+
+    fn report(&mut self, fpath: Option<&Path>, kind: ErrorKind, message: &dyn Display) {
+        report_with(fpath, kind, message);
+    }
+
+    fn start_action(&mut self, fpath: &Path, action: &Action) {
+        set_error_context(Some(fpath), Some(action.name()));
+    }
+}
@@ -0,0 +1,101 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use rsid3_core::frame_table::{frame_key_params, frame_params, is_frame_id_in_version, READ_ONLY_FRAMES, WRITABLE_FRAMES};
+use rsid3_core::text_values::is_multi_value_id;
+use anyhow::{anyhow, Result};
+use id3::Version;
+
+/// Returns what a frame parameter holds, and an example value of it, quoted for a shell.
+fn param_info(id: &str, param: &str) -> (&'static str, &'static str) {
+    match (id, param) {
+        (_, "DESC") => ("Description, telling apart frames with the same ID (may be empty)", "''"),
+        (_, "LANG") => ("ISO 639-2/T language code, e.g. eng, or XXX if unknown", "eng"),
+        ("USER", "TEXT") => ("Terms of use", "'All rights reserved'"),
+        (id, "TEXT") if is_multi_value_id(id) => ("Text; multiple values are separated by --value-sep", "'Some text'"),
+        (_, "TEXT") => ("Text", "'Some text'"),
+        (_, "URL") => ("URL", "https://example.com"),
+        (_, "LRC") => ("Lyrics in the LRC format, or @FILE to read them from an .lrc file", "@song.lrc"),
+        (_, "ROLE") => ("Role of the person, e.g. producer or engineer", "producer"),
+        (_, "PERSON") => ("Name of the person", "'Some Producer'"),
+        (_, "INSTRUMENT") => ("Instrument played by the musician", "violin"),
+        (_, "MUSICIAN") => ("Name of the musician", "'Some Violinist'"),
+        ("POPM", "OWNER") => ("E-mail address of the user who rated the file", "me@example.com"),
+        ("PRIV", "OWNER") => ("Identifier of the owner of the data, e.g. a URL", "WM/MediaClassPrimaryID"),
+        (_, "OWNER") => ("Identifier of the owner of the ID, e.g. a URL", "http://musicbrainz.org"),
+        (_, "RATING") => ("Rating from 1 (worst) to 255 (best), or 0 if unknown (see also --rating=)", "196"),
+        (_, "COUNTER") => ("Number of times the file was played", "42"),
+        (_, "DATA") => ("Binary data, or @FILE to read it from a file", "@data.bin"),
+        (_, "ID") => ("Identifier, up to 64 bytes", "f4f5c7c4-6f0e-4d0e-9a3e-2e0b6bfae8a1"),
+        _ => ("", ""),
+    }
+}
+
+/// Prints the help text of a frame: its description, the ID3v2 versions defining it, and how to
+/// print, set and delete it, with an example.
+pub fn print_frame_help(id: &str) -> Result<()> {
+    let id = id.to_ascii_uppercase();
+    let id = id.as_str();
+    let (description, is_writable) = WRITABLE_FRAMES.iter().map(|x| (x, true))
+        .chain(READ_ONLY_FRAMES.iter().map(|x| (x, false)))
+        .find(|((x, _), _)| *x == id)
+        .map(|((_, x), y)| (*x, y))
+        .ok_or_else(|| anyhow!("Unknown frame: '{id}' (see --list-frames)"))?;
+    let versions = [(Version::Id3v22, "ID3v2.2"), (Version::Id3v23, "ID3v2.3"), (Version::Id3v24, "ID3v2.4")]
+        .into_iter()
+        .filter(|(x, _)| is_frame_id_in_version(id, *x))
+        .map(|(_, x)| x)
+        .collect::<Vec<_>>();
+    let key_params = frame_key_params(id).join(" ");
+    let params = frame_params(id);
+    let with_params = |option: String, params: &str| match params {
+        "" => option,
+        x => format!("{option} {x}"),
+    };
+
+    println!("{id}: {description}");
+    println!("Defined in: {}", versions.join(", "));
+    println!();
+    println!("Usage:");
+    println!("  {:<40}Print the frame", with_params(format!("rsid3 --{id}"), &key_params) + " FILE...");
+    if is_writable && !params.is_empty() {
+        println!("  {:<40}Set the frame", with_params(format!("rsid3 --{id}="), &params.join(" ")) + " FILE...");
+    }
+    println!("  {:<40}Delete the frame", with_params(format!("rsid3 --{id}-"), &key_params) + " FILE...");
+    if is_multi_value_id(id) {
+        println!("  {:<40}Append a value", format!("rsid3 --{id}+ TEXT FILE..."));
+        println!("  {:<40}Remove a value", format!("rsid3 --{id}-value TEXT FILE..."));
+    }
+    if !is_writable {
+        println!();
+        println!("This frame can only be printed (in a rudimentary form) and deleted.");
+    } else if params.is_empty() {
+        println!();
+        println!("This frame is written by a dedicated option, see rsid3 --help.");
+    }
+
+    if !params.is_empty() {
+        println!();
+        println!("Parameters:");
+        for param in params {
+            println!("  {param:<12}{}", param_info(id, param).0);
+        }
+        let example = params.iter().map(|x| param_info(id, x).1).collect::<Vec<_>>().join(" ");
+        println!();
+        println!("Example:");
+        println!("  rsid3 --{id}= {example} file.mp3");
+    }
+    Ok(())
+}
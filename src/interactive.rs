@@ -0,0 +1,175 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::cli::Cli;
+use crate::errors::report_file_error;
+use rsid3_core::errors::ErrorKind;
+use rsid3_core::id3_helpers::{format_frame_pretty, frame_to_string, retain_tag_frames};
+use rsid3_core::text_transforms::with_frame_value;
+use rsid3_core::text_values::split_text_values;
+use std::ffi::OsStr;
+use std::io::{stdout, BufRead, Write};
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use id3::{Frame, Tag, TagLike};
+
+/// Describes the commands of the interactive editor.
+const HELP: &str = "\
+Commands:
+  l                List the frames of the tag.
+  e N VALUE        Set the value of frame N.
+  a FRAME VALUE    Add FRAME (e.g. TIT2, TXXX[DESC] or COMM[DESC](eng)) with VALUE,
+                   replacing the frame it would conflict with, if any.
+  d N              Delete frame N.
+  w                Write the changes to the file.
+  n                Go to the next file (n! discards unsaved changes).
+  q                Quit (q! discards unsaved changes).
+  h, ?             Show this help.";
+
+/// Represents what to do once the interactive editing of a file is over.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EditorExit {
+    /// Go on with the next file.
+    Next,
+    /// Do not edit any more files.
+    Quit,
+}
+
+/// Represents the interactive editing of a single file's tag.
+struct Editor<'a> {
+    fpath: &'a Path,
+    tag: Tag,
+    is_modified: bool,
+    color: bool,
+    value_sep: &'a str,
+}
+
+impl Editor<'_> {
+    /// Prints all frames of the tag, numbered from 1.
+    fn list(&self) {
+        let n_frames = self.tag.frames().count();
+        println!("{}: {}, {} frame{}:", self.fpath.display(), self.tag.version(), n_frames,
+            if n_frames == 1 { "" } else { "s" });
+        for (i, frame) in self.tag.frames().enumerate() {
+            let string = format_frame_pretty(frame, self.color, self.value_sep, false)
+                .unwrap_or_else(|_| frame.id().to_string());
+            println!("{:>3}  {}", i + 1, string.replace('\n', "\n     "));
+        }
+    }
+
+    /// Returns the index and the frame with the given number, as listed by `list`.
+    fn frame(&self, number: &str) -> Result<(usize, Frame)> {
+        number.parse::<usize>().ok()
+            .and_then(|x| x.checked_sub(1))
+            .and_then(|x| Some((x, self.tag.frames().nth(x)?.clone())))
+            .ok_or_else(|| anyhow!("No such frame: '{number}'"))
+    }
+
+    /// Sets the value of the frame with the given number, keeping it in its place.
+    fn edit(&mut self, number: &str, value: &str) -> Result<()> {
+        let (index, frame) = self.frame(number)?;
+        let new_frame = match with_frame_value(&frame, value) {
+            Some(x) => split_text_values(x, self.value_sep),
+            None => return Err(anyhow!("Editing {} is not supported", frame_to_string(&frame)?)),
+        };
+        // Replacing the frame in the tag would move it to the end, so the tag is rebuilt instead
+        let mut tag = Tag::with_version(self.tag.version());
+        for (i, x) in self.tag.frames().enumerate() {
+            tag.add_frame(if i == index { new_frame.clone() } else { x.clone() });
+        }
+        self.tag = tag;
+        Ok(())
+    }
+
+    /// Adds a frame, written like a FRAME command-line argument, with the given value.
+    fn add(&mut self, spec: &str, value: &str) -> Result<()> {
+        let frame = Cli::frame_query_arg(OsStr::new(spec))?;
+        let new_frame = match with_frame_value(&frame, value) {
+            Some(x) => split_text_values(x, self.value_sep),
+            None => return Err(anyhow!("Adding {} is not supported", frame.id())),
+        };
+        self.tag.add_frame(new_frame);
+        Ok(())
+    }
+
+    /// Deletes the frame with the given number.
+    fn delete(&mut self, number: &str) -> Result<()> {
+        let (_, frame) = self.frame(number)?;
+        retain_tag_frames(&mut self.tag, |x| *x != frame);
+        Ok(())
+    }
+}
+
+/// Lets the user edit the tag of a file with commands read from `input`, one per line (see
+/// `HELP`). The frames are listed at the start and after each modification. The tag is passed to
+/// `save` whenever the user writes the changes. Reaching the end of `input` quits, discarding any
+/// unsaved changes.
+pub fn edit_tag(fpath: &Path, tag: Tag, color: bool, value_sep: &str, input: &mut impl BufRead,
+    mut save: impl FnMut(&Tag) -> Result<()>) -> Result<EditorExit> {
+    let mut editor = Editor { fpath, tag, is_modified: false, color, value_sep };
+    editor.list();
+    loop {
+        print!("{}> ", fpath.display());
+        stdout().flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            println!();
+            if editor.is_modified {
+                report_file_error(fpath, ErrorKind::Warning, "Unsaved changes discarded");
+            }
+            return Ok(EditorExit::Quit);
+        }
+        let line = line.trim_end_matches(['\n', '\r']);
+        let (command, args) = line.trim_start().split_once(' ').unwrap_or((line.trim(), ""));
+        let result = match command {
+            "" => Ok(false),
+            "l" => {
+                editor.list();
+                Ok(false)
+            },
+            "e" => match args.split_once(' ') {
+                Some((number, value)) => editor.edit(number, value).map(|_| true),
+                None => Err(anyhow!("Usage: e N VALUE")),
+            },
+            "a" => match args.split_once(' ') {
+                Some((spec, value)) => editor.add(spec, value).map(|_| true),
+                None => Err(anyhow!("Usage: a FRAME VALUE")),
+            },
+            "d" => editor.delete(args.trim()).map(|_| true),
+            "w" => {
+                save(&editor.tag)?;
+                editor.is_modified = false;
+                Ok(false)
+            },
+            "n" | "q" if editor.is_modified => Err(anyhow!("There are unsaved changes, \
+                write them with 'w' or discard them with '{command}!'")),
+            "n" | "n!" => return Ok(EditorExit::Next),
+            "q" | "q!" => return Ok(EditorExit::Quit),
+            "h" | "?" => {
+                println!("{HELP}");
+                Ok(false)
+            },
+            x => Err(anyhow!("Unknown command: '{x}' (type 'h' for help)")),
+        };
+        match result {
+            Ok(true) => {
+                editor.is_modified = true;
+                editor.list();
+            },
+            Ok(false) => {},
+            Err(e) => report_file_error(fpath, ErrorKind::Usage, e),
+        }
+    }
+}
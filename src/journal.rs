@@ -0,0 +1,340 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use rsid3_core::id3_helpers::{read_raw_tag, remove_tag, try_write_tag};
+use anyhow::{anyhow, Result};
+use id3::Tag;
+use tempfile::NamedTempFile;
+use std::env::var_os;
+use std::fs::{canonicalize, create_dir_all, File, OpenOptions};
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Kind of journal entries which record the tag of a file before it was written.
+const ENTRY_SAVED: u8 = b'S';
+/// Kind of journal entries which mark the latest unmarked saved tag of a file as undone.
+const ENTRY_UNDONE: u8 = b'U';
+/// Size of the journal above which its oldest entries are dropped, before recording another one.
+/// The journal is then shrunk to half of this size, so that it is not rewritten on every write.
+const JOURNAL_MAX_SIZE: u64 = 64 << 20;
+
+/// Represents a single entry of the undo journal.
+struct JournalEntry {
+    /// Either `ENTRY_SAVED` or `ENTRY_UNDONE`.
+    kind: u8,
+    /// Time at which the entry was recorded, in seconds since the Unix epoch.
+    time: u64,
+    /// Absolute path of the file the entry is about, encoded like `OsStr::as_encoded_bytes`.
+    fpath: Vec<u8>,
+    /// Raw bytes of the file's ID3v2 tag, or none if the file had no tag.
+    tag: Vec<u8>,
+}
+
+/// Represents an entry of the undo journal as it is read, without the tag, which is only read
+/// when needed.
+struct EntryHeader {
+    kind: u8,
+    time: u64,
+    fpath: Vec<u8>,
+    /// Offset of the entry in the journal.
+    offset: u64,
+    /// Offset and length of the tag in the journal.
+    tag_offset: u64,
+    tag_len: u64,
+}
+
+/// Represents a tag recorded in the undo journal, to be restored by `--undo`.
+pub struct SavedTag {
+    /// Time at which the tag was recorded, in seconds since the Unix epoch.
+    pub time: u64,
+    /// Raw bytes of the tag, or none if the file had no tag.
+    pub bytes: Vec<u8>,
+}
+
+/// Returns the path of the undo journal, i.e. `rsid3/journal` in the user's cache directory,
+/// if it can be determined.
+pub fn journal_path() -> Option<PathBuf> {
+    let cache_dir = if cfg!(windows) {
+        var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        var_os("XDG_CACHE_HOME")
+            .filter(|x| !x.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| var_os("HOME").map(|x| PathBuf::from(x).join(".cache")))
+    };
+    cache_dir.map(|x| x.join("rsid3").join("journal"))
+}
+
+impl JournalEntry {
+    /// Encodes the entry as stored in the journal: the kind, the time, and then the path and the
+    /// tag, each preceded by its length. All numbers are little-endian.
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![self.kind];
+        bytes.extend(self.time.to_le_bytes());
+        bytes.extend((self.fpath.len() as u32).to_le_bytes());
+        bytes.extend(&self.fpath);
+        bytes.extend((self.tag.len() as u32).to_le_bytes());
+        bytes.extend(&self.tag);
+        bytes
+    }
+}
+
+/// Reads the entries of a journal one by one, skipping over their tags, so that the journal is
+/// never read into memory as a whole.
+struct JournalReader {
+    reader: BufReader<File>,
+    /// Offset of the next entry.
+    pos: u64,
+    len: u64,
+}
+
+impl JournalReader {
+    /// Opens a journal for reading. Returns `None` if it does not exist.
+    fn open(journal: &Path) -> io::Result<Option<JournalReader>> {
+        let file = match File::open(journal) {
+            Ok(x) => x,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let len = file.metadata()?.len();
+        Ok(Some(JournalReader { reader: BufReader::new(file), pos: 0, len }))
+    }
+
+    /// Reads a little-endian number of `N` bytes, or returns `None` if the journal ends first.
+    fn read_number<const N: usize>(&mut self) -> io::Result<Option<[u8; N]>> {
+        let mut bytes = [0; N];
+        match self.reader.read_exact(&mut bytes) {
+            Ok(()) => Ok(Some(bytes)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reads the next entry, skipping over its tag. A truncated entry at the end, e.g. left by an
+    /// interrupted write, is treated as the end of the journal.
+    fn next_entry(&mut self) -> io::Result<Option<EntryHeader>> {
+        let offset = self.pos;
+        let Some([kind]) = self.read_number::<1>()? else { return Ok(None) };
+        let Some(time) = self.read_number::<8>()?.map(u64::from_le_bytes) else { return Ok(None) };
+        let Some(fpath_len) = self.read_number::<4>()?.map(u32::from_le_bytes) else { return Ok(None) };
+        let mut fpath = vec![];
+        if (&mut self.reader).take(fpath_len as u64).read_to_end(&mut fpath)? < fpath_len as usize {
+            return Ok(None);
+        }
+        let Some(tag_len) = self.read_number::<4>()?.map(|x| u32::from_le_bytes(x) as u64) else { return Ok(None) };
+        let tag_offset = offset + 1 + 8 + 4 + fpath_len as u64 + 4;
+        if tag_offset + tag_len > self.len {
+            return Ok(None);
+        }
+        self.reader.seek_relative(tag_len as i64)?;
+        self.pos = tag_offset + tag_len;
+        Ok(Some(EntryHeader { kind, time, fpath, offset, tag_offset, tag_len }))
+    }
+
+    /// Reads the tag of an entry which was read before.
+    fn read_tag(&mut self, entry: &EntryHeader) -> io::Result<Vec<u8>> {
+        let mut tag = vec![0; entry.tag_len as usize];
+        self.reader.seek(SeekFrom::Start(entry.tag_offset))?;
+        self.reader.read_exact(&mut tag)?;
+        self.reader.seek(SeekFrom::Start(self.pos))?;
+        Ok(tag)
+    }
+}
+
+/// Drops the oldest entries of a journal which is larger than `max_size`, keeping the newest
+/// entries which fit in half of it. The journal is replaced as a whole, so that it is never left
+/// half-written; entries appended by concurrent runs in the meantime are lost.
+fn compact_journal(journal: &Path, max_size: u64) -> io::Result<()> {
+    let Some(mut reader) = JournalReader::open(journal)? else { return Ok(()) };
+    if reader.len <= max_size {
+        return Ok(());
+    }
+    let mut start = reader.len;
+    while let Some(entry) = reader.next_entry()? {
+        if reader.len - entry.offset <= max_size / 2 {
+            start = entry.offset;
+            break;
+        }
+    }
+    let dir = journal.parent().unwrap_or(Path::new("."));
+    let mut compacted = NamedTempFile::new_in(dir)?;
+    let mut file = reader.reader.into_inner();
+    file.seek(SeekFrom::Start(start))?;
+    io::copy(&mut file, &mut compacted)?;
+    compacted.persist(journal).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Appends an entry to the journal, dropping its oldest entries first if it grew too large.
+/// The entry is written at once, so that concurrent runs do not interleave their entries.
+fn append_entry(entry: &JournalEntry) -> Result<()> {
+    let journal = journal_path().ok_or_else(|| anyhow!("Cannot determine the cache directory"))?;
+    let result = journal.parent().map_or(Ok(()), create_dir_all)
+        .and_then(|_| compact_journal(&journal, JOURNAL_MAX_SIZE))
+        .and_then(|_| OpenOptions::new().create(true).append(true).open(&journal))
+        .and_then(|mut file| file.write_all(&entry.encode()));
+    result.map_err(|e| anyhow!("Failed to write to the undo journal '{}': {e}", journal.display()))
+}
+
+/// Returns the current time, in seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |x| x.as_secs())
+}
+
+/// Returns the absolute path of a file, as recorded in journal entries.
+fn canonical_path_bytes(fpath: &impl AsRef<Path>) -> Result<Vec<u8>> {
+    match canonicalize(fpath) {
+        Ok(x) => Ok(x.into_os_string().into_encoded_bytes()),
+        Err(e) => Err(anyhow!("Failed to find '{}': {e}", fpath.as_ref().display())),
+    }
+}
+
+/// Records the current ID3v2 tag of a file in the undo journal, before the file is written.
+pub fn record_tag(fpath: &impl AsRef<Path>) -> Result<()> {
+    let fpath = fpath.as_ref();
+    let tag = read_raw_tag(&fpath)
+        .map_err(|e| anyhow!("Failed to record the tag of '{}' for undoing: {e}", fpath.display()))?;
+    let fpath = canonical_path_bytes(&fpath)?;
+    append_entry(&JournalEntry { kind: ENTRY_SAVED, time: now(), fpath, tag })
+}
+
+/// Finds the latest tag recorded in a journal for a file (given as recorded in entries) which has
+/// not been undone yet.
+fn find_saved_tag_in(journal: &Path, fpath: &[u8]) -> io::Result<Option<SavedTag>> {
+    let Some(mut reader) = JournalReader::open(journal)? else { return Ok(None) };
+    // Each undone entry cancels out the latest saved entry that is not cancelled out yet
+    let mut saved = vec![];
+    while let Some(entry) = reader.next_entry()? {
+        if entry.fpath != fpath {
+            continue;
+        }
+        match entry.kind {
+            ENTRY_SAVED => saved.push(entry),
+            ENTRY_UNDONE => { saved.pop(); },
+            _ => {},
+        }
+    }
+    match saved.pop() {
+        Some(entry) => Ok(Some(SavedTag { time: entry.time, bytes: reader.read_tag(&entry)? })),
+        None => Ok(None),
+    }
+}
+
+/// Finds the tag to be restored by undoing the latest modification of a file, i.e. the latest
+/// tag recorded for it which has not been undone yet.
+pub fn find_saved_tag(fpath: &impl AsRef<Path>) -> Result<Option<SavedTag>> {
+    let fpath = canonical_path_bytes(fpath)?;
+    let journal = match journal_path() {
+        Some(x) => x,
+        None => return Ok(None),
+    };
+    find_saved_tag_in(&journal, &fpath)
+        .map_err(|e| anyhow!("Failed to read the undo journal '{}': {e}", journal.display()))
+}
+
+/// Marks the latest tag recorded for a file as undone, so that undoing again goes further back.
+pub fn mark_undone(fpath: &impl AsRef<Path>) -> Result<()> {
+    let fpath = canonical_path_bytes(fpath)?;
+    append_entry(&JournalEntry { kind: ENTRY_UNDONE, time: now(), fpath, tag: vec![] })
+}
+
+/// Writes a tag recorded in the journal back to a file, or removes the file's tag if it had none.
+/// See `try_write_tag` for the meaning of `atomic`.
+pub fn restore_tag(fpath: &impl AsRef<Path>, saved: &SavedTag, atomic: bool) -> Result<()> {
+    if saved.bytes.is_empty() {
+        return remove_tag(fpath);
+    }
+    let tag = Tag::read_from2(Cursor::new(&saved.bytes))
+        .map_err(|e| anyhow!("Failed to decode the recorded tag of '{}': {e}", fpath.as_ref().display()))?;
+    try_write_tag(&tag, fpath, tag.version(), None, atomic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Appends entries to a journal, without compacting it.
+    fn write_entries(journal: &Path, entries: &[(u8, &[u8], &[u8])]) {
+        let mut file = OpenOptions::new().create(true).append(true).open(journal).unwrap();
+        for (i, &(kind, fpath, tag)) in entries.iter().enumerate() {
+            let entry = JournalEntry { kind, time: i as u64, fpath: fpath.to_vec(), tag: tag.to_vec() };
+            file.write_all(&entry.encode()).unwrap();
+        }
+    }
+
+    /// Returns the tag that undoing would restore for a file, if any.
+    fn saved_tag(journal: &Path, fpath: &[u8]) -> Option<Vec<u8>> {
+        find_saved_tag_in(journal, fpath).unwrap().map(|x| x.bytes)
+    }
+
+    #[test]
+    fn finds_latest_saved_tag() {
+        let dir = tempdir().unwrap();
+        let journal = dir.path().join("journal");
+        assert_eq!(saved_tag(&journal, b"/a"), None);
+        write_entries(&journal, &[
+            (ENTRY_SAVED, b"/a", b"a1"),
+            (ENTRY_SAVED, b"/b", b"b1"),
+            (ENTRY_SAVED, b"/a", b"a2"),
+            (ENTRY_SAVED, b"/a", b""),
+            (ENTRY_UNDONE, b"/a", b""),
+        ]);
+        assert_eq!(saved_tag(&journal, b"/a").as_deref(), Some(&b"a2"[..]));
+        assert_eq!(saved_tag(&journal, b"/b").as_deref(), Some(&b"b1"[..]));
+        assert_eq!(saved_tag(&journal, b"/c"), None);
+        write_entries(&journal, &[(ENTRY_UNDONE, b"/a", b""), (ENTRY_UNDONE, b"/a", b"")]);
+        assert_eq!(saved_tag(&journal, b"/a"), None);
+    }
+
+    #[test]
+    fn ignores_truncated_entries() {
+        let dir = tempdir().unwrap();
+        let journal = dir.path().join("journal");
+        write_entries(&journal, &[(ENTRY_SAVED, b"/a", b"a1"), (ENTRY_SAVED, b"/a", b"a2")]);
+        let len = std::fs::metadata(&journal).unwrap().len();
+        for truncated_len in [len - 1, len - 3, len - 6, len - 10] {
+            OpenOptions::new().write(true).open(&journal).unwrap().set_len(truncated_len).unwrap();
+            assert_eq!(saved_tag(&journal, b"/a").as_deref(), Some(&b"a1"[..]));
+        }
+    }
+
+    #[test]
+    fn compacts_journal() {
+        let dir = tempdir().unwrap();
+        let journal = dir.path().join("journal");
+        let tag = [0; 1000];
+        for _ in 0..10 {
+            write_entries(&journal, &[(ENTRY_SAVED, b"/a", &tag), (ENTRY_SAVED, b"/b", &tag)]);
+        }
+        write_entries(&journal, &[(ENTRY_SAVED, b"/a", b"latest")]);
+        let len = std::fs::metadata(&journal).unwrap().len();
+
+        // Journals within the limit are left as they are
+        compact_journal(&journal, len).unwrap();
+        assert_eq!(std::fs::metadata(&journal).unwrap().len(), len);
+
+        compact_journal(&journal, 8000).unwrap();
+        let compacted_len = std::fs::metadata(&journal).unwrap().len();
+        assert!(compacted_len <= 4000 && compacted_len > 3000, "{compacted_len}");
+        assert_eq!(saved_tag(&journal, b"/a").as_deref(), Some(&b"latest"[..]));
+        assert_eq!(saved_tag(&journal, b"/b").as_deref(), Some(&tag[..]));
+
+        // An entry larger than the limit is dropped once another one is recorded
+        compact_journal(&journal, 10).unwrap();
+        assert_eq!(std::fs::metadata(&journal).unwrap().len(), 0);
+    }
+}
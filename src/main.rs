@@ -13,233 +13,957 @@
 // You should have received a copy of the GNU General Public License along
 // with this program; if not, write to the Free Software Foundation, Inc.,
 // 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+mod backup;
+mod batch;
 mod cli;
-mod id3_helpers;
+mod completions;
+mod config;
+mod errors;
+mod frame_help;
+mod interactive;
+mod journal;
+mod progress;
+mod tag_document;
 
-use cli::{Cli, Action, ConvertOpt, PurgeOpt};
-use std::path::Path;
-use id3_helpers::*;
+/// Reports what is being done to a file on stderr, if the verbosity level of `$options` (anything
+/// with a `verbosity` field) is at least `level`.
+macro_rules! verbose {
+    ($options:expr, $level:expr, $fpath:expr, $($arg:tt)*) => {
+        if $options.verbosity >= $level {
+            errors::report_file_error(&$fpath, ErrorKind::Info, format_args!($($arg)*));
+        }
+    };
+}
+
+use rsid3_core::{ape, diff, filter, Action, ExecError, ExecOptions, Executor};
+use rsid3_core::errors::ErrorKind;
+use errors::{report_error, report_file_error, set_error_context, set_json_errors, CliOutput};
+use rsid3_core::diff::FrameChange;
+use rsid3_core::engine::is_tag_unchanged;
+use rsid3_core::id3_helpers::*;
+use rsid3_core::id3v1_helpers::*;
+use rsid3_core::chapters::format_chapter_time;
+use rsid3_core::checksum::audio_md5;
+use rsid3_core::file_format::FileFormat;
+use rsid3_core::mpeg_audio::read_file_audio_info;
+use cli::{Cli, ErrorFormat, ColorOpt};
+use progress::{Progress, PROGRESS_THRESHOLD};
+use interactive::EditorExit;
+use std::fs::{FileTimes, Metadata, OpenOptions, Permissions};
+use std::env::args_os;
+use std::ffi::OsString;
+use std::io::{self, stderr, stdin, stdout, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 use anyhow::{anyhow, Result};
-use id3::{Tag, TagLike, Frame, Version};
+use id3::{Frame, Tag};
+
+/// Represents a class of failures, each of which is reported with a distinct exit code.
+/// If several failures happen in one run, the one with the highest code is reported.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Failure {
+    /// A queried frame or field was not found.
+    FrameNotFound = 1,
+    /// The command line was invalid, or requested an unsupported operation.
+    Usage = 2,
+    /// A file or its tag could not be read or written.
+    Io = 3,
+    /// A file could not be processed after some other files already had been.
+    PartialBatch = 4,
+    /// Problems were found in a tag by `--check`.
+    ProblemsFound = 5,
+}
+
+impl From<Failure> for u8 {
+    fn from(failure: Failure) -> u8 {
+        failure as u8
+    }
+}
+
+impl From<Failure> for ExitCode {
+    fn from(failure: Failure) -> ExitCode {
+        ExitCode::from(failure as u8)
+    }
+}
 
-/// Pretty-prints all supported frames stored in the file.
-fn print_all_file_frames_pretty(fpath: &impl AsRef<Path>) -> Result<()> {
-    let tag = match Tag::read_from_path(fpath) {
-        Ok(tag) => tag,
+/// Pretty-prints all supported frames stored in the file, followed by the ID3v1 tag, if any.
+/// Frames are colored with ANSI escapes, if `color` is set, and multiple values of text frames
+/// are separated with `value_sep`. Numeric genre references are resolved, if `genre_names` is set,
+/// frame IDs are followed by their names, if `describe` is set, each frame is followed by the flags
+/// of its stored header, if `show_flags` is set, and corrupt frames are skipped, if `lenient` is set.
+fn print_all_file_frames_pretty(fpath: &impl AsRef<Path>, color: bool, value_sep: &str, genre_names: bool,
+    describe: bool, show_flags: bool, lenient: bool) -> Result<()> {
+    let tag = match read_tag_for(&mut CliOutput::new(), fpath, lenient) {
+        Ok(tag) => Some(tag),
         Err(e) => match e.kind {
-            id3::ErrorKind::NoTag => {
-                eprintln!("{}: No tag found", fpath.as_ref().display());
-                return Ok(());
-            },
+            id3::ErrorKind::NoTag => None,
             _ => return Err(anyhow!("Failed to read tag from file '{}': {e}", fpath.as_ref().display())),
         }
     };
+    let v1_tag = read_id3v1_tag(fpath)?;
+    if let Some(ape_tag) = ape::find_ape_tag(fpath)? {
+        report_file_error(fpath, ErrorKind::Warning, format_args!("Found an APEv{} tag, which may confuse some players (remove with --purge-ape)", ape_tag.version));
+    }
+    if tag.is_none() && v1_tag.is_none() {
+        report_file_error(fpath, ErrorKind::Warning, "No tag found");
+        return Ok(());
+    }
 
-    let n_frames = tag.frames().count();
-    println!("{}: {}, {} frame{}:", fpath.as_ref().display(), tag.version(), n_frames,
-        if n_frames == 1 { "" } else { "s" });
-    for frame in tag.frames() {
-        print_frame_pretty(frame)?;
+    if let Some(tag) = tag {
+        let n_frames = tag.frames().count();
+        println!("{}: {}, {} frame{}:", fpath.as_ref().display(), tag.version(), n_frames,
+            if n_frames == 1 { "" } else { "s" });
+        // Frames are read in the order they are stored, so each one's stored header is the first
+        // one with its ID that is left
+        let layout = if show_flags { read_tag_layout(fpath)? } else { None };
+        let mut stored_frames = layout.iter().flat_map(|x| &x.frames).collect::<Vec<_>>();
+        for frame in tag.frames() {
+            if genre_names {
+                print_frame_pretty(&mut stdout(), &resolve_genre_frame(frame.clone()), color, value_sep, describe)?;
+            } else {
+                print_frame_pretty(&mut stdout(), frame, color, value_sep, describe)?;
+            }
+            if let Some(layout) = &layout {
+                let flags = match stored_frames.iter().position(|x| x.id == frame.id()) {
+                    Some(i) => stored_frames.remove(i).flag_names(layout.header.major_version),
+                    None => vec![],
+                };
+                println!("  flags: {}", if flags.is_empty() { "none".to_string() } else { flags.join(", ") });
+            }
+        }
+    }
+    if let Some(v1_tag) = v1_tag {
+        println!("{}: {}:", fpath.as_ref().display(), id3v1_version_string(&v1_tag));
+        print_id3v1_tag_pretty(&mut stdout(), &v1_tag)?;
     }
 
     Ok(())
 }
 
-/// Writes a frame into a tag. The previous value is overwritten, if any.
-fn set_tag_frame(tag: &mut Tag, frame: Frame) -> Result<()> {
-    match frame.id() {
-        x if x.starts_with('T') || x.starts_with('W') || x == "COMM" || x == "USLT" => {
-            let _ = tag.add_frame(frame);
-            Ok(())
-        },
-        _ => Err(anyhow!("Writing to {frame} is not supported")),
+/// Prints the properties of the audio stream of an MPEG file, as parsed from its frame headers.
+fn print_audio_info(fpath: &impl AsRef<Path>) -> Result<()> {
+    if FileFormat::detect(fpath) != FileFormat::Mpeg {
+        report_file_error(fpath, ErrorKind::Warning, "Audio properties can only be read from MPEG files");
+        return Ok(());
     }
+    let info = read_file_audio_info(fpath)?;
+    println!("{}: {} Layer {} audio:", fpath.as_ref().display(), info.version.name(), "I".repeat(info.layer as usize));
+    println!("  duration: {}", format_chapter_time(info.duration_ms.min(u32::MAX as u64) as u32));
+    println!("  bitrate: {} kbps ({})", info.bitrate, if info.is_vbr { "VBR" } else { "CBR" });
+    println!("  sample rate: {} Hz", info.sample_rate);
+    println!("  channels: {}", if info.channels == 1 { "mono" } else { "stereo" });
+    Ok(())
 }
 
-/// Converts a tag according to the given command-line option.
-/// On success, returns whether any conversion happened (`false` iff the tag's version was already
-/// the same as the requested version).
-fn convert_tag(tag: &mut Tag, opt: ConvertOpt) -> Result<bool> {
-    let (tag_version, force) = match opt {
-        ConvertOpt::Id3v22 => (Version::Id3v22, false),
-        ConvertOpt::Id3v23 => (Version::Id3v23, false),
-        ConvertOpt::Id3v24 => (Version::Id3v24, false),
-        ConvertOpt::Id3v22Force => (Version::Id3v22, true),
-        ConvertOpt::Id3v23Force => (Version::Id3v23, true),
-        ConvertOpt::Id3v24Force => (Version::Id3v24, true),
+/// Prints the frames which differ between the ID3v2 tags of two files, in a unified diff-like
+/// format. Files without a tag are treated as having an empty one. Multiple values of text frames
+/// are separated with `value_sep`, and corrupt frames are skipped, if `lenient` is set.
+/// Returns whether the tags differ.
+fn print_tag_diff(fpath1: &impl AsRef<Path>, fpath2: &impl AsRef<Path>, value_sep: &str, lenient: bool) -> Result<bool> {
+    let read = |fpath: &Path| match read_tag_for(&mut CliOutput::new(), &fpath, lenient) {
+        Ok(tag) => Ok(tag),
+        Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => Ok(Tag::new()),
+        Err(e) => Err(anyhow!("Failed to read tag from file '{}': {e}", fpath.display())),
     };
-    if tag.version() == tag_version {
+    let tag1 = read(fpath1.as_ref())?;
+    let tag2 = read(fpath2.as_ref())?;
+    let changes = diff::diff_tags(&tag1, &tag2);
+    if changes.is_empty() {
         return Ok(false);
     }
-    *tag = tag_with_version_from(tag, tag_version, force)?;
+
+    println!("--- {}", fpath1.as_ref().display());
+    println!("+++ {}", fpath2.as_ref().display());
+    let print_frame = |sign: char, frame: &Frame| -> Result<()> {
+        for line in format_frame_pretty(frame, false, value_sep, false)?.lines() {
+            println!("{sign}{line}");
+        }
+        Ok(())
+    };
+    for change in changes {
+        match change {
+            FrameChange::Removed(frame) => print_frame('-', frame)?,
+            FrameChange::Added(frame) => print_frame('+', frame)?,
+            FrameChange::Changed(frame1, frame2) => {
+                print_frame('-', frame1)?;
+                print_frame('+', frame2)?;
+            },
+        }
+    }
     Ok(true)
 }
 
+/// Restores the modification time and/or the access time of a file from its old metadata.
+fn restore_file_times(fpath: &impl AsRef<Path>, metadata: &Metadata, mtime: bool, atime: bool) -> Result<()> {
+    let result = OpenOptions::new().write(true).open(fpath).and_then(|file| {
+        let mut times = FileTimes::new();
+        if mtime {
+            times = times.set_modified(metadata.modified()?);
+        }
+        if atime {
+            times = times.set_accessed(metadata.accessed()?);
+        }
+        file.set_times(times)
+    });
+    result.map_err(|e| anyhow!("Failed to restore timestamps of '{}': {e}", fpath.as_ref().display()))
+}
+
+/// Checks if a file about to be modified is read-only, which is an error unless `force` is set.
+fn check_read_only_file(fpath: &impl AsRef<Path>, force: bool) -> Result<bool> {
+    let fpath = fpath.as_ref();
+    let metadata = std::fs::metadata(fpath)
+        .map_err(|e| anyhow!("Failed to read metadata of '{}': {e}", fpath.display()))?;
+    let is_read_only = metadata.permissions().readonly();
+    if is_read_only && !force {
+        return Err(anyhow!("Cannot modify '{}': File is read-only (use --force-write to modify it anyway)", fpath.display()));
+    }
+    Ok(is_read_only)
+}
+
+/// Makes a read-only file writable by its owner, returning its original permissions.
+fn make_file_writable(fpath: &impl AsRef<Path>) -> Result<Permissions> {
+    let result = std::fs::metadata(fpath).and_then(|metadata| {
+        let permissions = metadata.permissions();
+        let mut writable = permissions.clone();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            writable.set_mode(permissions.mode() | 0o200);
+        }
+        #[cfg(not(unix))]
+        {
+            #[allow(clippy::permissions_set_readonly_false)]
+            writable.set_readonly(false);
+        }
+        std::fs::set_permissions(fpath, writable)?;
+        Ok(permissions)
+    });
+    result.map_err(|e| anyhow!("Failed to make '{}' writable: {e}", fpath.as_ref().display()))
+}
+
+/// Interprets backslash escape sequences in a string: `\\`, `\0`, `\a`, `\b`, `\e`, `\f`, `\n`,
+/// `\r`, `\t`, `\v` and `\xHH` (ASCII only).
+fn unescape(s: &str) -> Result<String> {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        result.push(match chars.next() {
+            Some('\\') => '\\',
+            Some('0') => '\0',
+            Some('a') => '\x07',
+            Some('b') => '\x08',
+            Some('e') => '\x1b',
+            Some('f') => '\x0c',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('v') => '\x0b',
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(x) if hex.len() == 2 && x.is_ascii() => x as char,
+                    _ => return Err(anyhow!("Invalid escape sequence: '\\x{hex}'")),
+                }
+            },
+            Some(x) => return Err(anyhow!("Invalid escape sequence: '\\{x}'")),
+            None => return Err(anyhow!("Trailing backslash")),
+        });
+    }
+    Ok(result)
+}
+
 fn main() -> ExitCode {
     let cli = match Cli::parse_args() {
         Ok(cli) => cli,
         Err(e) => {
-            eprintln!("rsid3: {e}, try 'rsid3 --help'");
-            return ExitCode::FAILURE;
+            // The command line is invalid, but the requested error format is still honored
+            let args = args_os().collect::<Vec<_>>();
+            set_json_errors(args.windows(2).any(|x| x[0] == "--errors" && x[1] == "json"));
+            report_error(ErrorKind::Usage, format_args!("{e}, try 'rsid3 --help'"));
+            return Failure::Usage.into();
         }
     };
+    if let Some(delimiter) = cli.batch {
+        let prefix = args_os().skip(1).filter(|x| x != "--batch" && x != "--batch0").collect::<Vec<_>>();
+        return ExitCode::from(run_batch(&prefix, delimiter));
+    }
+    ExitCode::from(run(&cli))
+}
+
+/// Runs `delimiter`-separated commands read from stdin, each with the `prefix` arguments prepended.
+/// After each command's output, its exit code is printed as "exit CODE", surrounded by delimiters.
+/// Returns the highest exit code of all commands.
+fn run_batch(prefix: &[OsString], delimiter: u8) -> u8 {
+    let mut max_code = 0;
+    for command in stdin().lock().split(delimiter) {
+        let command = match command {
+            Ok(x) => x,
+            Err(e) => {
+                report_error(ErrorKind::Io, format_args!("Failed to read commands: {e}"));
+                return Failure::Io.into();
+            },
+        };
+        let command = match String::from_utf8(command) {
+            Ok(x) if x.trim().is_empty() => continue,
+            Ok(x) => batch::split_command(&x),
+            Err(_) => Err(anyhow!("Command is not valid UTF-8")),
+        };
+        let args = command.map(|x| {
+            let mut args = vec![OsString::from("rsid3")];
+            args.extend_from_slice(prefix);
+            args.extend(x.into_iter().map(OsString::from));
+            args
+        });
+        let code = match args.and_then(Cli::parse_args_from) {
+            Ok(cli) if cli.batch.is_some() => {
+                report_error(ErrorKind::Usage, "--batch cannot be used in batch commands");
+                Failure::Usage.into()
+            },
+            Ok(cli) => run(&cli),
+            Err(e) => {
+                report_error(ErrorKind::Usage, e);
+                Failure::Usage.into()
+            },
+        };
+        let delimiter = delimiter as char;
+        print!("{delimiter}exit {code}{delimiter}");
+        if let Err(e) = stdout().flush() {
+            report_error(ErrorKind::Io, format_args!("Failed to write output: {e}"));
+            return Failure::Io.into();
+        }
+        max_code = max_code.max(code);
+    }
+    max_code
+}
+
+/// Reports how many of the files failed to be processed, if any did, and returns the failure to
+/// report for them. Unless `--keep-going` is given, processing stops at the first failed file.
+fn finish_files(cli: &Cli, processed: usize, failed: usize) -> Option<Failure> {
+    if failed == 0 {
+        return None;
+    }
+    let total = cli.files.len();
+    if cli.keep_going {
+        report_error(ErrorKind::Io, format_args!("{failed} of {total} file(s) failed"));
+    } else if processed < total {
+        report_error(ErrorKind::Io, format_args!("Stopped after a failure, {} file(s) not processed \
+            (pass --keep-going to process them)", total - processed));
+    }
+    Some(if failed == processed { Failure::Io } else { Failure::PartialBatch })
+}
+
+/// Checks if progress should be shown while processing files: only if there are many of them,
+/// stderr is a terminal, and nothing else is printed to the terminal in the meantime.
+fn is_progress_shown(cli: &Cli, prints: bool) -> bool {
+    !cli.quiet && cli.verbosity == 0 && cli.files.len() >= PROGRESS_THRESHOLD && stderr().is_terminal()
+        && !(prints && stdout().is_terminal())
+}
+
+/// Checks if frame summaries should be colored, according to --color.
+fn is_color_shown(cli: &Cli) -> bool {
+    match cli.color {
+        ColorOpt::Always => true,
+        ColorOpt::Never => false,
+        ColorOpt::Auto => stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+    }
+}
+
+/// Gathers the options which actions are executed with from the command line, given the
+/// separators as they are printed.
+fn exec_options(cli: &Cli, frame_sep: &str, file_sep: &str, value_sep: &str) -> ExecOptions {
+    ExecOptions {
+        frame_sep: frame_sep.to_string(),
+        file_sep: file_sep.to_string(),
+        value_sep: value_sep.to_string(),
+        with_filename: cli.with_filename,
+        binary_format: cli.binary_format,
+        genre_names: cli.genre_names,
+        missing: cli.missing.clone(),
+        no_clobber: cli.no_clobber,
+        track_pad: cli.track_pad,
+        rating_owner: cli.rating_owner.clone(),
+        rating_scale: cli.rating_scale,
+        normalize: cli.normalize,
+        lenient: cli.lenient,
+        default_version: cli.default_version,
+        encoding: cli.encoding,
+        padding: cli.padding,
+        atomic: cli.atomic,
+        dry_run: cli.dry_run,
+        verbosity: cli.verbosity,
+    }
+}
+
+/// Writes a tag edited by the user with --interactive or --edit to a file, honoring --encoding,
+/// --dry-run, --backup, --no-journal, --padding and --atomic. The file is only backed up once, before
+/// `is_backed_up` is set.
+fn write_edited_tag(cli: &Cli, fpath: &Path, tag: &Tag, is_backed_up: &mut bool) -> Result<()> {
+    let mut tag = tag.clone();
+    if let Some(encoding) = cli.encoding {
+        set_text_encoding(&mut tag, encoding);
+    }
+    if cli.dry_run {
+        verbose!(cli, 1, fpath, "Would write {} tag with {} frame(s)", tag.version(), tag.frames().count());
+        return Ok(());
+    }
+    if let (Some(backup), false) = (&cli.backup, *is_backed_up) {
+        backup::backup_file(&fpath, backup)?;
+        *is_backed_up = true;
+    }
+    if !cli.no_journal {
+        journal::record_tag(&fpath)?;
+    }
+    try_write_tag(&tag, &fpath, tag.version(), cli.padding, cli.atomic)?;
+    verbose!(cli, 1, fpath, "Wrote {} tag with {} frame(s)", tag.version(), tag.frames().count());
+    Ok(())
+}
+
+/// Executes the actions on a file read from stdin, and writes the file with the modified tag to
+/// stdout, so that rsid3 can be placed in a pipeline without temporary files.
+fn run_pipe(cli: &Cli, frame_sep: &str, file_sep: &str, value_sep: &str) -> Option<Failure> {
+    // The file takes up stdout, and there is no file for actions to access on their own
+    if let Some(action) = cli.actions.iter().find(|x| x.is_print() || x.accesses_file()) {
+        report_error(ErrorKind::Usage, format_args!("Action '{}' cannot be combined with --stdin", action.name()));
+        return Some(Failure::Usage);
+    }
+    let fpath = Path::new("-");
+    set_error_context(Some(fpath), None);
+    // Only the start of the file up to the end of the tag is held in memory, the rest is copied
+    let mut input = stdin().lock();
+    let mut bytes = match read_stream_head(&mut input) {
+        Ok(x) => x,
+        Err(e) => {
+            report_error(ErrorKind::Io, format_args!("Failed to read file from stdin: {e}"));
+            return Some(Failure::Io);
+        },
+    };
+
+    let mut executor = Executor::new(exec_options(cli, frame_sep, file_sep, value_sep), 1, CliOutput::new());
+    let mut file = match executor.read_stream_tag(fpath, &bytes) {
+        Ok(x) => x,
+        Err(e) => {
+            report_error(ErrorKind::Io, e);
+            return Some(Failure::Io);
+        },
+    };
+    let mut result = None;
+    match executor.execute_actions(fpath, 0, &mut file, &cli.actions, &mut || Ok(())) {
+        Ok(outcome) => {
+            if outcome.not_found {
+                result = Some(Failure::FrameNotFound);
+            }
+        },
+        Err(ExecError::Usage(e)) => {
+            report_error(ErrorKind::Usage, e);
+            return Some(Failure::Usage);
+        },
+        Err(ExecError::File(e)) => {
+            report_error(ErrorKind::Io, e);
+            return Some(Failure::Io);
+        },
+    }
+
+    // The file is written even if the tag is unchanged, as the pipeline expects it either way
+    set_error_context(Some(fpath), None);
+    if let Err(e) = executor.write_stream_tag(fpath, &mut bytes, &mut file) {
+        report_error(ErrorKind::Io, e);
+        return Some(Failure::Io);
+    }
+    let mut output = stdout().lock();
+    if let Err(e) = output.write_all(&bytes).and_then(|_| io::copy(&mut input, &mut output)).and_then(|_| output.flush()) {
+        report_error(ErrorKind::Io, format_args!("Failed to write file to stdout: {e}"));
+        return Some(Failure::Io);
+    }
+    result
+}
+
+/// Does everything requested by the parsed command line.
+/// Returns the exit code: 0 on success, or that of the most severe failure.
+fn run(cli: &Cli) -> u8 {
+    set_json_errors(cli.errors == ErrorFormat::Json);
+    set_error_context(None, None);
 
     if cli.help {
         Cli::print_usage();
-        return ExitCode::SUCCESS;
+        return 0;
     }
 
     if cli.version {
         Cli::print_version();
-        return ExitCode::SUCCESS;
+        return 0;
     }
 
     if cli.list_frames {
-        Cli::print_all_frames();
-        return ExitCode::SUCCESS;
+        // A conversion option restricts the list to the frames which would survive it
+        let version = cli.actions.iter().rev().find_map(|x| match x {
+            Action::Convert(opt) => Some(opt.target().0),
+            _ => None,
+        });
+        Cli::print_all_frames(cli.output, version);
+        return 0;
+    }
+
+    if let Some(id) = &cli.help_frame {
+        if let Err(e) = frame_help::print_frame_help(id) {
+            report_error(ErrorKind::Usage, e);
+            return Failure::Usage.into();
+        }
+        return 0;
+    }
+
+    if let Some(shell) = cli.completions {
+        print!("{}", completions::completion_script(shell));
+        return 0;
     }
 
     // Define the separators
     if cli.frame_sep.is_some() && cli.frame_sep_null {
-        eprintln!("rsid3: --frame-sep and --frame-sep-null options are mutually exclusive");
-        return ExitCode::FAILURE;
+        report_error(ErrorKind::Usage, "--frame-sep and --frame-sep-null options are mutually exclusive");
+        return Failure::Usage.into();
     }
     let frame_sep = if cli.frame_sep_null {
         '\0'.to_string()
     } else {
         cli.frame_sep.clone().unwrap_or('\n'.to_string())
     };
+    let frame_sep = match (cli.escape_seps, unescape(&frame_sep)) {
+        (false, _) => frame_sep,
+        (true, Ok(x)) => x,
+        (true, Err(e)) => {
+            report_error(ErrorKind::Usage, format_args!("Invalid frame separator: {e}"));
+            return Failure::Usage.into();
+        },
+    };
     if cli.file_sep.is_some() && cli.file_sep_null {
-        eprintln!("rsid3: --file-sep and --file-sep-null options are mutually exclusive");
-        return ExitCode::FAILURE;
+        report_error(ErrorKind::Usage, "--file-sep and --file-sep-null options are mutually exclusive");
+        return Failure::Usage.into();
     }
     let file_sep = if cli.file_sep_null {
         '\0'.to_string()
     } else {
         cli.file_sep.clone().unwrap_or('\n'.to_string())
     };
+    let file_sep = match (cli.escape_seps, unescape(&file_sep)) {
+        (false, _) => file_sep,
+        (true, Ok(x)) => x,
+        (true, Err(e)) => {
+            report_error(ErrorKind::Usage, format_args!("Invalid file separator: {e}"));
+            return Failure::Usage.into();
+        },
+    };
+    let value_sep = cli.value_sep.clone().unwrap_or('\0'.to_string());
+    let value_sep = match (cli.escape_seps, unescape(&value_sep)) {
+        (false, _) => value_sep,
+        (true, Ok(x)) => x,
+        (true, Err(e)) => {
+            report_error(ErrorKind::Usage, format_args!("Invalid value separator: {e}"));
+            return Failure::Usage.into();
+        },
+    };
 
-    // Handle all actions
-    if !cli.actions.is_empty() {
-        let mut is_first_file_print = true;
+    if cli.diff {
+        if cli.files.len() != 2 || !cli.actions.is_empty() {
+            report_error(ErrorKind::Usage, "--diff expects exactly two files and no other actions");
+            return Failure::Usage.into();
+        }
+        return match print_tag_diff(&cli.files[0], &cli.files[1], &value_sep, cli.lenient) {
+            Ok(false) => 0,
+            // Like diff(1), differences are reported with exit code 1
+            Ok(true) => 1,
+            Err(e) => {
+                report_error(ErrorKind::Io, e);
+                Failure::Io.into()
+            },
+        };
+    }
+
+    if let Some(queries) = &cli.find_duplicates {
+        if !cli.actions.is_empty() {
+            report_error(ErrorKind::Usage, "--find-duplicates cannot be combined with other actions");
+            return Failure::Usage.into();
+        }
+        // Files are grouped by their values, in the order in which the groups are first found
+        let mut groups: Vec<(Vec<Vec<String>>, Vec<&PathBuf>)> = vec![];
+        let (mut processed, mut failed) = (0, 0);
+        let mut progress = Progress::new(cli.files.len(), is_progress_shown(cli, false));
         for fpath in &cli.files {
-            // Read the file's tag
-            let mut tag = match Tag::read_from_path(fpath) {
+            if failed > 0 && !cli.keep_going {
+                break;
+            }
+            processed += 1;
+            progress.update(processed);
+            set_error_context(Some(fpath), None);
+            let tag = match read_tag_for(&mut CliOutput::new(), fpath, cli.lenient) {
                 Ok(tag) => tag,
-                Err(e) => match e.kind {
-                    id3::ErrorKind::NoTag => {
-                        Tag::with_version(Version::Id3v24)
-                    },
-                    _ => {
-                        eprintln!("rsid3: Failed to read tag from file '{fpath}': {e}");
-                        break;
-                    },
+                Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => continue,
+                Err(e) => {
+                    report_error(ErrorKind::Io, format_args!("Failed to read tag from file '{}': {e}", fpath.display()));
+                    failed += 1;
+                    continue;
+                },
+            };
+            let key = queries.iter()
+                .map(|x| filter::find_values(&tag, x).map(|y| y.iter().map(|z| z.trim().to_lowercase()).collect::<Vec<_>>()))
+                .collect::<Option<Vec<_>>>();
+            let key = match key {
+                Some(x) => x,
+                None => {
+                    verbose!(cli, 2, fpath, "Skipped, not all frames found");
+                    continue;
+                },
+            };
+            match groups.iter_mut().find(|(x, _)| *x == key) {
+                Some((_, fpaths)) => fpaths.push(fpath),
+                None => groups.push((key, vec![fpath])),
+            }
+        }
+        let groups = groups.into_iter()
+            .filter(|(_, x)| x.len() > 1)
+            .map(|(_, x)| x.iter().map(|y| y.display().to_string()).collect::<Vec<_>>().join(&file_sep))
+            .collect::<Vec<_>>();
+        progress.finish();
+        print!("{}", groups.join(&format!("{file_sep}{file_sep}")));
+        let result = finish_files(cli, processed, failed);
+        return result.or(Some(Failure::FrameNotFound).filter(|_| groups.is_empty())).map_or(0, |x| x as u8);
+    }
+
+    if let Some((query, regex)) = &cli.grep {
+        if !cli.actions.is_empty() {
+            report_error(ErrorKind::Usage, "--grep cannot be combined with other actions");
+            return Failure::Usage.into();
+        }
+        let mut is_found = false;
+        let (mut processed, mut failed) = (0, 0);
+        let mut progress = Progress::new(cli.files.len(), is_progress_shown(cli, true));
+        for fpath in &cli.files {
+            if failed > 0 && !cli.keep_going {
+                break;
+            }
+            processed += 1;
+            progress.update(processed);
+            set_error_context(Some(fpath), None);
+            let tag = match read_tag_for(&mut CliOutput::new(), fpath, cli.lenient) {
+                Ok(tag) => tag,
+                Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => continue,
+                Err(e) => {
+                    report_error(ErrorKind::Io, format_args!("Failed to read tag from file '{}': {e}", fpath.display()));
+                    failed += 1;
+                    continue;
+                },
+            };
+            if filter::find_values(&tag, query).is_some_and(|x| x.iter().any(|y| regex.is_match(y))) {
+                if is_found {
+                    print!("{file_sep}");
                 }
+                print!("{}", fpath.display());
+                is_found = true;
+            }
+        }
+        // Like grep(1), finding nothing is reported with exit code 1
+        let result = finish_files(cli, processed, failed);
+        return result.or(Some(Failure::FrameNotFound).filter(|_| !is_found)).map_or(0, |x| x as u8);
+    }
+
+    if cli.undo {
+        if cli.files.is_empty() || !cli.actions.is_empty() {
+            report_error(ErrorKind::Usage, "--undo expects at least one file and no other actions");
+            return Failure::Usage.into();
+        }
+        let (mut processed, mut failed) = (0, 0);
+        for fpath in &cli.files {
+            if failed > 0 && !cli.keep_going {
+                break;
+            }
+            processed += 1;
+            set_error_context(Some(fpath), None);
+            let saved = match journal::find_saved_tag(fpath) {
+                Ok(Some(x)) => x,
+                Ok(None) => {
+                    report_error(ErrorKind::NotFound, format_args!("Nothing to undo for '{}'", fpath.display()));
+                    failed += 1;
+                    continue;
+                },
+                Err(e) => {
+                    report_error(ErrorKind::Io, e);
+                    failed += 1;
+                    continue;
+                },
             };
-            let mut tag_was_modified = false;
-            let mut is_first_frame_print = true;
-
-            for action in &cli.actions {
-                match action {
-                    Action::Print(frame) => {
-                        if !is_first_frame_print {
-                            print!("{frame_sep}");
-                        } else {
-                            is_first_frame_print = false;
-                            if !is_first_file_print {
-                                print!("{file_sep}");
-                            } else {
-                                is_first_file_print = false;
-                            }
-                        }
-                        if let Err(e) = print_tag_frame_query(&tag, frame, fpath) {
-                            eprintln!("rsid3: {e}");
-                            return ExitCode::FAILURE;
-                        }
-                    },
-                    Action::Set(frame) => {
-                        match set_tag_frame(&mut tag, frame.clone()) {
-                            Ok(_) => {
-                                tag_was_modified = true;
-                            },
-                            Err(e) => {
-                                eprintln!("rsid3: {e}");
-                                return ExitCode::FAILURE;
-                            },
-                        }
+            if cli.dry_run {
+                verbose!(cli, 1, fpath, "Would restore the tag recorded at {}", saved.time);
+                continue;
+            }
+            let result = cli.backup.as_ref().map_or(Ok(()), |x| backup::backup_file(fpath, x))
+                .and_then(|_| journal::restore_tag(fpath, &saved, cli.atomic))
+                .and_then(|_| journal::mark_undone(fpath));
+            match result {
+                Ok(()) => verbose!(cli, 1, fpath, "Restored the tag recorded at {}", saved.time),
+                Err(e) => {
+                    report_error(ErrorKind::Io, e);
+                    failed += 1;
+                },
+            }
+        }
+        return finish_files(cli, processed, failed).map_or(0, |x| x as u8);
+    }
+
+    if cli.edit {
+        if cli.files.is_empty() || !cli.actions.is_empty() || cli.interactive {
+            report_error(ErrorKind::Usage, "--edit expects at least one file and no other actions");
+            return Failure::Usage.into();
+        }
+        let (mut processed, mut failed) = (0, 0);
+        for fpath in &cli.files {
+            if failed > 0 && !cli.keep_going {
+                break;
+            }
+            processed += 1;
+            set_error_context(Some(fpath), None);
+            let tag = match read_tag_for(&mut CliOutput::new(), fpath, cli.lenient) {
+                Ok(tag) => tag,
+                Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => Tag::with_version(cli.default_version),
+                Err(e) => {
+                    report_error(ErrorKind::Io, format_args!("Failed to read tag from file '{}': {e}", fpath.display()));
+                    failed += 1;
+                    continue;
+                },
+            };
+            let document = tag_document::format_tag_document(fpath, &tag);
+            let result = tag_document::edit_document(&document).and_then(|edited| {
+                if edited == document {
+                    verbose!(cli, 1, fpath, "Document unchanged, nothing written");
+                    return Ok(());
+                }
+                let new_tag = tag_document::apply_tag_document(&tag, &edited)
+                    .map_err(|e| anyhow!("Failed to apply the edited tag of '{}': {e}", fpath.display()))?;
+                if is_tag_unchanged(fpath, &tag, &new_tag) {
+                    verbose!(cli, 1, fpath, "Tag unchanged, nothing written");
+                    return Ok(());
+                }
+                write_edited_tag(cli, fpath, &new_tag, &mut false)
+            });
+            if let Err(e) = result {
+                report_error(ErrorKind::Io, e);
+                failed += 1;
+            }
+        }
+        return finish_files(cli, processed, failed).map_or(0, |x| x as u8);
+    }
+
+    if cli.interactive {
+        if cli.files.is_empty() || !cli.actions.is_empty() {
+            report_error(ErrorKind::Usage, "--interactive expects at least one file and no other actions");
+            return Failure::Usage.into();
+        }
+        let color = is_color_shown(cli);
+        let (mut processed, mut failed) = (0, 0);
+        for fpath in &cli.files {
+            if failed > 0 && !cli.keep_going {
+                break;
+            }
+            processed += 1;
+            set_error_context(Some(fpath), None);
+            let tag = match read_tag_for(&mut CliOutput::new(), fpath, cli.lenient) {
+                Ok(tag) => tag,
+                Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => Tag::with_version(cli.default_version),
+                Err(e) => {
+                    report_error(ErrorKind::Io, format_args!("Failed to read tag from file '{}': {e}", fpath.display()));
+                    failed += 1;
+                    continue;
+                },
+            };
+            let mut is_backed_up = false;
+            let save = |tag: &Tag| write_edited_tag(cli, fpath, tag, &mut is_backed_up);
+            match interactive::edit_tag(fpath, tag, color, &value_sep, &mut stdin().lock(), save) {
+                Ok(EditorExit::Next) => {},
+                Ok(EditorExit::Quit) => break,
+                Err(e) => {
+                    report_error(ErrorKind::Io, e);
+                    failed += 1;
+                },
+            }
+        }
+        return finish_files(cli, processed, failed).map_or(0, |x| x as u8);
+    }
+
+    if cli.stdin {
+        return run_pipe(cli, &frame_sep, &file_sep, &value_sep).map_or(0, |x| x as u8);
+    }
+
+    // Handle all actions
+    let mut result: Option<Failure> = None;
+    if !cli.actions.is_empty() {
+        let mut executor = Executor::new(exec_options(cli, &frame_sep, &file_sep, &value_sep), cli.files.len(), CliOutput::new());
+        let (mut processed, mut failed) = (0, 0);
+        let mut progress = Progress::new(cli.files.len(), is_progress_shown(cli, cli.actions.iter().any(Action::is_print)));
+        let is_modifying = !cli.dry_run && cli.actions.iter().any(Action::modifies_file);
+        for (i, fpath) in cli.files.iter().enumerate() {
+            if failed > 0 && !cli.keep_going {
+                break;
+            }
+            processed += 1;
+            progress.update(processed);
+            set_error_context(Some(fpath), None);
+
+            // Fail early on read-only files, before any actions print partial output
+            let is_read_only = match is_modifying {
+                true => match check_read_only_file(fpath, cli.force_write) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        report_error(ErrorKind::Io, e);
+                        failed += 1;
+                        continue;
                     },
-                    Action::Delete(frame) => {
-                        match delete_tag_frame(&mut tag, frame, fpath) {
-                            Ok(modified) => {
-                                tag_was_modified |= modified;
-                            },
-                            Err(e) => {
-                                eprintln!("rsid3: {e}");
-                                return ExitCode::FAILURE;
-                            },
-                        }
+                },
+                false => false,
+            };
+
+            // Read the file's tag. The tag as stored is kept, to tell if modifications changed anything
+            let mut file = match executor.read_file_tag(fpath) {
+                Ok(x) => x,
+                Err(e) => {
+                    report_error(ErrorKind::Io, e);
+                    failed += 1;
+                    continue;
+                },
+            };
+
+            // Remember the file's timestamps, so that they can be restored after modifying it
+            let metadata = if cli.preserve_mtime || cli.preserve_atime {
+                match std::fs::metadata(fpath) {
+                    Ok(x) => Some(x),
+                    Err(e) => {
+                        report_error(ErrorKind::Io, format_args!("Failed to read metadata of '{}': {e}", fpath.display()));
+                        failed += 1;
+                        continue;
                     },
-                    Action::Convert(opt) => {
-                        match convert_tag(&mut tag, *opt) {
-                            Ok(modified) => {
-                                tag_was_modified |= modified;
-                            },
-                            Err(e) => {
-                                eprintln!("rsid3: {e}");
-                                return ExitCode::FAILURE;
-                            },
-                        }
+                }
+            } else {
+                None
+            };
+
+            // Prepare the file before it is first modified, backing it up if requested
+            let mut is_file_modified = false;
+            let mut original_permissions = None;
+            let mut audio_checksum = None;
+            let mut before_write = || -> Result<()> {
+                if !is_file_modified {
+                    if cli.verify_audio {
+                        audio_checksum = Some(audio_md5(fpath)?);
+                    }
+                    if let Some(backup) = &cli.backup {
+                        backup::backup_file(fpath, backup)?;
+                    }
+                    if !cli.no_journal {
+                        journal::record_tag(fpath)?;
+                    }
+                    if is_read_only {
+                        original_permissions = Some(make_file_writable(fpath)?);
+                    }
+                    is_file_modified = true;
+                }
+                Ok(())
+            };
+
+            let mut is_failed = false;
+            match executor.execute_actions(fpath, i, &mut file, &cli.actions, &mut before_write) {
+                Ok(outcome) => {
+                    if outcome.not_found {
+                        result = result.max(Some(Failure::FrameNotFound));
+                    }
+                    if outcome.problems_found {
+                        result = result.max(Some(Failure::ProblemsFound));
+                    }
+                },
+                Err(ExecError::Usage(e)) => {
+                    report_error(ErrorKind::Usage, e);
+                    return Failure::Usage.into();
+                },
+                Err(ExecError::File(e)) => {
+                    report_error(ErrorKind::Io, e);
+                    failed += 1;
+                    is_failed = true;
+                },
+            }
+
+            // Write the tag back to the file, if it was modified
+            set_error_context(Some(fpath), None);
+            if !is_failed {
+                if let Err(e) = executor.write_file_tag(fpath, &mut file, &cli.actions, &mut before_write) {
+                    report_error(ErrorKind::Io, e);
+                    failed += 1;
+                    is_failed = true;
+                }
+            }
+
+            // Make sure that writing the tag left the audio data intact, even if writing failed
+            if let Some(before) = audio_checksum {
+                match audio_md5(fpath) {
+                    Ok(after) if after == before => (),
+                    Ok(after) => {
+                        report_error(ErrorKind::Io, format_args!("Audio data of '{}' changed while writing its tag (MD5 {before} before, {after} after)",
+                            fpath.display()));
+                        failed += 1;
+                        is_failed = true;
                     },
-                    Action::Purge(opt) => {
-                        if match opt {
-                            PurgeOpt::Id3v22 => tag.version() == Version::Id3v22,
-                            PurgeOpt::Id3v23 => tag.version() == Version::Id3v23,
-                            PurgeOpt::Id3v24 => tag.version() == Version::Id3v24,
-                            PurgeOpt::All => true,
-                        } {
-                            match id3::v1v2::remove_from_path(fpath) {
-                                Ok(_) => {
-                                    tag = Tag::with_version(Version::Id3v24);
-                                    tag_was_modified = false;
-                                },
-                                Err(e) => {
-                                    eprintln!("rsid3: Failed to purge the tag of '{fpath}': {e}");
-                                },
-                            }
-                        }
+                    Err(e) => {
+                        report_error(ErrorKind::Io, e);
+                        failed += 1;
+                        is_failed = true;
                     },
                 }
             }
 
-            // Write the tag back to the file, if it was modified
-            if tag_was_modified {
-                if let Err(e) = try_write_tag(&tag, &fpath, tag.version()) {
-                    eprintln!("rsid3: {e}");
-                    return ExitCode::FAILURE;
+            if let (Some(metadata), true, false) = (&metadata, is_file_modified, is_failed) {
+                if let Err(e) = restore_file_times(fpath, metadata, cli.preserve_mtime, cli.preserve_atime) {
+                    report_error(ErrorKind::Io, e);
+                    failed += 1;
+                }
+            }
+
+            // Make the file read-only again, even if writing it failed
+            if let Some(permissions) = original_permissions {
+                if let Err(e) = std::fs::set_permissions(fpath, permissions) {
+                    report_error(ErrorKind::Io, format_args!("Failed to restore permissions of '{}': {e}", fpath.display()));
+                    failed += 1;
                 }
             }
         }
+        result = result.max(finish_files(cli, processed, failed));
     } else /* if cli.actions.is_empty() */ {
         if cli.files.is_empty() {
             Cli::print_usage();
-            return ExitCode::FAILURE;
+            return Failure::Usage.into();
         }
 
         // Print all frames if no options supplied
-        let mut is_first = true;
+        let color = is_color_shown(cli);
+        let (mut processed, mut failed) = (0, 0);
+        let mut progress = Progress::new(cli.files.len(), is_progress_shown(cli, true));
         for fpath in &cli.files {
-            if is_first {
-                is_first = false;
-            } else {
+            if failed > 0 && !cli.keep_going {
+                break;
+            }
+            if processed > 0 {
                 println!();
             }
-            if let Err(e) = print_all_file_frames_pretty(fpath) {
-                eprintln!("rsid3: {e}");
-                return ExitCode::FAILURE;
+            processed += 1;
+            progress.update(processed);
+            set_error_context(Some(fpath), None);
+            let result = print_all_file_frames_pretty(fpath, color, &value_sep, cli.genre_names, cli.describe,
+                cli.show_flags || cli.verbosity > 0, cli.lenient)
+                .and_then(|_| if cli.audio_info { print_audio_info(fpath) } else { Ok(()) });
+            if let Err(e) = result {
+                report_error(ErrorKind::Io, e);
+                failed += 1;
             }
         }
+        result = finish_files(cli, processed, failed);
     }
 
-    ExitCode::SUCCESS
+    result.map_or(0, |x| x as u8)
 }
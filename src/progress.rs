@@ -0,0 +1,80 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use std::io::{stderr, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Minimum number of files for which progress is shown.
+pub const PROGRESS_THRESHOLD: usize = 100;
+/// Minimum time between two redraws of the progress line.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Whether a progress line is currently shown on stderr.
+static IS_SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// Erases the progress line from stderr, if one is shown, so that a message can be printed in its
+/// place. The line is redrawn on the next update.
+pub fn clear_progress() {
+    if IS_SHOWN.swap(false, Ordering::Relaxed) {
+        eprint!("\r\x1b[K");
+    }
+}
+
+/// Shows how many of a number of files were processed, on a single line of stderr.
+/// The line is erased when the progress is dropped.
+pub struct Progress {
+    /// Total number of files, or `None` if progress is not shown.
+    total: Option<usize>,
+    /// Time of the last redraw.
+    last_redraw: Option<Instant>,
+}
+
+impl Progress {
+    /// Starts showing progress of processing `total` files, if `enabled` is set.
+    pub fn new(total: usize, enabled: bool) -> Progress {
+        Progress { total: Some(total).filter(|_| enabled), last_redraw: None }
+    }
+
+    /// Updates the number of processed files. Redraws are throttled, unless all files are done.
+    pub fn update(&mut self, done: usize) {
+        let total = match self.total {
+            Some(x) => x,
+            None => return,
+        };
+        let now = Instant::now();
+        let is_due = self.last_redraw.is_none_or(|x| now.duration_since(x) >= REDRAW_INTERVAL);
+        if !is_due && IS_SHOWN.load(Ordering::Relaxed) && done < total {
+            return;
+        }
+        self.last_redraw = Some(now);
+        eprint!("\r\x1b[Krsid3: {done}/{total} files ({}%)", done * 100 / total.max(1));
+        let _ = stderr().flush();
+        IS_SHOWN.store(true, Ordering::Relaxed);
+    }
+
+    /// Erases the progress line, e.g. before printing results which follow it.
+    pub fn finish(&mut self) {
+        if self.total.take().is_some() {
+            clear_progress();
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
@@ -0,0 +1,156 @@
+// rsid3 - a simple, command line ID3v2 tag editor designed for scripting
+// Copyright (C) 2024  Randoragon
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; version 2 of the License.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+use crate::cli::Cli;
+use rsid3_core::id3_helpers::{format_frame_pretty, frame_to_string};
+use rsid3_core::text_transforms::{frame_value, with_frame_value};
+use crate::unescape;
+use std::env::var_os;
+use std::ffi::{OsStr, OsString};
+use std::fs::{read_to_string, write};
+use std::path::Path;
+use std::process::Command;
+use anyhow::{anyhow, Result};
+use id3::{Frame, Tag, TagLike};
+use tempfile::Builder;
+
+/// Escapes a frame value so that it fits on a single line of a tag document.
+/// Null bytes, which separate multiple values of text frames, are escaped as `\0`.
+fn escape_value(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\0' => result.push_str("\\0"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Checks if a frame is listed as a FRAME=VALUE line of a tag document, rather than as a comment.
+fn is_editable_frame(frame: &Frame) -> bool {
+    (frame.id().starts_with(['T', 'W']) || matches!(frame.id(), "COMM" | "USLT"))
+        && frame_value(frame).is_some()
+}
+
+/// Formats a tag as a document to be edited by the user: one FRAME=VALUE line for each frame with
+/// a text value, with FRAME written like a FRAME command-line argument (e.g. TXXX[Mood]). All other
+/// frames are listed as comments, as they cannot be edited this way.
+pub fn format_tag_document(fpath: &Path, tag: &Tag) -> String {
+    let mut document = format!("\
+# Tag of {} ({}). Each line is FRAME=VALUE, with FRAME written like TIT2,
+# TXXX[DESC] or COMM[DESC](LANG). Edit values, delete lines to delete frames, and add lines to
+# add frames. In values, \\n is a line break, \\0 separates multiple values and \\\\ is a backslash.
+# Saving the document unchanged leaves the tag as it is.
+", fpath.display(), tag.version());
+    let mut kept = vec![];
+    for frame in tag.frames() {
+        match frame_to_string(frame) {
+            Ok(spec) if is_editable_frame(frame) => {
+                document.push_str(&format!("{spec}={}\n", escape_value(frame_value(frame).unwrap_or_default())));
+            },
+            _ => kept.push(format_frame_pretty(frame, false, "\\0", false).unwrap_or_else(|_| frame.id().to_string())),
+        }
+    }
+    if !kept.is_empty() {
+        document.push_str("\n# The following frames cannot be edited here, and are kept as they are:\n");
+        for frame in kept {
+            document.push_str(&format!("# {}\n", frame.replace('\n', "\n# ")));
+        }
+    }
+    document
+}
+
+/// Parses a single FRAME=VALUE line of a tag document into a frame.
+fn parse_document_line(line: &str) -> Result<Frame> {
+    // The FRAME part ends at the first '=' outside of a [DESC] or (LANG)
+    let mut depth = 0;
+    let split = line.char_indices().find(|&(_, c)| {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth -= 1,
+            _ => {},
+        }
+        c == '=' && depth <= 0
+    });
+    let (spec, value) = match split {
+        Some((i, _)) => (&line[..i], &line[i + 1..]),
+        None => return Err(anyhow!("Expected FRAME=VALUE")),
+    };
+    let frame = Cli::frame_query_arg(OsStr::new(spec.trim()))?;
+    let value = unescape(value)?;
+    match with_frame_value(&frame, &value) {
+        Some(x) if is_editable_frame(&x) => Ok(x),
+        _ => Err(anyhow!("{} cannot be edited here", frame.id())),
+    }
+}
+
+/// Applies an edited tag document to the tag from which it was formatted (see
+/// `format_tag_document`). Returns the resulting tag, in which the frames listed in the document
+/// replace all frames that were listed, and the frames that were only commented on are kept.
+pub fn apply_tag_document(tag: &Tag, document: &str) -> Result<Tag> {
+    let mut new_tag = Tag::with_version(tag.version());
+    for frame in tag.frames().filter(|x| !is_editable_frame(x) || frame_to_string(x).is_err()) {
+        new_tag.add_frame(frame.clone());
+    }
+    for (i, line) in document.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let frame = parse_document_line(line).map_err(|e| anyhow!("Line {}: {e}", i + 1))?;
+        new_tag.add_frame(frame);
+    }
+    Ok(new_tag)
+}
+
+/// Returns the command line of the user's editor: $VISUAL, $EDITOR, or vi if neither is set.
+fn editor_command() -> OsString {
+    var_os("VISUAL").filter(|x| !x.is_empty())
+        .or_else(|| var_os("EDITOR").filter(|x| !x.is_empty()))
+        .unwrap_or_else(|| OsString::from("vi"))
+}
+
+/// Lets the user edit a document in their editor, and returns the edited document.
+pub fn edit_document(document: &str) -> Result<String> {
+    let file = Builder::new().prefix("rsid3-").suffix(".txt").tempfile()
+        .map_err(|e| anyhow!("Failed to create a temporary file: {e}"))?;
+    write(file.path(), document)
+        .map_err(|e| anyhow!("Failed to write '{}': {e}", file.path().display()))?;
+
+    // Like git, the editor is run by the shell, so that it can be given with arguments
+    let editor = editor_command();
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(&editor).arg(file.path());
+        command
+    } else {
+        let mut script = editor.clone();
+        script.push(" \"$@\"");
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script).arg(&editor).arg(file.path());
+        command
+    };
+    match command.status() {
+        Ok(status) if status.success() => {},
+        Ok(status) => return Err(anyhow!("Editor '{}' failed with {status}", editor.to_string_lossy())),
+        Err(e) => return Err(anyhow!("Failed to run editor '{}': {e}", editor.to_string_lossy())),
+    }
+    read_to_string(file.path())
+        .map_err(|e| anyhow!("Failed to read '{}': {e}", file.path().display()))
+}
@@ -32,3 +32,1936 @@ fn gets_empty() {
         ": No tag found\n".as_bytes(),
     ].concat());
 }
+
+#[test]
+fn gets_id3v1_fields() {
+    let file = TestFile::id3v1();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--id3v1", "title", "--id3v1", "track", "--id3v1", "genre", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Sample Title\n1\nRock".as_bytes());
+}
+
+#[test]
+fn gets_id3v1_pretty() {
+    let file = TestFile::id3v1();
+    let output = rsid3_run(&[file.path()]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, [
+        file.path().as_os_str().as_encoded_bytes(),
+        ": ID3v1.1:\n".as_bytes(),
+        "title: Sample Title\nartist: Sample Artist\nalbum: Sample Album\nyear: 2000\n".as_bytes(),
+        "comment: Sample Comment\ntrack: 1\ngenre: Rock\n".as_bytes(),
+    ].concat());
+}
+
+#[test]
+fn syncs_id3v1() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TCON=", "(17)", "--sync-id3v1", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--id3v1", "title", "--id3v1", "genre", "--TIT2", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Sample Title\nRock\nSample Title".as_bytes());
+}
+
+#[test]
+fn syncs_id3v1_lossy() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TIT2=", "Zażółć gęślą jaźń — a very long title", "--sync-id3v1",
+        "--id3v1", "title", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Zazólc gesla jazn - a very lon".as_bytes());
+    assert_eq!(output.stderr, [
+        fpath, ": ID3v1 title was transliterated to Latin-1\n",
+        fpath, ": ID3v1 title was truncated to 30 characters\n",
+    ].concat().as_bytes());
+}
+
+#[test]
+fn purges_id3v1_only() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--sync-id3v1", "--purge-id3v1", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&[fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, [fpath, ": ID3v2.4, 1 frame:\nTIT2: Sample Title\n"].concat().as_bytes());
+}
+
+#[test]
+fn purges_ape() {
+    let file = TestFile::ape();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--sync-id3v1", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&[fpath]);
+    assert!(output.stderr.starts_with([fpath, ": Found an APEv2 tag"].concat().as_bytes()));
+    let output = rsid3_run(&["--purge-ape", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&[fpath]);
+    assert!(output.stderr.is_empty());
+    // Only the audio data and the ID3v1 tag should remain
+    let empty_len = std::fs::metadata(TestFile::empty().path()).unwrap().len();
+    assert_eq!(std::fs::metadata(fpath).unwrap().len(), empty_len + 128);
+}
+
+#[test]
+fn sets_and_purges_aiff() {
+    let file = TestFile::aiff();
+    let fpath = file.path().to_str().unwrap();
+    let original = std::fs::read(fpath).unwrap();
+    let output = rsid3_run(&["--TIT2=", "AIFF Title", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2", fpath]);
+    assert_eq!(output.stdout, "AIFF Title".as_bytes());
+    let output = rsid3_run(&["--purge-all", fpath]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(fpath).unwrap(), original);
+}
+
+#[test]
+fn purges_id3_chunk_claiming_to_be_larger_than_file() {
+    let file = TestFile::aiff();
+    let fpath = file.path().to_str().unwrap();
+    let original = std::fs::read(fpath).unwrap();
+    let mut contents = original.clone();
+    contents.extend(b"ID3 \x00\x10\x00\x00ID3\x03\x00\x00\x00\x00\x00\x00");
+    let root_size = u32::from_be_bytes(contents[4..8].try_into().unwrap()) + 18;
+    contents[4..8].copy_from_slice(&root_size.to_be_bytes());
+    std::fs::write(fpath, contents).unwrap();
+    let output = rsid3_run(&["--purge-all", fpath]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(fpath).unwrap(), original);
+}
+
+#[test]
+fn sets_and_purges_wav() {
+    let file = TestFile::wav();
+    let fpath = file.path().to_str().unwrap();
+    let original = std::fs::read(fpath).unwrap();
+    let output = rsid3_run(&["--TIT2=", "WAV Title", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2", fpath]);
+    assert_eq!(output.stdout, "WAV Title".as_bytes());
+    let output = rsid3_run(&["--purge-all", fpath]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(fpath).unwrap(), original);
+}
+
+#[test]
+fn processes_directories_recursively() {
+    let dir = test_dir();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    std::fs::copy(TestFile::tit2().path(), dir.path().join("a.mp3")).unwrap();
+    std::fs::copy(TestFile::tit2().path(), dir.path().join("sub/b.MP3")).unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "not audio").unwrap();
+    let output = rsid3_run(&["--TIT2".as_ref(), dir.path().as_os_str()]);
+    assert!(!output.status.success());
+    let output = rsid3_run(&["-r".as_ref(), "--TIT2".as_ref(), dir.path().as_os_str()]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Sample Title\nSample Title".as_bytes());
+}
+
+#[test]
+fn reads_file_list_from_stdin() {
+    let file1 = TestFile::tit2();
+    let file2 = TestFile::tit2();
+    let stdin = [file1.path().as_os_str().as_encoded_bytes(), b"\0",
+        file2.path().as_os_str().as_encoded_bytes(), b"\0"].concat();
+    let output = rsid3_run_with_stdin(&["--TIT2", "--files-from0", "-"], &stdin);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Sample Title\nSample Title".as_bytes());
+}
+
+#[test]
+fn expands_globs() {
+    let dir = test_dir();
+    std::fs::copy(TestFile::tit2().path(), dir.path().join("a.mp3")).unwrap();
+    std::fs::copy(TestFile::tit2().path(), dir.path().join("b.mp3")).unwrap();
+    let pattern = dir.path().join("*.mp3");
+    let output = rsid3_run(&["--glob".as_ref(), "--TIT2".as_ref(), pattern.as_os_str()]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Sample Title\nSample Title".as_bytes());
+}
+
+#[test]
+fn globs_files_with_brackets_literally() {
+    let dir = test_dir();
+    let fpath = dir.path().join("Song [Live].mp3");
+    std::fs::copy(TestFile::tit2().path(), &fpath).unwrap();
+    let output = rsid3_run(&["--glob".as_ref(), "--TIT2".as_ref(), fpath.as_os_str()]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"Sample Title");
+
+    // Not a valid pattern, but still an existing file
+    let fpath = dir.path().join("Song [Live.mp3");
+    std::fs::copy(TestFile::tit2().path(), &fpath).unwrap();
+    let output = rsid3_run(&["--glob".as_ref(), "--TIT2".as_ref(), fpath.as_os_str()]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"Sample Title");
+
+    let output = rsid3_run(&["--glob".as_ref(), "--TIT2".as_ref(), dir.path().join("[xyz].mp3").as_os_str()]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn writes_in_place_when_tag_fits() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let original_len = std::fs::metadata(fpath).unwrap().len();
+    let output = rsid3_run(&["--TIT2=", "A", "--TPE1=", "Some Artist", fpath]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::metadata(fpath).unwrap().len(), original_len);
+    let output = rsid3_run(&["--TIT2", "--TPE1", fpath]);
+    assert_eq!(output.stdout, "A\nSome Artist".as_bytes());
+
+    // A tag that does not fit in the padding should grow the file instead
+    let long_title = "x".repeat(2000);
+    let output = rsid3_run(&["--TIT2=", &long_title, fpath]);
+    assert!(output.status.success());
+    assert!(std::fs::metadata(fpath).unwrap().len() > original_len);
+    let output = rsid3_run(&["--TIT2", fpath]);
+    assert_eq!(output.stdout, long_title.as_bytes());
+}
+
+#[test]
+fn writes_requested_padding() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let audio_len = std::fs::metadata(TestFile::empty().path()).unwrap().len();
+    // Tag header, then TIT2 frame: 10 bytes of header, 1 byte of encoding, 12 bytes of text
+    let tag_len = 10 + (10 + 1 + 12);
+    let output = rsid3_run(&["--no-padding", "--TIT2=", "Sample Title", fpath]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::metadata(fpath).unwrap().len(), audio_len + tag_len);
+    let output = rsid3_run(&["--padding", "100", "--TIT2=", "Sample Title", fpath]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::metadata(fpath).unwrap().len(), audio_len + tag_len + 100);
+    let output = rsid3_run(&["--TIT2", fpath]);
+    assert_eq!(output.stdout, "Sample Title".as_bytes());
+}
+
+#[test]
+fn prints_tag_info() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--padding", "100", "--TIT2=", "Sample Title", "--TPE1=", "Artist", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--tag-info", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, format!(
+        "{fpath}: ID3v2.4.0, 150 bytes\nflags: none\nTIT2: 23 bytes\nTPE1: 17 bytes\npadding: 100 bytes"
+    ).as_bytes());
+}
+
+#[test]
+fn backs_up_modified_files() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let original = std::fs::read(fpath).unwrap();
+    let backup_path = format!("{fpath}.orig");
+    let output = rsid3_run(&["--backup=.orig", "--TIT2=", "New Title", "--TPE1=", "Artist", fpath]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(&backup_path).unwrap(), original);
+    std::fs::remove_file(&backup_path).unwrap();
+
+    let dir = test_dir();
+    let backup_dir = dir.path().join("backups");
+    let backup_arg = format!("--backup={}/", backup_dir.to_str().unwrap());
+    let output = rsid3_run(&[&backup_arg, "--purge-all", fpath]);
+    assert!(output.status.success());
+    let backup_path = backup_dir.join(file.path().file_name().unwrap());
+    let output = rsid3_run(&["--TIT2", backup_path.to_str().unwrap()]);
+    assert_eq!(output.stdout, "New Title".as_bytes());
+}
+
+#[test]
+fn writes_atomically() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let other_file = TestFile::tit2();
+    let other_fpath = other_file.path().to_str().unwrap();
+    let output = rsid3_run(&["--atomic", "--TIT2=", "New Title", "--TPE1=", "Artist", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2=", "New Title", "--TPE1=", "Artist", other_fpath]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(fpath).unwrap(), std::fs::read(other_fpath).unwrap());
+}
+
+#[test]
+fn preserves_mtime() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let mtime = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+    let times = std::fs::FileTimes::new().set_modified(mtime);
+    std::fs::File::options().write(true).open(fpath).unwrap().set_times(times).unwrap();
+    let output = rsid3_run(&["--preserve-mtime", "--TIT2=", "New Title", fpath]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::metadata(fpath).unwrap().modified().unwrap(), mtime);
+    let output = rsid3_run(&["--TIT2=", "Newer Title", fpath]);
+    assert!(output.status.success());
+    assert_ne!(std::fs::metadata(fpath).unwrap().modified().unwrap(), mtime);
+}
+
+#[cfg(unix)]
+#[test]
+fn handles_non_utf8_paths() {
+    use std::os::unix::ffi::OsStrExt;
+    let dir = test_dir();
+    let fpath = dir.path().join(std::ffi::OsStr::from_bytes(b"sample_\xff.mp3"));
+    std::fs::copy(TestFile::tit2().path(), &fpath).unwrap();
+    let output = rsid3_run(&["--TIT2=".as_ref(), "New Title".as_ref(), fpath.as_os_str()]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2".as_ref(), fpath.as_os_str()]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "New Title".as_bytes());
+}
+
+#[test]
+fn escapes_separators() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--escape-seps", "-d", "\\t", "-D", "\\x1f\\\\", "--TIT2", "--TIT2", fpath, fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Sample Title\tSample Title\x1f\\Sample Title\tSample Title".as_bytes());
+    let output = rsid3_run(&["-d", "\\t", "--TIT2", "--TIT2", fpath]);
+    assert_eq!(output.stdout, "Sample Title\\tSample Title".as_bytes());
+    let output = rsid3_run(&["--escape-seps", "-d", "\\q", "--TIT2", fpath]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn prints_with_filename() {
+    let file1 = TestFile::tit2();
+    let fpath1 = file1.path().to_str().unwrap();
+    let file2 = TestFile::id3v1();
+    let fpath2 = file2.path().to_str().unwrap();
+    let output = rsid3_run(&["-H", "--TIT2", "--id3v1", "title", fpath1, fpath2]);
+    assert_eq!(output.stdout, format!(
+        "{fpath1}: Sample Title\n{fpath1}: \n{fpath2}: \n{fpath2}: Sample Title"
+    ).as_bytes());
+    let output = rsid3_run(&["-H", "--no-filename", "--TIT2", fpath1]);
+    assert_eq!(output.stdout, "Sample Title".as_bytes());
+}
+
+#[test]
+fn prints_missing_placeholder() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--missing", "<none>", "--TIT2", "--TALB", "--id3v1", "title", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.stdout, "Sample Title\n<none>\n<none>".as_bytes());
+}
+
+#[test]
+fn exits_with_failure_class() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TIT2", fpath]);
+    assert_eq!(output.status.code(), Some(0));
+    let output = rsid3_run(&["--TALB", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--no-such-option", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = rsid3_run(&["--TIT2", "tests/samples/nonexistent.mp3"]);
+    assert_eq!(output.status.code(), Some(3));
+    let output = rsid3_run(&["--TIT2", fpath, "tests/samples/nonexistent.mp3"]);
+    assert_eq!(output.status.code(), Some(4));
+}
+
+#[test]
+fn reports_verbosely() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["-v", "--TIT2=", "New Title", "--TPE1=", "Artist", "--TALB", fpath]);
+    assert_eq!(output.stderr, format!(
+        "{fpath}: Overwrote TIT2\n{fpath}: Created TPE1\n{fpath}: Could not print TALB: Frame not found\n\
+         {fpath}: Wrote ID3v2.4 tag with 2 frame(s)\n"
+    ).as_bytes());
+    let output = rsid3_run(&["-vv", "--TIT2", fpath]);
+    assert_eq!(output.stderr, format!(
+        "{fpath}: Read ID3v2.4 tag with 2 frame(s)\n{fpath}: Printed TIT2\n\
+         {fpath}: Tag not modified, nothing written\n"
+    ).as_bytes());
+    let output = rsid3_run(&["-v", "--TIT2", fpath]);
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn colors_pretty_print() {
+    let file = TestFile::comm();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--color=always", fpath]);
+    assert!(output.status.success());
+    assert!(output.stdout.ends_with(
+        "\x1b[1;36mCOMM\x1b[0m[\x1b[33mDescription\x1b[0m](\x1b[33meng\x1b[0m): \x1b[32mSample Content\0\x1b[0m\n".as_bytes()
+    ));
+    // Output is not a terminal, so it is not colored by default
+    let output = rsid3_run(&[fpath]);
+    assert!(!output.stdout.contains(&b'\x1b'));
+}
+
+#[test]
+fn imports_lrc_lyrics() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--lyrics-import", "", "eng", SAMPLE_LRC, fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--USLT", "", "eng", fpath]);
+    assert_eq!(output.stdout, "First line\nChorus\nSecond line\nChorus".as_bytes());
+}
+
+#[test]
+fn exports_lyrics() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--lyrics-export", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--USLT=", "", "eng", "Some lyrics", "--lyrics-export", fpath]);
+    assert!(output.status.success());
+    let dest = file.path().with_extension("txt");
+    assert_eq!(std::fs::read_to_string(&dest).unwrap(), "Some lyrics\n");
+    std::fs::remove_file(&dest).unwrap();
+
+    let dir = test_dir();
+    let template = format!("--lyrics-export={}/{{name}}.lyrics", dir.path().to_str().unwrap());
+    let output = rsid3_run(&[&template, fpath]);
+    assert!(output.status.success());
+    let dest = dir.path().join(file.path().file_stem().unwrap()).with_extension("lyrics");
+    assert_eq!(std::fs::read_to_string(&dest).unwrap(), "Some lyrics\n");
+}
+
+#[test]
+fn sets_and_gets_sylt() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let lrc_arg = format!("@{SAMPLE_LRC}");
+    let output = rsid3_run(&["--SYLT=", "", "eng", &lrc_arg, fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--SYLT", "", "first", fpath]);
+    assert_eq!(output.stdout, "[00:00.50]First line\n[00:02.50]Chorus\n[00:04.50]Second line\n[00:06.50]Chorus".as_bytes());
+    let output = rsid3_run(&[fpath]);
+    assert!(output.stdout.ends_with("SYLT[](eng): Lyrics\n  [00:00.50] First line\n  [00:02.50] Chorus\n  [00:04.50] Second line\n  [00:06.50] Chorus\n".as_bytes()));
+    let output = rsid3_run(&["--SYLT-", "", "eng", "--SYLT", "", "eng", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn edits_chapters() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--chap-add", "ch1", "1:00", "2:30.5", "Second", "--chap-add", "ch0", "0", "60000", "First", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--chapters", fpath]);
+    assert_eq!(output.stdout, "ch0\t00:00:00.000\t00:01:00.000\tFirst\nch1\t00:01:00.000\t00:02:30.500\tSecond".as_bytes());
+    let output = rsid3_run(&["--chap-del", "ch0", "--chapters", fpath]);
+    assert_eq!(output.stdout, "ch1\t00:01:00.000\t00:02:30.500\tSecond".as_bytes());
+    let output = rsid3_run(&[fpath]);
+    assert!(output.stdout.ends_with("CHAP[ch1]: 00:01:00.000-00:02:30.500: Second\n".as_bytes()));
+}
+
+#[test]
+fn edits_tables_of_contents() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--chap-add", "ch1", "60000", "120000", "", "--chap-add", "ch0", "0", "60000", "", "--toc-auto", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--tocs", fpath]);
+    assert_eq!(output.stdout, "toc\ttop-level\tch0,ch1".as_bytes());
+    let output = rsid3_run(&["--toc-set", "main", "ch1", "--tocs", fpath]);
+    assert_eq!(output.stdout, "main\ttop-level\tch1\ntoc\tnested\tch0,ch1".as_bytes());
+    let output = rsid3_run(&["--toc-del", "toc", "--tocs", fpath]);
+    assert_eq!(output.stdout, "main\ttop-level\tch1".as_bytes());
+    let output = rsid3_run(&["--toc-del", "main", "--tocs", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn imports_chapters() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TLEN=", "300000", "--chapters-import", SAMPLE_CUE, "--chapters", "--tocs", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, concat!(
+        "ch0\t00:00:00.000\t00:01:30.493\tIntroduction\n",
+        "ch1\t00:01:30.493\t00:05:00.000\tMain topic\n",
+        "toc\ttop-level\tch0,ch1").as_bytes());
+    let output = rsid3_run(&["--chapters-import", SAMPLE_FFMETADATA, "--chapters", fpath]);
+    assert_eq!(output.stdout, concat!(
+        "ch0\t00:00:00.000\t00:01:30.500\tIntroduction\n",
+        "ch1\t00:01:30.500\t00:45:00.000\tQuestions = answers").as_bytes());
+}
+
+#[test]
+fn exports_chapters() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--chap-add", "ch0", "0", "1:30.5", "Intro \"A\"", "--chap-add", "ch1", "1:30.5", "5:00", "Q=A",
+        "--chapters-export", "ffmetadata", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, concat!(
+        ";FFMETADATA1\n\n",
+        "[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=90500\ntitle=Intro \"A\"\n\n",
+        "[CHAPTER]\nTIMEBASE=1/1000\nSTART=90500\nEND=300000\ntitle=Q\\=A").as_bytes());
+    let output = rsid3_run(&["--chapters-export", "cue", fpath]);
+    let file_name = file.path().file_name().unwrap().to_str().unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), format!(concat!(
+        "FILE \"{}\" MP3\n",
+        "  TRACK 01 AUDIO\n    TITLE \"Intro 'A'\"\n    INDEX 01 00:00:00\n",
+        "  TRACK 02 AUDIO\n    TITLE \"Q=A\"\n    INDEX 01 01:30:37"), file_name));
+}
+
+#[test]
+fn sets_and_gets_popm() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--POPM=", "a@example.com", "196", "12", "--POPM=", "b@example.com", "1", "0", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--POPM", "a@example.com", "--POPM", "b@example.com", fpath]);
+    assert_eq!(output.stdout, "196 12\n1 0".as_bytes());
+    let output = rsid3_run(&["--POPM-", "a@example.com", "--POPM", "a@example.com", "--POPM", "b@example.com", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.stdout, "\n1 0".as_bytes());
+    let output = rsid3_run(&["--POPM=", "a@example.com", "256", "0", fpath]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn sets_and_gets_rating() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--POPM=", "Windows Media Player 9 Series", "0", "7", "--rating=", "4", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--POPM", "Windows Media Player 9 Series", "--rating", "--rating%", fpath]);
+    assert_eq!(output.stdout, "196 7\n4\n77%".as_bytes());
+    let output = rsid3_run(&["--rating-scale", "linear", "--rating", fpath]);
+    assert_eq!(output.stdout, "4".as_bytes());
+    let output = rsid3_run(&["--rating-owner", "me", "--rating=", "60%", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--POPM", "me", "--rating-owner", "me", "--rating", fpath]);
+    assert_eq!(output.stdout, "153 0\n3".as_bytes());
+    let output = rsid3_run(&["--rating=", "6", fpath]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn sets_and_gets_compilation() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--compilation", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"off");
+    let output = rsid3_run(&["--compilation=", "on", "--TCMP", "--compilation", fpath]);
+    assert_eq!(output.stdout, b"1\non");
+    let output = rsid3_run(&["--TCMP=", "0", "--compilation", fpath]);
+    assert_eq!(output.stdout, b"off");
+    let output = rsid3_run(&["--compilation=", "off", "--TCMP", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--compilation=", "yes", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn increments_play_count() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--play-count-inc", "--play-count-inc", "--PCNT", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "2".as_bytes());
+    let output = rsid3_run(&["--PCNT=", "4294967295", "--rating-owner", "me", "--play-count-inc-popm", "--PCNT", "--POPM", "me", fpath]);
+    assert_eq!(output.stdout, "4294967296\n0 1".as_bytes());
+    let output = rsid3_run(&["--PCNT-", "--PCNT", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn sets_and_gets_ufid() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--UFID=", "http://musicbrainz.org", "abc", "--UFID=", "other", "def", "--UFID=", "http://musicbrainz.org", "ghi", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--UFID", "http://musicbrainz.org", "--UFID", "other", fpath]);
+    assert_eq!(output.stdout, "ghi\ndef".as_bytes());
+    let output = rsid3_run(&["--UFID-", "other", "--UFID", "other", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn sets_and_gets_musicbrainz_ids() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--mb-recordingid=", "rec", "--mb-releaseid=", "rel", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--UFID", "http://musicbrainz.org", "--TXXX", "MusicBrainz Album Id", "--mb-recordingid", fpath]);
+    assert_eq!(output.stdout, "rec\nrel\nrec".as_bytes());
+    let output = rsid3_run(&["--mb-releaseid-", "--mb-releaseid", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--mb-foo", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn sets_and_gets_replaygain() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--rg-track-gain=", "-6.5", "--rg-track-peak=", "0.98", "--rg-album-gain=", "+1.234 DB", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TXXX", "REPLAYGAIN_TRACK_GAIN", "--rg-track-peak", "--rg-album-gain", fpath]);
+    assert_eq!(output.stdout, "-6.50 dB\n0.980000\n1.23 dB".as_bytes());
+    let output = rsid3_run(&["--rg-album-gain-", "--rg-album-gain", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    for (arg, value) in [("--rg-track-gain=", "-6.5 dBFS"), ("--rg-track-peak=", "-1"), ("--rg-track-peak=", "1 dB"), ("--rg-foo=", "1")] {
+        let output = rsid3_run(&[arg, value, fpath]);
+        assert_eq!(output.status.code(), Some(2));
+    }
+    let output = rsid3_run(&["--rg-track-gain", fpath]);
+    assert_eq!(output.stdout, b"-6.50 dB");
+}
+
+#[test]
+fn sets_and_gets_priv() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let payload = TestFile::empty();
+    std::fs::write(payload.path(), [0x00, 0xff, 0x10, 0x20]).unwrap();
+    let payload_arg = format!("@{}", payload.path().to_str().unwrap());
+    let output = rsid3_run(&["--PRIV=", "owner", "abc", "--PRIV=", "owner", &payload_arg, "--PRIV=", "other", "text", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--PRIV", "owner", "--PRIV", "other", fpath]);
+    assert_eq!(output.stdout, "00ff1020\n74657874".as_bytes());
+    let output = rsid3_run(&["--binary-format", "base64", "--PRIV", "owner", "--PRIV", "other", fpath]);
+    assert_eq!(output.stdout, "AP8QIA==\ndGV4dA==".as_bytes());
+    let output = rsid3_run(&["--PRIV-", "owner", "--PRIV", "owner", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn embeds_and_extracts_geob() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--geob-embed", "Serato Markers2", "application/octet-stream", SAMPLE_LRC, fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&[fpath]);
+    let expected = format!("GEOB[Serato Markers2]: sample_lyrics.lrc (application/octet-stream), {} bytes\n",
+        std::fs::metadata(SAMPLE_LRC).unwrap().len());
+    assert!(output.stdout.ends_with(expected.as_bytes()));
+
+    let dir = test_dir();
+    let dest = dir.path().join("markers.bin");
+    let output = rsid3_run(&["--geob-extract", "Serato Markers2", dest.to_str().unwrap(), fpath]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(&dest).unwrap(), std::fs::read(SAMPLE_LRC).unwrap());
+    let output = rsid3_run(&["--GEOB-", "Serato Markers2", "--GEOB", "Serato Markers2", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn sets_and_gets_user() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--USER=", "eng", "Old terms", "--USER=", "eng", "Terms of use", "--USER=", "pol", "Warunki użytkowania", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--USER", "eng", "--USER", "pol", "--USER", "first", fpath]);
+    assert_eq!(output.stdout, "Terms of use\nWarunki użytkowania\nTerms of use".as_bytes());
+    let output = rsid3_run(&["--USER-", "eng", "--USER", "eng", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn sets_and_gets_tipl() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TIPL=", "producer", "Old Producer", "--TIPL=", "mix", "Some Engineer", "--TIPL=", "producer", "Some Producer", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIPL", "producer", "--TIPL", "mix", fpath]);
+    assert_eq!(output.stdout, "Some Producer\nSome Engineer".as_bytes());
+    let output = rsid3_run(&["--TIPL-", "mix", "--TIPL", "producer", fpath]);
+    assert_eq!(output.stdout, "Some Producer".as_bytes());
+    let output = rsid3_run(&["--TIPL", "mix", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--TIPL-", "mix", fpath]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Could not delete TIPL[mix]"));
+}
+
+#[test]
+fn sets_and_gets_tmcl() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TMCL=", "violin", "Some Violinist", "--TMCL=", "cello", "Some Cellist", "--TIPL=", "producer", "Some Producer", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TMCL", "violin", "--TMCL", "cello", "--TIPL", "producer", fpath]);
+    assert_eq!(output.stdout, "Some Violinist\nSome Cellist\nSome Producer".as_bytes());
+    let output = rsid3_run(&["--TMCL-", "violin", "--TMCL", "cello", fpath]);
+    assert_eq!(output.stdout, "Some Cellist".as_bytes());
+    let output = rsid3_run(&["--TMCL", "violin", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn edits_multi_valued_text_frames() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TPE1+", "Artist 1", "--TPE1+", "Artist 2", "--TPE1+", "Artist 3", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TPE1", fpath]);
+    assert_eq!(output.stdout, b"Artist 1\0Artist 2\0Artist 3");
+    let output = rsid3_run(&["--TPE1-value", "Artist 2", "--value-sep", "; ", "--TPE1", fpath]);
+    assert_eq!(output.stdout, "Artist 1; Artist 3".as_bytes());
+    let output = rsid3_run(&["--value-sep", "/", "--TCON=", "Synthwave/Electronic", "--TCON+", "Ambient", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--value-sep", ", ", "--TCON", fpath]);
+    assert_eq!(output.stdout, "Synthwave, Electronic, Ambient".as_bytes());
+    let output = rsid3_run(&["--TCON-value", "Rock", fpath]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Could not remove 'Rock' from TCON"));
+}
+
+#[test]
+fn resolves_genre_names() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TCON=", "(17)(4)Eurodisco", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TCON", fpath]);
+    assert_eq!(output.stdout, "(17)(4)Eurodisco".as_bytes());
+    let output = rsid3_run(&["--genre-names", "--value-sep", ", ", "--TCON", fpath]);
+    assert_eq!(output.stdout, "Rock, Eurodisco".as_bytes());
+    let output = rsid3_run(&["--genre-names", "--TCON=", "17", "--TCON+", "(RX)", "--TCON+", "Synthwave", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--value-sep", ", ", "--TCON", fpath]);
+    assert_eq!(output.stdout, "Rock, Remix, Synthwave".as_bytes());
+}
+
+#[test]
+fn uses_frame_aliases() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--artist=", "Some Artist", "--title=", "Some Title", "--year=", "2024", "--comment=", "Some comment", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TPE1", "--TIT2", "--TDRC", "--COMM", "", "eng", fpath]);
+    assert_eq!(output.stdout, "Some Artist\nSome Title\n2024\nSome comment".as_bytes());
+    let output = rsid3_run(&["--id3v2.3", "--year=", "1999", "--TYER", "--year", fpath]);
+    assert_eq!(output.stdout, "1999\n1999".as_bytes());
+    let output = rsid3_run(&["--comment-", "--artist-", "--title", "--artist", fpath]);
+    assert_eq!(output.stdout, "Some Title\n".as_bytes());
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn edits_track_and_disc_numbers() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TRCK=", "07/13", "--disc-total=", "2", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--track-number", "--track-total", "--disc-total", "--TPOS", fpath]);
+    assert_eq!(output.stdout, "7\n13\n2\n/2".as_bytes());
+    let output = rsid3_run(&["--track-number=", "8", "--disc-number=", "1", "--TRCK", "--TPOS", fpath]);
+    assert_eq!(output.stdout, "8/13\n1/2".as_bytes());
+    let output = rsid3_run(&["--track-total-", "--TRCK", "--disc-number", fpath]);
+    assert_eq!(output.stdout, "8\n1".as_bytes());
+    let output = rsid3_run(&["--track-total", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--track-number=", "x", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn numbers_tracks_automatically() {
+    let files = [TestFile::empty(), TestFile::empty(), TestFile::empty()];
+    let fpaths: Vec<_> = files.iter().map(|x| x.path().to_str().unwrap()).collect();
+    let output = rsid3_run(&[&["--auto-track"], &fpaths[..]].concat());
+    assert!(output.status.success());
+    let output = rsid3_run(&[&["--TRCK"], &fpaths[..]].concat());
+    assert_eq!(output.stdout, "1\n2\n3".as_bytes());
+    let output = rsid3_run(&[&["--auto-track=5/auto", "--TRCK"], &fpaths[..]].concat());
+    assert_eq!(output.stdout, "5/7\n6/7\n7/7".as_bytes());
+    let output = rsid3_run(&[&["--auto-track=1/12", "--TRCK"], &fpaths[..2]].concat());
+    assert_eq!(output.stdout, "1/12\n2/12".as_bytes());
+    let output = rsid3_run(&["--auto-track=x", fpaths[0]]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn pads_track_numbers() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--track-pad", "2", "--TRCK=", "7/13", "--disc-number=", "1", "--TRCK", "--TPOS", fpath]);
+    assert_eq!(output.stdout, "07/13\n01".as_bytes());
+    let output = rsid3_run(&["--TRCK=", "007/13", "--TPOS=", "1/2", "--normalize-numbers", "--TRCK", "--TPOS", fpath]);
+    assert_eq!(output.stdout, "07/13\n01/02".as_bytes());
+    let output = rsid3_run(&["--track-pad", "3", "--normalize-numbers", "--TRCK", fpath]);
+    assert_eq!(output.stdout, "007/013".as_bytes());
+}
+
+#[test]
+fn converts_date_frames() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--id3v2.3", "--TYER=", "1999", "--TDAT=", "3112", "--TIME=", "2359", "--TORY=", "1998", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--id3v2.4", "--TDRC", "--TDOR", fpath]);
+    assert_eq!(output.stdout, "1999-12-31T23:59\n1998".as_bytes());
+    let output = rsid3_run(&["--TYER", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--TDRC=", "2001-02-03", "--id3v2.3", "--TYER", "--TDAT", "--TORY", fpath]);
+    assert_eq!(output.stdout, "2001\n0302\n1998".as_bytes());
+    let output = rsid3_run(&["--TIME", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn validates_timestamps() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TDRC=", "2024-02-30T12", fpath]);
+    assert!(output.status.success());
+    for invalid in ["24", "2024-2-3", "2024-13", "2024-01-01 12:00", "2024-01-01T24", "last year"] {
+        let output = rsid3_run(&["--TDRL=", invalid, fpath]);
+        assert_eq!(output.status.code(), Some(2));
+    }
+    let output = rsid3_run(&["--TDRC", fpath]);
+    assert_eq!(output.stdout, "2024-02-30T12".as_bytes());
+    let output = rsid3_run(&["--no-validate", "--year=", "last year", "--TDRC", fpath]);
+    assert_eq!(output.stdout, "last year".as_bytes());
+}
+
+#[test]
+fn validates_languages() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    for lang in ["en", "english", "ENG", "xyz"] {
+        let output = rsid3_run(&["--COMM=", "", lang, "Some comment", fpath]);
+        assert_eq!(output.status.code(), Some(2));
+        let output = rsid3_run(&["--USER=", lang, "Some terms", fpath]);
+        assert_eq!(output.status.code(), Some(2));
+    }
+    let output = rsid3_run(&["--COMM=", "", "XXX", "Some comment", "--USLT=", "", "deu", "Text", "--USER=", "qab", "Terms", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--no-validate", "--COMM=", "", "en", "Some comment", "--COMM", "", "en", fpath]);
+    assert_eq!(output.stdout, "Some comment".as_bytes());
+    // USER frames have no room for longer languages, whether they are validated or not
+    let output = rsid3_run(&["--no-validate", "--USER=", "english", "Some terms", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn checks_tags() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TIT2=", "Title", "--TRCK=", "3/12", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--check", fpath]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let output = rsid3_run(&["--no-validate", "--TRCK=", "seven", "--TDRC=", "2024-13", "--TALB=", " ", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--check", fpath]);
+    assert_eq!(output.status.code(), Some(5));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(&format!("{fpath}: TRCK: Malformed number 'seven'")));
+    assert!(stdout.contains(&format!("{fpath}: TDRC: Malformed timestamp '2024-13'")));
+    assert!(stdout.contains(&format!("{fpath}: TALB: Frame is empty")));
+    assert!(!stdout.contains("TIT2"));
+}
+
+#[test]
+fn fixes_tags() {
+    let file = TestFile::with_raw_frames(&[
+        ("TIT2", "\0Caf\u{e9}".as_bytes()),
+        ("TPE1", b"\0Artist"),
+        ("TPE1", b"\0Other artist"),
+        ("TALB", b"\0 "),
+        ("TCON", b"\0(17)"),
+        ("TRCK", b"\0 3 / 12"),
+    ]);
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--check", fpath]);
+    assert_eq!(output.status.code(), Some(5));
+
+    let output = rsid3_run(&["--fix=empty,numbers", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TALB", "--TRCK", "--TCON", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.stdout, "\n3/12\n(17)".as_bytes());
+
+    let file = TestFile::with_raw_frames(&[
+        ("TIT2", "\0Caf\u{e9}".as_bytes()),
+        ("TPE1", b"\0Artist"),
+        ("TPE1", b"\0Other artist"),
+        ("TCON", b"\0(17)"),
+    ]);
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TIT2", fpath]);
+    assert_eq!(output.stdout, "CafÃ©".as_bytes());
+    let output = rsid3_run(&["--fix", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--value-sep", ";", "--TIT2", "--TPE1", "--TCON", fpath]);
+    assert_eq!(output.stdout, "Café\nArtist;Other artist\nRock".as_bytes());
+    let output = rsid3_run(&["--check", fpath]);
+    assert!(output.status.success());
+
+    let output = rsid3_run(&["--fix=empty,bogus", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn reads_corrupt_tags_leniently() {
+    let file = TestFile::with_raw_frames(&[
+        ("TIT2", b"\0Title"),
+        ("TALB", b"\x09Album"),
+        ("TPE1", b"\0Artist"),
+    ]);
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TIT2", fpath]);
+    assert_eq!(output.status.code(), Some(3));
+
+    let output = rsid3_run(&["--lenient", "--TIT2", "--TPE1", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Title\nArtist".as_bytes());
+    assert_eq!(output.stderr, format!("{fpath}: Skipped corrupt TALB frame\n").as_bytes());
+
+    let output = rsid3_run(&["--lenient", "--fix", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2", "--TPE1", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Title\nArtist".as_bytes());
+}
+
+#[test]
+fn sets_text_encoding() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--encoding", "utf8", "--TIT2=", "Café", "--TPE1=", "Артист", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--check", fpath]);
+    assert!(output.status.success());
+    let contents = std::fs::read(fpath).unwrap();
+    assert!(contents.windows(5).any(|x| x == b"\x03Caf\xc3"));
+
+    let output = rsid3_run(&["--encoding", "latin1", "--id3v2.3", fpath]);
+    assert!(output.status.success());
+    let contents = std::fs::read(fpath).unwrap();
+    assert!(contents.windows(5).any(|x| x == b"\x00Caf\xe9"));
+    assert!(contents.windows(3).any(|x| x == b"\x01\xff\xfe"));
+    let output = rsid3_run(&["--TIT2", "--TPE1", fpath]);
+    assert_eq!(output.stdout, "Café\nАртист".as_bytes());
+
+    let output = rsid3_run(&["--encoding", "utf8", "--TALB=", "Album", fpath]);
+    assert!(output.status.success());
+    let contents = std::fs::read(fpath).unwrap();
+    assert!(!contents.windows(6).any(|x| x == b"\x03Album"));
+    let output = rsid3_run(&["--encoding", "ascii", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn fixes_mojibake() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TIT2=", "CafÃ©", "--TPE1=", "Donâ€™t", "--TALB=", "Ã", "--TXXX=", "Note", "ÃƒÂ©tÃƒÂ©", fpath]);
+    assert!(output.status.success());
+
+    let output = rsid3_run(&["--dry-run", "--fix-encoding", fpath]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Fixed encoding of TIT2: 'CafÃ©' -> 'Café'"));
+    assert!(stderr.contains("Would write"));
+    let output = rsid3_run(&["--TIT2", fpath]);
+    assert_eq!(output.stdout, "CafÃ©".as_bytes());
+
+    let output = rsid3_run(&["--fix-encoding", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2", "--TPE1", "--TALB", "--TXXX", "Note", fpath]);
+    assert_eq!(output.stdout, "Café\nDon’t\nÃ\nété".as_bytes());
+
+    let output = rsid3_run(&["-n", "--purge-all", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2", fpath]);
+    assert_eq!(output.stdout, "Café".as_bytes());
+}
+
+#[test]
+fn normalizes_text() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--normalize", "nfd", "--TIT2=", "Café", "--TXXX=", "Été", "Ångström", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2", "--TXXX", "E\u{301}te\u{301}", fpath]);
+    assert_eq!(output.stdout, "Cafe\u{301}\nA\u{30a}ngstro\u{308}m".as_bytes());
+
+    let output = rsid3_run(&["--normalize-frames", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2", "--TXXX", "Été", fpath]);
+    assert_eq!(output.stdout, "Café\nÅngström".as_bytes());
+    let output = rsid3_run(&["--TXXX", "E\u{301}te\u{301}", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = rsid3_run(&["--normalize", "nfc", "--TALB=", "\u{1112}\u{1161}\u{11ab}", "--TALB", fpath]);
+    assert_eq!(output.stdout, "한".as_bytes());
+    let output = rsid3_run(&["--normalize", "nfkc", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn trims_whitespace() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TPE1=", "  Some \t artist ", "--TXXX=", "Key ", " Some  value ", "--TCON=", " Rock ", "--TCON+", "Pop  ", "--USLT=", "", "eng", "\n First  line \n\n Second line  \n", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--trim", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TPE1", "--TXXX", "Key", "--TCON", "--USLT", "", "eng", fpath]);
+    assert_eq!(output.stdout, "Some artist\nSome value\nRock\0Pop\nFirst line\n\nSecond line".as_bytes());
+    let output = rsid3_run(&["--TXXX", "Key ", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn strips_empty_frames() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TIT2=", "Title", "--TCOM=", "", "--TPE2=", "  ", "--TXXX=", "Key", "", "--WOAR=", " ", "--COMM=", "", "eng", "", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--strip-empty", fpath]);
+    assert!(output.status.success());
+    for (frame, code) in [("--TIT2", 0), ("--TCOM", 1), ("--TPE2", 1), ("--WOAR", 1)] {
+        let output = rsid3_run(&[frame, fpath]);
+        assert_eq!(output.status.code(), Some(code));
+    }
+    let output = rsid3_run(&["--TXXX", "Key", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--COMM", "", "eng", fpath]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn dedupes_frames() {
+    let comment = b"\0engdesc\0Some comment";
+    let file = TestFile::with_raw_frames(&[
+        ("COMM", comment),
+        ("COMM", comment),
+        ("TXXX", b"\0key\0first"),
+        ("COMM", comment),
+        ("TXXX", b"\0key\0second"),
+        ("TXXX", b"\0other\0value"),
+        ("PCNT", b"\0\0\0\x05"),
+        ("PCNT", b"\0\0\0\x07"),
+    ]);
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["-v", "--dedupe", fpath]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Removed 2 duplicate COMM frame(s)"));
+    assert!(stderr.contains("Removed 1 duplicate TXXX frame(s)"));
+    assert!(stderr.contains("Removed duplicate PCNT"));
+    let output = rsid3_run(&["--check", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TXXX", "key", "--TXXX", "other", "--COMM", "desc", "eng", "--PCNT", fpath]);
+    assert_eq!(output.stdout, "second\nvalue\nSome comment\n7".as_bytes());
+}
+
+#[test]
+fn changes_case() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TIT2=", "the END OF the  world as we know it", "--TPE1=", "paul McCartney", "--TALB=", "Some Album",
+        "--TXXX=", "Mood", "Calm", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--titlecase", "TIT2", "--titlecase", "TPE1", "--upper", "TALB", "--lower", "TXXX", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2", "--TPE1", "--TALB", "--TXXX", "Mood", fpath]);
+    assert_eq!(output.stdout, "The End of the  World as We Know It\nPaul McCartney\nSOME ALBUM\ncalm".as_bytes());
+    let output = rsid3_run(&["--upper", "PRIV", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn copies_tags_from_file() {
+    let source = TestFile::empty();
+    let spath = source.path().to_str().unwrap();
+    let target = TestFile::empty();
+    let tpath = target.path().to_str().unwrap();
+    let output = rsid3_run(&["--copy-from", spath, tpath]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = rsid3_run(&["--TIT2=", "Sample Title", "--TPE1=", "Artist", "--id3v2.3", spath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TALB=", "Some Album", tpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--copy-from", spath, tpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2", "--TPE1", tpath]);
+    assert_eq!(output.stdout, "Sample Title\nArtist".as_bytes());
+    let output = rsid3_run(&["--TALB", tpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--tag-info", tpath]);
+    assert!(String::from_utf8(output.stdout).unwrap().starts_with(&format!("{tpath}: ID3v2.3.0")));
+}
+
+#[test]
+fn merges_tags_from_file() {
+    let source = TestFile::empty();
+    let spath = source.path().to_str().unwrap();
+    let target = TestFile::empty();
+    let tpath = target.path().to_str().unwrap();
+    let output = rsid3_run(&["--TIT2=", "Better Title", "--TPE1=", "Artist", "--TXXX=", "Mood", "Calm", spath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2=", "Title", "--TALB=", "Some Album", "--TXXX=", "Tempo", "Slow", tpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--merge-from", spath, tpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2", "--TPE1", "--TALB", "--TXXX", "Mood", "--TXXX", "Tempo", tpath]);
+    assert_eq!(output.stdout, "Title\nArtist\nSome Album\nCalm\nSlow".as_bytes());
+    let output = rsid3_run(&["--merge-overwrite", spath, tpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2", "--TALB", tpath]);
+    assert_eq!(output.stdout, "Better Title\nSome Album".as_bytes());
+}
+
+#[test]
+fn copies_frames_from_file() {
+    let source = TestFile::empty();
+    let spath = source.path().to_str().unwrap();
+    let target = TestFile::empty();
+    let tpath = target.path().to_str().unwrap();
+    let output = rsid3_run(&["--TIT2=", "Sample Title", "--TPE1=", "Artist", "--TXXX=", "Mood", "Calm\nand quiet",
+        "--COMM=", "", "eng", "Comment", spath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2=", "Title", tpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--copy-frame", "TIT2", spath, "--copy-frame", "TXXX[Mood]", spath,
+        "--copy-frame", "COMM[](eng)", spath, tpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2", "--TXXX", "Mood", "--COMM", "", "eng", tpath]);
+    assert_eq!(output.stdout, "Sample Title\nCalm\nand quiet\nComment".as_bytes());
+    let output = rsid3_run(&["--TPE1", tpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--copy-frame", "TXXX[Tempo]", spath, tpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--copy-frame", "TXXX", spath, tpath]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn copies_and_moves_values() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TPE1=", "Artist", "--COMM=", "Subtitle", "eng", "Live", "--WOAR=", "https://example.com", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--copy", "TPE1", "TPE2", "--move", "COMM[Subtitle](eng)", "TXXX[Subtitle]",
+        "--copy", "WOAR", "WXXX[Artist]", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TPE1", "--TPE2", "--TXXX", "Subtitle", "--WXXX", "Artist", fpath]);
+    assert_eq!(output.stdout, "Artist\nArtist\nLive\nhttps://example.com".as_bytes());
+    let output = rsid3_run(&["--COMM", "Subtitle", "eng", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--move", "TIT3", "TIT2", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--copy", "TPE1", "PRIV[owner]", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn diffs_tags() {
+    let file1 = TestFile::empty();
+    let fpath1 = file1.path().to_str().unwrap();
+    let file2 = TestFile::empty();
+    let fpath2 = file2.path().to_str().unwrap();
+    let output = rsid3_run(&["--diff", fpath1, fpath2]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"");
+    let output = rsid3_run(&["--TIT2=", "Title", "--TPE1=", "Artist", "--TXXX=", "Mood", "Calm", fpath1]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TPE1=", "Artist", "--TIT2=", "Other Title", "--TXXX=", "Tempo", "Slow", fpath2]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--diff", fpath1, fpath2]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.stdout, format!(
+        "--- {fpath1}\n+++ {fpath2}\n-TIT2: Title\n+TIT2: Other Title\n-TXXX[Mood]: Calm\n+TXXX[Tempo]: Slow\n"
+    ).as_bytes());
+    let output = rsid3_run(&["--diff", fpath1]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn does_not_clobber_frames() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TPE2=", "Curated Artist", "--TXXX=", "Mood", "Calm", "--TIPL=", "producer", "Producer", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--no-clobber", "--TPE2=", "Various Artists", "--TLAN=", "eng", "--TXXX=", "Mood", "Loud",
+        "--TXXX=", "Tempo", "Slow", "--TIPL=", "producer", "Other", "--TIPL=", "mix", "Engineer",
+        "--copy", "TLAN", "TPE2", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TPE2", "--TLAN", "--TXXX", "Mood", "--TXXX", "Tempo", "--TIPL", "producer", "--TIPL", "mix", fpath]);
+    assert_eq!(output.stdout, "Curated Artist\neng\nCalm\nSlow\nProducer\nEngineer".as_bytes());
+}
+
+#[test]
+fn skips_unchanged_tags() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let backup_path = format!("{fpath}.orig");
+    let output = rsid3_run(&["-v", "--backup=.orig", "--TIT2=", "Sample Title", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stderr, format!("{fpath}: Overwrote TIT2\n{fpath}: Tag unchanged, nothing written\n").as_bytes());
+    assert!(!std::path::Path::new(&backup_path).exists());
+    let output = rsid3_run(&["-v", "--TIT2=", "New Title", "--TIT2=", "Sample Title", fpath]);
+    assert!(String::from_utf8(output.stderr).unwrap().ends_with("Tag unchanged, nothing written\n"));
+    let output = rsid3_run(&["-v", "--TIT2=", "New Title", fpath]);
+    assert!(String::from_utf8(output.stderr).unwrap().ends_with("Wrote ID3v2.4 tag with 1 frame(s)\n"));
+}
+
+#[test]
+fn filters_files_by_expression() {
+    let file1 = TestFile::empty();
+    let fpath1 = file1.path().to_str().unwrap();
+    let file2 = TestFile::empty();
+    let fpath2 = file2.path().to_str().unwrap();
+    let output = rsid3_run(&["--TPE1=", "Nirvana", "--TCON=", "Rock", "--TBPM=", "120", fpath1]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TPE1=", "Nirvana", "--TCON=", "Live", "--TXXX=", "Mood", "Calm", fpath2]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--where", "TPE1 == \"Nirvana\" && TCON != 'Live'", "--TPE2=", "Band", fpath1, fpath2]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TPE2", fpath1]);
+    assert_eq!(output.stdout, "Band".as_bytes());
+    let output = rsid3_run(&["--TPE2", fpath2]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["-H", "--TPE1", "--where", "TBPM > 99 || (TXXX[Mood] && !TBPM)", "--TCON", fpath1, fpath2]);
+    assert_eq!(output.stdout, format!("{fpath1}: Nirvana\n{fpath1}: Rock\n{fpath2}: Nirvana\n{fpath2}: Live").as_bytes());
+    let output = rsid3_run(&["-H", "--where", "TBPM < 99", "--TPE1", fpath1, fpath2]);
+    assert_eq!(output.stdout, b"");
+    let output = rsid3_run(&["--where", "TPE1 ==", "--TPE1", fpath1]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn greps_files() {
+    let file1 = TestFile::empty();
+    let fpath1 = file1.path().to_str().unwrap();
+    let file2 = TestFile::empty();
+    let fpath2 = file2.path().to_str().unwrap();
+    let file3 = TestFile::empty();
+    let fpath3 = file3.path().to_str().unwrap();
+    let output = rsid3_run(&["--COMM=", "", "eng", "Ripped by someone", "--TDRC=", "1994", fpath1]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--COMM=", "", "eng", "Bought on CD", "--TDRC=", "2003", "--TPE1+", "A", "--TPE1+", "B", fpath2]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--grep", "COMM[](eng)", "(?i)ripped by", fpath1, fpath2, fpath3]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, fpath1.as_bytes());
+    let output = rsid3_run(&["-0D", "--grep", "TDRC", "^(19[89]\\d|20[0-2]\\d)$", fpath1, fpath2, fpath3]);
+    assert_eq!(output.stdout, format!("{fpath1}\0{fpath2}").as_bytes());
+    let output = rsid3_run(&["--grep", "TPE1", "^B$", fpath1, fpath2, fpath3]);
+    assert_eq!(output.stdout, fpath2.as_bytes());
+    let output = rsid3_run(&["--grep", "TPE1", "^[^AB]+$", fpath1, fpath2, fpath3]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.stdout, b"");
+    let output = rsid3_run(&["--grep", "TPE1", "(unclosed", fpath1]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn greps_long_values_in_linear_time() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TIT2=", &"x".repeat(30_000), "--TPE1=", &"x".repeat(26), fpath]);
+    assert!(output.status.success());
+    let start = std::time::Instant::now();
+    for (frame, pattern) in [("TIT2", "x.*y"), ("TIT2", "(x*)*y"), ("TPE1", "(x+x+)+y"), ("TPE1", "^(x|xx)+$y")] {
+        let output = rsid3_run(&["--grep", frame, pattern, fpath]);
+        assert_eq!(output.status.code(), Some(1), "{pattern}");
+    }
+    let output = rsid3_run(&["--grep", "TIT2", "^x{30000}$", fpath]);
+    assert_eq!(output.stdout, fpath.as_bytes());
+    assert!(start.elapsed().as_secs() < 10);
+    let output = rsid3_run(&["--grep", "TPE1", "(unclosed", fpath]);
+    assert_eq!(output.stderr, b"rsid3: Invalid regular expression '(unclosed': unclosed group, try 'rsid3 --help'\n");
+}
+
+#[test]
+fn finds_duplicate_files() {
+    let files = (0..4).map(|_| TestFile::empty()).collect::<Vec<_>>();
+    let fpaths = files.iter().map(|x| x.path().to_str().unwrap()).collect::<Vec<_>>();
+    for (fpath, artist, title) in [(fpaths[0], "Artist", "Song"), (fpaths[1], "Other", "Song"),
+        (fpaths[2], "artist ", "SONG"), (fpaths[3], "Other", "Another Song")] {
+        let output = rsid3_run(&["--TPE1=", artist, "--TIT2=", title, "--TXXX=", "Source, Quality", "CD", fpath]);
+        assert!(output.status.success());
+    }
+    let output = rsid3_run(&[&["--find-duplicates", "TPE1,TIT2"], &fpaths[..]].concat());
+    assert!(output.status.success());
+    assert_eq!(output.stdout, format!("{}\n{}", fpaths[0], fpaths[2]).as_bytes());
+    let output = rsid3_run(&[&["-0D", "--find-duplicates", "TIT2,TXXX[Source, Quality]"], &fpaths[..]].concat());
+    assert_eq!(output.stdout, format!("{}\0{}\0{}", fpaths[0], fpaths[1], fpaths[2]).as_bytes());
+    let output = rsid3_run(&[&["-0D", "--find-duplicates", "TPE1"], &fpaths[..]].concat());
+    assert_eq!(output.stdout, format!("{}\0{}\0\0{}\0{}", fpaths[0], fpaths[2], fpaths[1], fpaths[3]).as_bytes());
+    let output = rsid3_run(&[&["--find-duplicates", "TPE1,TIT2,TALB"], &fpaths[..]].concat());
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn runs_batch_commands() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let commands = format!("--TIT2= 'Title \"1\"' --TPE1= \"Some \\\"Artist\\\"\" {fpath}\n\n--TIT2 --TPE1 {fpath}\n--TALB {fpath}\n--bogus\n");
+    let output = rsid3_run_with_stdin(&["--batch"], commands.as_bytes());
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(output.stdout, "\nexit 0\nTitle \"1\"\nSome \"Artist\"\nexit 0\n\nexit 1\n\nexit 2\n".as_bytes());
+    let commands = format!("--TIT2 {fpath}\0--TPE1 {fpath}\0");
+    let output = rsid3_run_with_stdin(&["--batch0", "-H"], commands.as_bytes());
+    assert!(output.status.success());
+    assert_eq!(output.stdout, format!("{fpath}: Title \"1\"\0exit 0\0{fpath}: Some \"Artist\"\0exit 0\0").as_bytes());
+}
+
+#[test]
+fn reports_errors_as_json() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let escaped = fpath.replace('\\', "\\\\");
+    let output = rsid3_run(&["--errors", "json", "--TIT2", "--TPE1=", "Artist", "--TPE1", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.stdout, "\nArtist".as_bytes());
+    assert_eq!(String::from_utf8(output.stderr).unwrap(),
+        format!("{{\"file\":\"{escaped}\",\"action\":\"print\",\"kind\":\"not-found\",\"message\":\"Could not print TIT2: Frame not found\"}}\n"));
+    let output = rsid3_run(&["--errors", "json", "--bogus", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().starts_with("{\"file\":null,\"action\":null,\"kind\":\"usage\",\"message\":"));
+    let output = rsid3_run(&["--TIT2", fpath]);
+    assert_eq!(output.stderr, format!("{fpath}: Could not print TIT2: Frame not found\n").as_bytes());
+}
+
+#[test]
+fn keeps_going_after_failed_files() {
+    let file1 = TestFile::tit2();
+    let file2 = TestFile::tit2();
+    let fpath1 = file1.path().to_str().unwrap();
+    let fpath2 = file2.path().to_str().unwrap();
+    let missing = "tests/samples/nonexistent.mp3";
+    let output = rsid3_run(&["--TIT2", fpath1, missing, fpath2]);
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(output.stdout, "Sample Title".as_bytes());
+    assert!(String::from_utf8(output.stderr).unwrap().ends_with("rsid3: Stopped after a failure, 1 file(s) not processed (pass --keep-going to process them)\n"));
+    let output = rsid3_run(&["--keep-going", "--TIT2", fpath1, missing, fpath2]);
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(output.stdout, "Sample Title\nSample Title".as_bytes());
+    assert!(String::from_utf8(output.stderr).unwrap().ends_with("rsid3: 1 of 3 file(s) failed\n"));
+    let output = rsid3_run(&["-k", "--TIT2", missing, missing]);
+    assert_eq!(output.status.code(), Some(3));
+    let output = rsid3_run(&["-k", "--fail-fast", "--TIT2", missing, fpath1]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn shows_no_progress_outside_terminals() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let mut args = vec!["--TIT2=", "Title"];
+    args.extend(std::iter::repeat_n(fpath, 150));
+    let output = rsid3_run(&args);
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    args.insert(0, "--quiet");
+    let output = rsid3_run(&args);
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn reads_config_file() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let dir = test_dir();
+    std::fs::create_dir(dir.path().join("rsid3")).unwrap();
+    let config = dir.path().join("rsid3/config.toml");
+    std::fs::write(&config, "# Shared defaults\nframe_sep = '|'\ndefault_version = 2.3\n").unwrap();
+    let env = [("XDG_CONFIG_HOME", dir.path())];
+    let output = rsid3_run_with_env(&["--TIT2=", "Title", "--TPE1=", "Artist", "--TIT2", "--TPE1", fpath], &env);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Title|Artist".as_bytes());
+    let output = rsid3_run_with_env(&["-d", ", ", "--TIT2", "--TPE1", fpath], &env);
+    assert_eq!(output.stdout, "Title, Artist".as_bytes());
+    let output = rsid3_run_with_env(&["-0d", "--TIT2", "--TPE1", fpath], &env);
+    assert_eq!(output.stdout, "Title\0Artist".as_bytes());
+    let output = rsid3_run_with_env(&["--no-config", "--TIT2", "--TPE1", fpath], &env);
+    assert_eq!(output.stdout, "Title\nArtist".as_bytes());
+    let output = rsid3_run(&["--tag-info", fpath]);
+    assert!(String::from_utf8(output.stdout).unwrap().contains("ID3v2.3"));
+
+    std::fs::write(&config, "frame_sep = 1\n").unwrap();
+    let output = rsid3_run_with_env(&["--TIT2", fpath], &env);
+    assert_eq!(output.status.code(), Some(2));
+    std::fs::write(&config, "colour = 'never'\n").unwrap();
+    let output = rsid3_run_with_env(&["--TIT2", fpath], &env);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Unknown option: 'colour'"));
+}
+
+#[test]
+fn reads_environment_overrides() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let dir = test_dir();
+    std::fs::create_dir(dir.path().join("rsid3")).unwrap();
+    std::fs::write(dir.path().join("rsid3/config.toml"), "frame_sep = '|'\nfile_sep = '|'\n").unwrap();
+    let config_home = dir.path().to_str().unwrap();
+    let env = [("XDG_CONFIG_HOME", config_home), ("RSID3_FRAME_SEP", ";"), ("RSID3_DEFAULT_VERSION", "2.3")];
+    let output = rsid3_run_with_env(&["--TIT2=", "Title", "--TPE1=", "Artist", "--TIT2", "--TPE1", fpath, fpath], &env);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Title;Artist|Title;Artist".as_bytes());
+    let output = rsid3_run_with_env(&["-d", ",", "--TIT2", "--TPE1", fpath], &env);
+    assert_eq!(output.stdout, "Title,Artist".as_bytes());
+    let output = rsid3_run(&["--tag-info", fpath]);
+    assert!(String::from_utf8(output.stdout).unwrap().contains("ID3v2.3"));
+    let output = rsid3_run_with_env(&["--TIT2", fpath], &[("RSID3_COLOR", "sometimes")]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Invalid RSID3_COLOR: Invalid color mode: 'sometimes'"));
+}
+
+#[test]
+fn uses_user_aliases() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--alias", "mood=TXXX[MOOD]", "--mood=", "Calm", "--mood", "--TXXX", "MOOD", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Calm\nCalm".as_bytes());
+    let output = rsid3_run(&["--alias", "mood=TXXX[MOOD]", "--mood-", "--mood", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let dir = test_dir();
+    std::fs::create_dir(dir.path().join("rsid3")).unwrap();
+    std::fs::write(dir.path().join("rsid3/config.toml"), "[aliases]\nnote = 'COMM[Note](eng)'\n").unwrap();
+    let env = [("XDG_CONFIG_HOME", dir.path())];
+    let output = rsid3_run_with_env(&["--note=", "Live", "--COMM", "Note", "eng", fpath], &env);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Live".as_bytes());
+    let output = rsid3_run_with_env(&["--alias", "note=TIT2", "--note", fpath], &env);
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = rsid3_run(&["--alias", "pop=POPM[someone]", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = rsid3_run(&["--alias", "mood", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn rejects_aliases_shadowing_options() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    for def in ["help=TXXX[x]", "rating=TXXX[RATING]", "help-frame=TIT2", "TIT2=TXXX[x]", "ABCD=TIT2", "title=TIT2", "mb-artistid=TIT2"] {
+        let output = rsid3_run(&["--alias", def, "--help"]);
+        assert_eq!(output.status.code(), Some(2), "{def}");
+        assert!(String::from_utf8(output.stderr).unwrap().contains("already an option"), "{def}");
+    }
+    // Names which only share a prefix with an option are fine
+    let output = rsid3_run(&["--alias", "hel=TIT2", "--hel", fpath]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn prints_completions() {
+    let output = rsid3_run(&["--completions", "bash"]);
+    assert!(output.status.success());
+    let script = String::from_utf8(output.stdout).unwrap();
+    assert!(script.contains("complete -o bashdefault -o default -F _rsid3 rsid3"));
+    assert!(script.contains(" --TIT2 --TIT2- --TIT2= --TIT2+ --TIT2-value "));
+    assert!(script.contains("--encoding) COMPREPLY=($(compgen -W 'latin1 utf16 utf16be utf8' -- \"$cur\")); return;;"));
+    let output = rsid3_run(&["--completions", "zsh"]);
+    assert!(output.status.success());
+    let script = String::from_utf8(output.stdout).unwrap();
+    assert!(script.starts_with("#compdef rsid3\n"));
+    assert!(script.contains("'--MCDI:Binary dump of CD'\\''s TOC'"));
+    let output = rsid3_run(&["--completions", "fish"]);
+    assert!(output.status.success());
+    let script = String::from_utf8(output.stdout).unwrap();
+    assert!(script.contains("complete -c rsid3 -n __rsid3_completing_option -a '--title='\n"));
+    assert!(script.contains("complete -c rsid3 -l errors -x -a 'text json'\n"));
+    let output = rsid3_run(&["--completions", "tcsh"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn edits_tags_interactively() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let commands = "a TPE1 Some Artist\nq\nd 9\ne 1 New Title\nw\nd 2\nq!\n";
+    let output = rsid3_run_with_stdin(&["--interactive", fpath], commands.as_bytes());
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with(&format!("{fpath}: ID3v2.4, 1 frame:\n  1  TIT2: Sample Title\n{fpath}> ")));
+    assert!(stdout.contains("  1  TIT2: New Title\n  2  TPE1: Some Artist\n"));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("There are unsaved changes"));
+    assert!(stderr.contains("No such frame: '9'"));
+    let output = rsid3_run(&["--TIT2", "--TPE1", fpath]);
+    assert_eq!(output.stdout, "New Title\nSome Artist".as_bytes());
+
+    // Unsaved changes are discarded at the end of input
+    let output = rsid3_run_with_stdin(&["--interactive", fpath], b"d 1\n");
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Unsaved changes discarded"));
+    let output = rsid3_run(&["--TIT2", fpath]);
+    assert_eq!(output.stdout, b"New Title");
+    let output = rsid3_run(&["--interactive", "--TIT2", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn edits_tags_in_editor() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let editor = "sed -i -e 's/^TIT2=.*/TIT2=New Title/' -e '$a TPE1=One\\\\0Two' -e '$a TXXX[Mood]=Calm'";
+    let output = rsid3_run_with_env(&["--edit", fpath], &[("VISUAL", ""), ("EDITOR", editor)]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--value-sep", "/", "--TIT2", "--TPE1", "--TXXX", "Mood", fpath]);
+    assert_eq!(output.stdout, "New Title\nOne/Two\nCalm".as_bytes());
+
+    // Deleted lines delete frames, and invalid lines are rejected without writing anything
+    let output = rsid3_run_with_env(&["--edit", fpath], &[("VISUAL", ""), ("EDITOR", "sed -i -e '/^TIT2/d' -e '$a bogus'")]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Expected FRAME=VALUE"));
+    let output = rsid3_run_with_env(&["--edit", fpath], &[("VISUAL", ""), ("EDITOR", "sed -i -e '/^TIT2/d'")]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2", "--TXXX", "Mood", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.stdout, "\nCalm".as_bytes());
+}
+
+#[test]
+fn undoes_modifications() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    assert!(rsid3_run(&["--TIT2=", "New Title", fpath]).status.success());
+    assert!(rsid3_run(&["--TPE1=", "Some Artist", fpath]).status.success());
+    assert!(rsid3_run(&["--no-journal", "--TALB=", "Some Album", fpath]).status.success());
+    assert!(rsid3_run(&["--purge-all", fpath]).status.success());
+
+    let output = rsid3_run(&["--undo", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--TIT2", "--TPE1", "--TALB", fpath]);
+    assert_eq!(output.stdout, "New Title\nSome Artist\nSome Album".as_bytes());
+    assert!(rsid3_run(&["--undo", fpath]).status.success());
+    let output = rsid3_run(&["--TIT2", "--TPE1", fpath]);
+    assert_eq!(output.stdout, "New Title\n".as_bytes());
+    assert!(rsid3_run(&["--undo", fpath]).status.success());
+    let output = rsid3_run(&["--TIT2", fpath]);
+    assert_eq!(output.stdout, b"Sample Title");
+    let output = rsid3_run(&["--undo", fpath]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Nothing to undo"));
+}
+
+#[test]
+fn lists_frames_as_json() {
+    let output = rsid3_run(&["-L", "json"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("[\n"));
+    assert!(stdout.ends_with("\n]\n"));
+    assert!(stdout.contains(r#"  {"id": "COMM", "description": "User comment (DESC, LANG, TEXT)", "params": ["DESC", "LANG", "TEXT"], "writable": true, "versions": ["2.2", "2.3", "2.4"]},"#));
+    assert!(stdout.contains(r#"  {"id": "TDRC", "description": "Recording time", "params": ["TEXT"], "writable": true, "versions": ["2.4"]},"#));
+    assert!(stdout.contains(r#"  {"id": "APIC", "description": "Attached (or linked) picture", "params": [], "writable": false, "versions": ["2.2", "2.3", "2.4"]},"#));
+    let output = rsid3_run(&["--output", "json", "--list-frames"]);
+    assert_eq!(output.stdout, stdout.as_bytes());
+    let output = rsid3_run(&["--list-frames", "--output", "yaml"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn lists_frames_of_version() {
+    let output = rsid3_run(&["-L", "--id3v2.3"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\nTYER\tYear of recording\n"));
+    assert!(stdout.contains("\nTIME\tTime of recording (HHMM)\n"));
+    assert!(!stdout.contains("\nTDRC\t"));
+    assert!(!stdout.contains("\nTIPL\t"));
+    let output = rsid3_run(&["-L", "json", "--id3v2.4"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(r#""id": "TDRC""#));
+    assert!(!stdout.contains(r#""id": "TYER""#));
+    let output = rsid3_run(&["--id3v2.2", "--list-frames"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("\nCHAP\t"));
+    assert!(stdout.contains("\nTYER\t"));
+}
+
+#[test]
+fn prints_frame_help() {
+    let output = rsid3_run(&["--help-frame", "COMM"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("COMM: User comment (DESC, LANG, TEXT)\nDefined in: ID3v2.2, ID3v2.3, ID3v2.4\n"));
+    assert!(stdout.contains("  rsid3 --COMM= DESC LANG TEXT FILE...    Set the frame\n"));
+    assert!(stdout.contains("  LANG        ISO 639-2/T language code, e.g. eng, or XXX if unknown\n"));
+    assert!(stdout.ends_with("  rsid3 --COMM= '' eng 'Some text' file.mp3\n"));
+    let output = rsid3_run(&["--help-frame", "tdrc"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Defined in: ID3v2.4\n"));
+    let output = rsid3_run(&["--help-frame", "APIC"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("--APIC="));
+    let output = rsid3_run(&["--help-frame", "ABCD"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn describes_frames_in_summaries() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TPE1=", "Nirvana", "--COMM=", "", "eng", "Grunge", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--describe", fpath]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\nTPE1 (Lead artist/performer/soloist/group): Nirvana\n"));
+    assert!(stdout.contains("\nCOMM (User comment)[](eng): Grunge\n"));
+    let output = rsid3_run(&[fpath]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\nTPE1: Nirvana\n"));
+}
+
+#[test]
+fn shows_frame_flags() {
+    let file = TestFile::with_raw_flagged_frames(&[
+        ("TIT2", [0xa0, 0x00], b"\x00Lithium"),
+        ("TPE1", [0x00, 0x00], b"\x00Nirvana"),
+    ]);
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--show-flags", fpath]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\nTIT2: Lithium\n  flags: tag alter preservation, read-only\n"));
+    assert!(stdout.contains("\nTPE1: Nirvana\n  flags: none\n"));
+    let output = rsid3_run(&["-v", fpath]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\nTIT2: Lithium\n  flags: tag alter preservation, read-only\n"));
+    let output = rsid3_run(&[fpath]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("flags:"));
+}
+
+#[test]
+fn sets_frame_flags() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TIT2=", "Lithium", "--TPE1=", "Nirvana", "--flag", "TIT2", "+read_only", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--show-flags", fpath]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\nTIT2: Lithium\n  flags: read-only\n"));
+    assert!(stdout.contains("\nTPE1: Nirvana\n  flags: none\n"));
+
+    let output = rsid3_run(&["--flag", "TIT2", "-read_only", "--flag", "TPE1", "+file_alter_preserve", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--show-flags", fpath]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\nTIT2: Lithium\n  flags: none\n"));
+    assert!(stdout.contains("\nTPE1: Nirvana\n  flags: file alter preservation\n"));
+
+    let output = rsid3_run(&["--flag", "TALB", "+read_only", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--flag", "TIT2", "+compression", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn handles_read_only_files() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TIT2=", "Lithium", fpath]);
+    assert!(output.status.success());
+    let mut permissions = std::fs::metadata(fpath).unwrap().permissions();
+    permissions.set_readonly(true);
+    std::fs::set_permissions(fpath, permissions).unwrap();
+
+    // Fails before printing anything
+    let output = rsid3_run(&["--TIT2", "--TIT2=", "Breed", fpath]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("read-only"));
+    let output = rsid3_run(&["--TIT2", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"Lithium");
+
+    let output = rsid3_run(&["--force-write", "--TIT2=", "Breed", fpath]);
+    assert!(output.status.success());
+    assert!(std::fs::metadata(fpath).unwrap().permissions().readonly());
+    let output = rsid3_run(&["--TIT2", fpath]);
+    assert_eq!(output.stdout, b"Breed");
+}
+
+#[test]
+fn pipes_file_through_stdin() {
+    let input = TestFile::empty();
+    let audio = std::fs::read(input.path()).unwrap();
+    let output = rsid3_run_with_stdin(&["--stdin", "--TIT2=", "Lithium", "-"], &audio);
+    assert!(output.status.success());
+    assert!(output.stdout.ends_with(&audio));
+    let file = TestFile::empty();
+    std::fs::write(file.path(), &output.stdout).unwrap();
+    let output = rsid3_run(&["--TIT2", file.path().to_str().unwrap()]);
+    assert_eq!(output.stdout, b"Lithium");
+
+    // Chunk-based formats are detected by their magic bytes
+    let input = TestFile::wav();
+    let output = rsid3_run_with_stdin(&["--stdin", "--TPE1=", "Nirvana", "-"], &std::fs::read(input.path()).unwrap());
+    assert!(output.status.success());
+    let file = TestFile::wav();
+    std::fs::write(file.path(), &output.stdout).unwrap();
+    let output = rsid3_run(&["--TPE1", file.path().to_str().unwrap()]);
+    assert_eq!(output.stdout, b"Nirvana");
+
+    // Printing would mix with the file on stdout
+    let output = rsid3_run_with_stdin(&["--stdin", "--TIT2", "-"], &audio);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    let output = rsid3_run_with_stdin(&["--TIT2=", "Lithium", "-"], &audio);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn pipes_long_files_with_undeclared_padding() {
+    // Zero bytes past the tag's declared size are padding, and the audio data after them is
+    // passed through as it is, however long it is
+    let input = TestFile::tit2();
+    let bytes = std::fs::read(input.path()).unwrap();
+    let tag_size = 10 + bytes[6..10].iter().fold(0, |size, &x| size << 7 | x as usize);
+    let mut audio = bytes[tag_size..].to_vec();
+    while audio.len() < 1 << 22 {
+        audio.extend_from_within(..);
+    }
+    let mut file = bytes[..tag_size].to_vec();
+    file.extend(vec![0; 10000]);
+    file.extend(&audio);
+    let output = rsid3_run_with_stdin(&["--stdin", "--TIT2=", "Lithium", "-"], &file);
+    assert!(output.status.success());
+    assert!(output.stdout.ends_with(&audio));
+    let file = TestFile::empty();
+    std::fs::write(file.path(), &output.stdout).unwrap();
+    let output = rsid3_run(&["--TIT2", file.path().to_str().unwrap()]);
+    assert_eq!(output.stdout, b"Lithium");
+}
+
+#[test]
+fn dumps_and_restores_raw_tags() {
+    let dir = test_dir();
+    let dump = dir.path().join("tag.bin");
+    let dump = dump.to_str().unwrap();
+    let source = TestFile::tit2();
+    let output = rsid3_run(&["--dump-tag", dump, source.path().to_str().unwrap()]);
+    assert!(output.status.success());
+    let bytes = std::fs::read(dump).unwrap();
+    assert!(bytes.starts_with(b"ID3"));
+
+    // Restored byte for byte, replacing any other tag
+    for file in [TestFile::empty(), TestFile::comm(), TestFile::wav()] {
+        let fpath = file.path().to_str().unwrap();
+        let output = rsid3_run(&["--restore-tag", dump, fpath]);
+        assert!(output.status.success());
+        let redump = dir.path().join("redump.bin");
+        let output = rsid3_run(&["--dump-tag", redump.to_str().unwrap(), fpath]);
+        assert!(output.status.success());
+        assert_eq!(std::fs::read(&redump).unwrap(), bytes);
+        let output = rsid3_run(&["--TIT2", fpath]);
+        assert_eq!(output.stdout, rsid3_run(&["--TIT2", source.path().to_str().unwrap()]).stdout);
+    }
+
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--dump-tag", dump, fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--restore-tag", fpath, fpath]);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn prints_raw_frames() {
+    let file = TestFile::with_raw_frames(&[("TIT2", b"\x00Lithium"), ("XYZW", b"\x01\x02\x03")]);
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--raw", "TIT2", fpath]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(),
+        "00000000  54 49 54 32 00 00 00 08  00 00 00 4c 69 74 68 69  |TIT2.......Lithi|\n\
+         00000010  75 6d                                             |um|");
+    let output = rsid3_run(&["--raw-binary", "TIT2", fpath]);
+    assert_eq!(output.stdout, b"TIT2\x00\x00\x00\x08\x00\x00\x00Lithium");
+    let output = rsid3_run(&["--raw-binary", "XYZW", fpath]);
+    assert_eq!(output.stdout, b"XYZW\x00\x00\x00\x03\x00\x00\x01\x02\x03");
+    let output = rsid3_run(&["--raw", "TPE1", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn prints_binary_frames_raw() {
+    let png = b"\x89PNG\r\n\x1a\n\x00\x00";
+    let mut apic = b"\x00image/png\x00\x03\x00".to_vec();
+    apic.extend(png);
+    let file = TestFile::with_raw_frames(&[("APIC", &apic), ("MCDI", b"\x00\x01\xff")]);
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--binary", "--APIC", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, png);
+    let output = rsid3_run(&["--binary", "--MCDI", fpath]);
+    assert_eq!(output.stdout, b"\x00\x01\xff");
+
+    let output = rsid3_run(&["--PRIV=", "owner", "\u{1}bin", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--binary-format", "raw", "--PRIV", "owner", fpath]);
+    assert_eq!(output.stdout, b"\x01bin");
+    let output = rsid3_run(&["--PRIV", "owner", fpath]);
+    assert_eq!(output.stdout, b"0162696e");
+}
+
+#[test]
+fn prints_event_timing_codes() {
+    let file = TestFile::with_raw_frames(&[
+        ("ETCO", b"\x02\x02\x00\x00\x00\x00\x10\x00\x00\x30\xd4\xe3\x00\x00\x75\x30\x04\x00\x36\xee\x80"),
+    ]);
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--ETCO", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"00:00:00.000 intro start\n00:00:12.500 intro end\n00:00:30.000 not predefined synch 3\n01:00:00.000 outro start");
+    let output = rsid3_run(&[fpath]);
+    assert!(output.stdout.ends_with(b"ETCO:\n  [00:00:00.000] intro start\n  [00:00:12.500] intro end\n  [00:00:30.000] not predefined synch 3\n  [01:00:00.000] outro start\n"));
+
+    let file = TestFile::with_raw_frames(&[("ETCO", b"\x01\x01\x00\x00\x04\x00")]);
+    let output = rsid3_run(&["--ETCO", file.path().to_str().unwrap()]);
+    assert_eq!(output.stdout, b"frame 1024 end of initial silence");
+}
+
+#[test]
+fn sets_podcast_frames() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--podcast-feed", "https://example.com/feed.xml", "--podcast", "--podcast-id", "ep42",
+        "--podcast-keywords", "id3,tags", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--binary", "--PCST", "--TGID", "--TKWD", "--WFED", fpath]);
+    assert_eq!(output.stdout, b"\0\0\0\0\nep42\nid3,tags\nhttps://example.com/feed.xml");
+    let output = rsid3_run(&["--TDES", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = rsid3_run(&["--podcast", "--podcast-description", "Episode", "--PCST-", "--TDES", fpath]);
+    assert_eq!(output.stdout, b"Episode");
+    let output = rsid3_run(&["--PCST", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = rsid3_run(&["--podcast-id", "ep43", fpath]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn sets_itunes_text_frames() {
+    let file = TestFile::empty();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--MVNM=", "Allegro ma non troppo", "--MVIN=", "1/4", "--GRP1=", "Symphonies", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--MVNM", "--MVIN", "--GRP1", fpath]);
+    assert_eq!(output.stdout, b"Allegro ma non troppo\n1/4\nSymphonies");
+    let output = rsid3_run(&["--MVNM=", "Largo – Allegro", "--MVNM", fpath]);
+    assert_eq!(output.stdout, "Largo – Allegro".as_bytes());
+    let output = rsid3_run(&[fpath]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\nMVIN: 1/4\n") && stdout.contains("\nMVNM: Largo – Allegro\n"));
+    let output = rsid3_run(&["--MVNM-", "--MVNM", fpath]);
+    assert_eq!(output.status.code(), Some(1));
+
+    // Frames written by iTunes, in ISO-8859-1 and UTF-16
+    let file = TestFile::with_raw_frames(&[("MVNM", b"\x00Adagio"), ("MVIN", b"\x01\xff\xfe2\x00/\x004\x00")]);
+    let output = rsid3_run(&["--MVNM", "--MVIN", file.path().to_str().unwrap()]);
+    assert_eq!(output.stdout, b"Adagio\n2/4");
+}
+
+#[test]
+fn sets_length_from_audio() {
+    for file in [TestFile::empty(), TestFile::id3v1(), TestFile::ape()] {
+        let fpath = file.path().to_str().unwrap();
+        let output = rsid3_run(&["--set-length", "--TLEN", fpath]);
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"365");
+    }
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--TLEN=", "1000", fpath]);
+    assert!(output.status.success());
+    let output = rsid3_run(&["--set-length", "--TLEN", "--TIT2", fpath]);
+    assert_eq!(output.stdout, b"365\nSample Title");
+
+    let file = TestFile::aiff();
+    let output = rsid3_run(&["--set-length", file.path().to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn prints_audio_info() {
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--audio-info", fpath]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().ends_with(&format!("TIT2: Sample Title\n\
+        {fpath}: MPEG-1 Layer III audio:\n  duration: 00:00:00.365\n  bitrate: 113 kbps (VBR)\n  \
+        sample rate: 44100 Hz\n  channels: stereo\n")));
+
+    // 10 frames of MPEG-1 Layer III at 128 kbps and 44100 Hz, mono, preceded by garbage and
+    // followed by an ID3v1 tag
+    let dir = test_dir();
+    let fpath = dir.path().join("cbr.mp3");
+    let mut contents = b"\xff\xfb\x00".to_vec();
+    for _ in 0..10 {
+        contents.extend(b"\xff\xfb\x90\xc4");
+        contents.extend([0; 413]);
+    }
+    contents.extend(b"TAG");
+    contents.extend([0; 125]);
+    std::fs::write(&fpath, contents).unwrap();
+    let output = rsid3_run(&["--audio-info".as_ref(), fpath.as_os_str()]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().skip_while(|x| !x.ends_with("MPEG-1 Layer III audio:")).skip(1).collect::<Vec<_>>(), [
+        "  duration: 00:00:00.261",
+        "  bitrate: 128 kbps (CBR)",
+        "  sample rate: 44100 Hz",
+        "  channels: mono",
+    ]);
+}
+
+#[test]
+fn verifies_audio_after_writing() {
+    let long_title = "x".repeat(5000);
+    for file in [TestFile::tit2(), TestFile::id3v1(), TestFile::ape(), TestFile::aiff(), TestFile::wav()] {
+        let fpath = file.path().to_str().unwrap();
+        let output = rsid3_run(&["--verify-audio", "--TIT2=", &long_title, "--TPE1=", "Artist", fpath]);
+        assert!(output.status.success());
+        assert!(output.stderr.is_empty());
+        let output = rsid3_run(&["--verify-audio", "--purge-all", fpath]);
+        assert!(output.status.success());
+        assert!(output.stderr.is_empty());
+    }
+}
+
+#[test]
+fn sets_and_verifies_checksum() {
+    for file in [TestFile::tit2(), TestFile::id3v1(), TestFile::ape(), TestFile::aiff(), TestFile::wav()] {
+        let fpath = file.path().to_str().unwrap();
+        let output = rsid3_run(&["--checksum-verify", fpath]);
+        assert_eq!(output.status.code(), Some(1));
+        let output = rsid3_run(&["--checksum-set", fpath]);
+        assert!(output.status.success());
+        let output = rsid3_run(&["--checksum-verify", "--TIT2=", "Title", "--checksum-verify", fpath]);
+        assert!(output.status.success());
+        assert!(output.stdout.is_empty());
+    }
+
+    let file = TestFile::tit2();
+    let fpath = file.path().to_str().unwrap();
+    let output = rsid3_run(&["--checksum-set", "--TXXX", "AUDIO_MD5", fpath]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"5fceffb28b09b3b824aff1ab74d75fbd");
+    let output = rsid3_run(&["--TXXX=", "AUDIO_MD5", "0123456789abcdef0123456789abcdef", "--checksum-verify", fpath]);
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), format!("{fpath}: audio checksum mismatch \
+        (stored 0123456789abcdef0123456789abcdef, computed 5fceffb28b09b3b824aff1ab74d75fbd)\n"));
+}
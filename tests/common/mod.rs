@@ -13,12 +13,17 @@
 // You should have received a copy of the GNU General Public License along
 // with this program; if not, write to the Free Software Foundation, Inc.,
 // 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+#![allow(dead_code)]
 use std::ffi::OsStr;
 use std::path::Path;
-use std::fs::{create_dir_all, copy};
-use tempfile::NamedTempFile;
-use std::process::{Command, Output};
+use std::fs::{create_dir_all, copy, read, write};
+use tempfile::{NamedTempFile, TempDir, tempdir_in};
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
 
+/// Path to a nonexistent configuration directory, so that tests are unaffected by the user's own
+/// configuration file.
+const NO_CONFIG_DIR: &str = "tests/samples/no-config";
 /// Path to a sample MP3 file with no tags.
 const SAMPLE_EMPTY: &str = "tests/samples/sample_0.mp3";
 /// Path to a sample MP3 file with a TIT2 "Sample Title" tag.
@@ -27,6 +32,21 @@ const SAMPLE_TIT2: &str = "tests/samples/sample_TIT2.mp3";
 const SAMPLE_TXXX: &str = "tests/samples/sample_TXXX.mp3";
 /// Path to a sample MP3 file with a COMM[Description](eng) "Sample Content" tag.
 const SAMPLE_COMM: &str = "tests/samples/sample_COMM.mp3";
+/// Path to a sample MP3 file with an ID3v1.1 tag only (title "Sample Title", artist "Sample Artist",
+/// album "Sample Album", year "2000", comment "Sample Comment", track 1, genre 17 "Rock").
+const SAMPLE_ID3V1: &str = "tests/samples/sample_ID3v1.mp3";
+/// Path to a sample MP3 file with an APEv2 tag only (Title "Sample Title").
+const SAMPLE_APE: &str = "tests/samples/sample_APE.mp3";
+/// Path to a sample AIFF file with no tags.
+const SAMPLE_AIFF: &str = "tests/samples/sample_0.aiff";
+/// Path to a sample WAV file with no tags.
+const SAMPLE_WAV: &str = "tests/samples/sample_0.wav";
+/// Path to a sample LRC file with metadata, an offset, word timestamps and a repeated line.
+pub const SAMPLE_LRC: &str = "tests/samples/sample_lyrics.lrc";
+/// Path to a sample cue sheet with two tracks, "Introduction" at 0 and "Main topic" at 01:30:37.
+pub const SAMPLE_CUE: &str = "tests/samples/sample_chapters.cue";
+/// Path to a sample FFmpeg metadata file with two chapters, "Introduction" and "Questions = answers".
+pub const SAMPLE_FFMETADATA: &str = "tests/samples/sample_chapters.ffmetadata";
 /// Path to the directory for storing temporary files constructed and operated on in integration tests.
 const SAMPLES_TMPDIR: &str = "tests/samples/tmp/";
 /// Path to the rsid3 executable.
@@ -55,6 +75,36 @@ impl TestFile {
     test_file_from_sample!(tit2, SAMPLE_TIT2);
     test_file_from_sample!(txxx, SAMPLE_TXXX);
     test_file_from_sample!(comm, SAMPLE_COMM);
+    test_file_from_sample!(id3v1, SAMPLE_ID3V1);
+    test_file_from_sample!(ape, SAMPLE_APE);
+    test_file_from_sample!(aiff, SAMPLE_AIFF);
+    test_file_from_sample!(wav, SAMPLE_WAV);
+
+    /// Creates a copy of the empty sample file with an ID3v2.3 tag of the given raw frames
+    /// (ID and body) prepended. Unlike tags written by rsid3, the frames may conflict.
+    pub fn with_raw_frames(frames: &[(&str, &[u8])]) -> Self {
+        let frames = frames.iter().map(|(id, data)| (*id, [0, 0], *data)).collect::<Vec<_>>();
+        TestFile::with_raw_flagged_frames(&frames)
+    }
+
+    /// Like `with_raw_frames`, but also takes the two flag bytes of each frame's header.
+    pub fn with_raw_flagged_frames(frames: &[(&str, [u8; 2], &[u8])]) -> Self {
+        let test_file = TestFile::empty();
+        let mut body = vec![];
+        for (id, flags, data) in frames {
+            body.extend(id.as_bytes());
+            body.extend((data.len() as u32).to_be_bytes());
+            body.extend(flags);
+            body.extend(*data);
+        }
+        let size = body.len() as u32;
+        let mut contents = b"ID3\x03\x00\x00".to_vec();
+        contents.extend([(size >> 21) as u8 & 0x7f, (size >> 14) as u8 & 0x7f, (size >> 7) as u8 & 0x7f, size as u8 & 0x7f]);
+        contents.extend(body);
+        contents.extend(read(test_file.path()).unwrap());
+        write(test_file.path(), contents).unwrap();
+        test_file
+    }
 
     /// Returns the path to the test file.
     pub fn path(&self) -> &Path {
@@ -62,13 +112,59 @@ impl TestFile {
     }
 }
 
-pub fn rsid3_run(args: &[impl AsRef<OsStr>]) -> Output {
+/// Creates a new temporary directory, for tests operating on directory trees.
+pub fn test_dir() -> TempDir {
+    create_dir_all(SAMPLES_TMPDIR).unwrap();
+    tempdir_in(SAMPLES_TMPDIR).unwrap()
+}
+
+/// Creates a command running rsid3, without any configuration file or environment overrides.
+/// The undo journal is kept in the directory for temporary files.
+fn rsid3_command() -> Command {
     let mut cmd = Command::new(PROGRAM_PATH);
+    cmd.env("XDG_CONFIG_HOME", NO_CONFIG_DIR).env("APPDATA", NO_CONFIG_DIR);
+    cmd.env("XDG_CACHE_HOME", SAMPLES_TMPDIR).env("LOCALAPPDATA", SAMPLES_TMPDIR);
+    for (key, _) in std::env::vars_os().filter(|(x, _)| x.to_string_lossy().starts_with("RSID3_")) {
+        cmd.env_remove(key);
+    }
+    cmd
+}
+
+pub fn rsid3_run(args: &[impl AsRef<OsStr>]) -> Output {
+    rsid3_run_with_env(args, &[] as &[(&str, &str)])
+}
+
+pub fn rsid3_run_with_env(args: &[impl AsRef<OsStr>], vars: &[(&str, impl AsRef<OsStr>)]) -> Output {
+    let mut cmd = rsid3_command();
     cmd.args(args);
+    for (key, value) in vars {
+        cmd.env(key, value);
+    }
     println!("Command: {:?}", cmd);
     let output = cmd.output().unwrap();
     println!("Status:  {:?}", output.status);
-    println!("Stdout:  {:?}", String::from_utf8(output.stdout.clone()).unwrap());
+    println!("Stdout:  {:?}", String::from_utf8_lossy(&output.stdout));
+    println!("Stderr:  {:?}", String::from_utf8(output.stderr.clone()).unwrap());
+    output
+}
+
+pub fn rsid3_run_with_stdin(args: &[impl AsRef<OsStr>], stdin: &[u8]) -> Output {
+    let mut cmd = rsid3_command();
+    cmd.args(args).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    println!("Command: {:?}", cmd);
+    println!("Stdin:   {:?}", String::from_utf8_lossy(&stdin[..stdin.len().min(1000)]));
+    let mut child = cmd.spawn().unwrap();
+    // Written from another thread, as the program may write its output before reading all input
+    let mut input = child.stdin.take().unwrap();
+    let stdin = stdin.to_vec();
+    let writer = std::thread::spawn(move || {
+        // The program may exit without reading its input, e.g. on a usage error
+        let _ = input.write_all(&stdin);
+    });
+    let output = child.wait_with_output().unwrap();
+    writer.join().unwrap();
+    println!("Status:  {:?}", output.status);
+    println!("Stdout:  {:?}", String::from_utf8_lossy(&output.stdout));
     println!("Stderr:  {:?}", String::from_utf8(output.stderr.clone()).unwrap());
     output
 }
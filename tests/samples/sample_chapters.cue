@@ -0,0 +1,10 @@
+PERFORMER "Sample Artist"
+TITLE "Sample Show"
+FILE "sample.mp3" MP3
+  TRACK 01 AUDIO
+    TITLE "Introduction"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Main topic"
+    INDEX 00 01:29:00
+    INDEX 01 01:30:37